@@ -262,6 +262,9 @@ struct sg_source_info {
     // The fully qualified name is a representation of the symbol that captures its name and its
     // embedded context (e.g. `foo.bar` for the symbol `bar` defined in the module `foo`).
     sg_string_handle fully_qualified_name;
+    // An identifier for this node that does not depend on the order in which the nodes of its
+    // file were created, so that it stays the same when the file is indexed again.
+    sg_string_handle stable_id;
 };
 
 // An array of all of the source information in a stack graph.  Source information is associated
@@ -440,6 +443,13 @@ struct sg_partial_path {
 struct sg_stitcher_config {
     // Enables similar path detection during stiching.
     bool detect_similar_paths;
+    // Processes paths best-first instead of breadth-first, so that the most likely complete
+    // paths are found first.
+    bool best_first;
+    // The maximum number of complete paths to find, or 0 for no limit.
+    size_t max_complete_paths;
+    // The maximum amount of work to perform, or 0 for no limit.
+    size_t max_work;
 };
 
 // An array of all of the partial paths in a partial path database.  Partial path handles are
@@ -836,6 +846,14 @@ void sg_forward_partial_path_stitcher_set_similar_path_detection(struct sg_forwa
 void sg_forward_partial_path_stitcher_set_max_work_per_phase(struct sg_forward_partial_path_stitcher *stitcher,
                                                              size_t max_work);
 
+// Sets whether paths are processed best-first instead of breadth-first. In best-first mode, the
+// queued paths are ordered by priority at the start of each phase, and each phase stops as soon
+// as any work has been performed. Paths that cross fewer files have the highest priority,
+// followed by paths with a higher total edge precedence, and paths with a shorter symbol stack.
+// Best-first mode is disabled by default.
+void sg_forward_partial_path_stitcher_set_best_first(struct sg_forward_partial_path_stitcher *stitcher,
+                                                     bool best_first);
+
 // Runs the next phase of the algorithm.  We will have built up a set of incomplete partial paths
 // during the _previous_ phase.  Before calling this function, you must ensure that `db` contains
 // all of the possible partial paths that we might want to extend any of those candidate partial
@@ -338,6 +338,15 @@ impl<H, T> SupplementalArena<H, T> {
             .skip(1)
             .map(|(i, x)| (Handle::from_some(i as u32), unsafe { &*(x.as_ptr()) }))
     }
+
+    /// Iterate mutably over the items in this arena.
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (Handle<T>, &mut T)> {
+        self.items.iter_mut().enumerate().skip(1).map(|(i, x)| {
+            (Handle::from_some(i as u32), unsafe {
+                &mut *(x.as_mut_ptr())
+            })
+        })
+    }
 }
 
 impl<H, T> SupplementalArena<H, T>
@@ -17,6 +17,7 @@ use crate::graph::StackGraph;
 use crate::graph::Symbol;
 use crate::partial::PartialPath;
 use crate::partial::PartialPaths;
+use crate::shadowing::ShadowingPolicy;
 use crate::stitching::Database;
 use crate::stitching::DatabaseCandidates;
 use crate::stitching::ForwardPartialPathStitcher;
@@ -144,13 +145,15 @@ impl From<CancellationError> for AssertionError {
 }
 
 impl Assertion {
-    /// Run this assertion against the given graph, using the given paths object for path search.
+    /// Run this assertion against the given graph, using the given paths object for path search,
+    /// and the given shadowing policy to decide which definitions are visible.
     pub fn run(
         &self,
         graph: &StackGraph,
         partials: &mut PartialPaths,
         db: &mut Database,
         stitcher_config: StitcherConfig,
+        shadowing_policy: &dyn ShadowingPolicy,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<(), AssertionError> {
         match self {
@@ -161,6 +164,7 @@ impl Assertion {
                 source,
                 targets,
                 stitcher_config,
+                shadowing_policy,
                 cancellation_flag,
             ),
             Self::Defines { source, symbols } => self.run_defines(graph, source, symbols),
@@ -176,6 +180,7 @@ impl Assertion {
         source: &AssertionSource,
         expected_targets: &Vec<AssertionTarget>,
         stitcher_config: StitcherConfig,
+        shadowing_policy: &dyn ShadowingPolicy,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<(), AssertionError> {
         let references = source.iter_references(graph).collect::<Vec<_>>();
@@ -198,9 +203,7 @@ impl Assertion {
                 },
             )?;
             for reference_path in &reference_paths {
-                if reference_paths
-                    .iter()
-                    .all(|other| !other.shadows(partials, reference_path))
+                if !shadowing_policy.is_shadowed(graph, partials, reference_path, &reference_paths)
                 {
                     actual_paths.push(reference_path.clone());
                 }
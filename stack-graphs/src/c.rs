@@ -638,6 +638,9 @@ pub struct sg_source_info {
     /// The fully qualified name is a representation of the symbol that captures its name and its
     /// embedded context (e.g. `foo.bar` for the symbol `bar` defined in the module `foo`).
     pub fully_qualified_name: sg_string_handle,
+    /// An identifier for this node that does not depend on the order in which the nodes of its
+    /// file were created, so that it stays the same when the file is indexed again.
+    pub stable_id: sg_string_handle,
 }
 
 /// All of the position information that we have about a range of content in a source file
@@ -1404,11 +1407,22 @@ pub struct sg_forward_partial_path_stitcher {
 pub struct sg_stitcher_config {
     /// Enables similar path detection during stiching.
     pub detect_similar_paths: bool,
+    /// Processes paths best-first instead of breadth-first, so that the most likely complete
+    /// paths are found first.
+    pub best_first: bool,
+    /// The maximum number of complete paths to find, or 0 for no limit.
+    pub max_complete_paths: usize,
+    /// The maximum amount of work to perform, or 0 for no limit.
+    pub max_work: usize,
 }
 
 impl Into<StitcherConfig> for sg_stitcher_config {
     fn into(self) -> StitcherConfig {
-        StitcherConfig::default().with_detect_similar_paths(self.detect_similar_paths)
+        StitcherConfig::default()
+            .with_detect_similar_paths(self.detect_similar_paths)
+            .with_best_first(self.best_first)
+            .with_max_complete_paths(Some(self.max_complete_paths).filter(|max| *max > 0))
+            .with_max_work(Some(self.max_work).filter(|max| *max > 0))
     }
 }
 
@@ -1536,6 +1550,20 @@ pub extern "C" fn sg_forward_partial_path_stitcher_set_max_work_per_phase(
     stitcher.stitcher.set_max_work_per_phase(max_work);
 }
 
+/// Sets whether paths are processed best-first instead of breadth-first. In best-first mode, the
+/// queued paths are ordered by priority at the start of each phase, and each phase stops as soon
+/// as any work has been performed. Paths that cross fewer files have the highest priority,
+/// followed by paths with a higher total edge precedence, and paths with a shorter symbol stack.
+/// Best-first mode is disabled by default.
+#[no_mangle]
+pub extern "C" fn sg_forward_partial_path_stitcher_set_best_first(
+    stitcher: *mut sg_forward_partial_path_stitcher,
+    best_first: bool,
+) {
+    let stitcher = unsafe { &mut *(stitcher as *mut InternalForwardPartialPathStitcher) };
+    stitcher.stitcher.set_best_first(best_first);
+}
+
 /// Runs the next phase of the algorithm.  We will have built up a set of incomplete partial paths
 /// during the _previous_ phase.  Before calling this function, you must ensure that `db` contains
 /// all of the possible partial paths that we might want to extend any of those candidate partial
@@ -1579,7 +1607,7 @@ impl CancellationFlag for AtomicUsizeCancellationFlag<'_> {
         self.0
             .map(|flag| {
                 if flag.fetch_and(0b0, std::sync::atomic::Ordering::Relaxed) != 0 {
-                    Err(CancellationError(at))
+                    Err(CancellationError::new(at))
                 } else {
                     Ok(())
                 }
@@ -32,7 +32,13 @@
 use enumset::EnumSet;
 use smallvec::SmallVec;
 use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::hash::Hash;
+use std::hash::Hasher;
 
 use crate::arena::Arena;
 use crate::arena::Handle;
@@ -49,9 +55,82 @@ use crate::stitching::Appendable;
 use crate::stitching::ToAppendable;
 
 /// Helps detect similar paths in the path-finding algorithm.
+///
+/// The detector keeps paths in buckets of paths with the same [`PathKey`][]. By default, buckets
+/// are kept for the lifetime of the detector. If a budget is set with
+/// [`set_max_paths`][Self::set_max_paths], the least recently used buckets are evicted when the
+/// total number of paths exceeds the budget. Evicting buckets bounds memory use, but paths that
+/// are similar to evicted ones are no longer detected, which can cause extra work.
 pub struct SimilarPathDetector<P> {
     paths: HashMap<PathKey, SmallVec<[P; 4]>>,
     counts: Option<HashMap<PathKey, SmallVec<[usize; 4]>>>,
+    max_paths: Option<usize>,
+    path_count: usize,
+    lru: LruBuckets,
+}
+
+/// Keeps track of bucket use and eviction for [`SimilarPathDetector`][].
+#[derive(Default)]
+struct LruBuckets {
+    tick: u64,
+    last_used: HashMap<PathKey, u64>,
+    by_last_use: BTreeMap<u64, PathKey>,
+    /// Hashes of the keys of evicted buckets, which are used to detect readmitted paths. At most
+    /// `max_evicted_keys` hashes are kept, and the oldest ones are forgotten first, so that the
+    /// memory used by the detector stays bounded.
+    evicted_keys: HashSet<u64>,
+    evicted_keys_order: VecDeque<u64>,
+    max_evicted_keys: usize,
+    evicted_buckets: usize,
+    evicted_paths: usize,
+    readmitted_paths: usize,
+    /// Statistics of evicted buckets, if statistics are collected.
+    evicted_stats: SimilarPathStats,
+}
+
+impl LruBuckets {
+    fn touch(&mut self, key: &PathKey) {
+        self.tick += 1;
+        if let Some(previous) = self.last_used.insert(key.clone(), self.tick) {
+            self.by_last_use.remove(&previous);
+        }
+        self.by_last_use.insert(self.tick, key.clone());
+    }
+
+    /// Removes and returns the least recently used key, unless it is the given key.
+    fn pop_least_recently_used(&mut self, keep: &PathKey) -> Option<PathKey> {
+        let (tick, key) = self.by_last_use.iter().next()?;
+        if key == keep {
+            return None;
+        }
+        let tick = *tick;
+        let key = self.by_last_use.remove(&tick)?;
+        self.last_used.remove(&key);
+        self.remember_evicted(Self::hash(&key));
+        Some(key)
+    }
+
+    fn remember_evicted(&mut self, hash: u64) {
+        if self.max_evicted_keys == 0 || !self.evicted_keys.insert(hash) {
+            return;
+        }
+        self.evicted_keys_order.push_back(hash);
+        if self.evicted_keys_order.len() > self.max_evicted_keys {
+            if let Some(oldest) = self.evicted_keys_order.pop_front() {
+                self.evicted_keys.remove(&oldest);
+            }
+        }
+    }
+
+    fn was_evicted(&self, key: &PathKey) -> bool {
+        !self.evicted_keys.is_empty() && self.evicted_keys.contains(&Self::hash(key))
+    }
+
+    fn hash(key: &PathKey) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
 }
 
 #[doc(hidden)]
@@ -95,9 +174,21 @@ where
         SimilarPathDetector {
             paths: HashMap::new(),
             counts: None,
+            max_paths: None,
+            path_count: 0,
+            lru: LruBuckets::default(),
         }
     }
 
+    /// Sets the maximum number of paths that are kept. When more paths are added, the least
+    /// recently used buckets are evicted until the number of paths is within the budget again.
+    /// The bucket of the most recently added path is never evicted. The budget must be set
+    /// before any paths are added. There is no limit by default.
+    pub fn set_max_paths(&mut self, max_paths: Option<usize>) {
+        self.max_paths = max_paths;
+        self.lru.max_evicted_keys = max_paths.unwrap_or(0);
+    }
+
     /// Set whether to collect statistics for this similar path detector.
     pub fn set_collect_stats(&mut self, collect_stats: bool) {
         if !collect_stats {
@@ -121,6 +212,9 @@ where
         Cmp: Fn(&mut P::Arena, &P, &P) -> Option<Ordering>,
     {
         let key = path.key();
+        if self.max_paths.is_some() {
+            self.lru.touch(&key);
+        }
 
         // Iterate through the bucket to determine if this paths is better than any already known
         // path. Note that the bucket might be modified during the loop if a path is removed which
@@ -129,7 +223,7 @@ where
         let mut possible_similar_counts = self
             .counts
             .as_mut()
-            .map(move |cs| cs.entry(key).or_default());
+            .map(|cs| cs.entry(key.clone()).or_default());
         let mut idx = 0;
         let mut count = 0;
         while idx < possibly_similar_paths.len() {
@@ -138,6 +232,7 @@ where
                 Some(Ordering::Less) => {
                     // the new path is better, remove the old one
                     possibly_similar_paths.remove(idx);
+                    self.path_count -= 1;
                     if let Some(possible_similar_counts) = possible_similar_counts.as_mut() {
                         count += possible_similar_counts[idx];
                         possible_similar_counts.remove(idx);
@@ -163,9 +258,42 @@ where
         if let Some(possible_similar_counts) = possible_similar_counts {
             possible_similar_counts.push(count);
         }
+        self.path_count += 1;
+        if self.max_paths.is_some() {
+            if self.lru.was_evicted(&key) {
+                self.lru.readmitted_paths += 1;
+            }
+            self.evict_over_budget(&key);
+        }
         false
     }
 
+    /// Evicts the least recently used buckets, except the one for the given key, until the
+    /// number of paths is within the budget.
+    fn evict_over_budget(&mut self, keep: &PathKey) {
+        let max_paths = match self.max_paths {
+            Some(max_paths) => max_paths,
+            None => return,
+        };
+        while self.path_count > max_paths {
+            let key = match self.lru.pop_least_recently_used(keep) {
+                Some(key) => key,
+                None => break,
+            };
+            let evicted_paths = self.paths.remove(&key).map_or(0, |b| b.len());
+            self.path_count -= evicted_paths;
+            self.lru.evicted_buckets += 1;
+            self.lru.evicted_paths += evicted_paths;
+            if let Some(bucket) = self.counts.as_mut().and_then(|cs| cs.remove(&key)) {
+                let evicted_stats = &mut self.lru.evicted_stats;
+                evicted_stats.similar_path_bucket_size.record(bucket.len());
+                for count in bucket {
+                    evicted_stats.similar_path_count.record(count);
+                }
+            }
+        }
+    }
+
     #[cfg(feature = "copious-debugging")]
     pub fn max_bucket_size(&self) -> usize {
         self.paths.iter().map(|b| b.1.len()).max().unwrap_or(0)
@@ -173,7 +301,14 @@ where
 
     // Returns the distribution of similar path counts.
     pub fn stats(&self) -> SimilarPathStats {
-        let mut stats = SimilarPathStats::default();
+        let mut stats = SimilarPathStats {
+            evicted_buckets: self.lru.evicted_buckets,
+            evicted_paths: self.lru.evicted_paths,
+            readmitted_paths: self.lru.readmitted_paths,
+            ..SimilarPathStats::default()
+        };
+        stats.similar_path_count += &self.lru.evicted_stats.similar_path_count;
+        stats.similar_path_bucket_size += &self.lru.evicted_stats.similar_path_bucket_size;
         if let Some(counts) = &self.counts {
             for bucket in counts.values() {
                 stats.similar_path_bucket_size.record(bucket.len());
@@ -192,12 +327,24 @@ pub struct SimilarPathStats {
     pub similar_path_count: FrequencyDistribution<usize>,
     // The distribution of the internal bucket sizes in the similar path detector
     pub similar_path_bucket_size: FrequencyDistribution<usize>,
+    // The number of buckets that were evicted to stay within the memory budget
+    pub evicted_buckets: usize,
+    // The number of paths in evicted buckets
+    pub evicted_paths: usize,
+    // The number of paths that were kept in buckets that had been evicted before. These paths
+    // might have been rejected as similar without eviction, and cause extra work. Only the most
+    // recently evicted buckets are remembered, as many as the path budget, so this is a lower
+    // bound.
+    pub readmitted_paths: usize,
 }
 
 impl std::ops::AddAssign<Self> for SimilarPathStats {
     fn add_assign(&mut self, rhs: Self) {
         self.similar_path_bucket_size += rhs.similar_path_bucket_size;
         self.similar_path_count += rhs.similar_path_count;
+        self.evicted_buckets += rhs.evicted_buckets;
+        self.evicted_paths += rhs.evicted_paths;
+        self.readmitted_paths += rhs.readmitted_paths;
     }
 }
 
@@ -205,13 +352,17 @@ impl std::ops::AddAssign<&Self> for SimilarPathStats {
     fn add_assign(&mut self, rhs: &Self) {
         self.similar_path_bucket_size += &rhs.similar_path_bucket_size;
         self.similar_path_count += &rhs.similar_path_count;
+        self.evicted_buckets += rhs.evicted_buckets;
+        self.evicted_paths += rhs.evicted_paths;
+        self.readmitted_paths += rhs.readmitted_paths;
     }
 }
 
 // ----------------------------------------------------------------------------
 // Cycle detector
 
-/// An arena used by [`AppendingCycleDetector`][] to store the path component lists.
+/// An arena used by [`AppendingCycleDetector`][] and [`PrependingCycleDetector`][] to store the
+/// path component lists.
 /// The arena is shared between all cycle detectors in a path stitching run, so that
 /// the cycle detectors themselves can be small and cheaply cloned.
 pub struct Appendables<H> {
@@ -404,3 +555,98 @@ where
         }
     }
 }
+
+/// A cycle detector that builds up paths by prepending elements to it.  This is the mirror image
+/// of [`AppendingCycleDetector`][], used when paths are extended backwards from their start node.
+/// Path elements are stored in a shared arena that must be provided when calling methods, so that
+/// cloning the cycle detector itself is cheap.
+#[derive(Clone)]
+pub struct PrependingCycleDetector<H> {
+    prependages: List<InternedOrHandle<H>>,
+}
+
+impl<H> Default for PrependingCycleDetector<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H> PrependingCycleDetector<H> {
+    pub fn new() -> Self {
+        Self {
+            prependages: List::empty(),
+        }
+    }
+
+    pub fn from(appendables: &mut Appendables<H>, path: PartialPath) -> Self {
+        let h = appendables.interned.add(path);
+        let mut result = Self::new();
+        result
+            .prependages
+            .push_front(&mut appendables.elements, InternedOrHandle::Interned(h));
+        result
+    }
+
+    pub fn prepend(&mut self, appendables: &mut Appendables<H>, prependage: H) {
+        self.prependages.push_front(
+            &mut appendables.elements,
+            InternedOrHandle::Database(prependage),
+        );
+    }
+}
+
+impl<H> PrependingCycleDetector<H>
+where
+    H: Clone,
+{
+    /// Tests if the path is cyclic. Returns a vector indicating the kind of cycles that were found.
+    /// If appending or concatenating all fragments succeeds, this function will never raise and error.
+    pub fn is_cyclic<'a, A, Db>(
+        &self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        db: &'a Db,
+        appendables: &mut Appendables<H>,
+    ) -> Result<EnumSet<Cyclicity>, PathResolutionError>
+    where
+        A: Appendable + 'a,
+        Db: ToAppendable<H, A>,
+    {
+        let mut cycles = EnumSet::new();
+
+        let start_node = match self.prependages.clone().pop_front(&appendables.elements) {
+            Some(prependage) => prependage.start_node(db, &appendables.interned),
+            None => return Ok(cycles),
+        };
+
+        // find the length of the longest cycle, so that we do not build up more of the path than
+        // necessary
+        let mut counting_prependages = self.prependages;
+        let mut length = 0usize;
+        let mut max_cycle_length = 0usize;
+        while let Some(prependage) = counting_prependages.pop_front(&appendables.elements) {
+            length += 1;
+            if prependage.end_node(db, &appendables.interned) == start_node {
+                max_cycle_length = length;
+            }
+        }
+
+        // Because the elements are stored in path order, every prefix of the path that ends at
+        // the start node is a cycle.  We build up the prefixes one element at a time.
+        let mut remaining_prependages = self.prependages;
+        let mut prefix_path = PartialPath::from_node(graph, partials, start_node);
+        for _ in 0..max_cycle_length {
+            let prependage = remaining_prependages
+                .pop_front(&appendables.elements)
+                .expect("")
+                .clone();
+            prependage.append_to(graph, partials, db, &appendables.interned, &mut prefix_path)?;
+            if prefix_path.end_node == start_node && !prefix_path.edges.is_empty() {
+                if let Some(cyclicity) = prefix_path.is_cyclic(graph, partials) {
+                    cycles |= cyclicity;
+                }
+            }
+        }
+        Ok(cycles)
+    }
+}
@@ -0,0 +1,323 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Compare two versions of a stack graph.
+//!
+//! A [`GraphDiff`][] describes the structural differences between an old and a new version of a
+//! stack graph, for instance to see how a change to the rules that construct a graph affects it.
+//! Graphs are compared in their [serializable][] form, so the two versions do not have to live
+//! in the same [`StackGraph`][] instance, and can be loaded from JSON files.
+//!
+//! Nodes are matched by their [`NodeID`][] first, as long as both nodes have the same symbol.
+//! Nodes that remain unmatched are then matched by their file, symbol and span, so that nodes
+//! which are only numbered differently are not reported as removed and added.  Edges are
+//! matched by the matches of their source and sink nodes.  Debug info is not compared, because
+//! it tends to change with every change to the rules.
+//!
+//! [serializable]: ../serde/index.html
+//! [`StackGraph`]: ../graph/struct.StackGraph.html
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
+use crate::serde::Edge;
+use crate::serde::Node;
+use crate::serde::NodeID;
+use crate::serde::StackGraph;
+
+/// The differences between two versions of a stack graph.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GraphDiff {
+    /// Nodes that only appear in the new graph.
+    pub added_nodes: Vec<Node>,
+    /// Nodes that only appear in the old graph.
+    pub removed_nodes: Vec<Node>,
+    /// Nodes that appear in both graphs, but differ.
+    pub changed_nodes: Vec<NodeDiff>,
+    /// Edges that only appear in the new graph.
+    pub added_edges: Vec<Edge>,
+    /// Edges that only appear in the old graph.
+    pub removed_edges: Vec<Edge>,
+    /// Edges that appear in both graphs, but differ.
+    pub changed_edges: Vec<EdgeDiff>,
+}
+
+/// A node that appears in both graphs, but differs between them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NodeDiff {
+    pub old: Node,
+    pub new: Node,
+    pub changes: Vec<NodeChange>,
+}
+
+/// The ways in which a matched node can differ.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum NodeChange {
+    /// The node was matched by its symbol and span, and has a different ID.
+    Id,
+    /// The node has a different kind, or different flags or scope.
+    Properties,
+    /// The node has different source info.
+    SourceInfo,
+}
+
+/// An edge that appears in both graphs, but differs between them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct EdgeDiff {
+    pub old: Edge,
+    pub new: Edge,
+}
+
+impl GraphDiff {
+    /// Computes the differences between two stack graphs.
+    pub fn from_graphs(old: &crate::graph::StackGraph, new: &crate::graph::StackGraph) -> Self {
+        Self::from_serializable(&old.to_serializable(), &new.to_serializable())
+    }
+
+    /// Computes the differences between two serializable stack graphs.
+    pub fn from_serializable(old: &StackGraph, new: &StackGraph) -> Self {
+        let mut diff = GraphDiff::default();
+        let matches = match_nodes(old, new);
+
+        let mut matched_new_nodes = HashSet::new();
+        for (old_index, old_node) in old.nodes.data.iter().enumerate() {
+            let new_node = match matches.new_index_for_old[old_index] {
+                Some(new_index) => &new.nodes.data[new_index],
+                None => {
+                    diff.removed_nodes.push(old_node.clone());
+                    continue;
+                }
+            };
+            matched_new_nodes.insert(new_node.id());
+            let mut changes = Vec::new();
+            if old_node.id() != new_node.id() {
+                changes.push(NodeChange::Id);
+            }
+            if !properties_equal(old_node, new_node, &matches.new_id_for_old) {
+                changes.push(NodeChange::Properties);
+            }
+            if old_node.source_info() != new_node.source_info() {
+                changes.push(NodeChange::SourceInfo);
+            }
+            if !changes.is_empty() {
+                diff.changed_nodes.push(NodeDiff {
+                    old: old_node.clone(),
+                    new: new_node.clone(),
+                    changes,
+                });
+            }
+        }
+        diff.added_nodes = new
+            .nodes
+            .data
+            .iter()
+            .filter(|node| !matched_new_nodes.contains(node.id()))
+            .cloned()
+            .collect();
+
+        let mut new_edges = new
+            .edges
+            .data
+            .iter()
+            .map(|edge| ((&edge.source, &edge.sink), edge))
+            .collect::<HashMap<_, _>>();
+        for old_edge in &old.edges.data {
+            let source = matches.new_id_for_old.get(&old_edge.source);
+            let sink = matches.new_id_for_old.get(&old_edge.sink);
+            let new_edge = match (source, sink) {
+                (Some(source), Some(sink)) => new_edges.remove(&(*source, *sink)),
+                _ => None,
+            };
+            match new_edge {
+                Some(new_edge) => {
+                    if old_edge.precedence != new_edge.precedence || old_edge.kind != new_edge.kind
+                    {
+                        diff.changed_edges.push(EdgeDiff {
+                            old: old_edge.clone(),
+                            new: new_edge.clone(),
+                        });
+                    }
+                }
+                None => diff.removed_edges.push(old_edge.clone()),
+            }
+        }
+        diff.added_edges = new
+            .edges
+            .data
+            .iter()
+            .filter(|edge| new_edges.contains_key(&(&edge.source, &edge.sink)))
+            .cloned()
+            .collect();
+
+        diff
+    }
+
+    /// Returns whether the two graphs are the same.
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.changed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && self.changed_edges.is_empty()
+    }
+}
+
+/// The node matching between an old and a new graph.
+struct NodeMatches<'a> {
+    /// For each node in the old graph, the index of the matching node in the new graph.
+    new_index_for_old: Vec<Option<usize>>,
+    /// For each matched node ID in the old graph, the ID of the matching node in the new graph.
+    new_id_for_old: HashMap<&'a NodeID, &'a NodeID>,
+}
+
+fn match_nodes<'a>(old: &'a StackGraph, new: &'a StackGraph) -> NodeMatches<'a> {
+    let mut new_index_for_old = vec![None; old.nodes.data.len()];
+    let mut matched_new = vec![false; new.nodes.data.len()];
+
+    // Match nodes with the same ID and symbol.
+    let new_index_by_id = new
+        .nodes
+        .data
+        .iter()
+        .enumerate()
+        .map(|(index, node)| (node.id(), index))
+        .collect::<HashMap<_, _>>();
+    for (old_index, old_node) in old.nodes.data.iter().enumerate() {
+        if let Some(new_index) = new_index_by_id.get(old_node.id()).copied() {
+            if new.nodes.data[new_index].symbol() == old_node.symbol() {
+                new_index_for_old[old_index] = Some(new_index);
+                matched_new[new_index] = true;
+            }
+        }
+    }
+
+    // Match the remaining nodes by file, symbol, and span, in the order in which they appear.
+    let mut new_indices_by_location = HashMap::<_, VecDeque<usize>>::new();
+    for (new_index, new_node) in new.nodes.data.iter().enumerate() {
+        if matched_new[new_index] {
+            continue;
+        }
+        if let Some(location) = location(new_node) {
+            new_indices_by_location
+                .entry(location)
+                .or_default()
+                .push_back(new_index);
+        }
+    }
+    for (old_index, old_node) in old.nodes.data.iter().enumerate() {
+        if new_index_for_old[old_index].is_some() {
+            continue;
+        }
+        let new_index = location(old_node)
+            .and_then(|location| new_indices_by_location.get_mut(&location))
+            .and_then(|new_indices| new_indices.pop_front());
+        if let Some(new_index) = new_index {
+            new_index_for_old[old_index] = Some(new_index);
+            matched_new[new_index] = true;
+        }
+    }
+
+    let new_id_for_old = new_index_for_old
+        .iter()
+        .enumerate()
+        .filter_map(|(old_index, new_index)| {
+            new_index.map(|new_index| {
+                (
+                    old.nodes.data[old_index].id(),
+                    new.nodes.data[new_index].id(),
+                )
+            })
+        })
+        .collect();
+    NodeMatches {
+        new_index_for_old,
+        new_id_for_old,
+    }
+}
+
+/// Returns the file, symbol, and span of a node, if it has a symbol and source info.
+fn location(node: &Node) -> Option<(Option<&str>, &str, &lsp_positions::Span)> {
+    let symbol = node.symbol()?;
+    let source_info = node.source_info()?;
+    Some((node.id().file.as_deref(), symbol, &source_info.span))
+}
+
+/// Returns whether two matched nodes have the same kind, flags, and scope, ignoring their IDs,
+/// source info, and debug info.
+fn properties_equal(old: &Node, new: &Node, new_id_for_old: &HashMap<&NodeID, &NodeID>) -> bool {
+    match (old, new) {
+        (
+            Node::PopScopedSymbol {
+                is_definition: old_is_definition,
+                ..
+            },
+            Node::PopScopedSymbol {
+                is_definition: new_is_definition,
+                ..
+            },
+        )
+        | (
+            Node::PopSymbol {
+                is_definition: old_is_definition,
+                ..
+            },
+            Node::PopSymbol {
+                is_definition: new_is_definition,
+                ..
+            },
+        ) => old_is_definition == new_is_definition,
+        (
+            Node::PushScopedSymbol {
+                scope: old_scope,
+                is_reference: old_is_reference,
+                ..
+            },
+            Node::PushScopedSymbol {
+                scope: new_scope,
+                is_reference: new_is_reference,
+                ..
+            },
+        ) => {
+            old_is_reference == new_is_reference
+                && new_id_for_old.get(old_scope).copied().unwrap_or(old_scope) == new_scope
+        }
+        (
+            Node::PushSymbol {
+                is_reference: old_is_reference,
+                ..
+            },
+            Node::PushSymbol {
+                is_reference: new_is_reference,
+                ..
+            },
+        ) => old_is_reference == new_is_reference,
+        (
+            Node::Scope {
+                is_exported: old_is_exported,
+                ..
+            },
+            Node::Scope {
+                is_exported: new_is_exported,
+                ..
+            },
+        ) => old_is_exported == new_is_exported,
+        (Node::DropScopes { .. }, Node::DropScopes { .. })
+        | (Node::JumpToScope { .. }, Node::JumpToScope { .. })
+        | (Node::Root { .. }, Node::Root { .. }) => true,
+        _ => false,
+    }
+}
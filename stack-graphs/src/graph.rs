@@ -62,6 +62,7 @@ use smallvec::SmallVec;
 
 use crate::arena::Arena;
 use crate::arena::Handle;
+use crate::arena::HandleSet;
 use crate::arena::SupplementalArena;
 
 //-------------------------------------------------------------------------------------------------
@@ -378,7 +379,65 @@ impl StackGraph {
     /// that because we're only returning _handles_, this iterator does not retain a reference to
     /// the `StackGraph`.)
     pub fn iter_files(&self) -> impl Iterator<Item = Handle<File>> + '_ {
-        self.files.iter_handles()
+        self.files
+            .iter_handles()
+            .filter(move |file| !self.removed_files.contains(*file))
+    }
+
+    /// Removes a file from the stack graph, along with all of its nodes, all of the edges that
+    /// begin or end at those nodes, and their source and debug information.
+    ///
+    /// Handles are never reused, so handles to other files, and to nodes in other files, remain
+    /// valid.  Handles to the removed file and its nodes can still be dereferenced, but they no
+    /// longer appear in [`iter_files`][], [`iter_nodes`][], [`nodes_for_file`][], or
+    /// [`node_for_id`][].  A new file with the same name can be added afterwards; it will get a
+    /// new handle.
+    ///
+    /// [`iter_files`]: #method.iter_files
+    /// [`iter_nodes`]: #method.iter_nodes
+    /// [`nodes_for_file`]: #method.nodes_for_file
+    /// [`node_for_id`]: #method.node_for_id
+    pub fn remove_file(&mut self, file: Handle<File>) {
+        if self.removed_files.contains(file) {
+            return;
+        }
+        self.removed_files.add(file);
+        self.file_handles.remove(self.files.get(file).name());
+
+        let nodes = self.node_id_handles.remove_file(file);
+        for node in nodes.iter().copied() {
+            self.removed_nodes.add(node);
+            if let Some(edges) = self.outgoing_edges.get_mut(node) {
+                for edge in edges.drain(..) {
+                    self.incoming_edges[edge.sink] -= 1;
+                }
+            }
+            if let Some(source_info) = self.source_info.get_mut(node) {
+                *source_info = SourceInfo::default();
+            }
+            if let Some(debug_info) = self.node_debug_info.get_mut(node) {
+                *debug_info = DebugInfo::default();
+            }
+            if let Some(edge_debug_info) = self.edge_debug_info.get_mut(node) {
+                edge_debug_info.clear();
+            }
+        }
+
+        // Edges from other files into the removed file have to go as well.
+        let removed_nodes = &self.removed_nodes;
+        for source in self.nodes.iter_handles() {
+            if let Some(edges) = self.outgoing_edges.get_mut(source) {
+                edges.retain(|edge| !removed_nodes.contains(edge.sink));
+            }
+            if let Some(edge_debug_info) = self.edge_debug_info.get_mut(source) {
+                edge_debug_info.retain(|(sink, _)| !removed_nodes.contains(*sink));
+            }
+        }
+        for node in nodes {
+            if let Some(degree) = self.incoming_edges.get_mut(node) {
+                *degree = 0;
+            }
+        }
     }
 }
 
@@ -666,7 +725,16 @@ impl StackGraph {
     /// Returns an iterator of all of the nodes in the graph.  (Note that because we're only
     /// returning _handles_, this iterator does not retain a reference to the `StackGraph`.)
     pub fn iter_nodes(&self) -> impl Iterator<Item = Handle<Node>> {
-        self.nodes.iter_handles()
+        let handles = self.nodes.iter_handles();
+        if self.removed_nodes.iter().next().is_none() {
+            return Either::Left(handles);
+        }
+        Either::Right(
+            handles
+                .filter(|node| !self.removed_nodes.contains(*node))
+                .collect::<Vec<_>>()
+                .into_iter(),
+        )
     }
 
     /// Returns the handle to the node with a particular ID, if it exists.
@@ -1202,6 +1270,13 @@ impl NodeIDHandles {
         NodeID::new_in_file(file, local_id)
     }
 
+    fn remove_file(&mut self, file: Handle<File>) -> Vec<Handle<Node>> {
+        match self.files.get_mut(file) {
+            Some(file_entry) => std::mem::take(file_entry).into_iter().flatten().collect(),
+            None => Vec::new(),
+        }
+    }
+
     fn nodes_for_file(&self, file: Handle<File>) -> impl Iterator<Item = Handle<Node>> + '_ {
         let file_entry = match self.files.get(file) {
             Some(file_entry) => file_entry,
@@ -1295,6 +1370,7 @@ pub struct Edge {
 pub(crate) struct OutgoingEdge {
     sink: Handle<Node>,
     precedence: i32,
+    kind: ControlledOption<Handle<InternedString>>,
 }
 
 impl StackGraph {
@@ -1302,12 +1378,40 @@ impl StackGraph {
     pub fn add_edge(&mut self, source: Handle<Node>, sink: Handle<Node>, precedence: i32) {
         let edges = &mut self.outgoing_edges[source];
         if let Err(index) = edges.binary_search_by_key(&sink, |o| o.sink) {
-            edges.insert(index, OutgoingEdge { sink, precedence });
-            self.incoming_edges[sink] += Degree::One;
+            edges.insert(
+                index,
+                OutgoingEdge {
+                    sink,
+                    precedence,
+                    kind: ControlledOption::none(),
+                },
+            );
+            self.incoming_edges[sink] += 1;
+        }
+    }
+
+    /// Removes an edge from the stack graph, together with its kind and debug info.  Returns
+    /// whether the edge existed.
+    pub fn remove_edge(&mut self, source: Handle<Node>, sink: Handle<Node>) -> bool {
+        let edges = match self.outgoing_edges.get_mut(source) {
+            Some(edges) => edges,
+            None => return false,
+        };
+        let index = match edges.binary_search_by_key(&sink, |o| o.sink) {
+            Ok(index) => index,
+            Err(_) => return false,
+        };
+        edges.remove(index);
+        self.incoming_edges[sink] -= 1;
+        if let Some(edge_debug_info) = self.edge_debug_info.get_mut(source) {
+            if let Ok(index) = edge_debug_info.binary_search_by_key(&sink, |e| e.0) {
+                edge_debug_info.remove(index);
+            }
         }
+        true
     }
 
-    /// Removes an edge from the stack graph.
+    /// Sets the precedence of an existing edge in the stack graph.
     pub fn set_edge_precedence(
         &mut self,
         source: Handle<Node>,
@@ -1320,6 +1424,32 @@ impl StackGraph {
         }
     }
 
+    /// Returns the kind of an edge, if the edge exists and has one.  Kinds are arbitrary strings
+    /// (e.g. `import` or `lexical`) that describe why an edge exists.  They are not used during
+    /// path finding, but are preserved when the graph is serialized.
+    pub fn edge_kind(
+        &self,
+        source: Handle<Node>,
+        sink: Handle<Node>,
+    ) -> Option<Handle<InternedString>> {
+        let edges = self.outgoing_edges.get(source)?;
+        let index = edges.binary_search_by_key(&sink, |o| o.sink).ok()?;
+        edges[index].kind.into_option()
+    }
+
+    /// Sets or clears the kind of an existing edge in the stack graph.
+    pub fn set_edge_kind(
+        &mut self,
+        source: Handle<Node>,
+        sink: Handle<Node>,
+        kind: Option<Handle<InternedString>>,
+    ) {
+        let edges = &mut self.outgoing_edges[source];
+        if let Ok(index) = edges.binary_search_by_key(&sink, |o| o.sink) {
+            edges[index].kind = kind.into();
+        }
+    }
+
     /// Returns an iterator of all of the edges that begin at a particular source node.
     pub fn outgoing_edges(&self, source: Handle<Node>) -> impl Iterator<Item = Edge> + '_ {
         match self.outgoing_edges.get(source) {
@@ -1334,7 +1464,7 @@ impl StackGraph {
 
     /// Returns the number of edges that end at a particular sink node.
     pub fn incoming_edge_degree(&self, sink: Handle<Node>) -> Degree {
-        self.incoming_edges[sink]
+        Degree::from_count(self.incoming_edges.get(sink).copied().unwrap_or(0))
     }
 }
 
@@ -1359,6 +1489,10 @@ pub struct SourceInfo {
     /// The fully qualified name is a representation of the symbol that captures its name and its
     /// embedded context (e.g. `foo.bar` for the symbol `bar` defined in the module `foo`).
     pub fully_qualified_name: ControlledOption<Handle<InternedString>>,
+    /// An identifier for this node that does not depend on the order in which the nodes of its
+    /// file were created, so that it stays the same when the file is indexed again.  See
+    /// [`StackGraph::assign_stable_ids`][].
+    pub stable_id: ControlledOption<Handle<InternedString>>,
 }
 
 impl StackGraph {
@@ -1372,6 +1506,104 @@ impl StackGraph {
     pub fn source_info_mut(&mut self, node: Handle<Node>) -> &mut SourceInfo {
         &mut self.source_info[node]
     }
+
+    /// Assigns stable IDs to the nodes of a file that have a source span.  The stable ID of a
+    /// node is a hash of its path in the file: the stable key of the innermost definition whose
+    /// definiens span contains the node, or the file name if there is none, followed by the kind
+    /// and symbol of the node, and the number of nodes with the same kind and symbol that come
+    /// before it in that definition.  Absolute positions are not part of the ID, so it does not
+    /// change when nodes are created in a different order, or when lines are added or removed
+    /// outside of the node and its siblings.  Nodes with identical spans are ordered by their
+    /// local IDs.  Nodes without a span are not changed.
+    pub fn assign_stable_ids(&mut self, file: Handle<File>) {
+        let mut nodes = Vec::new();
+        for node in self.nodes_for_file(file) {
+            let source_info = match self.source_info(node) {
+                Some(source_info) if source_info.span != lsp_positions::Span::default() => {
+                    source_info
+                }
+                _ => continue,
+            };
+            let definiens = Some(&source_info.definiens_span)
+                .filter(|span| self[node].is_definition() && **span != Default::default());
+            let span = definiens.unwrap_or(&source_info.span);
+            nodes.push((
+                node,
+                span.start.clone(),
+                span.end.clone(),
+                definiens.is_some(),
+            ));
+        }
+        // enclosing definitions come before the nodes they contain
+        nodes.sort_by(|(a, a_start, a_end, a_def), (b, b_start, b_end, b_def)| {
+            a_start
+                .cmp(b_start)
+                .then_with(|| b_end.cmp(a_end))
+                .then_with(|| b_def.cmp(a_def))
+                .then_with(|| self[*a].id().local_id().cmp(&self[*b].id().local_id()))
+        });
+
+        let mut enclosing: Vec<(lsp_positions::Position, String)> = Vec::new();
+        let mut occurrences = HashMap::new();
+        for (node, _, end, is_enclosing) in nodes {
+            while matches!(enclosing.last(), Some((enclosing_end, _)) if *enclosing_end < end) {
+                enclosing.pop();
+            }
+            let mut key = format!(
+                "{}\0{}\0{}",
+                enclosing
+                    .last()
+                    .map_or(self[file].name(), |(_, key)| key.as_str()),
+                node_kind(&self[node]),
+                self[node].symbol().map_or("", |symbol| &self[symbol]),
+            );
+            let occurrence = occurrences.entry(key.clone()).or_insert(0usize);
+            key.push_str(&format!("\0{}", occurrence));
+            *occurrence += 1;
+            let stable_id = self.add_string(&format!("{:016x}", stable_hash(key.as_bytes())));
+            self.source_info_mut(node).stable_id = ControlledOption::some(stable_id);
+            if is_enclosing {
+                enclosing.push((end, key));
+            }
+        }
+    }
+
+    /// Returns the node with the given stable ID, if there is one.  This looks at the source
+    /// info of every node, so callers that look up many IDs should build their own index.
+    pub fn node_for_stable_id(&self, stable_id: &str) -> Option<Handle<Node>> {
+        let stable_id = *self.string_handles.get(stable_id)?;
+        self.iter_nodes().find(|node| {
+            matches!(
+                self.source_info(*node),
+                Some(source_info) if source_info.stable_id.into_option() == Some(stable_id)
+            )
+        })
+    }
+}
+
+/// Returns the name of the kind of a node, as it is used in stable IDs.
+fn node_kind(node: &Node) -> &'static str {
+    match node {
+        Node::DropScopes(_) => "drop_scopes",
+        Node::JumpTo(_) => "jump_to_scope",
+        Node::PopScopedSymbol(_) => "pop_scoped_symbol",
+        Node::PopSymbol(_) => "pop_symbol",
+        Node::PushScopedSymbol(_) => "push_scoped_symbol",
+        Node::PushSymbol(_) => "push_symbol",
+        Node::Root(_) => "root",
+        Node::Scope(_) => "scope",
+    }
+}
+
+/// Computes a 64-bit FNV-1a hash, whose result, unlike the hashers of the standard library, does
+/// not depend on the platform or the Rust version.
+fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
 }
 
 //-------------------------------------------------------------------------------------------------
@@ -1455,6 +1687,17 @@ impl Default for Degree {
     }
 }
 
+impl Degree {
+    /// Returns the degree of a node with the given number of edges or paths.
+    pub(crate) fn from_count(count: u32) -> Degree {
+        match count {
+            0 => Self::Zero,
+            1 => Self::One,
+            _ => Self::Multiple,
+        }
+    }
+}
+
 impl std::ops::Add for Degree {
     type Output = Self;
     fn add(self, rhs: Self) -> Self::Output {
@@ -1480,11 +1723,13 @@ pub struct StackGraph {
     string_handles: FxHashMap<&'static str, Handle<InternedString>>,
     pub(crate) files: Arena<File>,
     file_handles: FxHashMap<&'static str, Handle<File>>,
+    removed_files: HandleSet<File>,
     pub(crate) nodes: Arena<Node>,
+    removed_nodes: HandleSet<Node>,
     pub(crate) source_info: SupplementalArena<Node, SourceInfo>,
     node_id_handles: NodeIDHandles,
     outgoing_edges: SupplementalArena<Node, SmallVec<[OutgoingEdge; 4]>>,
-    incoming_edges: SupplementalArena<Node, Degree>,
+    incoming_edges: SupplementalArena<Node, u32>,
     pub(crate) node_debug_info: SupplementalArena<Node, DebugInfo>,
     pub(crate) edge_debug_info: SupplementalArena<Node, SmallVec<[(Handle<Node>, DebugInfo); 4]>>,
 }
@@ -1620,6 +1865,11 @@ impl StackGraph {
                             .into(),
                         definiens_span: source_info.definiens_span.clone(),
                         fully_qualified_name: ControlledOption::default(),
+                        stable_id: source_info
+                            .stable_id
+                            .into_option()
+                            .map(|id| self.add_string(&other[id]))
+                            .into(),
                     };
                 }
                 if let Some(debug_info) = other.node_debug_info(other_node) {
@@ -1637,11 +1887,13 @@ impl StackGraph {
             }
             for other_node in nodes.keys().cloned() {
                 for other_edge in other.outgoing_edges(other_node) {
-                    self.add_edge(
-                        nodes[&other_edge.source],
-                        nodes[&other_edge.sink],
-                        other_edge.precedence,
-                    );
+                    let source = nodes[&other_edge.source];
+                    let sink = nodes[&other_edge.sink];
+                    self.add_edge(source, sink, other_edge.precedence);
+                    if let Some(kind) = other.edge_kind(other_edge.source, other_edge.sink) {
+                        let kind = self.add_string(&other[kind]);
+                        self.set_edge_kind(source, sink, Some(kind));
+                    }
                 }
             }
         }
@@ -1663,7 +1915,9 @@ impl Default for StackGraph {
             string_handles: FxHashMap::default(),
             files: Arena::new(),
             file_handles: FxHashMap::default(),
+            removed_files: HandleSet::new(),
             nodes,
+            removed_nodes: HandleSet::new(),
             source_info: SupplementalArena::new(),
             node_id_handles: NodeIDHandles::new(),
             outgoing_edges: SupplementalArena::new(),
@@ -59,16 +59,21 @@ use std::time::{Duration, Instant};
 
 use thiserror::Error;
 
+use crate::stitching::StitchingProgress;
+
 pub mod arena;
 pub mod assert;
 pub mod c;
 pub mod cycles;
 #[macro_use]
 mod debugging;
+pub mod diff;
 pub mod graph;
+pub mod lint;
 pub mod partial;
 pub mod paths;
 pub mod serde;
+pub mod shadowing;
 pub mod stats;
 pub mod stitching;
 #[cfg(feature = "storage")]
@@ -80,6 +85,11 @@ pub mod visualization;
 /// Trait to signal that the execution is cancelled
 pub trait CancellationFlag {
     fn check(&self, at: &'static str) -> Result<(), CancellationError>;
+
+    /// Receives the progress of path stitching, which is reported once per phase, right before
+    /// the flag is checked. This allows callers that can cancel a long running operation to
+    /// follow its progress. Progress is ignored by default.
+    fn report_progress(&self, _at: &'static str, _progress: &StitchingProgress) {}
 }
 
 pub struct NoCancellation;
@@ -106,12 +116,28 @@ impl CancelAfterDuration {
 impl CancellationFlag for CancelAfterDuration {
     fn check(&self, at: &'static str) -> Result<(), CancellationError> {
         if self.start.elapsed() > self.limit {
-            return Err(CancellationError(at));
+            return Err(CancellationError::new(at));
         }
         Ok(())
     }
 }
 
 #[derive(Clone, Debug, Error)]
-#[error("Cancelled at \"{0}\"")]
-pub struct CancellationError(pub &'static str);
+#[error("Cancelled at \"{at}\"{}", .progress.map(|p| format!(" ({})", p)).unwrap_or_default())]
+pub struct CancellationError {
+    /// What was being done when the execution was cancelled.
+    pub at: &'static str,
+    /// How much work was completed, if the execution was cancelled during path stitching.
+    pub progress: Option<StitchingProgress>,
+}
+
+impl CancellationError {
+    pub fn new(at: &'static str) -> Self {
+        Self { at, progress: None }
+    }
+
+    pub fn with_progress(mut self, progress: StitchingProgress) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+}
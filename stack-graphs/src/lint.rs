@@ -0,0 +1,196 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Check stack graphs for suspicious structure.
+//!
+//! A stack graph can be well-formed according to the graph API, and still contain structure that
+//! can never be part of a path, or that will not produce useful results.  Such structure is
+//! usually caused by bugs in the rules that construct the graph.  The checks in this module find
+//! some of these problems, and report them as [`Diagnostic`][]s, without having to write test
+//! assertions that happen to exercise the broken part of the graph.
+
+use std::collections::VecDeque;
+use std::fmt::Display;
+
+use lsp_positions::Span;
+
+use crate::arena::Handle;
+use crate::arena::HandleSet;
+use crate::graph::Degree;
+use crate::graph::File;
+use crate::graph::Node;
+use crate::graph::NodeID;
+use crate::graph::StackGraph;
+
+/// The kinds of problems that are reported by the checks in this module.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DiagnosticKind {
+    /// A definition has no source info, so it cannot be reported to users.
+    DefinitionWithoutSourceInfo,
+    /// The attached scope of a _push scoped symbol_ node is not an exported scope node, so it
+    /// cannot be jumped to.
+    ScopeNotExported,
+    /// A _pop symbol_ or _pop scoped symbol_ node has no incoming edges, so no path can pop
+    /// its symbol.
+    PopWithoutIncomingEdges,
+    /// A node cannot be reached from the root node, from any reference, or from any exported
+    /// scope, so it cannot be part of any path.  Internal scope nodes are not reported, because
+    /// rules commonly create scopes for syntax nodes that end up unused.
+    UnreachableNode,
+}
+
+impl Display for DiagnosticKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DefinitionWithoutSourceInfo => write!(f, "definition has no source info"),
+            Self::ScopeNotExported => write!(f, "attached scope is not an exported scope"),
+            Self::PopWithoutIncomingEdges => write!(f, "pop node has no incoming edges"),
+            Self::UnreachableNode => write!(f, "node is unreachable"),
+        }
+    }
+}
+
+/// A problem found in a stack graph.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    /// The node the problem was found at.
+    pub node: NodeID,
+    /// The span of the node, if it has source info.
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    fn new(graph: &StackGraph, kind: DiagnosticKind, node: Handle<Node>) -> Self {
+        Self {
+            kind,
+            node: graph[node].id(),
+            span: graph
+                .source_info(node)
+                .map(|source_info| source_info.span.clone())
+                .filter(|span| *span != Span::default()),
+        }
+    }
+
+    pub fn display<'a>(&'a self, graph: &'a StackGraph) -> impl Display + 'a {
+        DisplayDiagnostic {
+            wrapped: self,
+            graph,
+        }
+    }
+}
+
+struct DisplayDiagnostic<'a> {
+    wrapped: &'a Diagnostic,
+    graph: &'a StackGraph,
+}
+
+impl<'a> Display for DisplayDiagnostic<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.graph.node_for_id(self.wrapped.node) {
+            Some(node) => write!(f, "{}", node.display(self.graph))?,
+            None => write!(f, "{}", self.wrapped.node.display(self.graph))?,
+        }
+        if let Some(span) = &self.wrapped.span {
+            write!(
+                f,
+                " at {}:{}",
+                span.start.line + 1,
+                span.start.column.grapheme_offset + 1
+            )?;
+        }
+        write!(f, ": {}", self.wrapped.kind)
+    }
+}
+
+/// Checks all files in a stack graph, and returns the problems that were found.
+pub fn lint(graph: &StackGraph) -> Vec<Diagnostic> {
+    let reachable = find_reachable_nodes(graph);
+    graph
+        .iter_files()
+        .flat_map(|file| lint_file_nodes(graph, file, &reachable))
+        .collect()
+}
+
+/// Checks the nodes of a single file, and returns the problems that were found.  Reachability
+/// is determined using the whole graph, so the file can be reached from other files.
+pub fn lint_file(graph: &StackGraph, file: Handle<File>) -> Vec<Diagnostic> {
+    let reachable = find_reachable_nodes(graph);
+    lint_file_nodes(graph, file, &reachable)
+}
+
+fn lint_file_nodes(
+    graph: &StackGraph,
+    file: Handle<File>,
+    reachable: &HandleSet<Node>,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for node in graph.nodes_for_file(file) {
+        let value = &graph[node];
+        let has_span = matches!(
+            graph.source_info(node),
+            Some(source_info) if source_info.span != Span::default()
+        );
+        if value.is_definition() && !has_span {
+            diagnostics.push(Diagnostic::new(
+                graph,
+                DiagnosticKind::DefinitionWithoutSourceInfo,
+                node,
+            ));
+        }
+        if let Some(scope) = value.scope() {
+            if !matches!(graph.node_for_id(scope), Some(scope) if graph[scope].is_exported_scope())
+            {
+                diagnostics.push(Diagnostic::new(
+                    graph,
+                    DiagnosticKind::ScopeNotExported,
+                    node,
+                ));
+            }
+        }
+        let is_pop = matches!(value, Node::PopSymbol(_) | Node::PopScopedSymbol(_));
+        if is_pop && graph.incoming_edge_degree(node) == Degree::Zero {
+            diagnostics.push(Diagnostic::new(
+                graph,
+                DiagnosticKind::PopWithoutIncomingEdges,
+                node,
+            ));
+        } else if !reachable.contains(node) && !matches!(value, Node::Scope(_)) {
+            diagnostics.push(Diagnostic::new(
+                graph,
+                DiagnosticKind::UnreachableNode,
+                node,
+            ));
+        }
+    }
+    diagnostics
+}
+
+/// Returns the nodes that can be reached from the root node, from any reference, or from any
+/// exported scope.  Exported scopes are included because paths can jump to them.
+fn find_reachable_nodes(graph: &StackGraph) -> HandleSet<Node> {
+    let mut reachable = HandleSet::new();
+    let mut queue = graph
+        .iter_nodes()
+        .filter(|node| {
+            let value = &graph[*node];
+            value.is_root() || value.is_reference() || value.is_exported_scope()
+        })
+        .collect::<VecDeque<_>>();
+    for node in &queue {
+        reachable.add(*node);
+    }
+    while let Some(node) = queue.pop_front() {
+        for edge in graph.outgoing_edges(node) {
+            if !reachable.contains(edge.sink) {
+                reachable.add(edge.sink);
+                queue.push_back(edge.sink);
+            }
+        }
+    }
+    reachable
+}
@@ -1763,6 +1763,21 @@ impl PartialPath {
         }
     }
 
+    /// Creates a new empty partial path at the root node, which has the given symbol stack as its
+    /// pre- and postcondition.  Stitching from this path finds the definitions of the qualified
+    /// name that the symbol stack represents.
+    pub fn from_symbol_stack(symbol_stack: PartialSymbolStack) -> PartialPath {
+        PartialPath {
+            start_node: StackGraph::root_node(),
+            end_node: StackGraph::root_node(),
+            symbol_stack_precondition: symbol_stack,
+            symbol_stack_postcondition: symbol_stack,
+            scope_stack_precondition: PartialScopeStack::empty(),
+            scope_stack_postcondition: PartialScopeStack::empty(),
+            edges: PartialPathEdgeList::empty(),
+        }
+    }
+
     /// Returns whether one path shadows another.  Note that shadowing is not commutative — if path
     /// A shadows path B, the reverse is not true.
     pub fn shadows(&self, partials: &mut PartialPaths, other: &PartialPath) -> bool {
@@ -1816,6 +1831,46 @@ impl PartialPath {
             })
     }
 
+    /// Compares two paths in a canonical order, which does not depend on handles, and is therefore
+    /// the same regardless of the order in which files were loaded. Nodes are ordered by file name
+    /// and local ID. Paths are ordered by their start node, their end node, and the source nodes
+    /// and precedences of their edges. Remaining ties are broken by comparing the displayed paths,
+    /// which include their pre- and postconditions.
+    pub fn cmp_canonical(
+        &self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        other: &PartialPath,
+    ) -> std::cmp::Ordering {
+        fn node_key(graph: &StackGraph, id: NodeID) -> (Option<&str>, u32) {
+            (id.file().map(|f| graph[f].name()), id.local_id())
+        }
+        fn edge_keys<'a>(
+            graph: &'a StackGraph,
+            partials: &mut PartialPaths,
+            path: &PartialPath,
+        ) -> Vec<((Option<&'a str>, u32), i32)> {
+            path.edges
+                .iter(partials)
+                .map(|e| (node_key(graph, e.source_node_id), e.precedence))
+                .collect()
+        }
+        std::cmp::Ordering::Equal
+            .then_with(|| {
+                node_key(graph, graph[self.start_node].id())
+                    .cmp(&node_key(graph, graph[other.start_node].id()))
+            })
+            .then_with(|| {
+                node_key(graph, graph[self.end_node].id())
+                    .cmp(&node_key(graph, graph[other.end_node].id()))
+            })
+            .then_with(|| edge_keys(graph, partials, self).cmp(&edge_keys(graph, partials, other)))
+            .then_with(|| {
+                let this = self.display(graph, partials).to_string();
+                this.cmp(&other.display(graph, partials).to_string())
+            })
+    }
+
     /// Returns whether a partial path represents the start of a name binding from a reference to a
     /// definition.
     pub fn starts_at_reference(&self, graph: &StackGraph) -> bool {
@@ -143,7 +143,22 @@ impl StackGraph {
                             .as_ref()
                             .map(|st| graph.add_string(&st))
                             .into(),
-                        ..Default::default()
+                        containing_line: source_info
+                            .containing_line
+                            .as_ref()
+                            .map(|line| graph.add_string(&line))
+                            .into(),
+                        definiens_span: source_info.definiens_span.clone().unwrap_or_default(),
+                        fully_qualified_name: source_info
+                            .fully_qualified_name
+                            .as_ref()
+                            .map(|fqn| graph.add_string(&fqn))
+                            .into(),
+                        stable_id: source_info
+                            .stable_id
+                            .as_ref()
+                            .map(|id| graph.add_string(&id))
+                            .into(),
                     };
                 }
 
@@ -170,6 +185,7 @@ impl StackGraph {
             source,
             sink,
             precedence,
+            kind,
             debug_info,
         } in &self.edges.data
         {
@@ -185,6 +201,11 @@ impl StackGraph {
 
             graph.add_edge(source_handle, sink_handle, *precedence);
 
+            if let Some(kind) = kind {
+                let kind = graph.add_string(kind);
+                graph.set_edge_kind(source_handle, sink_handle, Some(kind));
+            }
+
             // load debug-info of each node
             if let Some(debug_info) = debug_info {
                 *graph.edge_debug_info_mut(source_handle, sink_handle) = debug_info
@@ -200,6 +221,22 @@ impl StackGraph {
         }
         Ok(())
     }
+
+    /// Renames a file, updating all references to it in nodes and edges.
+    pub fn rename_file(&mut self, from: &str, to: &str) {
+        for file in &mut self.files.data {
+            if file == from {
+                *file = to.to_string();
+            }
+        }
+        for node in &mut self.nodes.data {
+            node.rename_file(from, to);
+        }
+        for edge in &mut self.edges.data {
+            edge.source.rename_file(from, to);
+            edge.sink.rename_file(from, to);
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -293,7 +330,30 @@ pub enum Node {
 }
 
 impl Node {
-    fn source_info(&self) -> Option<&SourceInfo> {
+    pub fn id(&self) -> &NodeID {
+        match self {
+            Self::DropScopes { id, .. } => id,
+            Self::JumpToScope { id, .. } => id,
+            Self::PopScopedSymbol { id, .. } => id,
+            Self::PopSymbol { id, .. } => id,
+            Self::PushScopedSymbol { id, .. } => id,
+            Self::PushSymbol { id, .. } => id,
+            Self::Root { id, .. } => id,
+            Self::Scope { id, .. } => id,
+        }
+    }
+
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            Self::PopScopedSymbol { symbol, .. } => Some(symbol),
+            Self::PopSymbol { symbol, .. } => Some(symbol),
+            Self::PushScopedSymbol { symbol, .. } => Some(symbol),
+            Self::PushSymbol { symbol, .. } => Some(symbol),
+            _ => None,
+        }
+    }
+
+    pub fn source_info(&self) -> Option<&SourceInfo> {
         match self {
             Self::DropScopes { source_info, .. } => source_info,
             Self::JumpToScope { source_info, .. } => source_info,
@@ -307,7 +367,7 @@ impl Node {
         .as_ref()
     }
 
-    fn debug_info(&self) -> Option<&DebugInfo> {
+    pub fn debug_info(&self) -> Option<&DebugInfo> {
         match self {
             Self::DropScopes { debug_info, .. } => debug_info,
             Self::JumpToScope { debug_info, .. } => debug_info,
@@ -320,6 +380,22 @@ impl Node {
         }
         .as_ref()
     }
+
+    fn rename_file(&mut self, from: &str, to: &str) {
+        match self {
+            Self::PushScopedSymbol { id, scope, .. } => {
+                id.rename_file(from, to);
+                scope.rename_file(from, to);
+            }
+            Self::DropScopes { id, .. }
+            | Self::JumpToScope { id, .. }
+            | Self::PopScopedSymbol { id, .. }
+            | Self::PopSymbol { id, .. }
+            | Self::PushSymbol { id, .. }
+            | Self::Root { id, .. }
+            | Self::Scope { id, .. } => id.rename_file(from, to),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -332,6 +408,11 @@ impl Node {
 pub struct SourceInfo {
     pub span: lsp_positions::Span,
     pub syntax_type: Option<String>,
+    pub containing_line: Option<String>,
+    /// `None` if the node has no definiens, which is represented by the default span in the graph.
+    pub definiens_span: Option<lsp_positions::Span>,
+    pub fully_qualified_name: Option<String>,
+    pub stable_id: Option<String>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -353,7 +434,7 @@ pub struct DebugEntry {
     pub value: String,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(
     feature = "serde",
     serde_with::skip_serializing_none, // must come before derive
@@ -404,6 +485,12 @@ impl NodeID {
             .node_for_id(value)
             .ok_or_else(|| Error::NodeNotFound(self.clone()))?)
     }
+
+    pub fn rename_file(&mut self, from: &str, to: &str) {
+        if self.file.as_deref() == Some(from) {
+            self.file = Some(to.to_string());
+        }
+    }
 }
 
 impl std::fmt::Display for NodeID {
@@ -437,6 +524,7 @@ pub struct Edge {
     pub source: NodeID,
     pub sink: NodeID,
     pub precedence: i32,
+    pub kind: Option<String>,
     pub debug_info: Option<DebugInfo>,
 }
 
@@ -473,6 +561,17 @@ impl crate::graph::StackGraph {
         self.source_info(handle).map(|info| SourceInfo {
             span: info.span.clone(),
             syntax_type: info.syntax_type.into_option().map(|ty| self[ty].to_owned()),
+            containing_line: info
+                .containing_line
+                .into_option()
+                .map(|line| self[line].to_owned()),
+            definiens_span: (info.definiens_span != lsp_positions::Span::default())
+                .then(|| info.definiens_span.clone()),
+            fully_qualified_name: info
+                .fully_qualified_name
+                .into_option()
+                .map(|fqn| self[fqn].to_owned()),
+            stable_id: info.stable_id.into_option().map(|id| self[id].to_owned()),
         })
     }
 
@@ -571,6 +670,9 @@ impl crate::graph::StackGraph {
                             source: self.filter_node(filter, self[e.source].id()),
                             sink: self.filter_node(filter, self[e.sink].id()),
                             precedence: e.precedence,
+                            kind: self
+                                .edge_kind(e.source, e.sink)
+                                .map(|kind| self[kind].to_owned()),
                             debug_info: self.filter_edge_debug_info(filter, e.source, e.sink),
                         })
                 })
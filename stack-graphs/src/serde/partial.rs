@@ -79,6 +79,17 @@ impl PartialPath {
             edges: self.edges.to_partial_path_edge_list(graph, partials)?,
         })
     }
+
+    /// Renames a file, updating all references to it in the path's nodes, stacks, and edges.
+    pub fn rename_file(&mut self, from: &str, to: &str) {
+        self.start_node.rename_file(from, to);
+        self.end_node.rename_file(from, to);
+        self.symbol_stack_precondition.rename_file(from, to);
+        self.symbol_stack_postcondition.rename_file(from, to);
+        self.scope_stack_precondition.rename_file(from, to);
+        self.scope_stack_postcondition.rename_file(from, to);
+        self.edges.rename_file(from, to);
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -129,6 +140,12 @@ impl PartialScopeStack {
         }
         Ok(value)
     }
+
+    pub fn rename_file(&mut self, from: &str, to: &str) {
+        for scope in &mut self.scopes {
+            scope.rename_file(from, to);
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -160,7 +177,7 @@ impl ScopeStackVariable {
 #[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
 pub struct PartialSymbolStack {
     pub(crate) symbols: Vec<PartialScopedSymbol>,
-    variable: Option<SymbolStackVariable>,
+    pub(crate) variable: Option<SymbolStackVariable>,
 }
 
 impl PartialSymbolStack {
@@ -201,6 +218,12 @@ impl PartialSymbolStack {
         }
         Ok(value)
     }
+
+    pub fn rename_file(&mut self, from: &str, to: &str) {
+        for symbol in &mut self.symbols {
+            symbol.rename_file(from, to);
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -231,7 +254,7 @@ impl SymbolStackVariable {
 )]
 #[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
 pub struct PartialScopedSymbol {
-    symbol: String,
+    pub(crate) symbol: String,
     pub(crate) scopes: Option<PartialScopeStack>,
 }
 
@@ -264,6 +287,12 @@ impl PartialScopedSymbol {
                 .into(),
         })
     }
+
+    pub fn rename_file(&mut self, from: &str, to: &str) {
+        if let Some(scopes) = &mut self.scopes {
+            scopes.rename_file(from, to);
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -305,6 +334,12 @@ impl PartialPathEdgeList {
         }
         Ok(value)
     }
+
+    pub fn rename_file(&mut self, from: &str, to: &str) {
+        for edge in &mut self.edges {
+            edge.source.rename_file(from, to);
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -0,0 +1,122 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Decide which of the definitions found for a reference are visible.
+//!
+//! Path stitching finds _all_ complete paths that start at a reference, and each of those paths
+//! ends at a definition that the reference might refer to.  Most languages have rules that make
+//! some of those definitions invisible, for instance because a local variable hides a global
+//! one with the same name.  We say that the path to the visible definition _shadows_ the path to
+//! the hidden one.
+//!
+//! Different languages have different shadowing rules, so the rule that is used is defined by a
+//! [`ShadowingPolicy`][].  The default policy, [`EdgePrecedence`][], uses the precedences of the
+//! edges in the stack graph to decide.  [`ClosestScope`][] and [`FirstDeclaration`][] are
+//! provided for languages where the closest or the earliest definition is the visible one.
+
+use crate::graph::StackGraph;
+use crate::partial::PartialPath;
+use crate::partial::PartialPaths;
+
+/// A rule that decides whether one complete path shadows another.
+pub trait ShadowingPolicy {
+    /// Returns whether `path` shadows `other`.  Both paths are complete paths that start at the
+    /// same reference.  Note that shadowing is not commutative — if `path` shadows `other`, the
+    /// reverse must not be true.
+    fn shadows(
+        &self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        path: &PartialPath,
+        other: &PartialPath,
+    ) -> bool;
+
+    /// Returns whether `path` is shadowed by any of the complete paths in `paths`, which must all
+    /// start at the same reference as `path`.
+    fn is_shadowed(
+        &self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        path: &PartialPath,
+        paths: &[PartialPath],
+    ) -> bool {
+        paths
+            .iter()
+            .any(|other| self.shadows(graph, partials, other, path))
+    }
+}
+
+/// The default shadowing policy.  A path shadows another if they follow the same edges up to
+/// some node, and the next edge of the path has a higher precedence than the next edge of the
+/// other.  See [`PartialPath::shadows`][].
+///
+/// [`PartialPath::shadows`]: ../partial/struct.PartialPath.html#method.shadows
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EdgePrecedence;
+
+impl ShadowingPolicy for EdgePrecedence {
+    fn shadows(
+        &self,
+        _graph: &StackGraph,
+        partials: &mut PartialPaths,
+        path: &PartialPath,
+        other: &PartialPath,
+    ) -> bool {
+        path.shadows(partials, other)
+    }
+}
+
+/// A shadowing policy where the closest definition wins.  A path shadows another if it consists
+/// of fewer edges, which means that the definition it ends at is found in a scope that is closer
+/// to the reference.  Edge precedences are ignored.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ClosestScope;
+
+impl ShadowingPolicy for ClosestScope {
+    fn shadows(
+        &self,
+        _graph: &StackGraph,
+        _partials: &mut PartialPaths,
+        path: &PartialPath,
+        other: &PartialPath,
+    ) -> bool {
+        path.edges.len() < other.edges.len()
+    }
+}
+
+/// A shadowing policy where the first declaration wins.  A path shadows another if both end at
+/// definitions in the same file, and the definition of the path appears earlier in the source
+/// than the definition of the other.  Definitions in different files, or without source
+/// information, never shadow each other.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FirstDeclaration;
+
+impl ShadowingPolicy for FirstDeclaration {
+    fn shadows(
+        &self,
+        graph: &StackGraph,
+        _partials: &mut PartialPaths,
+        path: &PartialPath,
+        other: &PartialPath,
+    ) -> bool {
+        let file = graph[path.end_node].file();
+        if file.is_none() || file != graph[other.end_node].file() {
+            return false;
+        }
+        let (path_info, other_info) = match (
+            graph.source_info(path.end_node),
+            graph.source_info(other.end_node),
+        ) {
+            (Some(path_info), Some(other_info)) => (path_info, other_info),
+            _ => return false,
+        };
+        let path_start = &path_info.span.start;
+        let other_start = &other_info.span.start;
+        (path_start.line, path_start.column.utf8_offset)
+            < (other_start.line, other_start.column.utf8_offset)
+    }
+}
@@ -11,7 +11,7 @@ use std::hash::Hash;
 use itertools::Itertools;
 
 /// Frequency distribution maintains the frequency of T values.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct FrequencyDistribution<T>
 where
     T: Eq + Hash,
@@ -20,6 +20,15 @@ where
     total: usize,
 }
 
+impl<T: Eq + Hash> Default for FrequencyDistribution<T> {
+    fn default() -> Self {
+        Self {
+            values: HashMap::new(),
+            total: 0,
+        }
+    }
+}
+
 impl<T: Eq + Hash> FrequencyDistribution<T> {
     pub fn record(&mut self, value: T) {
         *self.values.entry(value).or_default() += 1;
@@ -36,6 +45,11 @@ impl<T: Eq + Hash> FrequencyDistribution<T> {
         return self.values.len();
     }
 
+    /// Returns an iterator over the unique recorded values and their frequencies.
+    pub fn iter(&self) -> impl Iterator<Item = (&T, usize)> + '_ {
+        self.values.iter().map(|(value, count)| (value, *count))
+    }
+
     pub fn frequencies(&self) -> FrequencyDistribution<usize> {
         let mut fs = FrequencyDistribution::default();
         for count in self.values.values() {
@@ -36,11 +36,15 @@
 //! [`PathStitcher`]: struct.PathStitcher.html
 
 use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 #[cfg(feature = "copious-debugging")]
 use std::fmt::Display;
 
+use either::Either;
+use enumset::EnumSet;
 use itertools::izip;
 use itertools::Itertools;
 
@@ -53,12 +57,14 @@ use crate::arena::ListCell;
 use crate::arena::SupplementalArena;
 use crate::cycles::Appendables;
 use crate::cycles::AppendingCycleDetector;
+use crate::cycles::PrependingCycleDetector;
 use crate::cycles::SimilarPathDetector;
 use crate::cycles::SimilarPathStats;
 use crate::graph::Degree;
 use crate::graph::Edge;
 use crate::graph::File;
 use crate::graph::Node;
+use crate::graph::NodeID;
 use crate::graph::StackGraph;
 use crate::graph::Symbol;
 use crate::partial::Cyclicity;
@@ -67,6 +73,7 @@ use crate::partial::PartialPaths;
 use crate::partial::PartialSymbolStack;
 use crate::paths::Extend;
 use crate::paths::PathResolutionError;
+use crate::shadowing::ShadowingPolicy;
 use crate::stats::FrequencyDistribution;
 use crate::CancellationError;
 use crate::CancellationFlag;
@@ -212,6 +219,37 @@ where
     fn get_graph_partials_and_db(&mut self) -> (&StackGraph, &mut PartialPaths, &Db);
 }
 
+/// A trait to support finding candidates for extending partial paths backwards, by prepending
+/// candidates to them.  The candidates are represented by handles `H`, which are mapped to partial
+/// paths using the database `Db`.  Loading errors are reported as values of the `Err` type.
+pub trait BackwardCandidates<H, Db, Err>
+where
+    Db: ToAppendable<H, PartialPath>,
+{
+    /// Load possible backward candidates for the given partial path into this candidates instance.
+    /// Must be called before [`get_backward_candidates`] to allow lazy-loading implementations.
+    fn load_backward_candidates(
+        &mut self,
+        _path: &PartialPath,
+        _cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), Err> {
+        Ok(())
+    }
+
+    /// Get backward candidates for extending the given partial path and add them to the provided
+    /// result instance. If this instance loads data lazily, this only considers previously loaded
+    /// data.
+    fn get_backward_candidates<R>(&mut self, path: &PartialPath, result: &mut R)
+    where
+        R: std::iter::Extend<H>;
+
+    /// Get the number of available candidates that share the given path's start node.
+    fn get_forking_candidate_degree(&self, path: &PartialPath) -> Degree;
+
+    /// Get the graph, partial path arena, and database backing this candidates instance.
+    fn get_graph_partials_and_db(&mut self) -> (&StackGraph, &mut PartialPaths, &Db);
+}
+
 //-------------------------------------------------------------------------------------------------
 // FileEdges
 
@@ -282,6 +320,7 @@ impl ToAppendable<Edge, Edge> for GraphEdges {
 /// needed.
 pub struct Database {
     pub(crate) partial_paths: Arena<PartialPath>,
+    removed_paths: HandleSet<PartialPath>,
     pub(crate) local_nodes: HandleSet<Node>,
     symbol_stack_keys: ListArena<Handle<Symbol>>,
     symbol_stack_key_cache: HashMap<SymbolStackCacheKey, SymbolStackKeyHandle>,
@@ -292,7 +331,15 @@ pub struct Database {
         SupplementalArena<SymbolStackKeyCell, Vec<Handle<PartialPath>>>,
     root_paths_by_precondition_without_variable:
         SupplementalArena<SymbolStackKeyCell, Vec<Handle<PartialPath>>>,
-    incoming_paths: SupplementalArena<Node, Degree>,
+    paths_by_end_node: SupplementalArena<Node, Vec<Handle<PartialPath>>>,
+    root_paths_by_postcondition_prefix:
+        SupplementalArena<SymbolStackKeyCell, Vec<Handle<PartialPath>>>,
+    root_paths_by_postcondition_with_variable:
+        SupplementalArena<SymbolStackKeyCell, Vec<Handle<PartialPath>>>,
+    root_paths_by_postcondition_without_variable:
+        SupplementalArena<SymbolStackKeyCell, Vec<Handle<PartialPath>>>,
+    incoming_paths: SupplementalArena<Node, u32>,
+    outgoing_paths: SupplementalArena<Node, u32>,
 }
 
 impl Database {
@@ -300,6 +347,7 @@ impl Database {
     pub fn new() -> Database {
         Database {
             partial_paths: Arena::new(),
+            removed_paths: HandleSet::new(),
             local_nodes: HandleSet::new(),
             symbol_stack_keys: List::new_arena(),
             symbol_stack_key_cache: HashMap::new(),
@@ -307,7 +355,12 @@ impl Database {
             root_paths_by_precondition_prefix: SupplementalArena::new(),
             root_paths_by_precondition_with_variable: SupplementalArena::new(),
             root_paths_by_precondition_without_variable: SupplementalArena::new(),
+            paths_by_end_node: SupplementalArena::new(),
+            root_paths_by_postcondition_prefix: SupplementalArena::new(),
+            root_paths_by_postcondition_with_variable: SupplementalArena::new(),
+            root_paths_by_postcondition_without_variable: SupplementalArena::new(),
             incoming_paths: SupplementalArena::new(),
+            outgoing_paths: SupplementalArena::new(),
         }
     }
 
@@ -316,6 +369,7 @@ impl Database {
     #[cfg_attr(not(feature = "storage"), allow(dead_code))]
     pub(crate) fn clear(&mut self) {
         self.partial_paths.clear();
+        self.removed_paths.clear();
         self.local_nodes.clear();
         self.symbol_stack_keys.clear();
         self.symbol_stack_key_cache.clear();
@@ -323,7 +377,12 @@ impl Database {
         self.root_paths_by_precondition_prefix.clear();
         self.root_paths_by_precondition_with_variable.clear();
         self.root_paths_by_precondition_without_variable.clear();
+        self.paths_by_end_node.clear();
+        self.root_paths_by_postcondition_prefix.clear();
+        self.root_paths_by_postcondition_with_variable.clear();
+        self.root_paths_by_postcondition_without_variable.clear();
         self.incoming_paths.clear();
+        self.outgoing_paths.clear();
     }
 
     /// Adds a partial path to this database.  We do not deduplicate partial paths in any way; it's
@@ -346,6 +405,7 @@ impl Database {
             path.display(graph, partials)
         );
         let symbol_stack_precondition = path.symbol_stack_precondition;
+        let symbol_stack_postcondition = path.symbol_stack_postcondition;
         let handle = self.partial_paths.add(path);
 
         // If the partial path starts at the root node, index it by its symbol stack precondition.
@@ -373,10 +433,105 @@ impl Database {
             self.paths_by_start_node[start_node].push(handle);
         }
 
-        self.incoming_paths[end_node] += Degree::One;
+        // Do the same for the end of the partial path, so that we can also find candidates when
+        // extending paths backwards.
+        if graph[end_node].is_root() {
+            let mut key = SymbolStackKey::from_partial_symbol_stack(
+                partials,
+                self,
+                symbol_stack_postcondition,
+            );
+            if !key.is_empty() {
+                match symbol_stack_postcondition.has_variable() {
+                    true => self.root_paths_by_postcondition_with_variable[key.back_handle()]
+                        .push(handle),
+                    false => self.root_paths_by_postcondition_without_variable[key.back_handle()]
+                        .push(handle),
+                }
+            }
+            while key.pop_back(self).is_some() && !key.is_empty() {
+                self.root_paths_by_postcondition_prefix[key.back_handle()].push(handle);
+            }
+        } else {
+            self.paths_by_end_node[end_node].push(handle);
+        }
+
+        self.incoming_paths[end_node] += 1;
+        self.outgoing_paths[start_node] += 1;
         handle
     }
 
+    /// Removes all of the partial paths in this database that touch any node in the given file,
+    /// either as their start or end node or via one of their edges.  Handles to the remaining
+    /// partial paths stay valid; handles to the removed ones no longer appear in
+    /// [`iter_partial_paths`][] or in any of the candidate lookups.
+    ///
+    /// This works whether or not the file has already been removed from the stack graph with
+    /// [`StackGraph::remove_file`][].
+    ///
+    /// [`iter_partial_paths`]: #method.iter_partial_paths
+    /// [`StackGraph::remove_file`]: ../graph/struct.StackGraph.html#method.remove_file
+    pub fn remove_paths_for_file(
+        &mut self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        file: Handle<File>,
+    ) {
+        let mut removed_any = false;
+        for handle in self.partial_paths.iter_handles() {
+            if self.removed_paths.contains(handle) {
+                continue;
+            }
+            let path = self.partial_paths.get_mut(handle);
+            let touches_file = graph[path.start_node].file() == Some(file)
+                || graph[path.end_node].file() == Some(file)
+                || path
+                    .edges
+                    .iter_unordered(partials)
+                    .any(|edge| edge.source_node_id.file() == Some(file));
+            if !touches_file {
+                continue;
+            }
+            self.removed_paths.add(handle);
+            self.incoming_paths[path.end_node] -= 1;
+            self.outgoing_paths[path.start_node] -= 1;
+            removed_any = true;
+        }
+        if !removed_any {
+            return;
+        }
+
+        let removed_paths = &self.removed_paths;
+        let retain = |paths: &mut Vec<Handle<PartialPath>>| {
+            paths.retain(|path| !removed_paths.contains(*path))
+        };
+        self.paths_by_start_node
+            .iter_mut()
+            .for_each(|(_, p)| retain(p));
+        self.paths_by_end_node
+            .iter_mut()
+            .for_each(|(_, p)| retain(p));
+        for index in [
+            &mut self.root_paths_by_precondition_prefix,
+            &mut self.root_paths_by_precondition_with_variable,
+            &mut self.root_paths_by_precondition_without_variable,
+            &mut self.root_paths_by_postcondition_prefix,
+            &mut self.root_paths_by_postcondition_with_variable,
+            &mut self.root_paths_by_postcondition_without_variable,
+        ] {
+            index.iter_mut().for_each(|(_, p)| retain(p));
+        }
+
+        let local_nodes_in_file = self
+            .local_nodes
+            .iter()
+            .filter(|node| graph[*node].file() == Some(file))
+            .collect::<Vec<_>>();
+        for node in local_nodes_in_file {
+            self.local_nodes.remove(node);
+        }
+    }
+
     /// Find all partial paths in this database that start at the given path's end node.
     /// If the end node is the root node, returns paths with a symbol stack precondition
     /// that are compatible with the path's symbol stack post condition.
@@ -419,87 +574,126 @@ impl Database {
         // symbol stack precondition is compatible with the path.
         match symbol_stack {
             Some(symbol_stack) => {
-                let mut key =
-                    SymbolStackKey::from_partial_symbol_stack(partials, self, symbol_stack);
+                let key = SymbolStackKey::from_partial_symbol_stack(partials, self, symbol_stack);
                 copious_debugging!(
                     "      Search for symbol stack <{}>",
                     key.display(graph, self)
                 );
-                // paths that have exactly this symbol stack
-                if let Some(paths) = self
-                    .root_paths_by_precondition_without_variable
-                    .get(key.back_handle())
-                {
-                    #[cfg(feature = "copious-debugging")]
-                    {
-                        for path in paths {
-                            copious_debugging!(
-                                "        Found path with exact stack {}",
-                                self[*path].display(graph, partials)
-                            );
-                        }
-                    }
-                    result.extend(paths.iter().copied());
-                }
-                // paths that have an extension of this symbol stack
-                if symbol_stack.has_variable() {
-                    if let Some(paths) = self
-                        .root_paths_by_precondition_prefix
-                        .get(key.back_handle())
-                    {
-                        #[cfg(feature = "copious-debugging")]
-                        {
-                            for path in paths {
-                                copious_debugging!(
-                                    "        Found path with smaller stack {}",
-                                    self[*path].display(graph, partials)
-                                );
-                            }
-                        }
-                        result.extend(paths.iter().copied());
-                    }
+                self.find_root_paths_by_symbol_stack_key(
+                    graph,
+                    partials,
+                    key,
+                    symbol_stack.has_variable(),
+                    &self.root_paths_by_precondition_prefix,
+                    &self.root_paths_by_precondition_with_variable,
+                    &self.root_paths_by_precondition_without_variable,
+                    result,
+                );
+            }
+            None => {
+                copious_debugging!("      Search for all root paths");
+                self.find_all_root_paths(
+                    graph,
+                    partials,
+                    &self.root_paths_by_precondition_with_variable,
+                    &self.root_paths_by_precondition_without_variable,
+                    result,
+                );
+            }
+        }
+    }
+
+    /// Looks up root paths in one set of symbol stack indexes, returning the paths whose indexed
+    /// symbol stack is compatible with the given key.
+    #[cfg_attr(not(feature = "copious-debugging"), allow(unused_variables))]
+    #[allow(clippy::too_many_arguments)]
+    fn find_root_paths_by_symbol_stack_key<R>(
+        &self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        mut key: SymbolStackKey,
+        has_variable: bool,
+        paths_by_prefix: &SupplementalArena<SymbolStackKeyCell, Vec<Handle<PartialPath>>>,
+        paths_with_variable: &SupplementalArena<SymbolStackKeyCell, Vec<Handle<PartialPath>>>,
+        paths_without_variable: &SupplementalArena<SymbolStackKeyCell, Vec<Handle<PartialPath>>>,
+        result: &mut R,
+    ) where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        // paths that have exactly this symbol stack
+        if let Some(paths) = paths_without_variable.get(key.back_handle()) {
+            #[cfg(feature = "copious-debugging")]
+            {
+                for path in paths {
+                    copious_debugging!(
+                        "        Found path with exact stack {}",
+                        self[*path].display(graph, partials)
+                    );
                 }
-                loop {
-                    // paths that have a prefix of this symbol stack
-                    if let Some(paths) = self
-                        .root_paths_by_precondition_with_variable
-                        .get(key.back_handle())
-                    {
-                        #[cfg(feature = "copious-debugging")]
-                        {
-                            for path in paths {
-                                copious_debugging!(
-                                    "        Found path with smaller stack {}",
-                                    self[*path].display(graph, partials)
-                                );
-                            }
-                        }
-                        result.extend(paths.iter().copied());
-                    }
-                    if key.pop_back(self).is_none() {
-                        break;
+            }
+            result.extend(paths.iter().copied());
+        }
+        // paths that have an extension of this symbol stack
+        if has_variable {
+            if let Some(paths) = paths_by_prefix.get(key.back_handle()) {
+                #[cfg(feature = "copious-debugging")]
+                {
+                    for path in paths {
+                        copious_debugging!(
+                            "        Found path with smaller stack {}",
+                            self[*path].display(graph, partials)
+                        );
                     }
                 }
+                result.extend(paths.iter().copied());
             }
-            None => {
-                copious_debugging!("      Search for all root paths");
-                for (_, paths) in self
-                    .root_paths_by_precondition_with_variable
-                    .iter()
-                    .chain(self.root_paths_by_precondition_without_variable.iter())
+        }
+        loop {
+            // paths that have a prefix of this symbol stack
+            if let Some(paths) = paths_with_variable.get(key.back_handle()) {
+                #[cfg(feature = "copious-debugging")]
                 {
-                    #[cfg(feature = "copious-debugging")]
-                    {
-                        for path in paths {
-                            copious_debugging!(
-                                "        Found path {}",
-                                self[*path].display(graph, partials)
-                            );
-                        }
+                    for path in paths {
+                        copious_debugging!(
+                            "        Found path with smaller stack {}",
+                            self[*path].display(graph, partials)
+                        );
                     }
-                    result.extend(paths.iter().copied());
+                }
+                result.extend(paths.iter().copied());
+            }
+            if key.pop_back(self).is_none() {
+                break;
+            }
+        }
+    }
+
+    /// Returns all root paths in one set of symbol stack indexes.
+    #[cfg_attr(not(feature = "copious-debugging"), allow(unused_variables))]
+    fn find_all_root_paths<R>(
+        &self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        paths_with_variable: &SupplementalArena<SymbolStackKeyCell, Vec<Handle<PartialPath>>>,
+        paths_without_variable: &SupplementalArena<SymbolStackKeyCell, Vec<Handle<PartialPath>>>,
+        result: &mut R,
+    ) where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        for (_, paths) in paths_with_variable
+            .iter()
+            .chain(paths_without_variable.iter())
+        {
+            #[cfg(feature = "copious-debugging")]
+            {
+                for path in paths {
+                    copious_debugging!(
+                        "        Found path {}",
+                        self[*path].display(graph, partials)
+                    );
                 }
             }
+            result.extend(paths.iter().copied());
         }
     }
 
@@ -535,7 +729,123 @@ impl Database {
 
     /// Returns the number of paths in this database that share the given end node.
     pub fn get_incoming_path_degree(&self, end_node: Handle<Node>) -> Degree {
-        self.incoming_paths[end_node]
+        Degree::from_count(self.incoming_paths[end_node])
+    }
+
+    /// Find all partial paths in this database that end at the given path's start node.  This is
+    /// the backwards counterpart of [`find_candidate_partial_paths`][].  If the start node is the
+    /// root node, returns paths with a symbol stack postcondition that is compatible with the
+    /// path's symbol stack precondition.
+    ///
+    /// If the start node is an exported scope, paths that end in a jump node are also returned,
+    /// since they might jump to that scope.  Whether they actually do is decided when the path is
+    /// extended with them.
+    ///
+    /// [`find_candidate_partial_paths`]: #method.find_candidate_partial_paths
+    pub fn find_backward_candidate_partial_paths<R>(
+        &mut self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        path: &PartialPath,
+        result: &mut R,
+    ) where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        if graph[path.start_node].is_root() {
+            self.find_candidate_partial_paths_to_root(
+                graph,
+                partials,
+                Some(path.symbol_stack_precondition),
+                result,
+            );
+        } else {
+            self.find_candidate_partial_paths_to_node(graph, partials, path.start_node, result);
+            if graph[path.start_node].is_exported_scope() {
+                self.find_candidate_partial_paths_to_node(
+                    graph,
+                    partials,
+                    StackGraph::jump_to_node(),
+                    result,
+                );
+            }
+        }
+    }
+
+    /// Find all partial paths in this database that end at the root node, and have a symbol
+    /// stack postcondition that is compatible with a given symbol stack.
+    #[cfg_attr(not(feature = "copious-debugging"), allow(unused_variables))]
+    pub fn find_candidate_partial_paths_to_root<R>(
+        &mut self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        symbol_stack: Option<PartialSymbolStack>,
+        result: &mut R,
+    ) where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        match symbol_stack {
+            Some(symbol_stack) => {
+                let key = SymbolStackKey::from_partial_symbol_stack(partials, self, symbol_stack);
+                copious_debugging!(
+                    "      Search for symbol stack <{}> at end",
+                    key.display(graph, self)
+                );
+                self.find_root_paths_by_symbol_stack_key(
+                    graph,
+                    partials,
+                    key,
+                    symbol_stack.has_variable(),
+                    &self.root_paths_by_postcondition_prefix,
+                    &self.root_paths_by_postcondition_with_variable,
+                    &self.root_paths_by_postcondition_without_variable,
+                    result,
+                );
+            }
+            None => {
+                copious_debugging!("      Search for all paths to root");
+                self.find_all_root_paths(
+                    graph,
+                    partials,
+                    &self.root_paths_by_postcondition_with_variable,
+                    &self.root_paths_by_postcondition_without_variable,
+                    result,
+                );
+            }
+        }
+    }
+
+    /// Find all partial paths in the database that end at the given node.  As for
+    /// [`find_candidate_partial_paths_from_node`][], we don't filter the results any further.
+    ///
+    /// [`find_candidate_partial_paths_from_node`]: #method.find_candidate_partial_paths_from_node
+    #[cfg_attr(not(feature = "copious-debugging"), allow(unused_variables))]
+    pub fn find_candidate_partial_paths_to_node<R>(
+        &self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        end_node: Handle<Node>,
+        result: &mut R,
+    ) where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        copious_debugging!("      Search for end node {}", end_node.display(graph));
+        if let Some(paths) = self.paths_by_end_node.get(end_node) {
+            #[cfg(feature = "copious-debugging")]
+            {
+                for path in paths {
+                    copious_debugging!(
+                        "        Found path {}",
+                        self[*path].display(graph, partials)
+                    );
+                }
+            }
+            result.extend(paths.iter().copied());
+        }
+    }
+
+    /// Returns the number of paths in this database that share the given start node.
+    pub fn get_outgoing_path_degree(&self, start_node: Handle<Node>) -> Degree {
+        Degree::from_count(self.outgoing_paths[start_node])
     }
 
     /// Determines which nodes in the stack graph are “local”, taking into account the partial
@@ -618,7 +928,16 @@ impl Database {
     /// (Note that because we're only returning _handles_, this iterator does not retain a
     /// reference to the `Database`.)
     pub fn iter_partial_paths(&self) -> impl Iterator<Item = Handle<PartialPath>> {
-        self.partial_paths.iter_handles()
+        let handles = self.partial_paths.iter_handles();
+        if self.removed_paths.iter().next().is_none() {
+            return Either::Left(handles);
+        }
+        Either::Right(
+            handles
+                .filter(|path| !self.removed_paths.contains(*path))
+                .collect::<Vec<_>>()
+                .into_iter(),
+        )
     }
 
     pub fn ensure_both_directions(&mut self, partials: &mut PartialPaths) {
@@ -690,14 +1009,39 @@ impl ForwardCandidates<Handle<PartialPath>, PartialPath, Database, CancellationE
     }
 }
 
+impl BackwardCandidates<Handle<PartialPath>, Database, CancellationError>
+    for DatabaseCandidates<'_>
+{
+    fn get_backward_candidates<R>(&mut self, path: &PartialPath, result: &mut R)
+    where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        self.database.find_backward_candidate_partial_paths(
+            self.graph,
+            self.partials,
+            path,
+            result,
+        );
+    }
+
+    fn get_forking_candidate_degree(&self, path: &PartialPath) -> Degree {
+        self.database.get_outgoing_path_degree(path.start_node)
+    }
+
+    fn get_graph_partials_and_db(&mut self) -> (&StackGraph, &mut PartialPaths, &Database) {
+        (self.graph, self.partials, self.database)
+    }
+}
+
 /// The key type that we use to find partial paths that start from the root node and have a
-/// particular symbol stack as their precondition.
+/// particular symbol stack as their precondition, or that end at the root node and have a
+/// particular symbol stack as their postcondition.
 #[derive(Clone, Copy)]
 pub struct SymbolStackKey {
     // Note: the symbols are stored in reverse order, with the "front" of the List being the "back"
     // of the symbol stack.  That lets us easily get a handle to the back of the symbol stack, and
     // also lets us easily pops items off the back of key, which we need to do to search for all
-    // prefixes of a particular symbol stack down in `find_candidate_partial_paths_from_root`.
+    // prefixes of a particular symbol stack down in `find_root_paths_by_symbol_stack_key`.
     symbols: List<Handle<Symbol>>,
 }
 
@@ -830,6 +1174,9 @@ pub struct ForwardPartialPathStitcher<H> {
     // tracks the number of initial paths in the queue because we do not want call
     // extend_until on those
     initial_paths_in_queue: usize,
+    // in best-first mode, all paths except the initial ones are queued here instead
+    best_first_queue: BinaryHeap<BestFirstEntry<H>>,
+    best_first_sequence: usize,
     // next_iteration is a tuple of queues instead of an queue of tuples so that the path queue
     // can be cheaply exposed through the C API as a continuous memory block
     next_iteration: (
@@ -840,7 +1187,10 @@ pub struct ForwardPartialPathStitcher<H> {
     appended_paths: Appendables<H>,
     similar_path_detector: Option<SimilarPathDetector<PartialPath>>,
     check_only_join_nodes: bool,
-    max_work_per_phase: usize,
+    max_work_per_phase: Option<usize>,
+    best_first: bool,
+    work_performed: usize,
+    phases_completed: usize,
     initial_paths: usize,
     stats: Option<Stats>,
     #[cfg(feature = "copious-debugging")]
@@ -873,14 +1223,21 @@ impl<H> ForwardPartialPathStitcher<H> {
             extensions: Vec::new(),
             queue: VecDeque::new(),
             initial_paths_in_queue: initial_paths,
+            best_first_queue: BinaryHeap::new(),
+            best_first_sequence: 0,
             next_iteration,
             appended_paths,
             // By default, all paths are checked for similarity
             similar_path_detector: Some(SimilarPathDetector::new()),
             // By default, all nodes are checked for cycles and (if enabled) similarity
             check_only_join_nodes: false,
-            // By default, there's no artificial bound on the amount of work done per phase
-            max_work_per_phase: usize::MAX,
+            // By default, there's no artificial bound on the amount of work done per phase,
+            // except in best-first mode
+            max_work_per_phase: None,
+            // By default, paths are processed breadth-first
+            best_first: false,
+            work_performed: 0,
+            phases_completed: 0,
             initial_paths,
             stats: None,
             #[cfg(feature = "copious-debugging")]
@@ -902,6 +1259,16 @@ impl<H> ForwardPartialPathStitcher<H> {
         }
     }
 
+    /// Sets the maximum number of paths that the similar path detector keeps. When the budget is
+    /// exceeded, the least recently used paths are forgotten, which bounds memory use at the cost
+    /// of possibly extending similar paths again. This has no effect if similar path detection is
+    /// disabled, and must be set before stitching starts. There is no limit by default.
+    pub fn set_max_similar_paths(&mut self, max_similar_paths: Option<usize>) {
+        if let Some(similar_path_detector) = &mut self.similar_path_detector {
+            similar_path_detector.set_max_paths(max_similar_paths);
+        }
+    }
+
     /// Sets whether all nodes are checked for cycles and (if enabled) similar paths, or only nodes with multiple
     /// incoming candidates. Checking only join nodes is **unsafe** unless the database of candidates is stable
     /// between all stitching phases. If paths are added to the database from one phase to another, for example if
@@ -914,9 +1281,39 @@ impl<H> ForwardPartialPathStitcher<H> {
     /// By bounding our work this way, you can ensure that it's not possible for our CPU-bound
     /// algorithm to starve any worker threads or processes that you might be using.  If you don't
     /// call this method, then we allow ourselves to process all of the extensions of all of the
-    /// paths found in the previous phase, with no additional bound.
+    /// paths found in the previous phase, with no additional bound, or in best-first mode, of a
+    /// single path.
     pub fn set_max_work_per_phase(&mut self, max_work_per_phase: usize) {
-        self.max_work_per_phase = max_work_per_phase;
+        self.max_work_per_phase = Some(max_work_per_phase);
+    }
+
+    /// Sets whether paths are processed best-first instead of breadth-first. In best-first mode,
+    /// the queued paths are kept in a priority queue, and unless a different maximum is set with
+    /// [`set_max_work_per_phase`][Self::set_max_work_per_phase], each phase stops as soon as any
+    /// work has been performed. Paths that cross fewer files have the highest
+    /// priority, followed by paths with a higher total edge precedence, and paths with a shorter
+    /// symbol stack. This finds the most likely complete paths first, which is useful together
+    /// with a limit on the number of results. Best-first mode is disabled by default.
+    pub fn set_best_first(&mut self, best_first: bool) {
+        self.best_first = best_first;
+    }
+
+    /// Returns the total amount of work performed by all phases so far.
+    pub fn work_performed(&self) -> usize {
+        self.work_performed
+    }
+
+    /// Returns the progress of the stitcher so far. The stitcher does not know which paths are
+    /// complete, so the number of complete paths is left at zero, for the caller to fill in.
+    pub fn progress(&self) -> StitchingProgress {
+        StitchingProgress {
+            phase: self.phases_completed,
+            queued_paths: self.queue.len()
+                + self.best_first_queue.len()
+                + self.next_iteration.0.len(),
+            complete_paths: 0,
+            work_performed: self.work_performed,
+        }
     }
 
     /// Sets whether to collect statistics during stitching.
@@ -943,6 +1340,62 @@ impl<H> ForwardPartialPathStitcher<H> {
     }
 }
 
+/// Returns the priority of a path in best-first mode, where lower values are processed first.
+/// The priority is made up of the number of files the path crosses, the negated total precedence
+/// of its edges, and the length of its symbol stack postcondition.
+fn best_first_priority(
+    graph: &StackGraph,
+    partials: &PartialPaths,
+    path: &PartialPath,
+) -> (usize, i64, usize) {
+    let mut files = HashSet::new();
+    files.extend(graph[path.end_node].file());
+    let mut precedence = 0i64;
+    for edge in path.edges.iter_unordered(partials) {
+        files.extend(edge.source_node_id.file());
+        precedence += edge.precedence as i64;
+    }
+    (
+        files.len(),
+        -precedence,
+        path.symbol_stack_postcondition.len(),
+    )
+}
+
+/// A path in the best-first queue. Entries are ordered so that the entry with the lowest priority
+/// value is at the top of the heap, and entries with equal priority are processed in the order in
+/// which they were queued.
+struct BestFirstEntry<H> {
+    priority: (usize, i64, usize),
+    sequence: usize,
+    path: PartialPath,
+    cycle_detector: AppendingCycleDetector<H>,
+    has_split: bool,
+}
+
+impl<H> PartialEq for BestFirstEntry<H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<H> Eq for BestFirstEntry<H> {}
+
+impl<H> PartialOrd for BestFirstEntry<H> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<H> Ord for BestFirstEntry<H> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .priority
+            .cmp(&self.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
 impl<H: Clone> ForwardPartialPathStitcher<H> {
     /// Returns an iterator of all of the (possibly incomplete) partial paths that were encountered
     /// during the most recent phase of the algorithm.
@@ -1009,6 +1462,15 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
             };
             if cyclic {
                 copious_debugging!("      is discontinued: cyclic");
+                if let Some(stats) = &mut self.stats {
+                    stats.discarded_cycles.record(DiscardedCycle::new(
+                        graph,
+                        partials,
+                        partial_path,
+                        cycles,
+                        CycleDirection::Forward,
+                    ));
+                }
                 return 0;
             }
         }
@@ -1109,7 +1571,9 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
 
     /// Returns whether the algorithm has completed.
     pub fn is_complete(&self) -> bool {
-        self.queue.is_empty() && self.next_iteration.0.is_empty()
+        self.queue.is_empty()
+            && self.best_first_queue.is_empty()
+            && self.next_iteration.0.is_empty()
     }
 
     /// Runs the next phase of the algorithm.  We will have built up a set of incomplete partial
@@ -1137,11 +1601,37 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
             self.next_iteration.1.drain(..),
             self.next_iteration.2.drain(..),
         ));
+        let max_work_per_phase = if self.best_first {
+            // initial paths are kept in the queue, because extend_while is not called on them
+            let (graph, partials, _) = candidates.get_graph_partials_and_db();
+            for (path, cycle_detector, has_split) in self.queue.drain(self.initial_paths_in_queue..)
+            {
+                self.best_first_queue.push(BestFirstEntry {
+                    priority: best_first_priority(graph, partials, &path),
+                    sequence: self.best_first_sequence,
+                    path,
+                    cycle_detector,
+                    has_split,
+                });
+                self.best_first_sequence += 1;
+            }
+            self.max_work_per_phase.unwrap_or(1)
+        } else {
+            self.max_work_per_phase.unwrap_or(usize::MAX)
+        };
         if let Some(stats) = &mut self.stats {
-            stats.queued_paths_per_phase.record(self.queue.len());
+            stats
+                .queued_paths_per_phase
+                .record(self.queue.len() + self.best_first_queue.len());
         }
         let mut work_performed = 0;
-        while let Some((partial_path, cycle_detector, has_split)) = self.queue.pop_front() {
+        while let Some((partial_path, cycle_detector, has_split)) =
+            self.queue.pop_front().or_else(|| {
+                self.best_first_queue
+                    .pop()
+                    .map(|entry| (entry.path, entry.cycle_detector, entry.has_split))
+            })
+        {
             let (graph, partials, _) = candidates.get_graph_partials_and_db();
             copious_debugging!(
                 "--> Candidate partial path {}",
@@ -1157,10 +1647,12 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
                 continue;
             }
             work_performed += self.extend(candidates, &partial_path, cycle_detector, has_split);
-            if work_performed >= self.max_work_per_phase {
+            if work_performed >= max_work_per_phase {
                 break;
             }
         }
+        self.work_performed += work_performed;
+        self.phases_completed += 1;
         if let Some(stats) = &mut self.stats {
             stats.processed_paths_per_phase.record(work_performed);
         }
@@ -1224,8 +1716,16 @@ impl ForwardPartialPathStitcher<Edge> {
         stitcher.set_check_only_join_nodes(true);
 
         let mut accepted_path_length = FrequencyDistribution::default();
+        let mut complete_paths = 0;
         while !stitcher.is_complete() {
-            cancellation_flag.check("finding complete partial paths")?;
+            check_with_progress(
+                cancellation_flag,
+                "finding complete partial paths",
+                StitchingProgress {
+                    complete_paths,
+                    ..stitcher.progress()
+                },
+            )?;
             stitcher.process_next_phase(
                 &mut GraphEdgeCandidates::new(graph, partials, Some(file)),
                 |g, _ps, p| !as_complete_as_necessary(g, p),
@@ -1234,6 +1734,7 @@ impl ForwardPartialPathStitcher<Edge> {
                 if as_complete_as_necessary(graph, path) {
                     accepted_path_length.record(path.edges.len());
                     visit(graph, partials, path);
+                    complete_paths += 1;
                 }
             }
         }
@@ -1288,30 +1789,720 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
         stitcher.set_check_only_join_nodes(true);
 
         let mut accepted_path_length = FrequencyDistribution::default();
-        while !stitcher.is_complete() {
-            cancellation_flag.check("finding complete partial paths")?;
+        let mut sorted_paths = config.sorted_results().then(Vec::new);
+        let mut complete_paths = 0;
+        while !stitcher.is_complete()
+            && !config.is_exhausted(complete_paths, stitcher.work_performed())
+        {
+            check_with_progress(
+                cancellation_flag,
+                "finding complete partial paths",
+                StitchingProgress {
+                    complete_paths,
+                    ..stitcher.progress()
+                },
+            )?;
             for path in stitcher.previous_phase_partial_paths() {
                 candidates.load_forward_candidates(path, cancellation_flag)?;
             }
             stitcher.process_next_phase(candidates, |_, _, _| true);
             let (graph, partials, _) = candidates.get_graph_partials_and_db();
             for path in stitcher.previous_phase_partial_paths() {
+                if config.is_exhausted(complete_paths, 0) {
+                    break;
+                }
                 if path.is_complete(graph) {
                     accepted_path_length.record(path.edges.len());
-                    visit(graph, partials, path);
+                    visit_or_keep(graph, partials, path, &mut sorted_paths, &mut visit);
+                    complete_paths += 1;
                 }
             }
         }
+        let (graph, partials, _) = candidates.get_graph_partials_and_db();
+        visit_sorted(graph, partials, sorted_paths, &mut visit);
 
         Ok(Stats {
             accepted_path_length,
             ..stitcher.into_stats()
         })
     }
-}
 
-#[derive(Clone, Debug, Default)]
-pub struct Stats {
+    /// Finds all complete partial paths that start at one of a set of reference nodes and end at
+    /// one of a set of definition nodes, calling the `visit` closure on each one.  This is the
+    /// reverse of [`find_all_complete_partial_paths`][]: it answers which references resolve to
+    /// the given definitions.
+    ///
+    /// Whether a path is shadowed can only be decided by looking at all complete paths of its
+    /// reference, so this function stitches all complete paths for the given references, and only
+    /// visits paths that end at one of the definitions and are not shadowed by any other complete
+    /// path from the same reference, according to the given shadowing policy.
+    ///
+    /// The same requirements on the database apply as for [`find_all_complete_partial_paths`][].
+    ///
+    /// [`find_all_complete_partial_paths`]: #method.find_all_complete_partial_paths
+    pub fn find_all_complete_partial_paths_to_definitions<I, J, F, A, Db, C, Err>(
+        candidates: &mut C,
+        starting_nodes: I,
+        definitions: J,
+        config: StitcherConfig,
+        shadowing_policy: &dyn ShadowingPolicy,
+        cancellation_flag: &dyn CancellationFlag,
+        mut visit: F,
+    ) -> Result<Stats, Err>
+    where
+        I: IntoIterator<Item = Handle<Node>>,
+        J: IntoIterator<Item = Handle<Node>>,
+        A: Appendable,
+        Db: ToAppendable<H, A>,
+        C: ForwardCandidates<H, A, Db, Err>,
+        F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
+        Err: std::convert::From<CancellationError>,
+    {
+        let mut definition_set = HandleSet::new();
+        for definition in definitions {
+            definition_set.add(definition);
+        }
+
+        let mut paths_by_reference = HashMap::<Handle<Node>, Vec<PartialPath>>::new();
+        let stats = Self::find_all_complete_partial_paths(
+            candidates,
+            starting_nodes,
+            config,
+            cancellation_flag,
+            |_, _, path| {
+                paths_by_reference
+                    .entry(path.start_node)
+                    .or_default()
+                    .push(path.clone());
+            },
+        )?;
+
+        let (graph, partials, _) = candidates.get_graph_partials_and_db();
+        let mut sorted_paths = config.sorted_results().then(Vec::new);
+        for paths in paths_by_reference.values() {
+            if !paths.iter().any(|p| definition_set.contains(p.end_node)) {
+                continue;
+            }
+            for path in paths {
+                cancellation_flag.check("filtering shadowed paths")?;
+                if !definition_set.contains(path.end_node) {
+                    continue;
+                }
+                if shadowing_policy.is_shadowed(graph, partials, path, paths) {
+                    continue;
+                }
+                visit_or_keep(graph, partials, path, &mut sorted_paths, &mut visit);
+            }
+        }
+
+        visit_sorted(graph, partials, sorted_paths, &mut visit);
+
+        Ok(stats)
+    }
+
+    /// Finds all partial paths from the root node to definitions of the qualified name given by
+    /// a symbol stack, calling the `visit` closure on each one.  Unlike the other functions, this
+    /// does not require a reference node to start from, so it can be used to look up definitions
+    /// by name.  The symbol stack must not contain a variable.
+    ///
+    /// Only partial paths starting at the root node are used to extend the initial path, which
+    /// means that only definitions that are reachable from the root, such as exported module
+    /// members, can be found.
+    ///
+    /// The same requirements on the database apply as for [`find_all_complete_partial_paths`][].
+    ///
+    /// [`find_all_complete_partial_paths`]: #method.find_all_complete_partial_paths
+    pub fn find_all_definitions_of_symbol_stack<F, A, Db, C, Err>(
+        candidates: &mut C,
+        symbol_stack: PartialSymbolStack,
+        config: StitcherConfig,
+        cancellation_flag: &dyn CancellationFlag,
+        mut visit: F,
+    ) -> Result<Stats, Err>
+    where
+        A: Appendable,
+        Db: ToAppendable<H, A>,
+        C: ForwardCandidates<H, A, Db, Err>,
+        F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
+        Err: std::convert::From<CancellationError>,
+    {
+        let (graph, partials, _) = candidates.get_graph_partials_and_db();
+        let initial_path = PartialPath::from_symbol_stack(symbol_stack);
+        let mut stitcher = ForwardPartialPathStitcher::from_partial_paths(
+            graph,
+            partials,
+            std::iter::once(initial_path),
+        );
+        config.apply(&mut stitcher);
+        stitcher.set_check_only_join_nodes(true);
+
+        let mut accepted_path_length = FrequencyDistribution::default();
+        let mut sorted_paths = config.sorted_results().then(Vec::new);
+        let mut complete_paths = 0;
+        while !stitcher.is_complete() {
+            check_with_progress(
+                cancellation_flag,
+                "finding definitions of symbol stack",
+                StitchingProgress {
+                    complete_paths,
+                    ..stitcher.progress()
+                },
+            )?;
+            for path in stitcher.previous_phase_partial_paths() {
+                candidates.load_forward_candidates(path, cancellation_flag)?;
+            }
+            stitcher.process_next_phase(candidates, |_, _, _| true);
+            let (graph, partials, _) = candidates.get_graph_partials_and_db();
+            for path in stitcher.previous_phase_partial_paths() {
+                if path.ends_at_definition(graph) {
+                    accepted_path_length.record(path.edges.len());
+                    visit_or_keep(graph, partials, path, &mut sorted_paths, &mut visit);
+                    complete_paths += 1;
+                }
+            }
+        }
+        let (graph, partials, _) = candidates.get_graph_partials_and_db();
+        visit_sorted(graph, partials, sorted_paths, &mut visit);
+
+        Ok(Stats {
+            accepted_path_length,
+            ..stitcher.into_stats()
+        })
+    }
+}
+
+/// Implements a phased backward partial path stitching algorithm.
+///
+/// This is the mirror image of [`ForwardPartialPathStitcher`][].  We start with a set of _seed_
+/// partial paths, and repeatedly extend each partial path by concatenating a compatible partial
+/// path onto the _start_ of it.  Starting from a definition, this finds the references that
+/// resolve to it, without having to start a forward search from every reference in the world.
+///
+/// The algorithm is processed in phases, in the same way as for [`ForwardPartialPathStitcher`][].
+/// Only partial paths can be used as candidates; the candidates are looked up by their end node,
+/// or, if they end at the root node, by their symbol stack postcondition.
+///
+/// [`ForwardPartialPathStitcher`]: struct.ForwardPartialPathStitcher.html
+pub struct BackwardPartialPathStitcher<H> {
+    candidates: Vec<H>,
+    extensions: Vec<(PartialPath, PrependingCycleDetector<H>)>,
+    queue: VecDeque<(PartialPath, PrependingCycleDetector<H>, bool)>,
+    // tracks the number of initial paths in the queue because we do not want call
+    // extend_until on those
+    initial_paths_in_queue: usize,
+    next_iteration: (
+        VecDeque<PartialPath>,
+        VecDeque<PrependingCycleDetector<H>>,
+        VecDeque<bool>,
+    ),
+    appended_paths: Appendables<H>,
+    similar_path_detector: Option<SimilarPathDetector<PartialPath>>,
+    check_only_fork_nodes: bool,
+    max_work_per_phase: usize,
+    work_performed: usize,
+    phases_completed: usize,
+    initial_paths: usize,
+    stats: Option<Stats>,
+    #[cfg(feature = "copious-debugging")]
+    phase_number: usize,
+}
+
+impl<H> BackwardPartialPathStitcher<H> {
+    /// Creates a new backward partial path stitcher that is "seeded" with a set of initial partial
+    /// paths.
+    pub fn from_partial_paths<I>(
+        _graph: &StackGraph,
+        _partials: &mut PartialPaths,
+        initial_partial_paths: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = PartialPath>,
+    {
+        let mut appended_paths = Appendables::new();
+        let next_iteration: (VecDeque<_>, VecDeque<_>, VecDeque<_>) = initial_partial_paths
+            .into_iter()
+            .map(|p| {
+                let c = PrependingCycleDetector::from(&mut appended_paths, p.clone());
+                (p, c, false)
+            })
+            .multiunzip();
+        let initial_paths = next_iteration.0.len();
+        Self {
+            candidates: Vec::new(),
+            extensions: Vec::new(),
+            queue: VecDeque::new(),
+            initial_paths_in_queue: initial_paths,
+            next_iteration,
+            appended_paths,
+            // By default, all paths are checked for similarity
+            similar_path_detector: Some(SimilarPathDetector::new()),
+            // By default, all nodes are checked for cycles and (if enabled) similarity
+            check_only_fork_nodes: false,
+            // By default, there's no artificial bound on the amount of work done per phase
+            max_work_per_phase: usize::MAX,
+            work_performed: 0,
+            phases_completed: 0,
+            initial_paths,
+            stats: None,
+            #[cfg(feature = "copious-debugging")]
+            phase_number: 1,
+        }
+    }
+
+    /// Sets whether similar path detection should be enabled during path stitching.  See
+    /// [`ForwardPartialPathStitcher::set_similar_path_detection`][].
+    ///
+    /// [`ForwardPartialPathStitcher::set_similar_path_detection`]: struct.ForwardPartialPathStitcher.html#method.set_similar_path_detection
+    pub fn set_similar_path_detection(&mut self, detect_similar_paths: bool) {
+        if !detect_similar_paths {
+            self.similar_path_detector = None;
+        } else if self.similar_path_detector.is_none() {
+            let mut similar_path_detector = SimilarPathDetector::new();
+            similar_path_detector.set_collect_stats(self.stats.is_some());
+            self.similar_path_detector = Some(similar_path_detector);
+        }
+    }
+
+    /// Sets the maximum number of paths that the similar path detector keeps.  See
+    /// [`ForwardPartialPathStitcher::set_max_similar_paths`][].
+    pub fn set_max_similar_paths(&mut self, max_similar_paths: Option<usize>) {
+        if let Some(similar_path_detector) = &mut self.similar_path_detector {
+            similar_path_detector.set_max_paths(max_similar_paths);
+        }
+    }
+
+    /// Sets whether all nodes are checked for cycles and (if enabled) similar paths, or only nodes with multiple
+    /// outgoing candidates. As for [`ForwardPartialPathStitcher::set_check_only_join_nodes`][], this is **unsafe**
+    /// unless the database of candidates is stable between all stitching phases.
+    ///
+    /// [`ForwardPartialPathStitcher::set_check_only_join_nodes`]: struct.ForwardPartialPathStitcher.html#method.set_check_only_join_nodes
+    pub fn set_check_only_fork_nodes(&mut self, check_only_fork_nodes: bool) {
+        self.check_only_fork_nodes = check_only_fork_nodes;
+    }
+
+    /// Sets the maximum amount of work that can be performed during each phase of the algorithm.
+    /// If you don't call this method, then we allow ourselves to process all of the extensions of
+    /// all of the paths found in the previous phase, with no additional bound.
+    pub fn set_max_work_per_phase(&mut self, max_work_per_phase: usize) {
+        self.max_work_per_phase = max_work_per_phase;
+    }
+
+    /// Returns the total amount of work performed by all phases so far.
+    pub fn work_performed(&self) -> usize {
+        self.work_performed
+    }
+
+    /// Returns the progress of the stitcher so far.  As for
+    /// [`ForwardPartialPathStitcher::progress`][], the number of complete paths is left at zero,
+    /// for the caller to fill in.
+    pub fn progress(&self) -> StitchingProgress {
+        StitchingProgress {
+            phase: self.phases_completed,
+            queued_paths: self.queue.len() + self.next_iteration.0.len(),
+            complete_paths: 0,
+            work_performed: self.work_performed,
+        }
+    }
+
+    /// Sets whether to collect statistics during stitching.
+    pub fn set_collect_stats(&mut self, collect_stats: bool) {
+        if !collect_stats {
+            self.stats = None;
+        } else if self.stats.is_none() {
+            let mut stats = Stats::default();
+            stats.initial_paths.record(self.initial_paths);
+            self.stats = Some(stats);
+        }
+        if let Some(similar_path_detector) = &mut self.similar_path_detector {
+            similar_path_detector.set_collect_stats(collect_stats);
+        }
+    }
+
+    pub fn into_stats(mut self) -> Stats {
+        if let (Some(stats), Some(similar_path_detector)) =
+            (&mut self.stats, self.similar_path_detector)
+        {
+            stats.similar_paths_stats = similar_path_detector.stats();
+        }
+        self.stats.unwrap_or_default()
+    }
+}
+
+impl<H: Clone> BackwardPartialPathStitcher<H> {
+    /// Returns an iterator of all of the (possibly incomplete) partial paths that were encountered
+    /// during the most recent phase of the algorithm.
+    pub fn previous_phase_partial_paths(&self) -> impl Iterator<Item = &PartialPath> + '_ {
+        self.next_iteration.0.iter()
+    }
+
+    /// Attempts to extend one partial path as part of the algorithm.  When calling this function,
+    /// you are responsible for ensuring that `db` already contains all of the possible partial
+    /// paths that we might want to prepend to `partial_path`.
+    fn extend<Db, C, Err>(
+        &mut self,
+        candidates: &mut C,
+        partial_path: &PartialPath,
+        cycle_detector: PrependingCycleDetector<H>,
+        has_split: bool,
+    ) -> usize
+    where
+        Db: ToAppendable<H, PartialPath>,
+        C: BackwardCandidates<H, Db, Err>,
+    {
+        let check_cycle = !self.check_only_fork_nodes
+            || partial_path.start_node == partial_path.end_node
+            || candidates.get_forking_candidate_degree(partial_path) == Degree::Multiple;
+
+        let (graph, partials, db) = candidates.get_graph_partials_and_db();
+        copious_debugging!(
+            "    Extend backwards {}",
+            partial_path.display(graph, partials)
+        );
+
+        if check_cycle {
+            // This mirrors the check in the forward stitcher: the path grows at its start, so it
+            // is the postcondition that cannot be strengthened by cycles if it has no variables.
+            let has_postcondition_variables =
+                partial_path.symbol_stack_postcondition.has_variable()
+                    || partial_path.scope_stack_postcondition.has_variable();
+            let cycles = cycle_detector
+                .is_cyclic(graph, partials, db, &mut self.appended_paths)
+                .expect("cyclic test failed when stitching partial paths");
+            let cyclic = match has_postcondition_variables {
+                false => !cycles
+                    .into_iter()
+                    .all(|c| c == Cyclicity::StrengthensPostcondition),
+                true => !cycles.is_empty(),
+            };
+            if cyclic {
+                copious_debugging!("      is discontinued: cyclic");
+                if let Some(stats) = &mut self.stats {
+                    stats.discarded_cycles.record(DiscardedCycle::new(
+                        graph,
+                        partials,
+                        partial_path,
+                        cycles,
+                        CycleDirection::Backward,
+                    ));
+                }
+                return 0;
+            }
+        }
+
+        // find candidates to prepend
+        self.candidates.clear();
+        candidates.get_backward_candidates(partial_path, &mut self.candidates);
+        let (graph, partials, db) = candidates.get_graph_partials_and_db();
+
+        // try to extend path with candidates
+        let candidate_count = self.candidates.len();
+        self.extensions.clear();
+        self.extensions.reserve(candidate_count);
+        for candidate in &self.candidates {
+            let prependable = db.get_appendable(candidate);
+            copious_debugging!("      with {}", prependable.display(graph, partials));
+
+            // Prepending the candidate is the same as appending the current path to it.
+            let mut new_partial_path = prependable.clone();
+            let mut new_cycle_detector = cycle_detector.clone();
+            // If there are errors concatenating these partial paths, or resolving the resulting
+            // partial path, just skip the extension — it's not a fatal error.
+            #[cfg_attr(not(feature = "copious-debugging"), allow(unused_variables))]
+            {
+                if let Err(err) = partial_path.append_to(graph, partials, &mut new_partial_path) {
+                    copious_debugging!("        is invalid: {:?}", err);
+                    continue;
+                }
+            }
+            new_cycle_detector.prepend(&mut self.appended_paths, candidate.clone());
+            copious_debugging!("        is {}", new_partial_path.display(graph, partials));
+            self.extensions.push((new_partial_path, new_cycle_detector));
+        }
+
+        let extension_count = self.extensions.len();
+        let new_has_split = has_split || self.extensions.len() > 1;
+        self.next_iteration.0.reserve(extension_count);
+        self.next_iteration.1.reserve(extension_count);
+        self.next_iteration.2.reserve(extension_count);
+        for (new_partial_path, new_cycle_detector) in self.extensions.drain(..) {
+            let check_similar_path = new_has_split
+                && (!self.check_only_fork_nodes
+                    || candidates.get_forking_candidate_degree(&new_partial_path)
+                        == Degree::Multiple);
+            let (graph, partials, _) = candidates.get_graph_partials_and_db();
+            if check_similar_path {
+                if let Some(similar_path_detector) = &mut self.similar_path_detector {
+                    if similar_path_detector.add_path(
+                        graph,
+                        partials,
+                        &new_partial_path,
+                        |ps, left, right| {
+                            if !left.equals(ps, right) {
+                                None
+                            } else if left.shadows(ps, right) {
+                                Some(Ordering::Less)
+                            } else if right.shadows(ps, left) {
+                                Some(Ordering::Greater)
+                            } else {
+                                Some(Ordering::Equal)
+                            }
+                        },
+                    ) {
+                        copious_debugging!(
+                            " extension {}",
+                            new_partial_path.display(graph, partials)
+                        );
+                        copious_debugging!("        is rejected: too many similar");
+                        continue;
+                    }
+                }
+            }
+
+            self.next_iteration.0.push(new_partial_path);
+            self.next_iteration.1.push(new_cycle_detector);
+            self.next_iteration.2.push(new_has_split);
+        }
+
+        if let Some(stats) = &mut self.stats {
+            let (graph, _, _) = candidates.get_graph_partials_and_db();
+            let start_node = &graph[partial_path.start_node];
+            if start_node.is_root() {
+                stats.candidates_per_root_path.record(candidate_count);
+                stats.extensions_per_root_path.record(extension_count);
+                stats.root_visits += 1;
+            } else {
+                stats.candidates_per_node_path.record(candidate_count);
+                stats.extensions_per_node_path.record(extension_count);
+                stats.node_visits.record(start_node.id());
+            }
+            if extension_count == 0 {
+                stats.terminal_path_lengh.record(partial_path.edges.len());
+            }
+        }
+        candidate_count
+    }
+
+    /// Returns whether the algorithm has completed.
+    pub fn is_complete(&self) -> bool {
+        self.queue.is_empty() && self.next_iteration.0.is_empty()
+    }
+
+    /// Runs the next phase of the algorithm.  Before calling this function, you must ensure that
+    /// `db` contains all of the possible partial paths that we might want to prepend to any of the
+    /// candidate partial paths from the previous phase.
+    ///
+    /// The `extend_while` closure is used to control whether the extended paths are further extended
+    /// or not. It is not called on the initial paths.
+    pub fn process_next_phase<Db, C, E, Err>(&mut self, candidates: &mut C, extend_while: E)
+    where
+        Db: ToAppendable<H, PartialPath>,
+        C: BackwardCandidates<H, Db, Err>,
+        E: Fn(&StackGraph, &mut PartialPaths, &PartialPath) -> bool,
+    {
+        copious_debugging!("==> Start backward phase {}", self.phase_number);
+        self.queue.extend(izip!(
+            self.next_iteration.0.drain(..),
+            self.next_iteration.1.drain(..),
+            self.next_iteration.2.drain(..),
+        ));
+        if let Some(stats) = &mut self.stats {
+            stats.queued_paths_per_phase.record(self.queue.len());
+        }
+        let mut work_performed = 0;
+        while let Some((partial_path, cycle_detector, has_split)) = self.queue.pop_front() {
+            let (graph, partials, _) = candidates.get_graph_partials_and_db();
+            copious_debugging!(
+                "--> Candidate partial path {}",
+                partial_path.display(graph, partials)
+            );
+            if self.initial_paths_in_queue > 0 {
+                self.initial_paths_in_queue -= 1;
+            } else if !extend_while(graph, partials, &partial_path) {
+                copious_debugging!(
+                    "    Do not extend {}",
+                    partial_path.display(graph, partials)
+                );
+                continue;
+            }
+            work_performed += self.extend(candidates, &partial_path, cycle_detector, has_split);
+            if work_performed >= self.max_work_per_phase {
+                break;
+            }
+        }
+        self.work_performed += work_performed;
+        self.phases_completed += 1;
+        if let Some(stats) = &mut self.stats {
+            stats.processed_paths_per_phase.record(work_performed);
+        }
+
+        #[cfg(feature = "copious-debugging")]
+        {
+            copious_debugging!("==> End backward phase {}", self.phase_number);
+            self.phase_number += 1;
+        }
+    }
+
+    /// Finds all complete partial paths that end at one of a set of definition nodes, building
+    /// them up backwards by stitching together partial paths from this database, and calling the
+    /// `visit` closure on each one.  This finds the references to the given definitions without
+    /// having to start at every reference.
+    ///
+    /// The precondition variables of the visited paths are eliminated, so that they are equal to
+    /// the paths found by [`ForwardPartialPathStitcher::find_all_complete_partial_paths`][].
+    /// Unlike [`ForwardPartialPathStitcher::find_all_complete_partial_paths_to_definitions`][],
+    /// this does not filter shadowed paths, since that requires all complete paths of a reference.
+    ///
+    /// Stitching stops early when the limits set with [`StitcherConfig::with_max_complete_paths`][]
+    /// or [`StitcherConfig::with_max_work`][] are reached, and otherwise does not return until all
+    /// reachable partial paths have been processed. Paths are always processed breadth-first;
+    /// [`StitcherConfig::with_best_first`][] has no effect here. Candidates are loaded with
+    /// [`BackwardCandidates::load_backward_candidates`][] before each phase.
+    ///
+    /// [`ForwardPartialPathStitcher::find_all_complete_partial_paths`]: struct.ForwardPartialPathStitcher.html#method.find_all_complete_partial_paths
+    /// [`ForwardPartialPathStitcher::find_all_complete_partial_paths_to_definitions`]: struct.ForwardPartialPathStitcher.html#method.find_all_complete_partial_paths_to_definitions
+    pub fn find_all_complete_partial_paths_from_definitions<I, F, Db, C, Err>(
+        candidates: &mut C,
+        definitions: I,
+        config: StitcherConfig,
+        cancellation_flag: &dyn CancellationFlag,
+        mut visit: F,
+    ) -> Result<Stats, Err>
+    where
+        I: IntoIterator<Item = Handle<Node>>,
+        Db: ToAppendable<H, PartialPath>,
+        C: BackwardCandidates<H, Db, Err>,
+        F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
+        Err: std::convert::From<CancellationError>,
+    {
+        let (graph, partials, _) = candidates.get_graph_partials_and_db();
+        let initial_paths = definitions
+            .into_iter()
+            .filter(|n| graph[*n].is_definition())
+            .map(|n| PartialPath::from_node(graph, partials, n))
+            .collect::<Vec<_>>();
+        let mut stitcher =
+            BackwardPartialPathStitcher::from_partial_paths(graph, partials, initial_paths);
+        config.apply_backward(&mut stitcher);
+        stitcher.set_check_only_fork_nodes(true);
+
+        let mut accepted_path_length = FrequencyDistribution::default();
+        let mut sorted_paths = config.sorted_results().then(Vec::new);
+        let mut complete_paths = 0;
+        while !stitcher.is_complete()
+            && !config.is_exhausted(complete_paths, stitcher.work_performed())
+        {
+            check_with_progress(
+                cancellation_flag,
+                "finding complete partial paths backwards",
+                StitchingProgress {
+                    complete_paths,
+                    ..stitcher.progress()
+                },
+            )?;
+            for path in stitcher.previous_phase_partial_paths() {
+                candidates.load_backward_candidates(path, cancellation_flag)?;
+            }
+            stitcher.process_next_phase(candidates, |_, _, _| true);
+            let (graph, partials, _) = candidates.get_graph_partials_and_db();
+            for path in stitcher.previous_phase_partial_paths() {
+                if config.is_exhausted(complete_paths, 0) {
+                    break;
+                }
+                if path.is_complete(graph) {
+                    let mut path = path.clone();
+                    path.eliminate_precondition_stack_variables(partials);
+                    accepted_path_length.record(path.edges.len());
+                    visit_or_keep(graph, partials, &path, &mut sorted_paths, &mut visit);
+                    complete_paths += 1;
+                }
+            }
+        }
+        let (graph, partials, _) = candidates.get_graph_partials_and_db();
+        visit_sorted(graph, partials, sorted_paths, &mut visit);
+
+        Ok(Stats {
+            accepted_path_length,
+            ..stitcher.into_stats()
+        })
+    }
+}
+
+/// The progress of path stitching, as reported to [`CancellationFlag::report_progress`][] and
+/// included in cancellation errors.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StitchingProgress {
+    /// The number of completed phases.
+    pub phase: usize,
+    /// The number of paths waiting to be extended.
+    pub queued_paths: usize,
+    /// The number of complete paths found so far.
+    pub complete_paths: usize,
+    /// The amount of work performed so far, which is limited by
+    /// [`StitcherConfig::with_max_work`][].
+    pub work_performed: usize,
+}
+
+impl std::fmt::Display for StitchingProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "phase {}, {} queued paths, {} complete paths, {} work performed",
+            self.phase, self.queued_paths, self.complete_paths, self.work_performed
+        )
+    }
+}
+
+/// Reports progress to a cancellation flag before checking it. If the flag signals cancellation,
+/// the progress is included in the error.
+fn check_with_progress(
+    cancellation_flag: &dyn CancellationFlag,
+    at: &'static str,
+    progress: StitchingProgress,
+) -> Result<(), CancellationError> {
+    cancellation_flag.report_progress(at, &progress);
+    cancellation_flag
+        .check(at)
+        .map_err(|err| err.with_progress(progress))
+}
+
+/// Visits a result path, or keeps it to be visited later by [`visit_sorted`][], if sorted results
+/// are requested.
+fn visit_or_keep<F>(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    path: &PartialPath,
+    sorted_paths: &mut Option<Vec<PartialPath>>,
+    visit: &mut F,
+) where
+    F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
+{
+    match sorted_paths {
+        Some(sorted_paths) => sorted_paths.push(path.clone()),
+        None => visit(graph, partials, path),
+    }
+}
+
+/// Visits the result paths that were kept by [`visit_or_keep`][] in canonical order.
+fn visit_sorted<F>(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    sorted_paths: Option<Vec<PartialPath>>,
+    visit: &mut F,
+) where
+    F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
+{
+    let mut sorted_paths = match sorted_paths {
+        Some(sorted_paths) => sorted_paths,
+        None => return,
+    };
+    sorted_paths.sort_by(|a, b| a.cmp_canonical(graph, partials, b));
+    for path in &sorted_paths {
+        visit(graph, partials, path);
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
     /// The distribution of the number of initial paths
     pub initial_paths: FrequencyDistribution<usize>,
     /// The distribution of the number of queued paths per stitching phase
@@ -1336,6 +2527,8 @@ pub struct Stats {
     pub node_visits: FrequencyDistribution<crate::graph::NodeID>,
     /// The distribution of the number of similar paths between node pairs.
     pub similar_paths_stats: SimilarPathStats,
+    /// The distribution of cycles that caused paths to be discarded
+    pub discarded_cycles: FrequencyDistribution<DiscardedCycle>,
 }
 
 impl std::ops::AddAssign<Self> for Stats {
@@ -1352,6 +2545,7 @@ impl std::ops::AddAssign<Self> for Stats {
         self.root_visits += rhs.root_visits;
         self.node_visits += rhs.node_visits;
         self.similar_paths_stats += rhs.similar_paths_stats;
+        self.discarded_cycles += rhs.discarded_cycles;
     }
 }
 
@@ -1368,6 +2562,60 @@ impl std::ops::AddAssign<&Self> for Stats {
         self.root_visits += rhs.root_visits;
         self.node_visits += &rhs.node_visits;
         self.similar_paths_stats += &rhs.similar_paths_stats;
+        self.discarded_cycles += &rhs.discarded_cycles;
+    }
+}
+
+/// A cycle that caused a path to be discarded during stitching. Stitchers record these in their
+/// [`Stats`][], which makes it possible to find rules that accidentally create cycles, such as
+/// infinite recursion.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct DiscardedCycle {
+    /// The kinds of cycles that were found.
+    pub cyclicity: EnumSet<Cyclicity>,
+    /// The nodes of the cycle, in path order. The first and last node are the same.
+    pub nodes: Vec<NodeID>,
+}
+
+enum CycleDirection {
+    Forward,
+    Backward,
+}
+
+impl DiscardedCycle {
+    /// Creates a discarded cycle for a path. A path extended forwards ends in a cycle, and the
+    /// nodes are taken from the first occurrence of its end node. A path extended backwards
+    /// starts with a cycle, and the nodes are taken up to the last occurrence of its start node.
+    fn new(
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        path: &PartialPath,
+        cyclicity: EnumSet<Cyclicity>,
+        direction: CycleDirection,
+    ) -> Self {
+        let mut nodes = path
+            .edges
+            .iter(partials)
+            .map(|e| e.source_node_id)
+            .collect::<Vec<_>>();
+        nodes.push(graph[path.end_node].id());
+        match direction {
+            CycleDirection::Forward => {
+                let end = nodes[nodes.len() - 1];
+                if let Some(first) = nodes.iter().position(|n| *n == end) {
+                    nodes.drain(..first);
+                }
+            }
+            CycleDirection::Backward => {
+                let start = nodes[0];
+                if let Some(last) = nodes.iter().rposition(|n| *n == start) {
+                    if last > 0 {
+                        nodes.truncate(last + 1);
+                    }
+                }
+            }
+        }
+        Self { cyclicity, nodes }
     }
 }
 
@@ -1376,8 +2624,18 @@ impl std::ops::AddAssign<&Self> for Stats {
 pub struct StitcherConfig {
     /// Enables similar path detection during path stitching.
     detect_similar_paths: bool,
+    /// The maximum number of paths kept by the similar path detector.
+    max_similar_paths: Option<usize>,
     /// Collect statistics about path stitching.
     collect_stats: bool,
+    /// Process paths best-first instead of breadth-first.
+    best_first: bool,
+    /// Stop after this many complete paths have been found.
+    max_complete_paths: Option<usize>,
+    /// Stop after this much work has been performed.
+    max_work: Option<usize>,
+    /// Visit results in canonical order.
+    sorted_results: bool,
 }
 
 impl StitcherConfig {
@@ -1390,6 +2648,17 @@ impl StitcherConfig {
         self
     }
 
+    pub fn max_similar_paths(&self) -> Option<usize> {
+        self.max_similar_paths
+    }
+
+    /// Limits the memory used by similar path detection. See
+    /// [`ForwardPartialPathStitcher::set_max_similar_paths`][].
+    pub fn with_max_similar_paths(mut self, max_similar_paths: Option<usize>) -> Self {
+        self.max_similar_paths = max_similar_paths;
+        self
+    }
+
     pub fn collect_stats(&self) -> bool {
         self.collect_stats
     }
@@ -1398,11 +2667,80 @@ impl StitcherConfig {
         self.collect_stats = collect_stats;
         self
     }
+
+    pub fn best_first(&self) -> bool {
+        self.best_first
+    }
+
+    /// Enables best-first stitching, which finds the most likely complete paths first. See
+    /// [`ForwardPartialPathStitcher::set_best_first`][]. Only forward stitching supports this.
+    /// [`BackwardPartialPathStitcher`][] always processes paths breadth-first, and ignores this
+    /// setting.
+    pub fn with_best_first(mut self, best_first: bool) -> Self {
+        self.best_first = best_first;
+        self
+    }
+
+    pub fn max_complete_paths(&self) -> Option<usize> {
+        self.max_complete_paths
+    }
+
+    /// Limits the number of complete paths that are found by
+    /// [`ForwardPartialPathStitcher::find_all_complete_partial_paths`][] and
+    /// [`BackwardPartialPathStitcher::find_all_complete_partial_paths_from_definitions`][].
+    /// Stitching stops when the limit is reached. This is most useful with best-first stitching.
+    /// Note that shadowing cannot be decided reliably when not all complete paths of a reference
+    /// are found.
+    pub fn with_max_complete_paths(mut self, max_complete_paths: Option<usize>) -> Self {
+        self.max_complete_paths = max_complete_paths;
+        self
+    }
+
+    pub fn max_work(&self) -> Option<usize> {
+        self.max_work
+    }
+
+    /// Limits the total amount of work performed by
+    /// [`ForwardPartialPathStitcher::find_all_complete_partial_paths`][] and
+    /// [`BackwardPartialPathStitcher::find_all_complete_partial_paths_from_definitions`][].
+    /// Stitching stops after the phase that reached the limit, and the complete paths found so far
+    /// are returned.
+    pub fn with_max_work(mut self, max_work: Option<usize>) -> Self {
+        self.max_work = max_work;
+        self
+    }
+
+    pub fn sorted_results(&self) -> bool {
+        self.sorted_results
+    }
+
+    /// Visits the paths found by the `find_*` methods of the stitchers in canonical order, see
+    /// [`PartialPath::cmp_canonical`][], instead of the order in which they are found. This makes
+    /// results deterministic, regardless of the order in which data was loaded, but no results
+    /// are visited until all of them are found.
+    pub fn with_sorted_results(mut self, sorted_results: bool) -> Self {
+        self.sorted_results = sorted_results;
+        self
+    }
 }
 
 impl StitcherConfig {
     fn apply<H>(&self, stitcher: &mut ForwardPartialPathStitcher<H>) {
         stitcher.set_similar_path_detection(self.detect_similar_paths);
+        stitcher.set_max_similar_paths(self.max_similar_paths);
+        stitcher.set_collect_stats(self.collect_stats);
+        stitcher.set_best_first(self.best_first);
+    }
+
+    /// Returns whether the result limits of this configuration have been reached.
+    fn is_exhausted(&self, complete_paths: usize, work_performed: usize) -> bool {
+        matches!(self.max_complete_paths, Some(max) if complete_paths >= max)
+            || matches!(self.max_work, Some(max) if work_performed >= max)
+    }
+
+    fn apply_backward<H>(&self, stitcher: &mut BackwardPartialPathStitcher<H>) {
+        stitcher.set_similar_path_detection(self.detect_similar_paths);
+        stitcher.set_max_similar_paths(self.max_similar_paths);
         stitcher.set_collect_stats(self.collect_stats);
     }
 }
@@ -1411,7 +2749,12 @@ impl Default for StitcherConfig {
     fn default() -> Self {
         Self {
             detect_similar_paths: true,
+            max_similar_paths: None,
             collect_stats: false,
+            best_first: false,
+            max_complete_paths: None,
+            max_work: None,
+            sorted_results: false,
         }
     }
 }
@@ -14,7 +14,11 @@ use rusqlite::Connection;
 use rusqlite::OptionalExtension;
 use rusqlite::Params;
 use rusqlite::Statement;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::hash::Hash;
+use std::hash::Hasher;
 use std::path::Path;
 use std::path::PathBuf;
 use thiserror::Error;
@@ -23,47 +27,141 @@ use crate::arena::Handle;
 use crate::graph::Degree;
 use crate::graph::File;
 use crate::graph::Node;
+use crate::graph::NodeID;
 use crate::graph::StackGraph;
+use crate::graph::ROOT_NODE_ID;
 use crate::partial::PartialPath;
 use crate::partial::PartialPaths;
 use crate::partial::PartialSymbolStack;
 use crate::serde;
 use crate::serde::FileFilter;
+use crate::shadowing::ShadowingPolicy;
+use crate::stitching::BackwardCandidates;
+use crate::stitching::BackwardPartialPathStitcher;
 use crate::stitching::Database;
 use crate::stitching::ForwardCandidates;
+use crate::stitching::ForwardPartialPathStitcher;
+use crate::stitching::Stats as StitchingStats;
+use crate::stitching::StitcherConfig;
 use crate::CancellationError;
 use crate::CancellationFlag;
 
-const VERSION: usize = 6;
+mod legacy;
+
+const VERSION: usize = 7;
 
 const SCHEMA: &str = r#"
         CREATE TABLE metadata (
-            version INTEGER NOT NULL
+            version    INTEGER NOT NULL,
+            generation INTEGER NOT NULL DEFAULT 0
         ) STRICT;
         CREATE TABLE graphs (
-            file   TEXT PRIMARY KEY,
-            tag    TEXT NOT NULL,
-            error  TEXT,
-            value  BLOB NOT NULL
+            file       TEXT PRIMARY KEY,
+            tag        TEXT NOT NULL,
+            error      TEXT,
+            value      BLOB NOT NULL,
+            generation INTEGER NOT NULL DEFAULT 0
         ) STRICT;
         CREATE TABLE file_paths (
-            file     TEXT NOT NULL,
-            local_id INTEGER NOT NULL,
-            value    BLOB NOT NULL,
+            file             TEXT NOT NULL,
+            local_id         INTEGER NOT NULL,
+            value            BLOB NOT NULL,
+            end_local_id     INTEGER,
+            end_symbol_stack TEXT,
             FOREIGN KEY(file) REFERENCES graphs(file)
         ) STRICT;
         CREATE TABLE root_paths (
+            file             TEXT NOT NULL,
+            symbol_stack     TEXT NOT NULL,
+            value            BLOB NOT NULL,
+            end_local_id     INTEGER,
+            end_symbol_stack TEXT,
+            FOREIGN KEY(file) REFERENCES graphs(file)
+        ) STRICT;
+        CREATE TABLE resolutions (
+            file                TEXT NOT NULL,
+            local_id            INTEGER NOT NULL,
+            definition_file     TEXT,
+            definition_local_id INTEGER,
+            FOREIGN KEY(file) REFERENCES graphs(file)
+        ) STRICT;
+        CREATE TABLE resolution_files (
+            file       TEXT NOT NULL,
+            local_id   INTEGER NOT NULL,
+            dependency TEXT NOT NULL,
+            FOREIGN KEY(file) REFERENCES graphs(file)
+        ) STRICT;
+        CREATE TABLE resolution_root_paths (
+            file         TEXT NOT NULL,
+            local_id     INTEGER NOT NULL,
+            symbol_stack TEXT NOT NULL,
+            FOREIGN KEY(file) REFERENCES graphs(file)
+        ) STRICT;
+    "#;
+
+/// Migrations that upgrade the database schema in place, in the order they must be applied. Each
+/// migration upgrades a database from version `from` to version `from + 1`. Databases with a version
+/// from which the current version cannot be reached by applying migrations are reset instead, see
+/// [`MigrationResult::Reset`][].
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 6,
+    script: r#"
+        CREATE TABLE resolutions (
+            file                TEXT NOT NULL,
+            local_id            INTEGER NOT NULL,
+            definition_file     TEXT,
+            definition_local_id INTEGER,
+            FOREIGN KEY(file) REFERENCES graphs(file)
+        ) STRICT;
+        CREATE TABLE resolution_files (
+            file       TEXT NOT NULL,
+            local_id   INTEGER NOT NULL,
+            dependency TEXT NOT NULL,
+            FOREIGN KEY(file) REFERENCES graphs(file)
+        ) STRICT;
+        CREATE TABLE resolution_root_paths (
             file         TEXT NOT NULL,
+            local_id     INTEGER NOT NULL,
             symbol_stack TEXT NOT NULL,
-            value        BLOB NOT NULL,
             FOREIGN KEY(file) REFERENCES graphs(file)
         ) STRICT;
+        ALTER TABLE file_paths ADD COLUMN end_local_id INTEGER;
+        ALTER TABLE file_paths ADD COLUMN end_symbol_stack TEXT;
+        ALTER TABLE root_paths ADD COLUMN end_local_id INTEGER;
+        ALTER TABLE root_paths ADD COLUMN end_symbol_stack TEXT;
+        ALTER TABLE metadata ADD COLUMN generation INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE graphs ADD COLUMN generation INTEGER NOT NULL DEFAULT 0;
+    "#,
+    graphs: Some(legacy::graph_from_v6),
+    path_ends: true,
+}];
+
+const DROP_SCHEMA: &str = r#"
+        DROP TABLE IF EXISTS resolution_root_paths;
+        DROP TABLE IF EXISTS resolution_files;
+        DROP TABLE IF EXISTS resolutions;
+        DROP TABLE IF EXISTS root_paths;
+        DROP TABLE IF EXISTS file_paths;
+        DROP TABLE IF EXISTS graphs;
+        DROP TABLE IF EXISTS metadata;
     "#;
 
+/// Error stored for files that must be reindexed after a database reset.
+const STALE_ERROR: &str = "database was upgraded, file must be reindexed";
+
 const INDEXES: &str = r#"
         CREATE INDEX IF NOT EXISTS idx_graphs_file ON graphs(file);
+        CREATE INDEX IF NOT EXISTS idx_graphs_generation ON graphs(generation);
         CREATE INDEX IF NOT EXISTS idx_file_paths_local_id ON file_paths(file, local_id);
         CREATE INDEX IF NOT EXISTS idx_root_paths_symbol_stack ON root_paths(symbol_stack);
+        CREATE INDEX IF NOT EXISTS idx_file_paths_end_local_id ON file_paths(file, end_local_id);
+        CREATE INDEX IF NOT EXISTS idx_file_paths_end_symbol_stack ON file_paths(end_symbol_stack);
+        CREATE INDEX IF NOT EXISTS idx_root_paths_end_local_id ON root_paths(file, end_local_id);
+        CREATE INDEX IF NOT EXISTS idx_root_paths_end_symbol_stack ON root_paths(end_symbol_stack);
+        CREATE INDEX IF NOT EXISTS idx_resolutions_local_id ON resolutions(file, local_id);
+        CREATE INDEX IF NOT EXISTS idx_resolution_files_local_id ON resolution_files(file, local_id);
+        CREATE INDEX IF NOT EXISTS idx_resolution_files_dependency ON resolution_files(dependency);
+        CREATE INDEX IF NOT EXISTS idx_resolution_root_paths_local_id ON resolution_root_paths(file, local_id);
     "#;
 
 const PRAGMAS: &str = r#"
@@ -76,12 +174,14 @@ pub static BINCODE_CONFIG: bincode::config::Configuration = bincode::config::sta
 
 #[derive(Debug, Error)]
 pub enum StorageError {
-    #[error("cancelled at {0}")]
-    Cancelled(&'static str),
+    #[error(transparent)]
+    Cancelled(CancellationError),
     #[error("unsupported database version {0}")]
     IncorrectVersion(usize),
     #[error("database does not exist {0}")]
     MissingDatabase(String),
+    #[error("file does not exist {0}")]
+    MissingFile(String),
     #[error(transparent)]
     Rusqlite(#[from] rusqlite::Error),
     #[error(transparent)]
@@ -96,10 +196,37 @@ pub type Result<T> = std::result::Result<T, StorageError>;
 
 impl From<CancellationError> for StorageError {
     fn from(value: CancellationError) -> Self {
-        Self::Cancelled(value.0)
+        Self::Cancelled(value)
     }
 }
 
+/// A function that converts a stored graph to the encoding of the next version.
+type GraphConversion = fn(&[u8]) -> Result<Vec<u8>>;
+
+/// A database migration, which upgrades the schema from one version to the next.
+struct Migration {
+    from: usize,
+    script: &'static str,
+    /// Converts a stored graph to the encoding of the next version, if it changed.
+    graphs: Option<GraphConversion>,
+    /// Whether the ends of all stored partial paths must be recorded, because the script added
+    /// the columns for them.
+    path_ends: bool,
+}
+
+/// The result of migrating a database to the current schema version.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MigrationResult {
+    /// The database was already at the current version.
+    UpToDate,
+    /// The database was upgraded in place from the given version.
+    Upgraded { from: usize },
+    /// The database could not be upgraded in place from the given version. All data was removed, and
+    /// the files that were in the database are marked as stale. Stale files have an error status, and
+    /// are reported missing when a tag is given, so that indexing picks them up again.
+    Reset { from: usize, stale_files: usize },
+}
+
 /// The status of a file in the database.
 pub enum FileStatus {
     Missing,
@@ -160,29 +287,174 @@ impl SQLiteWriter {
     }
 
     /// Open a file database.  If the file does not exist, it is automatically created.
-    /// An error is returned if the database version is not supported.
+    /// Databases of older versions are migrated to the current version, see [`Self::migrate`][].
+    /// An error is returned if the database version is newer than the supported version.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let (writer, _) = Self::open_and_migrate(path)?;
+        Ok(writer)
+    }
+
+    /// Migrate a file database to the current version. Databases are upgraded in place if
+    /// possible. Otherwise, all data is removed, and the files that were in the database are
+    /// marked as stale, so that they are indexed again.
+    pub fn migrate<P: AsRef<Path>>(path: P) -> Result<MigrationResult> {
+        if !path.as_ref().exists() {
+            return Err(StorageError::MissingDatabase(
+                path.as_ref().to_string_lossy().to_string(),
+            ));
+        }
+        let (_, result) = Self::open_and_migrate(path)?;
+        Ok(result)
+    }
+
+    fn open_and_migrate<P: AsRef<Path>>(path: P) -> Result<(Self, MigrationResult)> {
         let is_new = !path.as_ref().exists();
         let mut conn = Connection::open(path)?;
         set_pragmas_and_functions(&conn)?;
-        if is_new {
+        let result = if is_new {
             Self::init(&mut conn)?;
+            MigrationResult::UpToDate
         } else {
-            check_version(&conn)?;
-        }
+            Self::migrate_inner(&mut conn)?
+        };
         init_indexes(&mut conn)?;
-        Ok(Self { conn })
+        Ok((Self { conn }, result))
     }
 
     /// Create database tables and write metadata.
     fn init(conn: &mut Connection) -> Result<()> {
         let tx = conn.transaction()?;
-        tx.execute_batch(SCHEMA)?;
-        tx.execute("INSERT INTO metadata (version) VALUES (?)", [VERSION])?;
+        Self::init_inner(&tx)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Create database tables and write metadata.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn init_inner(conn: &Connection) -> Result<()> {
+        conn.execute_batch(SCHEMA)?;
+        conn.execute("INSERT INTO metadata (version) VALUES (?)", [VERSION])?;
+        Ok(())
+    }
+
+    /// Migrate the database to the current version.
+    fn migrate_inner(conn: &mut Connection) -> Result<MigrationResult> {
+        let version =
+            conn.query_row("SELECT version FROM metadata", [], |r| r.get::<_, usize>(0))?;
+        if version == VERSION {
+            return Ok(MigrationResult::UpToDate);
+        } else if version > VERSION {
+            return Err(StorageError::IncorrectVersion(version));
+        }
+        let tx = conn.transaction()?;
+        let mut current = version;
+        let migrations = MIGRATIONS
+            .iter()
+            .skip_while(|m| m.from < version)
+            .take_while(|m| {
+                if m.from != current {
+                    return false;
+                }
+                current += 1;
+                true
+            })
+            .collect::<Vec<_>>();
+        let result = if current == VERSION {
+            for migration in migrations {
+                copious_debugging!("--> Migrate from version {}", migration.from);
+                tx.execute_batch(migration.script)?;
+                if let Some(convert) = migration.graphs {
+                    Self::convert_graphs_inner(&tx, convert)?;
+                }
+                if migration.path_ends {
+                    Self::store_path_ends_inner(&tx)?;
+                }
+            }
+            tx.execute("UPDATE metadata SET version = ?", [VERSION])?;
+            MigrationResult::Upgraded { from: version }
+        } else {
+            let stale_files = Self::reset_inner(&tx)?;
+            MigrationResult::Reset {
+                from: version,
+                stale_files,
+            }
+        };
         tx.commit()?;
+        Ok(result)
+    }
+
+    /// Convert all stored graphs with the given function.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn convert_graphs_inner(conn: &Connection, convert: GraphConversion) -> Result<()> {
+        let mut select_stmt = conn.prepare("SELECT file, value FROM graphs")?;
+        let graphs = select_stmt
+            .query_map([], |r| {
+                Ok((r.get::<_, String>(0)?, r.get::<_, Vec<u8>>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut update_stmt = conn.prepare("UPDATE graphs SET value = ? WHERE file = ?")?;
+        for (file, value) in graphs {
+            update_stmt.execute((convert(&value)?, file))?;
+        }
+        Ok(())
+    }
+
+    /// Record the ends of all stored partial paths, which are used to load paths when stitching
+    /// backwards.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn store_path_ends_inner(conn: &Connection) -> Result<()> {
+        for table in ["file_paths", "root_paths"] {
+            let mut select_stmt =
+                conn.prepare(&format!("SELECT rowid, file, value FROM {}", table))?;
+            let paths = select_stmt
+                .query_map([], |r| {
+                    Ok((
+                        r.get::<_, i64>(0)?,
+                        r.get::<_, String>(1)?,
+                        r.get::<_, Vec<u8>>(2)?,
+                    ))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            let mut update_stmt = conn.prepare(&format!(
+                "UPDATE {} SET end_local_id = ?, end_symbol_stack = ? WHERE rowid = ?",
+                table
+            ))?;
+            for (rowid, file, value) in paths {
+                let (path, _): (serde::PartialPath, usize) =
+                    bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+                if let Some(end) = PathEnd::new(&file, &path) {
+                    let (end_local_id, end_symbol_stack) = end.columns();
+                    update_stmt.execute((end_local_id, end_symbol_stack, rowid))?;
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Recreate the database, and mark all files that were in the database as stale.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn reset_inner(conn: &Connection) -> Result<usize> {
+        copious_debugging!("--> Reset database");
+        // The graphs table has had a file column since the first version, but be lenient in case
+        // it cannot be read, because the only consequence is that fewer files are marked as stale.
+        let files = match conn.prepare("SELECT file FROM graphs") {
+            Ok(mut stmt) => stmt
+                .query_map([], |r| r.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?,
+            Err(_) => Vec::new(),
+        };
+        conn.execute_batch(DROP_SCHEMA)?;
+        Self::init_inner(conn)?;
+        for file in &files {
+            Self::store_error_for_file_inner(conn, Path::new(file), "", STALE_ERROR)?;
+        }
+        Ok(files.len())
+    }
+
     /// Clean all data from the database.
     pub fn clean_all(&mut self) -> Result<usize> {
         let tx = self.conn.transaction()?;
@@ -195,6 +467,11 @@ impl SQLiteWriter {
     ///
     /// This is an inner method, which does not wrap individual SQL statements in a transaction.
     fn clean_all_inner(conn: &Connection) -> Result<usize> {
+        Self::next_generation_inner(conn)?;
+        for table in ["resolutions", "resolution_files", "resolution_root_paths"] {
+            let mut stmt = conn.prepare_cached(&format!("DELETE FROM {}", table))?;
+            stmt.execute([])?;
+        }
         {
             let mut stmt = conn.prepare_cached("DELETE FROM file_paths")?;
             stmt.execute([])?;
@@ -224,6 +501,8 @@ impl SQLiteWriter {
     /// This is an inner method, which does not wrap individual SQL statements in a transaction.
     fn clean_file_inner(conn: &Connection, file: &Path) -> Result<usize> {
         let file = file.to_string_lossy();
+        Self::next_generation_inner(conn)?;
+        Self::invalidate_resolutions_inner(conn, &file)?;
         {
             let mut stmt = conn.prepare_cached("DELETE FROM file_paths WHERE file=?")?;
             stmt.execute([&file])?;
@@ -254,6 +533,17 @@ impl SQLiteWriter {
     /// This is an inner method, which does not wrap individual SQL statements in a transaction.
     fn clean_file_or_directory_inner(conn: &Connection, file_or_directory: &Path) -> Result<usize> {
         let file_or_directory = file_or_directory.to_string_lossy();
+        Self::next_generation_inner(conn)?;
+        {
+            let mut stmt =
+                conn.prepare_cached("SELECT file FROM graphs WHERE path_descendant_of(file, ?)")?;
+            let files = stmt
+                .query_map([&file_or_directory], |r| r.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for file in files {
+                Self::invalidate_resolutions_inner(conn, &file)?;
+            }
+        }
         {
             let mut stmt =
                 conn.prepare_cached("DELETE FROM file_paths WHERE path_descendant_of(file, ?)")?;
@@ -272,6 +562,120 @@ impl SQLiteWriter {
         Ok(count)
     }
 
+    /// Move file data in the database to a new path, without having to reindex the file. Any
+    /// data that was stored for the new path is replaced. Returns whether data was stored for
+    /// the old path.
+    ///
+    /// Only the file name in node IDs is changed. Symbols or other data that were derived from the
+    /// old path when the file was indexed, for example using the `FILE_PATH` variable of
+    /// tree-sitter-stack-graphs, are kept. Files whose stack graphs depend on their path must be
+    /// indexed again instead.
+    pub fn rename_file(&mut self, from: &Path, to: &Path) -> Result<bool> {
+        let tx = self.conn.transaction()?;
+        let renamed = Self::rename_file_inner(&tx, from, to)?;
+        tx.commit()?;
+        Ok(renamed)
+    }
+
+    /// Move file data in the database to a new path.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn rename_file_inner(conn: &Connection, from: &Path, to: &Path) -> Result<bool> {
+        copious_debugging!("--> Rename {} to {}", from.display(), to.display());
+        let from_str = from.to_string_lossy();
+        let to_str = to.to_string_lossy();
+        let row = {
+            let mut stmt =
+                conn.prepare_cached("SELECT tag, error, value FROM graphs WHERE file = ?")?;
+            stmt.query_row([&from_str], |r| {
+                Ok((
+                    r.get::<_, String>(0)?,
+                    r.get::<_, Option<String>>(1)?,
+                    r.get::<_, Vec<u8>>(2)?,
+                ))
+            })
+            .optional()?
+        };
+        let (tag, error, value) = match row {
+            Some(row) => row,
+            None => return Ok(false),
+        };
+        Self::clean_file_inner(conn, to)?;
+        {
+            let (mut graph, _): (serde::StackGraph, usize) =
+                bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+            graph.rename_file(&from_str, &to_str);
+            let serialized = bincode::encode_to_vec(&graph, BINCODE_CONFIG)?;
+            let generation = Self::next_generation_inner(conn)?;
+            let mut stmt = conn.prepare_cached(
+                "INSERT INTO graphs (file, tag, error, value, generation) VALUES (?, ?, ?, ?, ?)",
+            )?;
+            stmt.execute((&to_str, tag, error, serialized, generation))?;
+        }
+        {
+            let mut select_stmt = conn.prepare_cached(
+                "SELECT local_id, end_local_id, end_symbol_stack, value FROM file_paths WHERE file = ?",
+            )?;
+            let mut insert_stmt = conn.prepare_cached(
+                "INSERT INTO file_paths (file, local_id, end_local_id, end_symbol_stack, value) VALUES (?, ?, ?, ?, ?)",
+            )?;
+            let paths = select_stmt.query_map([&from_str], |r| {
+                Ok((
+                    r.get::<_, u32>(0)?,
+                    r.get::<_, Option<u32>>(1)?,
+                    r.get::<_, Option<String>>(2)?,
+                    r.get::<_, Vec<u8>>(3)?,
+                ))
+            })?;
+            for path in paths {
+                let (local_id, end_local_id, end_symbol_stack, value) = path?;
+                let (mut path, _): (serde::PartialPath, usize) =
+                    bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+                path.rename_file(&from_str, &to_str);
+                let serialized = bincode::encode_to_vec(&path, BINCODE_CONFIG)?;
+                insert_stmt.execute((
+                    &to_str,
+                    local_id,
+                    end_local_id,
+                    end_symbol_stack,
+                    serialized,
+                ))?;
+            }
+        }
+        {
+            let mut select_stmt = conn.prepare_cached(
+                "SELECT symbol_stack, end_local_id, end_symbol_stack, value FROM root_paths WHERE file = ?",
+            )?;
+            let mut insert_stmt = conn.prepare_cached(
+                "INSERT INTO root_paths (file, symbol_stack, end_local_id, end_symbol_stack, value) VALUES (?, ?, ?, ?, ?)",
+            )?;
+            let paths = select_stmt.query_map([&from_str], |r| {
+                Ok((
+                    r.get::<_, String>(0)?,
+                    r.get::<_, Option<u32>>(1)?,
+                    r.get::<_, Option<String>>(2)?,
+                    r.get::<_, Vec<u8>>(3)?,
+                ))
+            })?;
+            for path in paths {
+                let (symbol_stack, end_local_id, end_symbol_stack, value) = path?;
+                let (mut path, _): (serde::PartialPath, usize) =
+                    bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
+                path.rename_file(&from_str, &to_str);
+                let serialized = bincode::encode_to_vec(&path, BINCODE_CONFIG)?;
+                insert_stmt.execute((
+                    &to_str,
+                    symbol_stack,
+                    end_local_id,
+                    end_symbol_stack,
+                    serialized,
+                ))?;
+            }
+        }
+        Self::clean_file_inner(conn, from)?;
+        Ok(true)
+    }
+
     /// Store an error, indicating that indexing this file failed.
     pub fn store_error_for_file(&mut self, file: &Path, tag: &str, error: &str) -> Result<()> {
         let tx = self.conn.transaction()?;
@@ -290,11 +694,13 @@ impl SQLiteWriter {
         error: &str,
     ) -> Result<()> {
         copious_debugging!("--> Store error for {}", file.display());
-        let mut stmt = conn
-            .prepare_cached("INSERT INTO graphs (file, tag, error, value) VALUES (?, ?, ?, ?)")?;
+        let generation = Self::next_generation_inner(conn)?;
+        let mut stmt = conn.prepare_cached(
+            "INSERT INTO graphs (file, tag, error, value, generation) VALUES (?, ?, ?, ?, ?)",
+        )?;
         let graph = crate::serde::StackGraph::default();
         let serialized = bincode::encode_to_vec(&graph, BINCODE_CONFIG)?;
-        stmt.execute((&file.to_string_lossy(), tag, error, serialized))?;
+        stmt.execute((&file.to_string_lossy(), tag, error, serialized, generation))?;
         Ok(())
     }
 
@@ -310,83 +716,258 @@ impl SQLiteWriter {
     where
         IP: IntoIterator<Item = &'a PartialPath>,
     {
-        let path = Path::new(graph[file].name());
+        let result = FileResult::new(graph, file, partials, paths)?;
+        self.conn.store_file_result(tag, &result)
+    }
+
+    /// Store the serialized result of a file index.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn store_file_result_inner(conn: &Connection, tag: &str, result: &FileResult) -> Result<()> {
+        copious_debugging!("--> Store result for {}", result.file);
+        let generation = Self::next_generation_inner(conn)?;
+        let mut graph_stmt = conn.prepare_cached(
+            "INSERT INTO graphs (file, tag, value, generation) VALUES (?, ?, ?, ?)",
+        )?;
+        graph_stmt.execute((&result.file, tag, &result.graph, generation))?;
+        let mut node_stmt = conn.prepare_cached(
+            "INSERT INTO file_paths (file, local_id, end_local_id, end_symbol_stack, value) VALUES (?, ?, ?, ?, ?)",
+        )?;
+        for (local_id, values) in &result.node_paths {
+            for (end, value) in values {
+                let (end_local_id, end_symbol_stack) = end.columns();
+                node_stmt.execute((
+                    &result.file,
+                    local_id,
+                    end_local_id,
+                    end_symbol_stack,
+                    value,
+                ))?;
+            }
+        }
+        let mut root_stmt = conn.prepare_cached(
+            "INSERT INTO root_paths (file, symbol_stack, end_local_id, end_symbol_stack, value) VALUES (?, ?, ?, ?, ?)",
+        )?;
+        for (symbol_stack, end, value) in &result.root_paths {
+            let (end_local_id, end_symbol_stack) = end.columns();
+            root_stmt.execute((
+                &result.file,
+                symbol_stack.to_string(),
+                end_local_id,
+                end_symbol_stack,
+                value,
+            ))?;
+        }
+        // resolutions may depend on the root paths that were added
+        Self::invalidate_resolutions_inner(conn, &result.file)?;
+        Ok(())
+    }
+
+    /// Store the resolutions of references, which are used by [`SQLiteReader::load_resolved_definitions`][]
+    /// to answer queries without stitching paths. Any resolutions that were stored earlier for the
+    /// same references are replaced.
+    ///
+    /// Stored resolutions are removed when any of the files they depend on is changed or removed,
+    /// or when root paths that could have been used to resolve the reference are added.
+    pub fn store_resolutions<'a, IR>(&mut self, resolutions: IR) -> Result<()>
+    where
+        IR: IntoIterator<Item = &'a Resolution>,
+    {
         let tx = self.conn.transaction()?;
-        Self::clean_file_inner(&tx, path)?;
-        Self::store_graph_for_file_inner(&tx, graph, file, tag)?;
-        Self::store_partial_paths_for_file_inner(&tx, graph, file, partials, paths)?;
+        for resolution in resolutions {
+            Self::store_resolution_inner(&tx, resolution)?;
+        }
         tx.commit()?;
         Ok(())
     }
 
-    /// Store the file graph.
+    /// Store the resolution of a reference.
     ///
     /// This is an inner method, which does not wrap individual SQL statements in a transaction.
-    fn store_graph_for_file_inner(
-        conn: &Connection,
-        graph: &StackGraph,
-        file: Handle<File>,
-        tag: &str,
-    ) -> Result<()> {
-        let file_str = graph[file].name();
-        copious_debugging!("--> Store graph for {}", file_str);
-        let mut stmt =
-            conn.prepare_cached("INSERT INTO graphs (file, tag, value) VALUES (?, ?, ?)")?;
-        let graph = serde::StackGraph::from_graph_filter(graph, &FileFilter(file));
-        let serialized = bincode::encode_to_vec(&graph, BINCODE_CONFIG)?;
-        stmt.execute((file_str, tag, &serialized))?;
+    fn store_resolution_inner(conn: &Connection, resolution: &Resolution) -> Result<()> {
+        let (file, local_id) = &resolution.reference;
+        copious_debugging!("--> Store resolution for {}:{}", file, local_id);
+        Self::remove_resolution_inner(conn, file, *local_id)?;
+        {
+            let mut stmt = conn.prepare_cached(
+                "INSERT INTO resolutions (file, local_id, definition_file, definition_local_id) VALUES (?, ?, ?, ?)",
+            )?;
+            if resolution.definitions.is_empty() {
+                // an unresolved reference is stored without a definition, so that it is known to be up to date
+                stmt.execute((file, local_id, None::<&str>, None::<u32>))?;
+            }
+            for (definition_file, definition_local_id) in &resolution.definitions {
+                stmt.execute((file, local_id, definition_file, definition_local_id))?;
+            }
+        }
+        {
+            let mut stmt = conn.prepare_cached(
+                "INSERT INTO resolution_files (file, local_id, dependency) VALUES (?, ?, ?)",
+            )?;
+            for dependency in &resolution.dependency_files {
+                stmt.execute((file, local_id, dependency))?;
+            }
+        }
+        {
+            let mut stmt = conn.prepare_cached(
+                "INSERT INTO resolution_root_paths (file, local_id, symbol_stack) VALUES (?, ?, ?)",
+            )?;
+            for pattern in &resolution.dependency_root_paths {
+                stmt.execute((file, local_id, pattern.like_pattern().0))?;
+            }
+        }
         Ok(())
     }
 
-    /// Store the file partial paths.
+    /// Remove the resolutions that may be affected by a change to the given file. These are the
+    /// resolutions of references in the file, the resolutions that depend on the file, and the
+    /// resolutions that looked up root paths matching the root paths currently stored for the file.
     ///
     /// This is an inner method, which does not wrap individual SQL statements in a transaction.
-    fn store_partial_paths_for_file_inner<'a, IP>(
-        conn: &Connection,
+    fn invalidate_resolutions_inner(conn: &Connection, file: &str) -> Result<()> {
+        let references = {
+            let mut stmt = conn.prepare_cached(
+                r#"
+                SELECT file, local_id FROM resolutions WHERE file = ?1
+                UNION
+                SELECT file, local_id FROM resolution_files WHERE dependency = ?1
+                UNION
+                SELECT d.file, d.local_id FROM resolution_root_paths AS d
+                    JOIN root_paths AS r ON r.symbol_stack LIKE d.symbol_stack ESCAPE '\'
+                    WHERE r.file = ?1
+                "#,
+            )?;
+            let references = stmt
+                .query_map([file], |r| {
+                    Ok((r.get::<_, String>(0)?, r.get::<_, u32>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            references
+        };
+        for (file, local_id) in references {
+            Self::remove_resolution_inner(conn, &file, local_id)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the resolution of the given reference.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn remove_resolution_inner(conn: &Connection, file: &str, local_id: u32) -> Result<()> {
+        for table in ["resolutions", "resolution_files", "resolution_root_paths"] {
+            let mut stmt = conn.prepare_cached(&format!(
+                "DELETE FROM {} WHERE file = ? AND local_id = ?",
+                table
+            ))?;
+            stmt.execute((file, local_id))?;
+        }
+        Ok(())
+    }
+
+    /// Increment the generation of the database, and return the new generation. Files are stored
+    /// with the generation at which they were written, so that readers can find the files that
+    /// changed since an earlier generation.
+    ///
+    /// This is an inner method, which does not wrap individual SQL statements in a transaction.
+    fn next_generation_inner(conn: &Connection) -> Result<u64> {
+        let mut update_stmt =
+            conn.prepare_cached("UPDATE metadata SET generation = generation + 1")?;
+        update_stmt.execute([])?;
+        let mut select_stmt = conn.prepare_cached("SELECT generation FROM metadata")?;
+        let generation = select_stmt.query_row([], |r| r.get::<_, u64>(0))?;
+        Ok(generation)
+    }
+
+    /// Get the file's status in the database. If a tag is provided, it must match or the file
+    /// is reported missing.
+    pub fn status_for_file(&mut self, file: &str, tag: Option<&str>) -> Result<FileStatus> {
+        status_for_file(&self.conn, file, tag)
+    }
+
+    /// Returns a [`Files`][] value that can be used to iterate over all descendants of a
+    /// file or directory in the database.
+    pub fn list_file_or_directory<'a>(
+        &'a self,
+        file_or_directory: &Path,
+    ) -> Result<Files<'a, [String; 1]>> {
+        SQLiteReader::list_file_or_directory_inner(&self.conn, file_or_directory)
+    }
+
+    /// Convert this writer into a reader for the same database.
+    pub fn into_reader(self) -> SQLiteReader {
+        SQLiteReader::new(self.conn)
+    }
+}
+
+/// The serialized index results for a single file, as they are stored by a [`StorageBackend`][].
+#[derive(Clone)]
+pub struct FileResult {
+    /// The file the results are for.
+    pub file: String,
+    /// The serialized stack graph of the file.
+    pub graph: Vec<u8>,
+    /// The serialized partial paths starting at nodes in the file, by the local ID of their start
+    /// node, with their end.
+    pub node_paths: HashMap<u32, Vec<(PathEnd, Vec<u8>)>>,
+    /// The serialized partial paths starting at the root node, with the key of their symbol stack
+    /// precondition, and their end.
+    pub root_paths: Vec<(SymbolStackKey, PathEnd, Vec<u8>)>,
+}
+
+impl FileResult {
+    /// Create a result from the graph and partial paths of a file. The partial paths must
+    /// start in the given file or at the root node, and end in the given file, at the root node,
+    /// or at the jump to scope node.
+    pub fn new<'a, IP>(
         graph: &StackGraph,
         file: Handle<File>,
         partials: &mut PartialPaths,
         paths: IP,
-    ) -> Result<()>
+    ) -> Result<Self>
     where
         IP: IntoIterator<Item = &'a PartialPath>,
     {
-        let file_str = graph[file].name();
-        let mut node_stmt =
-            conn.prepare_cached("INSERT INTO file_paths (file, local_id, value) VALUES (?, ?, ?)")?;
-        let mut root_stmt = conn.prepare_cached(
-            "INSERT INTO root_paths (file, symbol_stack, value) VALUES (?, ?, ?)",
-        )?;
-        #[cfg_attr(not(feature = "copious-debugging"), allow(unused))]
-        let mut node_path_count = 0usize;
-        #[cfg_attr(not(feature = "copious-debugging"), allow(unused))]
-        let mut root_path_count = 0usize;
+        let file_graph = serde::StackGraph::from_graph_filter(graph, &FileFilter(file));
+        let mut result = Self {
+            file: graph[file].name().to_string(),
+            graph: bincode::encode_to_vec(&file_graph, BINCODE_CONFIG)?,
+            node_paths: HashMap::new(),
+            root_paths: Vec::new(),
+        };
         for path in paths {
             copious_debugging!(
                 "--> Add {} partial path {}",
-                file_str,
+                result.file,
                 path.display(graph, partials)
             );
             let start_node = graph[path.start_node].id();
+            let serialized_path = serde::PartialPath::from_partial_path(graph, partials, path);
+            let end = PathEnd::new(&result.file, &serialized_path).unwrap_or_else(|| {
+                panic!(
+                    "added path {} must end in given file {}, at root, or at jump to scope",
+                    path.display(graph, partials),
+                    graph[file].name()
+                )
+            });
             if start_node.is_root() {
                 copious_debugging!(
                     " * Add as root path with symbol stack {}",
                     path.symbol_stack_precondition.display(graph, partials),
                 );
                 let symbol_stack = path.symbol_stack_precondition.storage_key(graph, partials);
-                let path = serde::PartialPath::from_partial_path(graph, partials, path);
-                let serialized = bincode::encode_to_vec(&path, BINCODE_CONFIG)?;
-                root_stmt.execute((file_str, symbol_stack, serialized))?;
-                root_path_count += 1;
+                let serialized = bincode::encode_to_vec(&serialized_path, BINCODE_CONFIG)?;
+                result.root_paths.push((symbol_stack, end, serialized));
             } else if start_node.is_in_file(file) {
                 copious_debugging!(
                     " * Add as node path from node {}",
                     path.start_node.display(graph),
                 );
-                let path = serde::PartialPath::from_partial_path(graph, partials, path);
-                let serialized = bincode::encode_to_vec(&path, BINCODE_CONFIG)?;
-                node_stmt.execute((file_str, path.start_node.local_id, serialized))?;
-                node_path_count += 1;
+                let serialized = bincode::encode_to_vec(&serialized_path, BINCODE_CONFIG)?;
+                result
+                    .node_paths
+                    .entry(start_node.local_id())
+                    .or_default()
+                    .push((end, serialized));
             } else {
                 panic!(
                     "added path {} must start in given file {} or at root",
@@ -394,80 +975,821 @@ impl SQLiteWriter {
                     graph[file].name()
                 );
             }
-            copious_debugging!(
-                " * Added {} node paths and {} root paths",
-                node_path_count,
-                root_path_count,
-            );
         }
-        Ok(())
+        Ok(result)
     }
 
-    /// Get the file's status in the database. If a tag is provided, it must match or the file
-    /// is reported missing.
-    pub fn status_for_file(&mut self, file: &str, tag: Option<&str>) -> Result<FileStatus> {
-        status_for_file(&self.conn, file, tag)
+    /// Returns all serialized partial paths of this result, with their end.
+    fn all_paths(&self) -> impl Iterator<Item = (&PathEnd, &Vec<u8>)> + '_ {
+        self.node_paths
+            .values()
+            .flatten()
+            .map(|(end, value)| (end, value))
+            .chain(self.root_paths.iter().map(|(_, end, value)| (end, value)))
     }
+}
 
-    /// Convert this writer into a reader for the same database.
-    pub fn into_reader(self) -> SQLiteReader {
-        SQLiteReader {
-            conn: self.conn,
-            loaded_graphs: HashSet::new(),
-            loaded_node_paths: HashSet::new(),
-            loaded_root_paths: HashSet::new(),
-            graph: StackGraph::new(),
-            partials: PartialPaths::new(),
-            db: Database::new(),
-            stats: Stats::default(),
+/// The end of a stored partial path, which is used to find the partial paths that can extend a
+/// path backwards.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum PathEnd {
+    /// The path ends at the node with the given local ID in the file it is stored for.
+    Node(u32),
+    /// The path ends at the root node, with the given key of its symbol stack postcondition.
+    Root(SymbolStackKey),
+    /// The path ends at the jump to scope node.
+    JumpTo,
+}
+
+impl PathEnd {
+    /// Returns the end of a serialized path stored for the given file, or `None` if the path ends
+    /// at a node in another file.
+    fn new(file: &str, path: &serde::PartialPath) -> Option<Self> {
+        let end_node = &path.end_node;
+        match &end_node.file {
+            Some(end_file) if end_file == file => Some(Self::Node(end_node.local_id)),
+            Some(_) => None,
+            None if end_node.local_id == ROOT_NODE_ID => {
+                let symbol_stack = &path.symbol_stack_postcondition;
+                Some(Self::Root(SymbolStackKey {
+                    symbols: symbol_stack
+                        .symbols
+                        .iter()
+                        .map(|s| s.symbol.clone())
+                        .collect(),
+                    has_variable: symbol_stack.variable.is_some(),
+                }))
+            }
+            None => Some(Self::JumpTo),
+        }
+    }
+
+    /// Returns the values of the `end_local_id` and `end_symbol_stack` columns for this end. Both
+    /// are `NULL` for paths ending at the jump to scope node.
+    fn columns(&self) -> (Option<u32>, Option<String>) {
+        match self {
+            Self::Node(local_id) => (Some(*local_id), None),
+            Self::Root(symbol_stack) => (None, Some(symbol_stack.to_string())),
+            Self::JumpTo => (None, None),
         }
     }
 }
 
-/// Reader to load stack graphs and partial paths from a SQLite database.
-pub struct SQLiteReader {
-    conn: Connection,
-    loaded_graphs: HashSet<String>,
-    loaded_node_paths: HashSet<Handle<Node>>,
-    loaded_root_paths: HashSet<String>,
-    graph: StackGraph,
-    partials: PartialPaths,
-    db: Database,
-    stats: Stats,
+/// In-memory index results for a single file. When added to a [`StorageReader`][], the overlay
+/// takes precedence over any data stored in the backend for the same file. This makes it
+/// possible to query the contents of files that have not been written to the database, such as
+/// unsaved editor buffers.
+#[derive(Clone)]
+pub struct FileOverlay {
+    result: FileResult,
 }
 
-impl SQLiteReader {
-    /// Open a file database.
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        if !path.as_ref().exists() {
-            return Err(StorageError::MissingDatabase(
-                path.as_ref().to_string_lossy().to_string(),
-            ));
-        }
-        let mut conn = Connection::open(path)?;
-        set_pragmas_and_functions(&conn)?;
-        check_version(&conn)?;
-        init_indexes(&mut conn)?;
+impl FileOverlay {
+    /// Create an overlay from the graph and partial paths of a file. The partial paths must
+    /// start in the given file or at the root node.
+    pub fn new<'a, IP>(
+        graph: &StackGraph,
+        file: Handle<File>,
+        partials: &mut PartialPaths,
+        paths: IP,
+    ) -> Result<Self>
+    where
+        IP: IntoIterator<Item = &'a PartialPath>,
+    {
         Ok(Self {
-            conn,
-            loaded_graphs: HashSet::new(),
-            loaded_node_paths: HashSet::new(),
-            loaded_root_paths: HashSet::new(),
-            graph: StackGraph::new(),
+            result: FileResult::new(graph, file, partials, paths)?,
+        })
+    }
+
+    /// The file this overlay contains data for.
+    pub fn file(&self) -> &str {
+        &self.result.file
+    }
+
+    /// Returns a version for the content of this overlay, which is used instead of the tag of
+    /// the file to decide whether cached stitching results are up to date.
+    fn version(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        self.result.graph.hash(&mut hasher);
+        format!("overlay:{:016x}", hasher.finish())
+    }
+
+    /// Returns whether this overlay has root paths whose symbol stack key matches the given
+    /// pattern.
+    fn has_root_paths(&self, pattern: &SymbolStackKeyPattern) -> bool {
+        self.result
+            .root_paths
+            .iter()
+            .any(|(key, _, _)| pattern.matches(key))
+    }
+}
+
+/// A cache of the complete paths found by path stitching, which can be reused by later queries.
+/// Entries are keyed by the start node and symbol stack of the stitched paths. The cache does not
+/// depend on the handles of a particular [`StorageReader`][], so it remains valid when the reader
+/// is cleared, and it can be moved from one reader to another.
+///
+/// Each entry records the tags of the files that contributed partial paths to its results, the
+/// root path patterns that were looked up, the generation of the backend, and the versions of the
+/// overlays of the reader. When an entry is looked up, it is discarded and its paths are stitched
+/// again if the data it was computed from may have changed. This is the case if the tag of any
+/// of the files it depends on has changed, for example because a [`SQLiteWriter`][] stored new
+/// results for it, or if a file that was stored since, by any writer, has root paths matching the
+/// patterns. Similarly, entries are discarded if an overlay was removed, or if an overlay was
+/// added or changed for a file they depend on or with root paths matching the patterns.
+#[derive(Clone, Default)]
+pub struct StitchingCache {
+    entries: HashMap<StitchingCacheKey, StitchingCacheEntry>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct StitchingCacheKey {
+    file: Option<String>,
+    local_id: u32,
+    symbol_stack: SymbolStackKey,
+    detect_similar_paths: bool,
+    max_similar_paths: Option<usize>,
+    max_complete_paths: Option<usize>,
+    max_work: Option<usize>,
+    sorted_results: bool,
+}
+
+#[derive(Clone)]
+struct StitchingCacheEntry {
+    /// The generation of the backend when the entry was created.
+    generation: u64,
+    /// The versions of the overlays of the reader when the entry was created.
+    overlays: HashMap<String, String>,
+    /// The files that contributed partial paths, with their version when the entry was created.
+    dependencies: HashMap<String, Option<String>>,
+    /// The files that contain the nodes of the paths, which must be loaded to restore them.
+    files: HashSet<String>,
+    /// The patterns of the root paths that were looked up.
+    root_paths: HashSet<SymbolStackKeyPattern>,
+    paths: Vec<serde::PartialPath>,
+}
+
+impl StitchingCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of cached entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all entries from the cache.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Remove all entries whose results depend on the given file.
+    pub fn invalidate_file(&mut self, file: &str) {
+        self.entries
+            .retain(|_, entry| !entry.dependencies.contains_key(file));
+    }
+}
+
+/// The visible definitions of a reference, together with the data that was used to find them.
+/// Resolutions are computed by [`StorageReader::resolve_reference`][], and stored in the
+/// database by [`SQLiteWriter::store_resolutions`][].
+#[derive(Clone, Debug)]
+pub struct Resolution {
+    /// The file and local ID of the reference.
+    reference: (String, u32),
+    /// The files and local IDs of the visible definitions.
+    definitions: Vec<(String, u32)>,
+    /// The files whose graphs or node paths were used.
+    dependency_files: HashSet<String>,
+    /// The patterns of the root paths that were looked up.
+    dependency_root_paths: HashSet<SymbolStackKeyPattern>,
+}
+
+impl Resolution {
+    /// Returns the number of visible definitions of the reference.
+    pub fn definition_count(&self) -> usize {
+        self.definitions.len()
+    }
+}
+
+/// The data that was loaded while stitching, if it is being tracked.
+#[derive(Default)]
+struct LoadedDependencies {
+    /// The files of loaded node paths, and of candidates returned to the stitcher.
+    files: HashSet<Handle<File>>,
+    /// The patterns of the root paths that were looked up.
+    root_paths: HashSet<SymbolStackKeyPattern>,
+}
+
+/// The direction in which stored paths are looked up: by their start, to extend paths forwards, or
+/// by their end, to extend paths backwards.
+#[derive(Clone, Copy)]
+enum PathDirection {
+    Forward,
+    Backward,
+}
+
+/// The key of a symbol stack, which is used to find root paths whose symbol stack precondition
+/// is compatible with the symbol stack of the path that is extended.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SymbolStackKey {
+    pub symbols: Vec<String>,
+    pub has_variable: bool,
+}
+
+// The string format of a storage key, as stored in the database, is:
+//
+//     has-var GS ( symbol (US symbol)* )?
+//
+// where has-var is "V" if the symbol stack has a variable, "X" otherwise.
+impl std::fmt::Display for SymbolStackKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.has_variable {
+            true => write!(f, "V\u{241E}")?,
+            false => write!(f, "X\u{241E}")?,
+        }
+        write!(f, "{}", self.symbols.iter().join("\u{241F}"))
+    }
+}
+
+/// A pattern that selects symbol stack keys.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum SymbolStackKeyPattern {
+    /// Matches keys with exactly the given symbols, and a variable.
+    Prefix(Vec<String>),
+    /// Matches keys with exactly the given symbols, and no variable.
+    Exact(Vec<String>),
+    /// Matches keys that start with the given symbols, followed by at least one more symbol.
+    Extension(Vec<String>),
+}
+
+impl SymbolStackKeyPattern {
+    /// Returns whether the given key matches this pattern.
+    pub fn matches(&self, key: &SymbolStackKey) -> bool {
+        match self {
+            Self::Prefix(symbols) => key.has_variable && &key.symbols == symbols,
+            Self::Exact(symbols) => !key.has_variable && &key.symbols == symbols,
+            Self::Extension(symbols) => {
+                key.symbols.len() > symbols.len() && key.symbols.starts_with(symbols)
+            }
+        }
+    }
+
+    /// Returns a pattern for the SQL `LIKE` operator, and the escape character used in it.
+    fn like_pattern(&self) -> (String, String) {
+        let symbols = |symbols: &[String]| {
+            symbols
+                .iter()
+                .map(|s| s.replace("%", "\\%").replace("_", "\\_"))
+                .join("\u{241F}")
+        };
+        let pattern = match self {
+            Self::Prefix(s) => "V\u{241E}".to_string() + &symbols(s),
+            Self::Exact(s) => "X\u{241E}".to_string() + &symbols(s),
+            Self::Extension(s) => "_\u{241E}".to_string() + &symbols(s) + "\u{241F}%",
+        };
+        (pattern, "\\".to_string())
+    }
+}
+
+/// A storage backend for the serialized stack graphs and partial paths of indexed files.
+pub trait StorageBackend {
+    /// Get the file's status. If a tag is provided, it must match or the file is reported missing.
+    fn status_for_file(&self, file: &str, tag: Option<&str>) -> Result<FileStatus>;
+
+    /// Get the tag the file was stored with, or `None` if the file is missing.
+    fn tag_for_file(&self, file: &str) -> Result<Option<String>>;
+
+    /// Get the generation of the stored data, which increases whenever data is stored or removed.
+    fn generation(&self) -> Result<u64>;
+
+    /// Returns whether any file that was stored after the given generation has root paths whose
+    /// symbol stack key matches the given pattern.
+    fn has_root_paths_since(
+        &self,
+        generation: u64,
+        pattern: &SymbolStackKeyPattern,
+    ) -> Result<bool>;
+
+    /// Store the result of a successful file index, replacing any data that was stored for the file.
+    fn store_file_result(&mut self, tag: &str, result: &FileResult) -> Result<()>;
+
+    /// Load the serialized graph of a file.
+    fn load_graph(&self, file: &str) -> Result<Vec<u8>>;
+
+    /// Load the serialized partial paths starting at the node with the given local ID in a file.
+    fn load_node_paths(&self, file: &str, local_id: u32) -> Result<Vec<Vec<u8>>>;
+
+    /// Load the serialized partial paths starting at the root node, whose symbol stack key
+    /// matches the given pattern, together with the file they belong to.
+    fn load_root_paths(&self, pattern: &SymbolStackKeyPattern) -> Result<Vec<(String, Vec<u8>)>>;
+
+    /// Load the serialized partial paths ending at the node with the given local ID in a file.
+    fn load_paths_to_node(&self, file: &str, local_id: u32) -> Result<Vec<Vec<u8>>>;
+
+    /// Load the serialized partial paths ending at the root node, whose symbol stack
+    /// postcondition key matches the given pattern, together with the file they belong to.
+    fn load_paths_to_root(&self, pattern: &SymbolStackKeyPattern)
+        -> Result<Vec<(String, Vec<u8>)>>;
+
+    /// Load the serialized partial paths ending at the jump to scope node, together with the file
+    /// they belong to.
+    fn load_paths_to_jump_node(&self) -> Result<Vec<(String, Vec<u8>)>>;
+}
+
+impl StorageBackend for Connection {
+    fn status_for_file(&self, file: &str, tag: Option<&str>) -> Result<FileStatus> {
+        status_for_file(self, file, tag)
+    }
+
+    fn tag_for_file(&self, file: &str) -> Result<Option<String>> {
+        let mut stmt = self.prepare_cached("SELECT tag FROM graphs WHERE file = ?")?;
+        let tag = stmt
+            .query_row([file], |row| row.get::<_, String>(0))
+            .optional()?;
+        Ok(tag)
+    }
+
+    fn generation(&self) -> Result<u64> {
+        let mut stmt = self.prepare_cached("SELECT generation FROM metadata")?;
+        let generation = stmt.query_row([], |row| row.get::<_, u64>(0))?;
+        Ok(generation)
+    }
+
+    fn has_root_paths_since(
+        &self,
+        generation: u64,
+        pattern: &SymbolStackKeyPattern,
+    ) -> Result<bool> {
+        let (like_pattern, escape) = pattern.like_pattern();
+        let mut stmt = self.prepare_cached(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM root_paths AS r JOIN graphs AS g ON g.file = r.file
+                    WHERE g.generation > ?1 AND r.symbol_stack LIKE ?2 ESCAPE ?3
+            )
+            "#,
+        )?;
+        let exists = stmt.query_row((generation, like_pattern, escape), |row| {
+            row.get::<_, bool>(0)
+        })?;
+        Ok(exists)
+    }
+
+    fn store_file_result(&mut self, tag: &str, result: &FileResult) -> Result<()> {
+        let tx = self.transaction()?;
+        SQLiteWriter::clean_file_inner(&tx, Path::new(&result.file))?;
+        SQLiteWriter::store_file_result_inner(&tx, tag, result)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_graph(&self, file: &str) -> Result<Vec<u8>> {
+        let mut stmt = self.prepare_cached("SELECT value FROM graphs WHERE file = ?")?;
+        let value = stmt.query_row([file], |row| row.get::<_, Vec<u8>>(0))?;
+        Ok(value)
+    }
+
+    fn load_node_paths(&self, file: &str, local_id: u32) -> Result<Vec<Vec<u8>>> {
+        let mut stmt =
+            self.prepare_cached("SELECT value from file_paths WHERE file = ? AND local_id = ?")?;
+        let values = stmt
+            .query_map((file, local_id), |row| row.get::<_, Vec<u8>>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(values)
+    }
+
+    fn load_root_paths(&self, pattern: &SymbolStackKeyPattern) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut stmt = self.prepare_cached(
+            "SELECT file,value from root_paths WHERE symbol_stack LIKE ? ESCAPE ?",
+        )?;
+        let values = stmt
+            .query_map(pattern.like_pattern(), |row| {
+                let file = row.get::<_, String>(0)?;
+                let value = row.get::<_, Vec<u8>>(1)?;
+                Ok((file, value))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(values)
+    }
+
+    fn load_paths_to_node(&self, file: &str, local_id: u32) -> Result<Vec<Vec<u8>>> {
+        let mut stmt = self.prepare_cached(
+            r#"
+            SELECT value from file_paths WHERE file = ?1 AND end_local_id = ?2
+            UNION ALL
+            SELECT value from root_paths WHERE file = ?1 AND end_local_id = ?2
+            "#,
+        )?;
+        let values = stmt
+            .query_map((file, local_id), |row| row.get::<_, Vec<u8>>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(values)
+    }
+
+    fn load_paths_to_root(
+        &self,
+        pattern: &SymbolStackKeyPattern,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut stmt = self.prepare_cached(
+            r#"
+            SELECT file,value from file_paths WHERE end_symbol_stack LIKE ?1 ESCAPE ?2
+            UNION ALL
+            SELECT file,value from root_paths WHERE end_symbol_stack LIKE ?1 ESCAPE ?2
+            "#,
+        )?;
+        let values = stmt
+            .query_map(pattern.like_pattern(), |row| {
+                let file = row.get::<_, String>(0)?;
+                let value = row.get::<_, Vec<u8>>(1)?;
+                Ok((file, value))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(values)
+    }
+
+    fn load_paths_to_jump_node(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut stmt = self.prepare_cached(
+            r#"
+            SELECT file,value from file_paths WHERE end_local_id IS NULL AND end_symbol_stack IS NULL
+            UNION ALL
+            SELECT file,value from root_paths WHERE end_local_id IS NULL AND end_symbol_stack IS NULL
+            "#,
+        )?;
+        let values = stmt
+            .query_map([], |row| {
+                let file = row.get::<_, String>(0)?;
+                let value = row.get::<_, Vec<u8>>(1)?;
+                Ok((file, value))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(values)
+    }
+}
+
+/// A storage backend that keeps all data in memory.
+#[derive(Clone, Default)]
+pub struct InMemoryBackend {
+    /// The stored files, with their tag and the generation at which they were stored.
+    files: HashMap<String, (String, u64, FileResult)>,
+    generation: u64,
+}
+
+impl InMemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remove all data for the given file. Returns whether data was stored for the file.
+    pub fn clean_file(&mut self, file: &str) -> bool {
+        self.generation += 1;
+        self.files.remove(file).is_some()
+    }
+}
+
+impl StorageBackend for InMemoryBackend {
+    fn status_for_file(&self, file: &str, tag: Option<&str>) -> Result<FileStatus> {
+        let status = match self.files.get(file) {
+            Some((file_tag, _, _)) if tag.is_none() || tag == Some(file_tag) => FileStatus::Indexed,
+            _ => FileStatus::Missing,
+        };
+        Ok(status)
+    }
+
+    fn tag_for_file(&self, file: &str) -> Result<Option<String>> {
+        Ok(self.files.get(file).map(|(tag, _, _)| tag.clone()))
+    }
+
+    fn generation(&self) -> Result<u64> {
+        Ok(self.generation)
+    }
+
+    fn has_root_paths_since(
+        &self,
+        generation: u64,
+        pattern: &SymbolStackKeyPattern,
+    ) -> Result<bool> {
+        let exists = self.files.values().any(|(_, file_generation, result)| {
+            *file_generation > generation
+                && result
+                    .root_paths
+                    .iter()
+                    .any(|(key, _, _)| pattern.matches(key))
+        });
+        Ok(exists)
+    }
+
+    fn store_file_result(&mut self, tag: &str, result: &FileResult) -> Result<()> {
+        self.generation += 1;
+        self.files.insert(
+            result.file.clone(),
+            (tag.to_string(), self.generation, result.clone()),
+        );
+        Ok(())
+    }
+
+    fn load_graph(&self, file: &str) -> Result<Vec<u8>> {
+        self.files
+            .get(file)
+            .map(|(_, _, result)| result.graph.clone())
+            .ok_or_else(|| StorageError::MissingFile(file.to_string()))
+    }
+
+    fn load_node_paths(&self, file: &str, local_id: u32) -> Result<Vec<Vec<u8>>> {
+        let values = self
+            .files
+            .get(file)
+            .and_then(|(_, _, result)| result.node_paths.get(&local_id))
+            .into_iter()
+            .flatten()
+            .map(|(_, value)| value.clone())
+            .collect();
+        Ok(values)
+    }
+
+    fn load_root_paths(&self, pattern: &SymbolStackKeyPattern) -> Result<Vec<(String, Vec<u8>)>> {
+        let values = self
+            .files
+            .values()
+            .flat_map(|(_, _, result)| {
+                result
+                    .root_paths
+                    .iter()
+                    .filter(|(key, _, _)| pattern.matches(key))
+                    .map(move |(_, _, value)| (result.file.clone(), value.clone()))
+            })
+            .collect();
+        Ok(values)
+    }
+
+    fn load_paths_to_node(&self, file: &str, local_id: u32) -> Result<Vec<Vec<u8>>> {
+        let values = self
+            .files
+            .get(file)
+            .into_iter()
+            .flat_map(|(_, _, result)| result.all_paths())
+            .filter(|(end, _)| **end == PathEnd::Node(local_id))
+            .map(|(_, value)| value.clone())
+            .collect();
+        Ok(values)
+    }
+
+    fn load_paths_to_root(
+        &self,
+        pattern: &SymbolStackKeyPattern,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        let values = self
+            .files
+            .values()
+            .flat_map(|(_, _, result)| {
+                result
+                    .all_paths()
+                    .filter(|(end, _)| matches!(end, PathEnd::Root(key) if pattern.matches(key)))
+                    .map(move |(_, value)| (result.file.clone(), value.clone()))
+            })
+            .collect();
+        Ok(values)
+    }
+
+    fn load_paths_to_jump_node(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        let values = self
+            .files
+            .values()
+            .flat_map(|(_, _, result)| {
+                result
+                    .all_paths()
+                    .filter(|(end, _)| **end == PathEnd::JumpTo)
+                    .map(move |(_, value)| (result.file.clone(), value.clone()))
+            })
+            .collect();
+        Ok(values)
+    }
+}
+
+/// Reader to load stack graphs and partial paths from a storage backend.
+pub struct StorageReader<B: StorageBackend> {
+    backend: B,
+    overlays: HashMap<String, FileOverlay>,
+    loaded_graphs: HashSet<String>,
+    loaded_node_paths: HashSet<Handle<Node>>,
+    loaded_root_paths: HashSet<SymbolStackKeyPattern>,
+    loaded_overlay_root_paths: HashSet<String>,
+    loaded_node_paths_to: HashSet<Handle<Node>>,
+    loaded_root_paths_to: HashSet<SymbolStackKeyPattern>,
+    loaded_overlay_root_paths_to: HashSet<String>,
+    loaded_jump_paths: bool,
+    graph: StackGraph,
+    partials: PartialPaths,
+    db: Database,
+    stats: Stats,
+    stitching_cache: Option<StitchingCache>,
+    dependencies: Option<LoadedDependencies>,
+}
+
+/// Reader to load stack graphs and partial paths from a SQLite database.
+pub type SQLiteReader = StorageReader<Connection>;
+
+impl SQLiteReader {
+    /// Open a file database.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        if !path.as_ref().exists() {
+            return Err(StorageError::MissingDatabase(
+                path.as_ref().to_string_lossy().to_string(),
+            ));
+        }
+        let mut conn = Connection::open(path)?;
+        set_pragmas_and_functions(&conn)?;
+        check_version(&conn)?;
+        init_indexes(&mut conn)?;
+        Ok(Self::new(conn))
+    }
+
+    /// Returns a [`Files`][] value that can be used to iterate over all files in the database.
+    pub fn list_all<'a>(&'a mut self) -> Result<Files<'a, ()>> {
+        self.backend
+            .prepare("SELECT file, tag, error FROM graphs")
+            .map(|stmt| Files(stmt, ()))
+            .map_err(|e| e.into())
+    }
+
+    /// Returns a [`Files`][] value that can be used to iterate over all descendants of a
+    /// file or directory in the database.
+    pub fn list_file_or_directory<'a>(
+        &'a self,
+        file_or_directory: &Path,
+    ) -> Result<Files<'a, [String; 1]>> {
+        Self::list_file_or_directory_inner(&self.backend, file_or_directory)
+    }
+
+    fn list_file_or_directory_inner<'a>(
+        conn: &'a Connection,
+        file_or_directory: &Path,
+    ) -> Result<Files<'a, [String; 1]>> {
+        let file_or_directory = file_or_directory.to_string_lossy().to_string();
+        conn.prepare("SELECT file, tag, error FROM graphs WHERE path_descendant_of(file, ?)")
+            .map(|stmt| Files(stmt, [file_or_directory]))
+            .map_err(|e| e.into())
+    }
+
+    /// Returns whether an up-to-date resolution is stored for the given reference. Resolutions
+    /// that depend on a file with an overlay, or that looked up root paths matching those of an
+    /// overlay, are not up to date.
+    pub fn is_resolved(&self, reference: Handle<Node>) -> Result<bool> {
+        let id = self.graph[reference].id();
+        let file = match id.file() {
+            Some(file) => self.graph[file].name(),
+            None => return Ok(false),
+        };
+        let local_id = id.local_id();
+        {
+            let mut stmt = self
+                .backend
+                .prepare_cached("SELECT 1 FROM resolutions WHERE file = ? AND local_id = ?")?;
+            if !stmt.exists((file, local_id))? {
+                return Ok(false);
+            }
+        }
+        for overlay in self.overlays.values() {
+            if overlay.file() == file {
+                return Ok(false);
+            }
+            {
+                let mut stmt = self.backend.prepare_cached(
+                    "SELECT 1 FROM resolution_files WHERE file = ? AND local_id = ? AND dependency = ?",
+                )?;
+                if stmt.exists((file, local_id, overlay.file()))? {
+                    return Ok(false);
+                }
+            }
+            let mut stmt = self.backend.prepare_cached(
+                "SELECT 1 FROM resolution_root_paths WHERE file = ? AND local_id = ? AND ? LIKE symbol_stack ESCAPE '\\'",
+            )?;
+            for (symbol_stack, _, _) in &overlay.result.root_paths {
+                if stmt.exists((file, local_id, symbol_stack.to_string()))? {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Loads the visible definitions of the given reference from the resolutions stored by
+    /// [`SQLiteWriter::store_resolutions`][], and ensures the graphs of their files are loaded.
+    /// Returns `None` if no up-to-date resolution is stored for the reference, see
+    /// [`is_resolved`][], in which case the definitions must be found by stitching paths.
+    ///
+    /// [`is_resolved`]: #method.is_resolved
+    pub fn load_resolved_definitions(
+        &mut self,
+        reference: Handle<Node>,
+    ) -> Result<Option<Vec<Handle<Node>>>> {
+        if !self.is_resolved(reference)? {
+            return Ok(None);
+        }
+        let id = self.graph[reference].id();
+        let file = id.file().expect("resolved reference must be in a file");
+        let definitions = {
+            let mut stmt = self.backend.prepare_cached(
+                "SELECT definition_file, definition_local_id FROM resolutions WHERE file = ? AND local_id = ? AND definition_file IS NOT NULL",
+            )?;
+            let definitions = stmt
+                .query_map((self.graph[file].name(), id.local_id()), |r| {
+                    Ok((r.get::<_, String>(0)?, r.get::<_, u32>(1)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            definitions
+        };
+        self.stats.resolution_loads += 1;
+        let mut result = Vec::with_capacity(definitions.len());
+        for (file, local_id) in definitions {
+            let file = self.load_graph_for_file(&file)?;
+            match self.graph.node_for_id(NodeID::new_in_file(file, local_id)) {
+                Some(node) => result.push(node),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(result))
+    }
+
+    pub fn load_graphs_for_file_or_directory(
+        &mut self,
+        file_or_directory: &Path,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<()> {
+        for file in
+            Self::list_file_or_directory_inner(&self.backend, file_or_directory)?.try_iter()?
+        {
+            cancellation_flag.check("loading graphs")?;
+            let file = file?;
+            Self::load_graph_for_file_inner(
+                &file.path.to_string_lossy(),
+                &mut self.graph,
+                &mut self.loaded_graphs,
+                &self.backend,
+                &self.overlays,
+                &mut self.stats,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<B: StorageBackend> StorageReader<B> {
+    /// Create a reader for the given storage backend.
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            overlays: HashMap::new(),
+            loaded_graphs: HashSet::new(),
+            loaded_node_paths: HashSet::new(),
+            loaded_root_paths: HashSet::new(),
+            loaded_overlay_root_paths: HashSet::new(),
+            loaded_node_paths_to: HashSet::new(),
+            loaded_root_paths_to: HashSet::new(),
+            loaded_overlay_root_paths_to: HashSet::new(),
+            loaded_jump_paths: false,
+            graph: StackGraph::new(),
             partials: PartialPaths::new(),
             db: Database::new(),
             stats: Stats::default(),
-        })
+            stitching_cache: None,
+            dependencies: None,
+        }
+    }
+
+    /// Set the cache that is used by [`find_all_complete_partial_paths`][] to reuse the results
+    /// of earlier queries. Caching is disabled until a cache is set.
+    ///
+    /// [`find_all_complete_partial_paths`]: #method.find_all_complete_partial_paths
+    pub fn set_stitching_cache(&mut self, cache: StitchingCache) {
+        self.stitching_cache = Some(cache);
+    }
+
+    /// Remove the stitching cache from this reader, so that it can be used by another reader.
+    pub fn take_stitching_cache(&mut self) -> Option<StitchingCache> {
+        self.stitching_cache.take()
     }
 
     /// Clear all data that has been loaded into this reader instance.
     /// After this call, all existing handles from this reader are invalid.
+    /// The stitching cache, if any, is kept.
     pub fn clear(&mut self) {
         self.loaded_graphs.clear();
         self.graph = StackGraph::new();
 
         self.loaded_node_paths.clear();
         self.loaded_root_paths.clear();
+        self.loaded_overlay_root_paths.clear();
+        self.loaded_node_paths_to.clear();
+        self.loaded_root_paths_to.clear();
+        self.loaded_overlay_root_paths_to.clear();
+        self.loaded_jump_paths = false;
         self.partials.clear();
         self.db.clear();
 
@@ -480,47 +1802,45 @@ impl SQLiteReader {
     pub fn clear_paths(&mut self) {
         self.loaded_node_paths.clear();
         self.loaded_root_paths.clear();
+        self.loaded_overlay_root_paths.clear();
+        self.loaded_node_paths_to.clear();
+        self.loaded_root_paths_to.clear();
+        self.loaded_overlay_root_paths_to.clear();
+        self.loaded_jump_paths = false;
         self.partials.clear();
         self.db.clear();
 
         self.stats.clear_paths();
     }
 
-    /// Get the file's status in the database. If a tag is provided, it must match or the file
-    /// is reported missing.
-    pub fn status_for_file<T: AsRef<str>>(
-        &mut self,
-        file: &str,
-        tag: Option<T>,
-    ) -> Result<FileStatus> {
-        status_for_file(&self.conn, file, tag)
-    }
-
-    /// Returns a [`Files`][] value that can be used to iterate over all files in the database.
-    pub fn list_all<'a>(&'a mut self) -> Result<Files<'a, ()>> {
-        self.conn
-            .prepare("SELECT file, tag, error FROM graphs")
-            .map(|stmt| Files(stmt, ()))
-            .map_err(|e| e.into())
+    /// Add an overlay, which takes precedence over the data in the backend for the overlay's
+    /// file. Any previous overlay for the same file is replaced. If data for the file was already
+    /// loaded, all loaded data is cleared, and all existing handles from this reader are invalid.
+    pub fn add_overlay(&mut self, overlay: FileOverlay) {
+        if self.loaded_graphs.contains(overlay.file()) {
+            self.clear();
+        }
+        self.overlays.insert(overlay.file().to_string(), overlay);
     }
 
-    /// Returns a [`Files`][] value that can be used to iterate over all descendants of a
-    /// file or directory in the database.
-    pub fn list_file_or_directory<'a>(
-        &'a self,
-        file_or_directory: &Path,
-    ) -> Result<Files<'a, [String; 1]>> {
-        Self::list_file_or_directory_inner(&self.conn, file_or_directory)
+    /// Returns the files for which this reader has overlays.
+    pub fn overlay_files(&self) -> impl Iterator<Item = &str> {
+        self.overlays.keys().map(|f| f.as_str())
     }
 
-    fn list_file_or_directory_inner<'a>(
-        conn: &'a Connection,
-        file_or_directory: &Path,
-    ) -> Result<Files<'a, [String; 1]>> {
-        let file_or_directory = file_or_directory.to_string_lossy().to_string();
-        conn.prepare("SELECT file, tag, error FROM graphs WHERE path_descendant_of(file, ?)")
-            .map(|stmt| Files(stmt, [file_or_directory]))
-            .map_err(|e| e.into())
+    /// Get the file's status in the backend. If a tag is provided, it must match or the file
+    /// is reported missing. Files with an overlay are always reported as indexed, regardless
+    /// of the tag.
+    pub fn status_for_file<T: AsRef<str>>(
+        &mut self,
+        file: &str,
+        tag: Option<T>,
+    ) -> Result<FileStatus> {
+        if self.overlays.contains_key(file) {
+            return Ok(FileStatus::Indexed);
+        }
+        self.backend
+            .status_for_file(file, tag.as_ref().map(|t| t.as_ref()))
     }
 
     /// Ensure the graph for the given file is loaded.
@@ -529,7 +1849,8 @@ impl SQLiteReader {
             file,
             &mut self.graph,
             &mut self.loaded_graphs,
-            &self.conn,
+            &self.backend,
+            &self.overlays,
             &mut self.stats,
         )
     }
@@ -538,7 +1859,8 @@ impl SQLiteReader {
         file: &str,
         graph: &mut StackGraph,
         loaded_graphs: &mut HashSet<String>,
-        conn: &Connection,
+        backend: &B,
+        overlays: &HashMap<String, FileOverlay>,
         stats: &mut Stats,
     ) -> Result<Handle<File>> {
         copious_debugging!("--> Load graph for {}", file);
@@ -547,35 +1869,20 @@ impl SQLiteReader {
             stats.file_cached += 1;
             return Ok(graph.get_file(file).expect("loaded file to exist"));
         }
-        copious_debugging!(" * Load from database");
         stats.file_loads += 1;
-        let mut stmt = conn.prepare_cached("SELECT value FROM graphs WHERE file = ?")?;
-        let value = stmt.query_row([file], |row| row.get::<_, Vec<u8>>(0))?;
+        let value = if let Some(overlay) = overlays.get(file) {
+            copious_debugging!(" * Load from overlay");
+            overlay.result.graph.clone()
+        } else {
+            copious_debugging!(" * Load from backend");
+            backend.load_graph(file)?
+        };
         let (file_graph, _): (serde::StackGraph, usize) =
             bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
         file_graph.load_into(graph)?;
         Ok(graph.get_file(file).expect("loaded file to exist"))
     }
 
-    pub fn load_graphs_for_file_or_directory(
-        &mut self,
-        file_or_directory: &Path,
-        cancellation_flag: &dyn CancellationFlag,
-    ) -> Result<()> {
-        for file in Self::list_file_or_directory_inner(&self.conn, file_or_directory)?.try_iter()? {
-            cancellation_flag.check("loading graphs")?;
-            let file = file?;
-            Self::load_graph_for_file_inner(
-                &file.path.to_string_lossy(),
-                &mut self.graph,
-                &mut self.loaded_graphs,
-                &self.conn,
-                &mut self.stats,
-            )?;
-        }
-        Ok(())
-    }
-
     /// Ensure the paths starting a the given node are loaded.
     fn load_paths_for_node(
         &mut self,
@@ -583,6 +1890,9 @@ impl SQLiteReader {
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<()> {
         copious_debugging!(" * Load extensions from node {}", node.display(&self.graph));
+        if let Some(dependencies) = &mut self.dependencies {
+            dependencies.files.extend(self.graph[node].file());
+        }
         if !self.loaded_node_paths.insert(node) {
             copious_debugging!("   > Already loaded");
             self.stats.node_path_cached += 1;
@@ -591,36 +1901,37 @@ impl SQLiteReader {
         self.stats.node_path_loads += 1;
         let id = self.graph[node].id();
         let file = id.file().expect("file node required");
-        let file = self.graph[file].name();
-        let mut stmt = self
-            .conn
-            .prepare_cached("SELECT file,value from file_paths WHERE file = ? AND local_id = ?")?;
-        let paths = stmt.query_map((file, id.local_id()), |row| {
-            let file = row.get::<_, String>(0)?;
-            let value = row.get::<_, Vec<u8>>(1)?;
-            Ok((file, value))
-        })?;
+        let file = self.graph[file].name().to_string();
+        if let Some(overlay) = self.overlays.get(&file) {
+            copious_debugging!("   > Load from overlay");
+            let values = overlay
+                .result
+                .node_paths
+                .get(&id.local_id())
+                .into_iter()
+                .flatten()
+                .map(|(_, value)| value.clone())
+                .collect::<Vec<_>>();
+            for value in values {
+                cancellation_flag.check("loading node paths")?;
+                self.add_path(&file, &value, PathDirection::Forward)?;
+            }
+            return Ok(());
+        }
+        let paths = self.backend.load_node_paths(&file, id.local_id())?;
         #[cfg_attr(not(feature = "copious-debugging"), allow(unused))]
         let mut count = 0usize;
-        for path in paths {
+        for value in paths {
             cancellation_flag.check("loading node paths")?;
-            let (file, value) = path?;
             Self::load_graph_for_file_inner(
                 &file,
                 &mut self.graph,
                 &mut self.loaded_graphs,
-                &self.conn,
+                &self.backend,
+                &self.overlays,
                 &mut self.stats,
             )?;
-            let (path, _): (serde::PartialPath, usize) =
-                bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
-            let path = path.to_partial_path(&mut self.graph, &mut self.partials)?;
-            copious_debugging!(
-                "   > Loaded {}",
-                path.display(&self.graph, &mut self.partials)
-            );
-            self.db
-                .add_partial_path(&self.graph, &mut self.partials, path);
+            self.add_path(&file, &value, PathDirection::Forward)?;
             count += 1;
         }
         copious_debugging!("   > Loaded {}", count);
@@ -637,14 +1948,39 @@ impl SQLiteReader {
             " * Load extensions from root with symbol stack {}",
             symbol_stack.display(&self.graph, &mut self.partials)
         );
-        let mut stmt = self.conn.prepare_cached(
-            "SELECT file,value from root_paths WHERE symbol_stack LIKE ? ESCAPE ?",
-        )?;
-        let (symbol_stack_patterns, escape) =
+        // Overlays are small, so we load all their root paths at once, instead of matching them
+        // against the symbol stack patterns.
+        let overlay_files = self
+            .overlays
+            .keys()
+            .filter(|file| !self.loaded_overlay_root_paths.contains(*file))
+            .cloned()
+            .collect::<Vec<_>>();
+        for file in overlay_files {
+            copious_debugging!(" * Load root paths from overlay {}", file);
+            self.loaded_overlay_root_paths.insert(file.clone());
+            self.load_graph_for_file(&file)?;
+            let values = self.overlays[&file]
+                .result
+                .root_paths
+                .iter()
+                .map(|(_, _, value)| value.clone())
+                .collect::<Vec<_>>();
+            for value in values {
+                cancellation_flag.check("loading root paths")?;
+                self.add_path(&file, &value, PathDirection::Forward)?;
+            }
+        }
+        let symbol_stack_patterns =
             symbol_stack.storage_key_patterns(&self.graph, &mut self.partials);
+        if let Some(dependencies) = &mut self.dependencies {
+            dependencies
+                .root_paths
+                .extend(symbol_stack_patterns.iter().cloned());
+        }
         for symbol_stack in symbol_stack_patterns {
             copious_debugging!(
-                " * Load extensions from root with prefix symbol stack {}",
+                " * Load extensions from root with prefix symbol stack {:?}",
                 symbol_stack
             );
             if !self.loaded_root_paths.insert(symbol_stack.clone()) {
@@ -653,32 +1989,17 @@ impl SQLiteReader {
                 continue;
             }
             self.stats.root_path_loads += 1;
-            let paths = stmt.query_map([symbol_stack, escape.clone()], |row| {
-                let file = row.get::<_, String>(0)?;
-                let value = row.get::<_, Vec<u8>>(1)?;
-                Ok((file, value))
-            })?;
+            let paths = self.backend.load_root_paths(&symbol_stack)?;
             #[cfg_attr(not(feature = "copious-debugging"), allow(unused))]
             let mut count = 0usize;
-            for path in paths {
+            for (file, value) in paths {
                 cancellation_flag.check("loading root paths")?;
-                let (file, value) = path?;
-                Self::load_graph_for_file_inner(
-                    &file,
-                    &mut self.graph,
-                    &mut self.loaded_graphs,
-                    &self.conn,
-                    &mut self.stats,
-                )?;
-                let (path, _): (serde::PartialPath, usize) =
-                    bincode::decode_from_slice(&value, BINCODE_CONFIG)?;
-                let path = path.to_partial_path(&mut self.graph, &mut self.partials)?;
-                copious_debugging!(
-                    "   > Loaded {}",
-                    path.display(&self.graph, &mut self.partials)
-                );
-                self.db
-                    .add_partial_path(&self.graph, &mut self.partials, path);
+                if self.overlays.contains_key(&file) {
+                    // root paths for this file are loaded from the overlay
+                    continue;
+                }
+                self.load_graph_for_file(&file)?;
+                self.add_path(&file, &value, PathDirection::Forward)?;
                 count += 1;
             }
             copious_debugging!("   > Loaded {}", count);
@@ -686,6 +2007,185 @@ impl SQLiteReader {
         Ok(())
     }
 
+    /// Ensure the paths ending at the given node are loaded.
+    fn load_paths_to_node(
+        &mut self,
+        node: Handle<Node>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<()> {
+        copious_debugging!(
+            " * Load backward extensions to node {}",
+            node.display(&self.graph)
+        );
+        if !self.loaded_node_paths_to.insert(node) {
+            copious_debugging!("   > Already loaded");
+            self.stats.node_path_cached += 1;
+            return Ok(());
+        }
+        self.stats.node_path_loads += 1;
+        let id = self.graph[node].id();
+        let file = id.file().expect("file node required");
+        let file = self.graph[file].name().to_string();
+        let paths = match self.overlays.get(&file) {
+            Some(overlay) => {
+                copious_debugging!("   > Load from overlay");
+                overlay
+                    .result
+                    .all_paths()
+                    .filter(|(end, _)| **end == PathEnd::Node(id.local_id()))
+                    .map(|(_, value)| value.clone())
+                    .collect()
+            }
+            None => self.backend.load_paths_to_node(&file, id.local_id())?,
+        };
+        for value in paths {
+            cancellation_flag.check("loading node paths")?;
+            self.add_path(&file, &value, PathDirection::Backward)?;
+        }
+        Ok(())
+    }
+
+    /// Ensure the paths ending at the root and matching the given symbol stack are loaded.
+    fn load_paths_to_root(
+        &mut self,
+        symbol_stack: PartialSymbolStack,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<()> {
+        copious_debugging!(
+            " * Load backward extensions to root with symbol stack {}",
+            symbol_stack.display(&self.graph, &mut self.partials)
+        );
+        // As for forward extensions, we load all paths to the root of overlays at once.
+        let overlay_files = self
+            .overlays
+            .keys()
+            .filter(|file| !self.loaded_overlay_root_paths_to.contains(*file))
+            .cloned()
+            .collect::<Vec<_>>();
+        for file in overlay_files {
+            copious_debugging!(" * Load paths to root from overlay {}", file);
+            self.loaded_overlay_root_paths_to.insert(file.clone());
+            self.load_graph_for_file(&file)?;
+            let values = self.overlays[&file]
+                .result
+                .all_paths()
+                .filter(|(end, _)| matches!(end, PathEnd::Root(_)))
+                .map(|(_, value)| value.clone())
+                .collect::<Vec<_>>();
+            for value in values {
+                cancellation_flag.check("loading root paths")?;
+                self.add_path(&file, &value, PathDirection::Backward)?;
+            }
+        }
+        for symbol_stack in symbol_stack.storage_key_patterns(&self.graph, &mut self.partials) {
+            if !self.loaded_root_paths_to.insert(symbol_stack.clone()) {
+                copious_debugging!("   > Already loaded");
+                self.stats.root_path_cached += 1;
+                continue;
+            }
+            self.stats.root_path_loads += 1;
+            for (file, value) in self.backend.load_paths_to_root(&symbol_stack)? {
+                cancellation_flag.check("loading root paths")?;
+                if self.overlays.contains_key(&file) {
+                    // paths to the root for this file are loaded from the overlay
+                    continue;
+                }
+                self.load_graph_for_file(&file)?;
+                self.add_path(&file, &value, PathDirection::Backward)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Ensure the paths ending at the jump to scope node are loaded. These can belong to any file,
+    /// so they are all loaded at once.
+    fn load_paths_to_jump_node(&mut self, cancellation_flag: &dyn CancellationFlag) -> Result<()> {
+        copious_debugging!(" * Load backward extensions to jump to scope node");
+        if self.loaded_jump_paths {
+            copious_debugging!("   > Already loaded");
+            return Ok(());
+        }
+        self.loaded_jump_paths = true;
+        let mut paths = Vec::new();
+        for overlay in self.overlays.values() {
+            paths.extend(
+                overlay
+                    .result
+                    .all_paths()
+                    .filter(|(end, _)| **end == PathEnd::JumpTo)
+                    .map(|(_, value)| (overlay.result.file.clone(), value.clone())),
+            );
+        }
+        for (file, value) in self.backend.load_paths_to_jump_node()? {
+            if !self.overlays.contains_key(&file) {
+                paths.push((file, value));
+            }
+        }
+        for (file, value) in paths {
+            cancellation_flag.check("loading jump paths")?;
+            self.load_graph_for_file(&file)?;
+            self.add_path(&file, &value, PathDirection::Backward)?;
+        }
+        Ok(())
+    }
+
+    /// Add a serialized path of the given file to the database, unless it was loaded before when
+    /// looking up paths in the other direction.
+    fn add_path(&mut self, file: &str, value: &[u8], direction: PathDirection) -> Result<()> {
+        let (path, _): (serde::PartialPath, usize) =
+            bincode::decode_from_slice(value, BINCODE_CONFIG)?;
+        let path = path.to_partial_path(&mut self.graph, &mut self.partials)?;
+        let is_loaded = match direction {
+            PathDirection::Forward => self.is_loaded_by_end(file, &path),
+            PathDirection::Backward => self.is_loaded_by_start(file, &path),
+        };
+        if is_loaded {
+            copious_debugging!(
+                "   > Already loaded {}",
+                path.display(&self.graph, &mut self.partials)
+            );
+            return Ok(());
+        }
+        copious_debugging!(
+            "   > Loaded {}",
+            path.display(&self.graph, &mut self.partials)
+        );
+        self.db
+            .add_partial_path(&self.graph, &mut self.partials, path);
+        Ok(())
+    }
+
+    /// Returns whether the given path of the given file was loaded by its start node.
+    fn is_loaded_by_start(&mut self, file: &str, path: &PartialPath) -> bool {
+        if !self.graph[path.start_node].is_root() {
+            return self.loaded_node_paths.contains(&path.start_node);
+        }
+        if self.overlays.contains_key(file) {
+            return self.loaded_overlay_root_paths.contains(file);
+        }
+        let key = path
+            .symbol_stack_precondition
+            .storage_key(&self.graph, &mut self.partials);
+        self.loaded_root_paths.iter().any(|p| p.matches(&key))
+    }
+
+    /// Returns whether the given path of the given file was loaded by its end node.
+    fn is_loaded_by_end(&mut self, file: &str, path: &PartialPath) -> bool {
+        if self.graph[path.end_node].is_jump_to() {
+            return self.loaded_jump_paths;
+        }
+        if !self.graph[path.end_node].is_root() {
+            return self.loaded_node_paths_to.contains(&path.end_node);
+        }
+        if self.overlays.contains_key(file) {
+            return self.loaded_overlay_root_paths_to.contains(file);
+        }
+        let key = path
+            .symbol_stack_postcondition
+            .storage_key(&self.graph, &mut self.partials);
+        self.loaded_root_paths_to.iter().any(|p| p.matches(&key))
+    }
+
     /// Ensure all possible extensions for the given partial path are loaded.
     pub fn load_partial_path_extensions(
         &mut self,
@@ -705,69 +2205,379 @@ impl SQLiteReader {
         Ok(())
     }
 
+    /// Ensure all possible backward extensions for the given partial path are loaded. These are
+    /// the paths that end at its start node, and, if it starts at an exported scope, the paths
+    /// that end at the jump to scope node.
+    pub fn load_partial_path_backward_extensions(
+        &mut self,
+        path: &PartialPath,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<()> {
+        copious_debugging!(
+            "--> Load backward extensions for {}",
+            path.display(&self.graph, &mut self.partials)
+        );
+        let start_node = &self.graph[path.start_node];
+        if start_node.file().is_some() {
+            let is_exported_scope = start_node.is_exported_scope();
+            self.load_paths_to_node(path.start_node, cancellation_flag)?;
+            if is_exported_scope {
+                self.load_paths_to_jump_node(cancellation_flag)?;
+            }
+        } else if start_node.is_root() {
+            self.load_paths_to_root(path.symbol_stack_precondition, cancellation_flag)?;
+        }
+        Ok(())
+    }
+
     /// Get the stack graph, partial paths arena, and path database for the currently loaded data.
     pub fn get(&mut self) -> (&mut StackGraph, &mut PartialPaths, &mut Database) {
         (&mut self.graph, &mut self.partials, &mut self.db)
     }
 
-    /// Return stats about this database reader.
+    /// Return stats about this reader.
     pub fn stats(&self) -> Stats {
         self.stats.clone()
     }
+
+    /// Finds all complete partial paths that start at one of the given nodes, calling the `visit`
+    /// closure on each one. This works like
+    /// [`ForwardPartialPathStitcher::find_all_complete_partial_paths`][], but if a stitching cache
+    /// is set, the paths for each node are taken from the cache when they are up to date, and
+    /// stitched and added to the cache otherwise. The returned stats only cover the paths that
+    /// were stitched.
+    ///
+    /// The limits of the configuration apply to all starting nodes together, so the results for
+    /// a single node depend on the other nodes. The cache is therefore only used for limited
+    /// searches if there is a single starting node.
+    ///
+    /// [`ForwardPartialPathStitcher::find_all_complete_partial_paths`]: ../stitching/struct.ForwardPartialPathStitcher.html#method.find_all_complete_partial_paths
+    pub fn find_all_complete_partial_paths<I, F>(
+        &mut self,
+        starting_nodes: I,
+        config: StitcherConfig,
+        cancellation_flag: &dyn CancellationFlag,
+        mut visit: F,
+    ) -> Result<StitchingStats>
+    where
+        I: IntoIterator<Item = Handle<Node>>,
+        F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
+    {
+        let starting_nodes = starting_nodes.into_iter().collect::<Vec<_>>();
+        let is_limited = config.max_complete_paths().is_some() || config.max_work().is_some();
+        let mut cache = match self.stitching_cache.take() {
+            Some(cache) if !is_limited || starting_nodes.len() <= 1 => cache,
+            cache => {
+                self.stitching_cache = cache;
+                return ForwardPartialPathStitcher::find_all_complete_partial_paths(
+                    self,
+                    starting_nodes,
+                    config,
+                    cancellation_flag,
+                    visit,
+                );
+            }
+        };
+        let mut result = Ok(StitchingStats::default());
+        for node in starting_nodes {
+            match self.find_complete_partial_paths_cached(
+                &mut cache,
+                node,
+                config,
+                cancellation_flag,
+                &mut visit,
+            ) {
+                Ok(node_stats) => {
+                    if let Ok(stats) = &mut result {
+                        *stats += node_stats;
+                    }
+                }
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+        self.stitching_cache = Some(cache);
+        result
+    }
+
+    /// Finds all complete partial paths that end at one of the given definition nodes, calling
+    /// the `visit` closure on each one. This uses
+    /// [`BackwardPartialPathStitcher::find_all_complete_partial_paths_from_definitions`][] to find
+    /// the references to the definitions, without stitching from every reference. The partial
+    /// paths that are needed to extend paths backwards are loaded lazily by their end node.
+    ///
+    /// [`BackwardPartialPathStitcher::find_all_complete_partial_paths_from_definitions`]: ../stitching/struct.BackwardPartialPathStitcher.html#method.find_all_complete_partial_paths_from_definitions
+    pub fn find_all_complete_partial_paths_from_definitions<I, F>(
+        &mut self,
+        definitions: I,
+        config: StitcherConfig,
+        cancellation_flag: &dyn CancellationFlag,
+        visit: F,
+    ) -> Result<StitchingStats>
+    where
+        I: IntoIterator<Item = Handle<Node>>,
+        F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
+    {
+        BackwardPartialPathStitcher::find_all_complete_partial_paths_from_definitions(
+            self,
+            definitions,
+            config,
+            cancellation_flag,
+            visit,
+        )
+    }
+
+    fn find_complete_partial_paths_cached<F>(
+        &mut self,
+        cache: &mut StitchingCache,
+        node: Handle<Node>,
+        config: StitcherConfig,
+        cancellation_flag: &dyn CancellationFlag,
+        visit: &mut F,
+    ) -> Result<StitchingStats>
+    where
+        F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
+    {
+        let initial_path = PartialPath::from_node(&self.graph, &mut self.partials, node);
+        let id = self.graph[node].id();
+        let key = StitchingCacheKey {
+            file: id.file().map(|f| self.graph[f].name().to_string()),
+            local_id: id.local_id(),
+            symbol_stack: initial_path
+                .symbol_stack_postcondition
+                .storage_key(&self.graph, &mut self.partials),
+            detect_similar_paths: config.detect_similar_paths(),
+            max_similar_paths: config.max_similar_paths(),
+            max_complete_paths: config.max_complete_paths(),
+            max_work: config.max_work(),
+            sorted_results: config.sorted_results(),
+        };
+
+        if let Some(entry) = cache.entries.get(&key) {
+            if self.is_up_to_date(entry)? {
+                copious_debugging!("--> Use cached paths for {}", node.display(&self.graph));
+                self.stats.complete_path_cached += 1;
+                for file in &entry.files {
+                    self.load_graph_for_file(file)?;
+                }
+                for path in &entry.paths {
+                    cancellation_flag.check("loading cached paths")?;
+                    let path = path.to_partial_path(&mut self.graph, &mut self.partials)?;
+                    visit(&self.graph, &mut self.partials, &path);
+                }
+                return Ok(StitchingStats::default());
+            }
+            cache.entries.remove(&key);
+        }
+
+        self.stats.complete_path_stitches += 1;
+        // files stored while stitching are considered new, so that the entry does not miss them
+        let generation = self.backend.generation()?;
+        let mut paths = Vec::new();
+        self.dependencies = Some(LoadedDependencies::default());
+        let result = ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            self,
+            std::iter::once(node),
+            config,
+            cancellation_flag,
+            |_, _, path| paths.push(path.clone()),
+        );
+        let loaded = self.dependencies.take().unwrap_or_default();
+        let stats = result?;
+
+        let mut files = HashSet::new();
+        for path in &paths {
+            files.extend(self.graph[path.start_node].file());
+            files.extend(self.graph[path.end_node].file());
+            for edge in path.edges.iter_unordered(&self.partials) {
+                files.extend(edge.source_node_id.file());
+            }
+        }
+        let mut dependencies = HashMap::new();
+        for file in files.iter().chain(loaded.files.iter()) {
+            let file = self.graph[*file].name();
+            if !dependencies.contains_key(file) {
+                dependencies.insert(file.to_string(), self.file_version(file)?);
+            }
+        }
+        let mut entry = StitchingCacheEntry {
+            generation,
+            overlays: self
+                .overlays
+                .iter()
+                .map(|(file, overlay)| (file.clone(), overlay.version()))
+                .collect(),
+            dependencies,
+            files: files
+                .into_iter()
+                .map(|f| self.graph[f].name().to_string())
+                .collect(),
+            root_paths: loaded.root_paths,
+            paths: Vec::with_capacity(paths.len()),
+        };
+        for path in &paths {
+            entry.paths.push(serde::PartialPath::from_partial_path(
+                &self.graph,
+                &mut self.partials,
+                path,
+            ));
+            visit(&self.graph, &mut self.partials, path);
+        }
+        cache.entries.insert(key, entry);
+
+        Ok(stats)
+    }
+
+    /// Returns whether the data that a cache entry was computed from is unchanged, or has only
+    /// changed in ways that cannot affect its paths.
+    fn is_up_to_date(&self, entry: &StitchingCacheEntry) -> Result<bool> {
+        for (file, overlay) in &self.overlays {
+            if entry.overlays.get(file) == Some(&overlay.version()) {
+                continue;
+            }
+            if entry.dependencies.contains_key(file)
+                || entry.root_paths.iter().any(|p| overlay.has_root_paths(p))
+            {
+                return Ok(false);
+            }
+        }
+        if entry
+            .overlays
+            .keys()
+            .any(|file| !self.overlays.contains_key(file))
+        {
+            // the stored data of the file, which may have other paths, is used again
+            return Ok(false);
+        }
+
+        let generation = self.backend.generation()?;
+        if generation == entry.generation {
+            return Ok(true);
+        } else if generation < entry.generation {
+            // the database was reset
+            return Ok(false);
+        }
+        for (file, version) in &entry.dependencies {
+            if self.file_version(file)? != *version {
+                return Ok(false);
+            }
+        }
+        for pattern in &entry.root_paths {
+            if self
+                .backend
+                .has_root_paths_since(entry.generation, pattern)?
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the version of the given file, which is the tag it was stored with, or a version
+    /// computed from the overlay content if the file has an overlay.
+    fn file_version(&self, file: &str) -> Result<Option<String>> {
+        if let Some(overlay) = self.overlays.get(file) {
+            return Ok(Some(overlay.version()));
+        }
+        self.backend.tag_for_file(file)
+    }
+
+    /// Finds the visible definitions of the given reference, and returns them as a resolution
+    /// that can be stored with [`SQLiteWriter::store_resolutions`][]. The resolution records the
+    /// files and root paths that were loaded to find the definitions, so that it can be
+    /// invalidated when they change. Resolutions should not be computed by readers with
+    /// overlays, because the stored data would not match the database.
+    pub fn resolve_reference(
+        &mut self,
+        reference: Handle<Node>,
+        config: StitcherConfig,
+        shadowing_policy: &dyn ShadowingPolicy,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Resolution> {
+        let mut paths = Vec::new();
+        self.dependencies = Some(LoadedDependencies::default());
+        let result = ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            self,
+            std::iter::once(reference),
+            config,
+            cancellation_flag,
+            |_, _, path| paths.push(path.clone()),
+        );
+        let dependencies = self.dependencies.take().unwrap_or_default();
+        result?;
+
+        let id = self.graph[reference].id();
+        let file = id.file().expect("reference must be in a file");
+        let mut resolution = Resolution {
+            reference: (self.graph[file].name().to_string(), id.local_id()),
+            definitions: Vec::new(),
+            dependency_files: dependencies
+                .files
+                .into_iter()
+                .chain(std::iter::once(file))
+                .map(|f| self.graph[f].name().to_string())
+                .collect(),
+            dependency_root_paths: dependencies.root_paths,
+        };
+        for path in &paths {
+            cancellation_flag.check("shadowing")?;
+            if shadowing_policy.is_shadowed(&self.graph, &mut self.partials, path, &paths) {
+                continue;
+            }
+            let id = self.graph[path.end_node].id();
+            let file = match id.file() {
+                Some(file) => self.graph[file].name().to_string(),
+                None => continue,
+            };
+            resolution.dependency_files.insert(file.clone());
+            resolution.definitions.push((file, id.local_id()));
+        }
+        Ok(resolution)
+    }
 }
 
-// Methods for computing keys and patterns for a symbol stack. The format of a storage key is:
-//
-//     has-var GS ( symbol (US symbol)* )?
-//
-// where has-var is "V" if the symbol stack has a variable, "X" otherwise.
+// Methods for computing keys and patterns for a symbol stack.
 impl PartialSymbolStack {
-    /// Returns a string representation of this symbol stack for indexing in the database.
-    fn storage_key(self, graph: &StackGraph, partials: &mut PartialPaths) -> String {
-        let mut key = String::new();
-        match self.has_variable() {
-            true => key += "V\u{241E}",
-            false => key += "X\u{241E}",
-        }
-        key += &self
-            .iter(partials)
-            .map(|s| &graph[s.symbol])
-            .join("\u{241F}");
-        key
-    }
-
-    /// Returns string representations for all prefixes of this symbol stack for querying the
-    /// index in the database.
+    /// Returns the key of this symbol stack for indexing in the backend.
+    fn storage_key(self, graph: &StackGraph, partials: &mut PartialPaths) -> SymbolStackKey {
+        SymbolStackKey {
+            symbols: self
+                .iter(partials)
+                .map(|s| graph[s.symbol].to_string())
+                .collect(),
+            has_variable: self.has_variable(),
+        }
+    }
+
+    /// Returns patterns that select the keys of all root paths that may extend a path with this
+    /// symbol stack.
     fn storage_key_patterns(
         mut self,
         graph: &StackGraph,
         partials: &mut PartialPaths,
-    ) -> (Vec<String>, String) {
+    ) -> Vec<SymbolStackKeyPattern> {
         let mut key_patterns = Vec::new();
-        let mut symbols = String::new();
+        let mut symbols = Vec::new();
         while let Some(symbol) = self.pop_front(partials) {
-            if !symbols.is_empty() {
-                symbols += "\u{241F}";
-            }
-            let symbol = graph[symbol.symbol]
-                .replace("%", "\\%")
-                .replace("_", "\\_")
-                .to_string();
-            symbols += &symbol;
+            symbols.push(graph[symbol.symbol].to_string());
             // patterns for paths matching a prefix of this stack
-            key_patterns.push("V\u{241E}".to_string() + &symbols);
+            key_patterns.push(SymbolStackKeyPattern::Prefix(symbols.clone()));
         }
         // pattern for paths matching exactly this stack
-        key_patterns.push("X\u{241E}".to_string() + &symbols);
-        if self.has_variable() {
+        key_patterns.push(SymbolStackKeyPattern::Exact(symbols.clone()));
+        if self.has_variable() && !symbols.is_empty() {
             // patterns for paths for which this stack is a prefix
-            key_patterns.push("_\u{241E}".to_string() + &symbols + "\u{241F}%");
+            key_patterns.push(SymbolStackKeyPattern::Extension(symbols));
         }
-        (key_patterns, "\\".to_string())
+        key_patterns
     }
 }
 
-impl ForwardCandidates<Handle<PartialPath>, PartialPath, Database, StorageError> for SQLiteReader {
+impl<B: StorageBackend> ForwardCandidates<Handle<PartialPath>, PartialPath, Database, StorageError>
+    for StorageReader<B>
+{
     fn load_forward_candidates(
         &mut self,
         path: &PartialPath,
@@ -780,8 +2590,31 @@ impl ForwardCandidates<Handle<PartialPath>, PartialPath, Database, StorageError>
     where
         R: std::iter::Extend<Handle<PartialPath>>,
     {
-        self.db
-            .find_candidate_partial_paths(&self.graph, &mut self.partials, path, result);
+        let dependencies = match &mut self.dependencies {
+            Some(dependencies) => dependencies,
+            None => {
+                self.db
+                    .find_candidate_partial_paths(&self.graph, &mut self.partials, path, result);
+                return;
+            }
+        };
+        let mut candidates = Vec::new();
+        self.db.find_candidate_partial_paths(
+            &self.graph,
+            &mut self.partials,
+            path,
+            &mut candidates,
+        );
+        let graph = &self.graph;
+        for candidate in &candidates {
+            let candidate = &self.db[*candidate];
+            dependencies.files.extend(
+                graph[candidate.start_node]
+                    .file()
+                    .or_else(|| graph[candidate.end_node].file()),
+            );
+        }
+        result.extend(candidates);
     }
 
     fn get_joining_candidate_degree(&self, path: &PartialPath) -> Degree {
@@ -793,6 +2626,38 @@ impl ForwardCandidates<Handle<PartialPath>, PartialPath, Database, StorageError>
     }
 }
 
+impl<B: StorageBackend> BackwardCandidates<Handle<PartialPath>, Database, StorageError>
+    for StorageReader<B>
+{
+    fn load_backward_candidates(
+        &mut self,
+        path: &PartialPath,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> std::result::Result<(), StorageError> {
+        self.load_partial_path_backward_extensions(path, cancellation_flag)
+    }
+
+    fn get_backward_candidates<R>(&mut self, path: &PartialPath, result: &mut R)
+    where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        self.db.find_backward_candidate_partial_paths(
+            &self.graph,
+            &mut self.partials,
+            path,
+            result,
+        );
+    }
+
+    fn get_forking_candidate_degree(&self, path: &PartialPath) -> Degree {
+        self.db.get_outgoing_path_degree(path.start_node)
+    }
+
+    fn get_graph_partials_and_db(&mut self) -> (&StackGraph, &mut PartialPaths, &Database) {
+        (&self.graph, &mut self.partials, &self.db)
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Stats {
     pub file_loads: usize,
@@ -801,6 +2666,9 @@ pub struct Stats {
     pub root_path_cached: usize,
     pub node_path_loads: usize,
     pub node_path_cached: usize,
+    pub complete_path_stitches: usize,
+    pub complete_path_cached: usize,
+    pub resolution_loads: usize,
 }
 
 impl Stats {
@@ -862,7 +2730,7 @@ fn status_for_file<T: AsRef<str>>(
             .optional()?
             .unwrap_or(FileStatus::Missing)
     } else {
-        let mut stmt = conn.prepare_cached("SELECT status FROM graphs WHERE file = ?")?;
+        let mut stmt = conn.prepare_cached("SELECT error FROM graphs WHERE file = ?")?;
         stmt.query_row([file], |r| r.get_ref(0).map(FileStatus::from))
             .optional()?
             .unwrap_or(FileStatus::Missing)
@@ -0,0 +1,257 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Encodings of graphs stored by older database versions, used to convert stored graphs when a
+//! database is upgraded in place.
+//!
+//! Bincode encodings are not self-describing, so graphs must be decoded with the exact types they
+//! were encoded with. The types here are generic over the source info and edge types, which are
+//! the parts of the encoding that changed between versions. Instantiated with the current types,
+//! they encode exactly like [`serde::StackGraph`][].
+
+use crate::serde;
+
+use super::Result;
+use super::BINCODE_CONFIG;
+
+#[derive(bincode::Encode, bincode::Decode)]
+struct StackGraph<S, E> {
+    files: serde::Files,
+    nodes: Vec<Node<S>>,
+    edges: Vec<E>,
+}
+
+impl<S, E> StackGraph<S, E> {
+    fn decode(value: &[u8]) -> Result<Self>
+    where
+        S: bincode::Decode<()>,
+        E: bincode::Decode<()>,
+    {
+        let (graph, _) = bincode::decode_from_slice(value, BINCODE_CONFIG)?;
+        Ok(graph)
+    }
+
+    fn encode(&self) -> Result<Vec<u8>>
+    where
+        S: bincode::Encode,
+        E: bincode::Encode,
+    {
+        Ok(bincode::encode_to_vec(self, BINCODE_CONFIG)?)
+    }
+
+    fn map_source_info<T>(self, mut f: impl FnMut(S) -> T) -> StackGraph<T, E> {
+        StackGraph {
+            files: self.files,
+            nodes: self
+                .nodes
+                .into_iter()
+                .map(|n| n.map_source_info(&mut f))
+                .collect(),
+            edges: self.edges,
+        }
+    }
+
+    fn map_edges<F>(self, f: impl FnMut(E) -> F) -> StackGraph<S, F> {
+        StackGraph {
+            files: self.files,
+            nodes: self.nodes,
+            edges: self.edges.into_iter().map(f).collect(),
+        }
+    }
+}
+
+#[derive(bincode::Encode, bincode::Decode)]
+enum Node<S> {
+    DropScopes {
+        id: serde::NodeID,
+        source_info: Option<S>,
+        debug_info: Option<serde::DebugInfo>,
+    },
+    JumpToScope {
+        id: serde::NodeID,
+        source_info: Option<S>,
+        debug_info: Option<serde::DebugInfo>,
+    },
+    PopScopedSymbol {
+        id: serde::NodeID,
+        symbol: String,
+        is_definition: bool,
+        source_info: Option<S>,
+        debug_info: Option<serde::DebugInfo>,
+    },
+    PopSymbol {
+        id: serde::NodeID,
+        symbol: String,
+        is_definition: bool,
+        source_info: Option<S>,
+        debug_info: Option<serde::DebugInfo>,
+    },
+    PushScopedSymbol {
+        id: serde::NodeID,
+        symbol: String,
+        scope: serde::NodeID,
+        is_reference: bool,
+        source_info: Option<S>,
+        debug_info: Option<serde::DebugInfo>,
+    },
+    PushSymbol {
+        id: serde::NodeID,
+        symbol: String,
+        is_reference: bool,
+        source_info: Option<S>,
+        debug_info: Option<serde::DebugInfo>,
+    },
+    Root {
+        id: serde::NodeID,
+        source_info: Option<S>,
+        debug_info: Option<serde::DebugInfo>,
+    },
+    Scope {
+        id: serde::NodeID,
+        is_exported: bool,
+        source_info: Option<S>,
+        debug_info: Option<serde::DebugInfo>,
+    },
+}
+
+impl<S> Node<S> {
+    fn map_source_info<T>(self, f: impl FnMut(S) -> T) -> Node<T> {
+        match self {
+            Self::DropScopes {
+                id,
+                source_info,
+                debug_info,
+            } => Node::DropScopes {
+                id,
+                source_info: source_info.map(f),
+                debug_info,
+            },
+            Self::JumpToScope {
+                id,
+                source_info,
+                debug_info,
+            } => Node::JumpToScope {
+                id,
+                source_info: source_info.map(f),
+                debug_info,
+            },
+            Self::PopScopedSymbol {
+                id,
+                symbol,
+                is_definition,
+                source_info,
+                debug_info,
+            } => Node::PopScopedSymbol {
+                id,
+                symbol,
+                is_definition,
+                source_info: source_info.map(f),
+                debug_info,
+            },
+            Self::PopSymbol {
+                id,
+                symbol,
+                is_definition,
+                source_info,
+                debug_info,
+            } => Node::PopSymbol {
+                id,
+                symbol,
+                is_definition,
+                source_info: source_info.map(f),
+                debug_info,
+            },
+            Self::PushScopedSymbol {
+                id,
+                symbol,
+                scope,
+                is_reference,
+                source_info,
+                debug_info,
+            } => Node::PushScopedSymbol {
+                id,
+                symbol,
+                scope,
+                is_reference,
+                source_info: source_info.map(f),
+                debug_info,
+            },
+            Self::PushSymbol {
+                id,
+                symbol,
+                is_reference,
+                source_info,
+                debug_info,
+            } => Node::PushSymbol {
+                id,
+                symbol,
+                is_reference,
+                source_info: source_info.map(f),
+                debug_info,
+            },
+            Self::Root {
+                id,
+                source_info,
+                debug_info,
+            } => Node::Root {
+                id,
+                source_info: source_info.map(f),
+                debug_info,
+            },
+            Self::Scope {
+                id,
+                is_exported,
+                source_info,
+                debug_info,
+            } => Node::Scope {
+                id,
+                is_exported,
+                source_info: source_info.map(f),
+                debug_info,
+            },
+        }
+    }
+}
+
+/// Source info in version 6.
+#[derive(bincode::Encode, bincode::Decode)]
+struct SourceInfoV6 {
+    span: lsp_positions::Span,
+    syntax_type: Option<String>,
+}
+
+/// Edges in version 6.
+#[derive(bincode::Encode, bincode::Decode)]
+struct EdgeV6 {
+    source: serde::NodeID,
+    sink: serde::NodeID,
+    precedence: i32,
+    debug_info: Option<serde::DebugInfo>,
+}
+
+/// Converts a graph from version 6, by adding the source info fields and the edge kind introduced
+/// in version 7. None of them can be recovered from the stored graph, so they are left empty until
+/// the file is indexed again.
+pub(super) fn graph_from_v6(value: &[u8]) -> Result<Vec<u8>> {
+    StackGraph::<SourceInfoV6, EdgeV6>::decode(value)?
+        .map_source_info(|s| serde::SourceInfo {
+            span: s.span,
+            syntax_type: s.syntax_type,
+            containing_line: None,
+            definiens_span: None,
+            fully_qualified_name: None,
+            stable_id: None,
+        })
+        .map_edges(|e| serde::Edge {
+            source: e.source,
+            sink: e.sink,
+            precedence: e.precedence,
+            kind: None,
+            debug_info: e.debug_info,
+        })
+        .encode()
+}
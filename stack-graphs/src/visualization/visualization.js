@@ -716,6 +716,9 @@ class StackGraph {
         if (edge.hasOwnProperty("precedence")) {
             tooltip.add_row("precedence", edge.precedence);
         }
+        if (edge.hasOwnProperty("kind")) {
+            tooltip.add_row("kind", edge.kind);
+        }
 
         if (edge.hasOwnProperty("debug_info") && edge.debug_info.length > 0) {
             tooltip.add_header("debug info");
@@ -36,6 +36,9 @@ fn check_local_nodes(graph: &TestGraph, file: &str, expected_local_nodes: &[&str
     let path_list = sg_partial_path_list_new();
     let stitcher_config = sg_stitcher_config {
         detect_similar_paths: false,
+        best_first: false,
+        max_complete_paths: 0,
+        max_work: 0,
     };
     sg_partial_path_arena_find_partial_paths_in_file(
         graph.graph,
@@ -80,6 +80,9 @@ fn check_partial_paths_in_file(graph: &TestGraph, file: &str, expected_paths: &[
     let path_list = sg_partial_path_list_new();
     let stitcher_config = sg_stitcher_config {
         detect_similar_paths: false,
+        best_first: false,
+        max_complete_paths: 0,
+        max_work: 0,
     };
     sg_partial_path_arena_find_partial_paths_in_file(
         graph.graph,
@@ -31,6 +31,9 @@ fn check_jump_to_definition(graph: &TestGraph, expected_paths: &[&str]) {
         .collect::<Vec<_>>();
     let stitcher_config = sg_stitcher_config {
         detect_similar_paths: false,
+        best_first: false,
+        max_complete_paths: 0,
+        max_work: 0,
     };
     sg_partial_path_arena_find_all_complete_paths(
         graph.graph,
@@ -52,6 +52,9 @@ impl StorageLayer {
         for file in rust_graph.iter_files() {
             let stitcher_config = sg_stitcher_config {
                 detect_similar_paths: false,
+                best_first: false,
+                max_complete_paths: 0,
+                max_work: 0,
             };
             sg_partial_path_arena_find_partial_paths_in_file(
                 graph,
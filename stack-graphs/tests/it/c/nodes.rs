@@ -684,6 +684,7 @@ fn can_create_source_info() {
             containing_line,
             definiens_span: sg_span::default(),
             fully_qualified_name,
+            stable_id: SG_NULL_HANDLE,
         },
     }];
     infos[0].source_info.span.start.line = 17;
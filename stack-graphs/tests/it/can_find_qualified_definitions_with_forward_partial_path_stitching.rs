@@ -0,0 +1,132 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::collections::BTreeSet;
+
+use controlled_option::ControlledOption;
+use pretty_assertions::assert_eq;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::partial::PartialScopedSymbol;
+use stack_graphs::partial::PartialSymbolStack;
+use stack_graphs::stitching::Database;
+use stack_graphs::stitching::DatabaseCandidates;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::StitcherConfig;
+use stack_graphs::NoCancellation;
+
+use crate::test_graphs;
+
+fn check_find_qualified_definitions(
+    graph: &mut StackGraph,
+    stack_symbols: &[&str],
+    expected_partial_paths: &[&str],
+) {
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+
+    // Generate partial paths for everything in the database.
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let mut symbol_stack = PartialSymbolStack::empty();
+    for stack_symbol in stack_symbols {
+        let symbol = graph.add_symbol(stack_symbol);
+        symbol_stack.push_back(
+            &mut partials,
+            PartialScopedSymbol {
+                symbol,
+                scopes: ControlledOption::none(),
+            },
+        );
+    }
+
+    let mut partial_paths = Vec::new();
+    ForwardPartialPathStitcher::find_all_definitions_of_symbol_stack(
+        &mut DatabaseCandidates::new(graph, &mut partials, &mut db),
+        symbol_stack,
+        StitcherConfig::default(),
+        &NoCancellation,
+        |_, _, p| {
+            partial_paths.push(p.clone());
+        },
+    )
+    .expect("should never be cancelled");
+    let results = partial_paths
+        .into_iter()
+        .map(|partial_path| partial_path.display(graph, &mut partials).to_string())
+        .collect::<BTreeSet<_>>();
+
+    let expected_partial_paths = expected_partial_paths
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<BTreeSet<_>>();
+    assert_eq!(expected_partial_paths, results);
+}
+
+#[test]
+fn class_field_through_function_parameter() {
+    let mut graph = test_graphs::class_field_through_function_parameter::new();
+    check_find_qualified_definitions(
+        &mut graph,
+        &["a", ".", "foo"],
+        &["<a.foo> () [root] -> [a.py(5) definition foo] <> ()"],
+    );
+    check_find_qualified_definitions(
+        &mut graph,
+        &["b", ".", "A", ".", "bar"],
+        &["<b.A.bar> () [root] -> [b.py(8) definition bar] <> ()"],
+    );
+}
+
+#[test]
+fn cyclic_imports_python() {
+    let mut graph = test_graphs::cyclic_imports_python::new();
+    check_find_qualified_definitions(
+        &mut graph,
+        &["a"],
+        &["<a> () [root] -> [a.py(0) definition a] <> ()"],
+    );
+    check_find_qualified_definitions(
+        &mut graph,
+        &["a", ".", "foo"],
+        &["<a.foo> () [root] -> [b.py(6) definition foo] <> ()"],
+    );
+}
+
+#[test]
+fn cyclic_imports_rust() {
+    // Everything in this example is local to one file, so nothing is reachable from the root.
+    let mut graph = test_graphs::cyclic_imports_rust::new();
+    check_find_qualified_definitions(&mut graph, &["a", "::", "BAR"], &[]);
+}
+
+#[test]
+fn sequenced_import_star() {
+    let mut graph = test_graphs::sequenced_import_star::new();
+    check_find_qualified_definitions(
+        &mut graph,
+        &["a", ".", "foo"],
+        &["<a.foo> () [root] -> [b.py(5) definition foo] <> ()"],
+    );
+    check_find_qualified_definitions(
+        &mut graph,
+        &["b", ".", "foo"],
+        &["<b.foo> () [root] -> [b.py(5) definition foo] <> ()"],
+    );
+}
@@ -0,0 +1,159 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::collections::BTreeSet;
+
+use pretty_assertions::assert_eq;
+use stack_graphs::graph::NodeID;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::BackwardPartialPathStitcher;
+use stack_graphs::stitching::Database;
+use stack_graphs::stitching::DatabaseCandidates;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::StitcherConfig;
+use stack_graphs::NoCancellation;
+
+use crate::test_graphs;
+
+fn find_references(
+    graph: &StackGraph,
+    file: &str,
+    definition: u32,
+    config: StitcherConfig,
+) -> BTreeSet<String> {
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+
+    // Generate partial paths for everything in the database.
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let file = graph.get_file(file).expect("missing file");
+    let definition = graph
+        .node_for_id(NodeID::new_in_file(file, definition))
+        .expect("missing definition");
+    let mut complete_partial_paths = Vec::new();
+    BackwardPartialPathStitcher::find_all_complete_partial_paths_from_definitions(
+        &mut DatabaseCandidates::new(graph, &mut partials, &mut db),
+        std::iter::once(definition),
+        config,
+        &NoCancellation,
+        |_, _, p| {
+            complete_partial_paths.push(p.clone());
+        },
+    )
+    .expect("should never be cancelled");
+    complete_partial_paths
+        .into_iter()
+        .map(|partial_path| partial_path.display(graph, &mut partials).to_string())
+        .collect()
+}
+
+fn check_find_references(
+    graph: &StackGraph,
+    file: &str,
+    definition: u32,
+    expected_partial_paths: &[&str],
+) {
+    let results = find_references(graph, file, definition, StitcherConfig::default());
+    let expected_partial_paths = expected_partial_paths
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<BTreeSet<_>>();
+    assert_eq!(expected_partial_paths, results);
+}
+
+#[test]
+fn class_field_through_function_parameter() {
+    let graph = test_graphs::class_field_through_function_parameter::new();
+    check_find_references(
+        &graph,
+        "b.py",
+        8,
+        &[
+            // reference to `bar` on result flows through body of `foo` to find `A.bar`
+            "<> () [main.py(10) reference bar] -> [b.py(8) definition bar] <> ()",
+        ],
+    );
+}
+
+#[test]
+fn cyclic_imports_python() {
+    let graph = test_graphs::cyclic_imports_python::new();
+    check_find_references(
+        &graph,
+        "a.py",
+        0,
+        &[
+            // reference to `a` in import statement in `main`
+            "<> () [main.py(8) reference a] -> [a.py(0) definition a] <> ()",
+            // reference to `a` in import statement in `b`
+            "<> () [b.py(8) reference a] -> [a.py(0) definition a] <> ()",
+        ],
+    );
+}
+
+#[test]
+fn cyclic_imports_rust() {
+    let graph = test_graphs::cyclic_imports_rust::new();
+    check_find_references(
+        &graph,
+        "test.rs",
+        204,
+        &[
+            // reference to `a::FOO` in `main` can resolve to `a::BAR`
+            "<> () [test.rs(101) reference FOO] -> [test.rs(204) definition BAR] <> ()",
+            // reference to `BAR` in module `b`
+            "<> () [test.rs(305) reference BAR] -> [test.rs(204) definition BAR] <> ()",
+        ],
+    );
+}
+
+#[test]
+fn sequenced_import_star() {
+    let graph = test_graphs::sequenced_import_star::new();
+    check_find_references(
+        &graph,
+        "b.py",
+        5,
+        &[
+            // reference to `foo` resolves through intermediate file to find `b.foo`
+            "<> () [main.py(6) reference foo] -> [b.py(5) definition foo] <> ()",
+        ],
+    );
+}
+
+#[test]
+fn stitching_stops_at_limits() {
+    let graph = test_graphs::cyclic_imports_python::new();
+    let results = find_references(
+        &graph,
+        "a.py",
+        0,
+        StitcherConfig::default().with_max_complete_paths(Some(1)),
+    );
+    assert_eq!(1, results.len());
+    let results = find_references(
+        &graph,
+        "a.py",
+        0,
+        StitcherConfig::default().with_max_work(Some(1)),
+    );
+    assert_eq!(0, results.len());
+}
@@ -9,6 +9,8 @@ use enumset::enum_set;
 use stack_graphs::arena::Handle;
 use stack_graphs::cycles::Appendables;
 use stack_graphs::cycles::AppendingCycleDetector;
+use stack_graphs::cycles::PrependingCycleDetector;
+use stack_graphs::cycles::SimilarPathDetector;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::Cyclicity;
 use stack_graphs::partial::PartialPath;
@@ -18,6 +20,7 @@ use stack_graphs::stitching::ForwardPartialPathStitcher;
 use stack_graphs::stitching::GraphEdges;
 use stack_graphs::stitching::StitcherConfig;
 use stack_graphs::CancelAfterDuration;
+use std::cmp::Ordering;
 use std::time::Duration;
 
 use crate::util::*;
@@ -337,6 +340,44 @@ fn stitching_composite_identity_cycle_is_detected() {
     }
 }
 
+#[test]
+fn prepending_composite_identity_cycle_is_detected() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let r = StackGraph::root_node();
+    let foo_ref = create_push_symbol_node(&mut graph, file, "foo", false);
+    let foo_def = create_pop_symbol_node(&mut graph, file, "foo", false);
+    let bar_ref = create_push_symbol_node(&mut graph, file, "bar", false);
+    let bar_def = create_pop_symbol_node(&mut graph, file, "bar", false);
+
+    let mut partials = PartialPaths::new();
+    let p0 = create_partial_path_and_edges(&mut graph, &mut partials, &[r, bar_def, foo_ref, r])
+        .unwrap();
+    let p1 = create_partial_path_and_edges(&mut graph, &mut partials, &[r, foo_def, bar_ref, r])
+        .unwrap();
+
+    let mut db = Database::new();
+    let p0 = db.add_partial_path(&graph, &mut partials, p0);
+    let p1 = db.add_partial_path(&graph, &mut partials, p1);
+
+    // test prepending cycle detector
+    {
+        let mut paths = Appendables::new();
+        let mut cd: PrependingCycleDetector<Handle<PartialPath>> =
+            PrependingCycleDetector::from(&mut paths, db[p1].clone());
+        assert!(cd
+            .is_cyclic(&graph, &mut partials, &db, &mut paths)
+            .unwrap()
+            .is_empty());
+        cd.prepend(&mut paths, p0);
+        assert_eq!(
+            enum_set![Cyclicity::StrengthensPostcondition],
+            cd.is_cyclic(&graph, &mut partials, &db, &mut paths)
+                .unwrap()
+        );
+    }
+}
+
 #[test]
 fn appending_eliminating_cycle_terminates() {
     let mut graph = StackGraph::new();
@@ -364,3 +405,157 @@ fn appending_eliminating_cycle_terminates() {
         assert_eq!(1, path_count);
     }
 }
+
+// ----------------------------------------------------------------------------
+// similar paths
+
+fn add_similar_path(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    detector: &mut SimilarPathDetector<PartialPath>,
+    path: &PartialPath,
+) -> bool {
+    detector.add_path(graph, partials, path, |ps, left, right| {
+        left.equals(ps, right).then_some(Ordering::Equal)
+    })
+}
+
+#[test]
+fn similar_path_detector_evicts_least_recently_used_buckets() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let s = create_scope_node(&mut graph, file, false);
+    let t = create_scope_node(&mut graph, file, false);
+    let u = create_scope_node(&mut graph, file, false);
+
+    let mut partials = PartialPaths::new();
+    let s_path = PartialPath::from_node(&graph, &mut partials, s);
+    let t_path = PartialPath::from_node(&graph, &mut partials, t);
+    let u_path = PartialPath::from_node(&graph, &mut partials, u);
+
+    let mut detector = SimilarPathDetector::new();
+    detector.set_max_paths(Some(2));
+    assert!(!add_similar_path(
+        &graph,
+        &mut partials,
+        &mut detector,
+        &s_path
+    ));
+    assert!(!add_similar_path(
+        &graph,
+        &mut partials,
+        &mut detector,
+        &t_path
+    ));
+    // using the bucket of s makes t the least recently used one
+    assert!(add_similar_path(
+        &graph,
+        &mut partials,
+        &mut detector,
+        &s_path
+    ));
+    assert!(!add_similar_path(
+        &graph,
+        &mut partials,
+        &mut detector,
+        &u_path
+    ));
+    assert!(add_similar_path(
+        &graph,
+        &mut partials,
+        &mut detector,
+        &s_path
+    ));
+    // the bucket of t was evicted, so the path is not detected as similar
+    assert!(!add_similar_path(
+        &graph,
+        &mut partials,
+        &mut detector,
+        &t_path
+    ));
+
+    let stats = detector.stats();
+    assert_eq!(2, stats.evicted_buckets);
+    assert_eq!(2, stats.evicted_paths);
+    assert_eq!(1, stats.readmitted_paths);
+}
+
+#[test]
+fn similar_path_detector_remembers_limited_number_of_evicted_buckets() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let mut partials = PartialPaths::new();
+    let paths = (0..4)
+        .map(|_| {
+            let node = create_scope_node(&mut graph, file, false);
+            PartialPath::from_node(&graph, &mut partials, node)
+        })
+        .collect::<Vec<_>>();
+
+    let mut detector = SimilarPathDetector::new();
+    detector.set_max_paths(Some(1));
+    for path in &paths {
+        assert!(!add_similar_path(
+            &graph,
+            &mut partials,
+            &mut detector,
+            path
+        ));
+    }
+    // only the most recently evicted bucket is remembered
+    assert!(!add_similar_path(
+        &graph,
+        &mut partials,
+        &mut detector,
+        &paths[2]
+    ));
+    assert!(!add_similar_path(
+        &graph,
+        &mut partials,
+        &mut detector,
+        &paths[0]
+    ));
+
+    let stats = detector.stats();
+    assert_eq!(5, stats.evicted_buckets);
+    assert_eq!(1, stats.readmitted_paths);
+}
+
+#[test]
+fn similar_path_detector_without_budget_keeps_all_buckets() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let s = create_scope_node(&mut graph, file, false);
+    let t = create_scope_node(&mut graph, file, false);
+
+    let mut partials = PartialPaths::new();
+    let s_path = PartialPath::from_node(&graph, &mut partials, s);
+    let t_path = PartialPath::from_node(&graph, &mut partials, t);
+
+    let mut detector = SimilarPathDetector::new();
+    assert!(!add_similar_path(
+        &graph,
+        &mut partials,
+        &mut detector,
+        &s_path
+    ));
+    assert!(!add_similar_path(
+        &graph,
+        &mut partials,
+        &mut detector,
+        &t_path
+    ));
+    assert!(add_similar_path(
+        &graph,
+        &mut partials,
+        &mut detector,
+        &s_path
+    ));
+    assert!(add_similar_path(
+        &graph,
+        &mut partials,
+        &mut detector,
+        &t_path
+    ));
+    assert_eq!(0, detector.stats().evicted_buckets);
+}
@@ -0,0 +1,118 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use lsp_positions::Position;
+use lsp_positions::Span;
+use stack_graphs::arena::Handle;
+use stack_graphs::diff::GraphDiff;
+use stack_graphs::diff::NodeChange;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::StackGraph;
+
+use crate::util::create_pop_symbol_node;
+use crate::util::create_push_symbol_node;
+use crate::util::create_scope_node;
+
+fn set_span(graph: &mut StackGraph, node: Handle<Node>, line: usize) {
+    graph.source_info_mut(node).span = Span {
+        start: Position {
+            line,
+            ..Default::default()
+        },
+        end: Position {
+            line,
+            ..Default::default()
+        },
+    };
+}
+
+/// Creates a graph with a reference and a definition of `x`, connected by a scope.  If
+/// `extra_scope` is set, an unrelated scope node is created first, which shifts the local IDs of
+/// all other nodes.
+fn create_graph(extra_scope: bool, precedence: i32) -> StackGraph {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    if extra_scope {
+        create_scope_node(&mut graph, file, false);
+    }
+    let reference = create_push_symbol_node(&mut graph, file, "x", true);
+    set_span(&mut graph, reference, 1);
+    let scope = create_scope_node(&mut graph, file, false);
+    let definition = create_pop_symbol_node(&mut graph, file, "x", true);
+    set_span(&mut graph, definition, 2);
+    graph.add_edge(reference, scope, 0);
+    graph.add_edge(scope, definition, precedence);
+    graph
+}
+
+#[test]
+fn identical_graphs_have_no_differences() {
+    let diff = GraphDiff::from_graphs(&create_graph(false, 0), &create_graph(false, 0));
+    assert!(diff.is_empty());
+}
+
+#[test]
+fn can_diff_precedences() {
+    let diff = GraphDiff::from_graphs(&create_graph(false, 0), &create_graph(false, 1));
+    assert!(diff.added_edges.is_empty());
+    assert!(diff.removed_edges.is_empty());
+    assert_eq!(1, diff.changed_edges.len());
+    assert_eq!(0, diff.changed_edges[0].old.precedence);
+    assert_eq!(1, diff.changed_edges[0].new.precedence);
+}
+
+#[test]
+fn renumbered_nodes_are_matched_by_symbol_and_span() {
+    let diff = GraphDiff::from_graphs(&create_graph(false, 0), &create_graph(true, 0));
+    // The reference and definition are matched by their symbol and span.  Scopes have no symbol,
+    // so the old scope cannot be matched, and the edges through it are replaced.
+    let changed = diff
+        .changed_nodes
+        .iter()
+        .map(|node| (node.old.symbol(), node.changes.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            (Some("x"), vec![NodeChange::Id]),
+            (Some("x"), vec![NodeChange::Id]),
+        ],
+        changed
+    );
+    assert_eq!(2, diff.added_nodes.len());
+    assert_eq!(1, diff.removed_nodes.len());
+    assert_eq!(2, diff.added_edges.len());
+    assert_eq!(2, diff.removed_edges.len());
+    assert!(diff.changed_edges.is_empty());
+}
+
+#[test]
+fn can_diff_added_nodes_and_changed_source_info() {
+    let old = create_graph(false, 0);
+    let mut new = create_graph(false, 0);
+    let file = new.get_file("test").unwrap();
+    let definition = new
+        .iter_nodes()
+        .find(|node| new[*node].is_definition())
+        .unwrap();
+    new.source_info_mut(definition).span.end.line = 3;
+    let other = create_pop_symbol_node(&mut new, file, "y", true);
+    new.add_edge(StackGraph::root_node(), other, 0);
+
+    let diff = GraphDiff::from_graphs(&old, &new);
+    assert_eq!(
+        vec![Some("y")],
+        diff.added_nodes
+            .iter()
+            .map(|node| node.symbol())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(1, diff.added_edges.len());
+    assert_eq!(1, diff.changed_nodes.len());
+    assert_eq!(vec![NodeChange::SourceInfo], diff.changed_nodes[0].changes);
+    assert!(diff.removed_nodes.is_empty());
+    assert!(diff.removed_edges.is_empty());
+}
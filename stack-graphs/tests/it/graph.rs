@@ -7,7 +7,13 @@
 
 use std::collections::HashSet;
 
+use lsp_positions::Position;
+use lsp_positions::Span;
 use maplit::hashset;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::Degree;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::NodeID;
 use stack_graphs::graph::StackGraph;
 
 use crate::test_graphs;
@@ -157,6 +163,234 @@ fn can_add_and_remove_edges() {
             .collect::<HashSet<_>>(),
         hashset! { (h2, 0), (h3, 0), (h4, 0) }
     );
+    assert_eq!(Degree::One, graph.incoming_edge_degree(h3));
+    // Removing an edge only removes that edge, and can be done once.
+    assert!(graph.remove_edge(h1, h3));
+    assert!(!graph.remove_edge(h1, h3));
+    assert!(!graph.remove_edge(h2, h1));
+    assert_eq!(
+        graph
+            .outgoing_edges(h1)
+            .map(|edge| (edge.sink, edge.precedence))
+            .collect::<HashSet<_>>(),
+        hashset! { (h2, 0), (h4, 0) }
+    );
+    assert_eq!(Degree::Zero, graph.incoming_edge_degree(h3));
+    // A removed edge can be added again.
+    graph.add_edge(h1, h3, 1);
+    assert_eq!(
+        graph
+            .outgoing_edges(h1)
+            .map(|edge| (edge.sink, edge.precedence))
+            .collect::<HashSet<_>>(),
+        hashset! { (h2, 0), (h3, 1), (h4, 0) }
+    );
+}
+
+#[test]
+fn can_set_edge_kinds() {
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let h1 = graph.internal_scope(file, 0);
+    let h2 = graph.internal_scope(file, 1);
+    let h3 = graph.internal_scope(file, 2);
+    graph.add_edge(h1, h2, 0);
+    graph.add_edge(h1, h3, 0);
+    assert_eq!(None, graph.edge_kind(h1, h2));
+    let import = graph.add_string("import");
+    graph.set_edge_kind(h1, h2, Some(import));
+    // Setting the kind of a missing edge has no effect.
+    graph.set_edge_kind(h2, h3, Some(import));
+    assert_eq!(Some(import), graph.edge_kind(h1, h2));
+    assert_eq!(None, graph.edge_kind(h1, h3));
+    assert_eq!(None, graph.edge_kind(h2, h3));
+
+    let mut copy = StackGraph::new();
+    copy.add_from_graph(&graph).expect("Adding graph failed");
+    let copy_file = copy.get_file("test.py").unwrap();
+    let c1 = copy.node_for_id(NodeID::new_in_file(copy_file, 0)).unwrap();
+    let c2 = copy.node_for_id(NodeID::new_in_file(copy_file, 1)).unwrap();
+    let kind = copy.edge_kind(c1, c2).expect("Missing edge kind");
+    assert_eq!("import", &copy[kind]);
+
+    graph.set_edge_kind(h1, h2, None);
+    assert_eq!(None, graph.edge_kind(h1, h2));
+    graph.set_edge_kind(h1, h2, Some(import));
+    graph.remove_edge(h1, h2);
+    graph.add_edge(h1, h2, 0);
+    assert_eq!(None, graph.edge_kind(h1, h2));
+}
+
+#[test]
+fn can_remove_files() {
+    let mut graph = StackGraph::new();
+    let kept = graph.get_or_create_file("kept.py");
+    let removed = graph.get_or_create_file("removed.py");
+    let k1 = graph.internal_scope(kept, 0);
+    let k2 = graph.internal_scope(kept, 1);
+    let r1 = graph.internal_scope(removed, 0);
+    let r2 = graph.internal_scope(removed, 1);
+    graph.add_edge(k1, k2, 0);
+    graph.add_edge(k1, r1, 0);
+    graph.add_edge(r1, k2, 0);
+    graph.add_edge(r1, r2, 0);
+    let key = graph.add_string("key");
+    let value = graph.add_string("value");
+    graph.node_debug_info_mut(r1).add(key, value);
+    graph.edge_debug_info_mut(k1, r1).add(key, value);
+
+    graph.remove_file(removed);
+
+    assert_eq!(graph.iter_files().collect::<HashSet<_>>(), hashset! {kept});
+    assert_eq!(graph.get_file("removed.py"), None);
+    assert_eq!(graph.nodes_for_file(removed).count(), 0);
+    assert_eq!(
+        graph.iter_nodes().collect::<HashSet<_>>(),
+        hashset! {StackGraph::root_node(), StackGraph::jump_to_node(), k1, k2}
+    );
+    assert_eq!(
+        graph
+            .outgoing_edges(k1)
+            .map(|edge| edge.sink)
+            .collect::<HashSet<_>>(),
+        hashset! {k2}
+    );
+    assert_eq!(graph.incoming_edge_degree(k2), Degree::One);
+    assert!(graph.node_debug_info(r1).unwrap().iter().next().is_none());
+    assert!(graph.edge_debug_info(k1, r1).is_none());
+    assert_eq!(graph.node_for_id(graph[k2].id()), Some(k2));
+    assert_eq!(graph.node_for_id(graph[r1].id()), None);
+
+    let readded = graph.get_or_create_file("removed.py");
+    assert_ne!(readded, removed);
+    assert_eq!(graph.iter_files().count(), 2);
+}
+
+#[test]
+fn can_assign_stable_ids() {
+    fn span(line: usize) -> Span {
+        Span {
+            start: Position {
+                line,
+                ..Default::default()
+            },
+            end: Position {
+                line,
+                ..Default::default()
+            },
+        }
+    }
+
+    // Creates a graph with a reference and two identical definitions, and returns those nodes
+    // and their stable IDs.  If `reversed` is set, the nodes are created in a different order,
+    // after an unrelated scope node without a span, which changes all local IDs.
+    fn stable_ids(reversed: bool) -> (StackGraph, Vec<Handle<Node>>, Vec<String>) {
+        let mut graph = StackGraph::new();
+        let file = graph.get_or_create_file("test.py");
+        let x = graph.add_symbol("x");
+        let nodes = if reversed {
+            let scope = graph.internal_scope(file, 0);
+            let def1 = graph.definition(file, 1, x);
+            let def2 = graph.definition(file, 2, x);
+            let reference = graph.reference(file, 3, x);
+            graph.source_info_mut(scope).span = Span::default();
+            vec![reference, def1, def2]
+        } else {
+            let reference = graph.reference(file, 0, x);
+            let def1 = graph.definition(file, 1, x);
+            let def2 = graph.definition(file, 2, x);
+            vec![reference, def1, def2]
+        };
+        graph.source_info_mut(nodes[0]).span = span(1);
+        graph.source_info_mut(nodes[1]).span = span(2);
+        graph.source_info_mut(nodes[2]).span = span(2);
+        graph.assign_stable_ids(file);
+        let ids = nodes
+            .iter()
+            .map(|node| {
+                let id = graph.source_info(*node).unwrap().stable_id;
+                graph[id.into_option().expect("Missing stable ID")].to_string()
+            })
+            .collect();
+        (graph, nodes, ids)
+    }
+
+    let (graph, nodes, ids) = stable_ids(false);
+    let (reversed_graph, reversed_nodes, reversed_ids) = stable_ids(true);
+    assert_eq!(ids, reversed_ids);
+    assert_ne!(ids[0], ids[1]);
+    assert_ne!(ids[1], ids[2]);
+    for (node, id) in nodes.iter().zip(&ids) {
+        assert_eq!(Some(*node), graph.node_for_stable_id(id));
+    }
+    for (node, id) in reversed_nodes.iter().zip(&ids) {
+        assert_eq!(Some(*node), reversed_graph.node_for_stable_id(id));
+    }
+    assert_eq!(None, graph.node_for_stable_id("unknown"));
+
+    // Nodes without a span do not get a stable ID.
+    let file = reversed_graph.get_file("test.py").unwrap();
+    let scope = reversed_graph
+        .node_for_id(NodeID::new_in_file(file, 0))
+        .unwrap();
+    assert_eq!(
+        None,
+        reversed_graph
+            .source_info(scope)
+            .unwrap()
+            .stable_id
+            .into_option()
+    );
+}
+
+#[test]
+fn stable_ids_do_not_change_when_earlier_lines_are_edited() {
+    fn span(start: usize, end: usize) -> Span {
+        Span {
+            start: Position {
+                line: start,
+                ..Default::default()
+            },
+            end: Position {
+                line: end,
+                ..Default::default()
+            },
+        }
+    }
+
+    // Creates a graph with two definitions that each contain a reference, and a reference after
+    // them, and returns the stable IDs of these nodes.  The `inserted` lines are added inside
+    // the first definition, before its reference.
+    fn stable_ids(inserted: usize) -> Vec<String> {
+        let mut graph = StackGraph::new();
+        let file = graph.get_or_create_file("test.py");
+        let x = graph.add_symbol("x");
+        let f = graph.add_symbol("f");
+        let def1 = graph.definition(file, 0, f);
+        let ref1 = graph.reference(file, 1, x);
+        let def2 = graph.definition(file, 2, f);
+        let ref2 = graph.reference(file, 3, x);
+        let ref3 = graph.reference(file, 4, x);
+        graph.source_info_mut(def1).span = span(1, 1);
+        graph.source_info_mut(def1).definiens_span = span(1, 3 + inserted);
+        graph.source_info_mut(ref1).span = span(2 + inserted, 2 + inserted);
+        graph.source_info_mut(def2).span = span(5 + inserted, 5 + inserted);
+        graph.source_info_mut(def2).definiens_span = span(5 + inserted, 7 + inserted);
+        graph.source_info_mut(ref2).span = span(6 + inserted, 6 + inserted);
+        graph.source_info_mut(ref3).span = span(9 + inserted, 9 + inserted);
+        graph.assign_stable_ids(file);
+        [def1, ref1, def2, ref2, ref3]
+            .iter()
+            .map(|node| {
+                let id = graph.source_info(*node).unwrap().stable_id;
+                graph[id.into_option().expect("Missing stable ID")].to_string()
+            })
+            .collect()
+    }
+
+    let ids = stable_ids(0);
+    assert_eq!(ids, stable_ids(3));
+    assert_eq!(ids.len(), ids.iter().collect::<HashSet<_>>().len());
 }
 
 #[test]
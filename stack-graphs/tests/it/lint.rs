@@ -0,0 +1,130 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use lsp_positions::Position;
+use lsp_positions::Span;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::lint::lint;
+use stack_graphs::lint::lint_file;
+use stack_graphs::lint::DiagnosticKind;
+
+use crate::util::create_pop_symbol_node;
+use crate::util::create_push_scoped_symbol_node;
+use crate::util::create_push_symbol_node;
+use crate::util::create_scope_node;
+
+fn set_span(graph: &mut StackGraph, node: Handle<Node>, line: usize) {
+    graph.source_info_mut(node).span = Span {
+        start: Position {
+            line,
+            ..Default::default()
+        },
+        end: Position {
+            line,
+            ..Default::default()
+        },
+    };
+}
+
+fn diagnostic_kinds(graph: &StackGraph) -> Vec<(DiagnosticKind, Handle<Node>)> {
+    lint(graph)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.kind, graph.node_for_id(diagnostic.node).unwrap()))
+        .collect()
+}
+
+#[test]
+fn well_formed_graph_has_no_diagnostics() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let reference = create_push_symbol_node(&mut graph, file, "x", true);
+    let scope = create_scope_node(&mut graph, file, false);
+    let definition = create_pop_symbol_node(&mut graph, file, "x", true);
+    set_span(&mut graph, definition, 1);
+    graph.add_edge(reference, scope, 0);
+    graph.add_edge(scope, definition, 0);
+    assert!(lint(&graph).is_empty());
+    assert!(lint_file(&graph, file).is_empty());
+}
+
+#[test]
+fn can_find_definitions_without_source_info() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let reference = create_push_symbol_node(&mut graph, file, "x", true);
+    let definition = create_pop_symbol_node(&mut graph, file, "x", true);
+    graph.add_edge(reference, definition, 0);
+    assert_eq!(
+        vec![(DiagnosticKind::DefinitionWithoutSourceInfo, definition)],
+        diagnostic_kinds(&graph)
+    );
+}
+
+#[test]
+fn can_find_attached_scopes_that_are_not_exported() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let scope = create_scope_node(&mut graph, file, false);
+    let scope_id = graph[scope].id();
+    let reference = create_push_scoped_symbol_node(&mut graph, file, "x", scope_id, true);
+    let root = StackGraph::root_node();
+    graph.add_edge(reference, root, 0);
+    graph.add_edge(root, scope, 0);
+    assert_eq!(
+        vec![(DiagnosticKind::ScopeNotExported, reference)],
+        diagnostic_kinds(&graph)
+    );
+
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let scope = create_scope_node(&mut graph, file, true);
+    let scope_id = graph[scope].id();
+    let reference = create_push_scoped_symbol_node(&mut graph, file, "x", scope_id, true);
+    graph.add_edge(reference, StackGraph::root_node(), 0);
+    assert!(lint(&graph).is_empty());
+}
+
+#[test]
+fn can_find_pop_nodes_without_incoming_edges() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let definition = create_pop_symbol_node(&mut graph, file, "x", true);
+    set_span(&mut graph, definition, 1);
+    assert_eq!(
+        vec![(DiagnosticKind::PopWithoutIncomingEdges, definition)],
+        diagnostic_kinds(&graph)
+    );
+}
+
+#[test]
+fn can_find_unreachable_nodes() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let scope = create_scope_node(&mut graph, file, false);
+    let definition = create_pop_symbol_node(&mut graph, file, "x", true);
+    set_span(&mut graph, definition, 1);
+    graph.add_edge(scope, definition, 0);
+    assert_eq!(
+        vec![(DiagnosticKind::UnreachableNode, definition)],
+        diagnostic_kinds(&graph)
+    );
+}
+
+#[test]
+fn can_lint_single_file() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let other_file = graph.add_file("other").unwrap();
+    let push = create_push_symbol_node(&mut graph, file, "x", false);
+    create_push_symbol_node(&mut graph, other_file, "x", false);
+    let diagnostics = lint_file(&graph, file);
+    assert_eq!(1, diagnostics.len());
+    assert_eq!(graph[push].id(), diagnostics[0].node);
+    assert_eq!(None, diagnostics[0].span);
+}
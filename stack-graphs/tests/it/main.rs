@@ -16,14 +16,20 @@ mod can_create_graph;
 mod can_find_local_nodes;
 mod can_find_node_partial_paths_in_database;
 mod can_find_partial_paths_in_file;
+mod can_find_qualified_definitions_with_forward_partial_path_stitching;
+mod can_find_references_with_backward_partial_path_stitching;
+mod can_find_references_with_forward_partial_path_stitching;
 mod can_find_root_partial_paths_in_database;
 mod can_jump_to_definition;
 mod can_jump_to_definition_with_forward_partial_path_stitching;
 mod cycles;
+mod diff;
 mod graph;
+mod lint;
 mod partial;
 #[cfg(feature = "serde")]
 mod serde;
+mod shadowing;
 mod stats;
 mod stitching;
 #[cfg(feature = "storage")]
@@ -53,6 +53,10 @@ fn serde_json_stack_graph() {
                         },
                     },
                     syntax_type: None,
+                    containing_line: None,
+                    definiens_span: None,
+                    fully_qualified_name: None,
+                    stable_id: None,
                 }),
                 debug_info: Some(serde::DebugInfo { data: vec![] }),
             }],
@@ -68,6 +72,7 @@ fn serde_json_stack_graph() {
                     local_id: 0,
                 },
                 precedence: 0,
+                kind: None,
                 debug_info: Some(serde::DebugInfo { data: vec![] }),
             }],
         },
@@ -325,6 +330,59 @@ fn can_load_serialized_stack_graph() {
     assert!(sg.node_debug_info(handle).is_some());
 }
 
+#[test]
+fn can_serialize_edge_kinds() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let scope = graph
+        .add_scope_node(graph::NodeID::new_in_file(file, 0), false)
+        .unwrap();
+    let root = StackGraph::root_node();
+    graph.add_edge(root, scope, 0);
+    graph.add_edge(scope, root, 0);
+    let import = graph.add_string("import");
+    graph.set_edge_kind(root, scope, Some(import));
+
+    let serialized = graph.to_serializable();
+    let actual = serde_json::to_value(&serialized.edges).unwrap();
+    let expected = json!(
+        [
+            {
+                "kind" : "import",
+                "precedence" : 0,
+                "sink" : {
+                    "file" : "test",
+                    "local_id" : 0
+                },
+                "source" : {
+                    "local_id" : 1
+                }
+            },
+            {
+                "precedence" : 0,
+                "sink" : {
+                    "local_id" : 1
+                },
+                "source" : {
+                    "file" : "test",
+                    "local_id" : 0
+                }
+            }
+        ]
+    );
+    assert_json_eq!(expected, actual);
+
+    let mut loaded = StackGraph::new();
+    serialized.load_into(&mut loaded).unwrap();
+    let file = loaded.get_file("test").unwrap();
+    let scope = loaded
+        .node_for_id(graph::NodeID::new_in_file(file, 0))
+        .unwrap();
+    let kind = loaded.edge_kind(root, scope).expect("Missing edge kind");
+    assert_eq!("import", &loaded[kind]);
+    assert_eq!(None, loaded.edge_kind(scope, root));
+}
+
 #[test]
 fn can_serialize_graph() {
     let graph: StackGraph = test_graphs::simple::new();
@@ -580,6 +638,7 @@ fn can_serialize_graph() {
                                 }
                             }
                         },
+                        "containing_line" : "print(x)",
                         "syntax_type" : "variable"
                     },
                     "symbol" : "x",
@@ -963,6 +1022,7 @@ fn can_serialize_graph() {
                                 }
                             }
                         },
+                        "containing_line" : "x = 42",
                         "syntax_type" : "variable"
                     },
                     "symbol" : "x",
@@ -0,0 +1,129 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use std::collections::BTreeSet;
+
+use pretty_assertions::assert_eq;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::shadowing::ClosestScope;
+use stack_graphs::shadowing::EdgePrecedence;
+use stack_graphs::shadowing::FirstDeclaration;
+use stack_graphs::shadowing::ShadowingPolicy;
+use stack_graphs::stitching::Database;
+use stack_graphs::stitching::DatabaseCandidates;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::StitcherConfig;
+use stack_graphs::NoCancellation;
+
+use crate::util::*;
+
+struct Shadowing {
+    graph: StackGraph,
+    reference: Handle<Node>,
+    /// Reached by a higher precedence edge, declared on the last line.
+    high_precedence: Handle<Node>,
+    /// Reached by a lower precedence edge, declared on the second line.
+    low_precedence: Handle<Node>,
+    /// Reached through an extra scope, declared on the first line.
+    nested: Handle<Node>,
+}
+
+fn create_graph() -> Shadowing {
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let reference = create_push_symbol_node(&mut graph, file, "x", true);
+    let scope = create_scope_node(&mut graph, file, false);
+    let inner = create_scope_node(&mut graph, file, false);
+    let high_precedence = create_pop_symbol_node(&mut graph, file, "x", true);
+    let low_precedence = create_pop_symbol_node(&mut graph, file, "x", true);
+    let nested = create_pop_symbol_node(&mut graph, file, "x", true);
+    graph.add_edge(reference, scope, 0);
+    graph.add_edge(scope, high_precedence, 1);
+    graph.add_edge(scope, low_precedence, 0);
+    graph.add_edge(scope, inner, 0);
+    graph.add_edge(inner, nested, 0);
+    graph.source_info_mut(high_precedence).span.start.line = 2;
+    graph.source_info_mut(low_precedence).span.start.line = 1;
+    graph.source_info_mut(nested).span.start.line = 0;
+    Shadowing {
+        graph,
+        reference,
+        high_precedence,
+        low_precedence,
+        nested,
+    }
+}
+
+fn check_visible_definitions(
+    shadowing: &Shadowing,
+    policy: &dyn ShadowingPolicy,
+    expected_definitions: &[Handle<Node>],
+) {
+    let graph = &shadowing.graph;
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let mut paths = Vec::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut DatabaseCandidates::new(graph, &mut partials, &mut db),
+        std::iter::once(shadowing.reference),
+        StitcherConfig::default(),
+        &NoCancellation,
+        |_, _, p| paths.push(p.clone()),
+    )
+    .expect("should never be cancelled");
+    assert_eq!(3, paths.len());
+
+    let results = paths
+        .iter()
+        .filter(|p| !policy.is_shadowed(graph, &mut partials, p, &paths))
+        .map(|p| p.end_node)
+        .collect::<BTreeSet<_>>();
+    let expected_definitions = expected_definitions
+        .iter()
+        .cloned()
+        .collect::<BTreeSet<_>>();
+    assert_eq!(expected_definitions, results);
+}
+
+#[test]
+fn edge_precedence_selects_highest_precedence_definition() {
+    let shadowing = create_graph();
+    check_visible_definitions(&shadowing, &EdgePrecedence, &[shadowing.high_precedence]);
+}
+
+#[test]
+fn closest_scope_selects_definitions_with_shortest_paths() {
+    let shadowing = create_graph();
+    check_visible_definitions(
+        &shadowing,
+        &ClosestScope,
+        &[shadowing.high_precedence, shadowing.low_precedence],
+    );
+}
+
+#[test]
+fn first_declaration_selects_earliest_definition() {
+    let shadowing = create_graph();
+    check_visible_definitions(&shadowing, &FirstDeclaration, &[shadowing.nested]);
+}
@@ -7,12 +7,24 @@
 
 use itertools::Itertools;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPath;
 use stack_graphs::partial::PartialPaths;
+use stack_graphs::stitching::BackwardPartialPathStitcher;
 use stack_graphs::stitching::Database;
+use stack_graphs::stitching::DatabaseCandidates;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::GraphEdgeCandidates;
+use stack_graphs::stitching::StitcherConfig;
+use stack_graphs::stitching::StitchingProgress;
+use stack_graphs::CancellationError;
+use stack_graphs::CancellationFlag;
+use stack_graphs::NoCancellation;
+use std::cell::RefCell;
 
 use crate::util::create_partial_path_and_edges;
 use crate::util::create_pop_symbol_node;
 use crate::util::create_push_symbol_node;
+use crate::util::create_scope_node;
 
 fn test_foo_bar_root_candidate_paths(symbols: &[&str], variable: bool) -> usize {
     let mut graph = StackGraph::new();
@@ -102,3 +114,331 @@ fn find_candidates_for_shorter_symbol_stack_without_variable() {
     let results = test_foo_bar_root_candidate_paths(&["foo"], false);
     assert_eq!(0, results);
 }
+
+/// Creates a graph where a reference in file `a` can reach a definition in file `b` through the
+/// root node in two steps, and a definition in file `a` through higher precedence edges in three
+/// steps.
+fn create_best_first_graph() -> StackGraph {
+    let mut graph = StackGraph::new();
+    let a = graph.add_file("a").unwrap();
+    let b = graph.add_file("b").unwrap();
+    let r = StackGraph::root_node();
+    let reference = create_push_symbol_node(&mut graph, a, "x", true);
+    let outer = create_scope_node(&mut graph, a, false);
+    let inner = create_scope_node(&mut graph, a, false);
+    let local = create_pop_symbol_node(&mut graph, a, "x", true);
+    let remote = create_pop_symbol_node(&mut graph, b, "x", true);
+    graph.add_edge(reference, r, 0);
+    graph.add_edge(r, remote, 0);
+    graph.add_edge(reference, outer, 1);
+    graph.add_edge(outer, inner, 1);
+    graph.add_edge(inner, local, 0);
+    graph
+}
+
+fn find_definitions(graph: &StackGraph, config: StitcherConfig) -> Vec<String> {
+    let mut partials = PartialPaths::new();
+    let references = graph
+        .iter_nodes()
+        .filter(|n| graph[*n].is_reference())
+        .collect_vec();
+    let mut definitions = Vec::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut GraphEdgeCandidates::new(graph, &mut partials, None),
+        references,
+        config,
+        &NoCancellation,
+        |graph, _, path| definitions.push(graph[path.end_node].display(graph).to_string()),
+    )
+    .unwrap();
+    definitions
+}
+
+#[test]
+fn breadth_first_stitching_finds_shortest_paths_first() {
+    let graph = create_best_first_graph();
+    let definitions = find_definitions(
+        &graph,
+        StitcherConfig::default().with_max_complete_paths(Some(1)),
+    );
+    assert_eq!(vec!["[b(0) definition x]"], definitions);
+}
+
+#[test]
+fn best_first_stitching_finds_paths_within_file_first() {
+    let graph = create_best_first_graph();
+    let definitions = find_definitions(
+        &graph,
+        StitcherConfig::default()
+            .with_best_first(true)
+            .with_max_complete_paths(Some(1)),
+    );
+    assert_eq!(vec!["[a(3) definition x]"], definitions);
+
+    let definitions = find_definitions(&graph, StitcherConfig::default().with_best_first(true));
+    assert_eq!(2, definitions.len());
+}
+
+/// Returns the number of phases needed to find all complete paths in the given graph.
+fn count_phases(graph: &StackGraph, best_first: bool, max_work_per_phase: Option<usize>) -> usize {
+    let mut partials = PartialPaths::new();
+    let initial_paths = graph
+        .iter_nodes()
+        .filter(|n| graph[*n].is_reference())
+        .map(|n| {
+            let mut p = PartialPath::from_node(graph, &mut partials, n);
+            p.eliminate_precondition_stack_variables(&mut partials);
+            p
+        })
+        .collect_vec();
+    let mut stitcher =
+        ForwardPartialPathStitcher::from_partial_paths(graph, &mut partials, initial_paths);
+    stitcher.set_best_first(best_first);
+    if let Some(max_work_per_phase) = max_work_per_phase {
+        stitcher.set_max_work_per_phase(max_work_per_phase);
+    }
+    let mut candidates = GraphEdgeCandidates::new(graph, &mut partials, None);
+    while !stitcher.is_complete() {
+        stitcher.process_next_phase(&mut candidates, |_, _, _| true);
+    }
+    stitcher.progress().phase
+}
+
+#[test]
+fn best_first_stitching_respects_max_work_per_phase() {
+    let graph = create_best_first_graph();
+    let breadth_first = count_phases(&graph, false, None);
+    assert!(count_phases(&graph, true, None) > breadth_first);
+    assert_eq!(breadth_first, count_phases(&graph, true, Some(usize::MAX)));
+}
+
+#[test]
+fn stitching_stops_when_work_budget_is_exhausted() {
+    let graph = create_best_first_graph();
+    let definitions = find_definitions(&graph, StitcherConfig::default().with_max_work(Some(1)));
+    assert!(definitions.is_empty());
+}
+
+/// Creates a graph where a reference in file `main` reaches a definition in each of the given
+/// files through the root node. The files are added to the graph in the given order.
+fn create_multi_file_graph(files: &[&str]) -> StackGraph {
+    let mut graph = StackGraph::new();
+    let main = graph.add_file("main").unwrap();
+    let r = StackGraph::root_node();
+    let reference = create_push_symbol_node(&mut graph, main, "x", true);
+    graph.add_edge(reference, r, 0);
+    for file in files {
+        let file = graph.add_file(file).unwrap();
+        let definition = create_pop_symbol_node(&mut graph, file, "x", true);
+        graph.add_edge(r, definition, 0);
+    }
+    graph
+}
+
+#[test]
+fn sorted_results_are_independent_of_load_order() {
+    let config = StitcherConfig::default().with_sorted_results(true);
+    let forward = find_definitions(&create_multi_file_graph(&["a", "b", "c"]), config);
+    let backward = find_definitions(&create_multi_file_graph(&["c", "b", "a"]), config);
+    assert_eq!(
+        vec![
+            "[a(0) definition x]",
+            "[b(0) definition x]",
+            "[c(0) definition x]"
+        ],
+        forward
+    );
+    assert_eq!(forward, backward);
+}
+
+#[test]
+fn sorted_results_are_ordered_by_file_before_length() {
+    let graph = create_best_first_graph();
+    let definitions = find_definitions(&graph, StitcherConfig::default().with_sorted_results(true));
+    assert_eq!(
+        vec!["[a(3) definition x]", "[b(0) definition x]"],
+        definitions
+    );
+}
+
+#[test]
+fn discarded_cycles_are_recorded_in_stats() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let reference = create_push_symbol_node(&mut graph, file, "x", true);
+    let outer = create_scope_node(&mut graph, file, false);
+    let inner = create_scope_node(&mut graph, file, false);
+    let definition = create_pop_symbol_node(&mut graph, file, "x", true);
+    graph.add_edge(reference, outer, 0);
+    graph.add_edge(outer, inner, 0);
+    graph.add_edge(inner, outer, 0);
+    graph.add_edge(outer, definition, 0);
+
+    let mut partials = PartialPaths::new();
+    let stats = ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+        vec![reference],
+        StitcherConfig::default().with_collect_stats(true),
+        &NoCancellation,
+        |_, _, _| {},
+    )
+    .unwrap();
+
+    let discarded_cycles = stats.discarded_cycles.iter().collect_vec();
+    assert_eq!(1, discarded_cycles.len());
+    let (cycle, count) = discarded_cycles[0];
+    assert_eq!(1, count);
+    assert!(!cycle.cyclicity.is_empty());
+    assert_eq!(
+        vec![graph[outer].id(), graph[inner].id(), graph[outer].id()],
+        cycle.nodes
+    );
+}
+
+#[test]
+fn removed_files_are_not_stitched() {
+    let mut graph = create_multi_file_graph(&["a", "b"]);
+    let a = graph.get_file("a").unwrap();
+    graph.remove_file(a);
+    let definitions = find_definitions(&graph, StitcherConfig::default());
+    assert_eq!(vec!["[b(0) definition x]"], definitions);
+}
+
+#[test]
+fn can_remove_paths_for_file_from_database() {
+    let graph = create_multi_file_graph(&["a", "b"]);
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .unwrap();
+    }
+
+    let a = graph.get_file("a").unwrap();
+    db.remove_paths_for_file(&graph, &mut partials, a);
+    assert!(db
+        .iter_partial_paths()
+        .all(|path| graph[db[path].end_node].file() != Some(a)));
+
+    let references = graph
+        .iter_nodes()
+        .filter(|n| graph[*n].is_reference())
+        .collect_vec();
+    let mut definitions = Vec::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut DatabaseCandidates::new(&graph, &mut partials, &mut db),
+        references,
+        StitcherConfig::default(),
+        &NoCancellation,
+        |graph, _, path| definitions.push(graph[path.end_node].display(graph).to_string()),
+    )
+    .unwrap();
+    assert_eq!(vec!["[b(0) definition x]"], definitions);
+}
+
+/// A cancellation flag that records the reported progress, and cancels after a number of phases.
+struct CancelAfterPhases {
+    phases: usize,
+    reported: RefCell<Vec<StitchingProgress>>,
+}
+
+impl CancellationFlag for CancelAfterPhases {
+    fn check(&self, at: &'static str) -> Result<(), CancellationError> {
+        match self.reported.borrow().last() {
+            Some(progress) if progress.phase >= self.phases => Err(CancellationError::new(at)),
+            _ => Ok(()),
+        }
+    }
+
+    fn report_progress(&self, _at: &'static str, progress: &StitchingProgress) {
+        self.reported.borrow_mut().push(*progress);
+    }
+}
+
+#[test]
+fn stitching_reports_progress_and_includes_it_when_cancelled() {
+    let graph = create_best_first_graph();
+    let mut partials = PartialPaths::new();
+    let references = graph
+        .iter_nodes()
+        .filter(|n| graph[*n].is_reference())
+        .collect_vec();
+    let cancellation_flag = CancelAfterPhases {
+        phases: 2,
+        reported: RefCell::new(Vec::new()),
+    };
+    let result = ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+        references,
+        StitcherConfig::default(),
+        &cancellation_flag,
+        |_, _, _| {},
+    );
+
+    let reported = cancellation_flag.reported.into_inner();
+    assert_eq!(
+        vec![0, 1, 2],
+        reported.iter().map(|p| p.phase).collect_vec()
+    );
+    assert_eq!(1, reported[2].complete_paths);
+    assert_eq!(0, reported[0].work_performed);
+    assert!(reported
+        .windows(2)
+        .all(|w| w[0].work_performed < w[1].work_performed));
+    let err = result.unwrap_err();
+    assert_eq!("finding complete partial paths", err.at);
+    assert_eq!(Some(reported[2]), err.progress);
+}
+
+#[test]
+fn backward_stitching_reports_progress_and_includes_it_when_cancelled() {
+    let graph = create_best_first_graph();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .unwrap();
+    }
+    let definitions = graph
+        .iter_nodes()
+        .filter(|n| graph[*n].is_definition())
+        .collect_vec();
+    let cancellation_flag = CancelAfterPhases {
+        phases: 1,
+        reported: RefCell::new(Vec::new()),
+    };
+    let result = BackwardPartialPathStitcher::find_all_complete_partial_paths_from_definitions(
+        &mut DatabaseCandidates::new(&graph, &mut partials, &mut db),
+        definitions,
+        StitcherConfig::default(),
+        &cancellation_flag,
+        |_, _, _| {},
+    );
+
+    let reported = cancellation_flag.reported.into_inner();
+    assert_eq!(vec![0, 1], reported.iter().map(|p| p.phase).collect_vec());
+    assert_eq!(2, reported[0].queued_paths);
+    assert_eq!(1, reported[1].complete_paths);
+    assert!(reported[0].work_performed < reported[1].work_performed);
+    let err = result.unwrap_err();
+    assert_eq!("finding complete partial paths backwards", err.at);
+    assert_eq!(Some(reported[1]), err.progress);
+}
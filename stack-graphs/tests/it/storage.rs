@@ -5,19 +5,35 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use controlled_option::ControlledOption;
 use itertools::Itertools;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::InternedString;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::PartialPaths;
+use stack_graphs::shadowing::EdgePrecedence;
+use stack_graphs::stitching::StitcherConfig;
+use stack_graphs::storage::FileOverlay;
+use stack_graphs::storage::FileResult;
+use stack_graphs::storage::FileStatus;
+use stack_graphs::storage::InMemoryBackend;
+use stack_graphs::storage::MigrationResult;
+use stack_graphs::storage::SQLiteReader;
 use stack_graphs::storage::SQLiteWriter;
+use stack_graphs::storage::StitchingCache;
+use stack_graphs::storage::StorageBackend;
+use stack_graphs::storage::StorageReader;
 use stack_graphs::NoCancellation;
+use std::path::Path;
 
 use crate::util::create_partial_path_and_edges;
 use crate::util::create_pop_symbol_node;
 use crate::util::create_push_symbol_node;
 
 fn test_foo_bar_root_candidate_paths(symbols: &[&str], variable: bool) -> usize {
-    let mut reader = {
+    let (mut sqlite_reader, mut in_memory_reader) = {
         let mut writer = SQLiteWriter::open_in_memory().unwrap();
+        let mut backend = InMemoryBackend::new();
 
         let mut graph = StackGraph::new();
         let file = graph.add_file("test1").unwrap();
@@ -33,49 +49,55 @@ fn test_foo_bar_root_candidate_paths(symbols: &[&str], variable: bool) -> usize
         let mut path_without_variable = path_with_variable.clone();
         path_without_variable.eliminate_precondition_stack_variables(&mut partials);
 
+        let paths = vec![&path_with_variable, &path_without_variable];
         writer
-            .store_result_for_file(
-                &graph,
-                file,
-                "",
-                &mut partials,
-                vec![&path_with_variable, &path_without_variable],
-            )
+            .store_result_for_file(&graph, file, "", &mut partials, paths.clone())
             .unwrap();
+        let result = FileResult::new(&graph, file, &mut partials, paths).unwrap();
+        backend.store_file_result("", &result).unwrap();
 
-        writer.into_reader()
+        (writer.into_reader(), StorageReader::new(backend))
     };
 
-    {
-        let (graph, partials, _) = reader.get();
-        let file = graph.add_file("test2").unwrap();
-
-        let r = StackGraph::root_node();
-        let refs = symbols
-            .into_iter()
-            .map(|r| create_push_symbol_node(graph, file, *r, true))
-            .chain(std::iter::once(r))
-            .collect_vec();
-        let mut path = create_partial_path_and_edges(graph, partials, &refs).unwrap();
-        if !variable {
-            path.eliminate_precondition_stack_variables(partials);
-        }
-
-        reader
-            .load_partial_path_extensions(&path, &NoCancellation)
-            .unwrap();
+    let sqlite_results = find_root_candidate_paths(&mut sqlite_reader, symbols, variable);
+    let in_memory_results = find_root_candidate_paths(&mut in_memory_reader, symbols, variable);
+    assert_eq!(sqlite_results, in_memory_results);
+    sqlite_results
+}
 
-        let (graph, partials, db) = reader.get();
-        let mut results = Vec::new();
-        db.find_candidate_partial_paths_from_root(
-            graph,
-            partials,
-            Some(path.symbol_stack_postcondition),
-            &mut results,
-        );
+fn find_root_candidate_paths<B: StorageBackend>(
+    reader: &mut StorageReader<B>,
+    symbols: &[&str],
+    variable: bool,
+) -> usize {
+    let (graph, partials, _) = reader.get();
+    let file = graph.add_file("test2").unwrap();
 
-        results.len()
+    let r = StackGraph::root_node();
+    let refs = symbols
+        .iter()
+        .map(|r| create_push_symbol_node(graph, file, r, true))
+        .chain(std::iter::once(r))
+        .collect_vec();
+    let mut path = create_partial_path_and_edges(graph, partials, &refs).unwrap();
+    if !variable {
+        path.eliminate_precondition_stack_variables(partials);
     }
+
+    reader
+        .load_partial_path_extensions(&path, &NoCancellation)
+        .unwrap();
+
+    let (graph, partials, db) = reader.get();
+    let mut results = Vec::new();
+    db.find_candidate_partial_paths_from_root(
+        graph,
+        partials,
+        Some(path.symbol_stack_postcondition),
+        &mut results,
+    );
+
+    results.len()
 }
 
 #[test]
@@ -125,3 +147,687 @@ fn find_candidates_for_shorter_symbol_stack_without_variable() {
     let results = test_foo_bar_root_candidate_paths(&["foo"], false);
     assert_eq!(0, results);
 }
+
+fn count_root_candidate_paths<B: StorageBackend>(
+    reader: &mut StorageReader<B>,
+    symbol: &str,
+) -> usize {
+    let (graph, partials, _) = reader.get();
+    let file = match graph.get_file("test2") {
+        Some(file) => file,
+        None => graph.add_file("test2").unwrap(),
+    };
+
+    let r = StackGraph::root_node();
+    let reference = create_push_symbol_node(graph, file, symbol, true);
+    let mut path = create_partial_path_and_edges(graph, partials, &[reference, r]).unwrap();
+    path.eliminate_precondition_stack_variables(partials);
+
+    reader
+        .load_partial_path_extensions(&path, &NoCancellation)
+        .unwrap();
+
+    let (graph, partials, db) = reader.get();
+    let mut results = Vec::new();
+    db.find_candidate_partial_paths_from_root(
+        graph,
+        partials,
+        Some(path.symbol_stack_postcondition),
+        &mut results,
+    );
+
+    results.len()
+}
+
+#[test]
+fn in_memory_backend_stores_and_loads_file_results() {
+    let mut reader = {
+        let mut backend = InMemoryBackend::new();
+
+        let mut graph = StackGraph::new();
+        let file = graph.add_file("test1").unwrap();
+        let mut partials = PartialPaths::new();
+
+        let r = StackGraph::root_node();
+        let foo = create_pop_symbol_node(&mut graph, file, "foo", true);
+        let path = create_partial_path_and_edges(&mut graph, &mut partials, &[r, foo]).unwrap();
+
+        let result = FileResult::new(&graph, file, &mut partials, vec![&path]).unwrap();
+        backend.store_file_result("tag", &result).unwrap();
+
+        StorageReader::new(backend)
+    };
+
+    assert!(matches!(
+        reader.status_for_file("test1", Some("tag")).unwrap(),
+        FileStatus::Indexed
+    ));
+    assert!(matches!(
+        reader.status_for_file("test1", Some("other")).unwrap(),
+        FileStatus::Missing
+    ));
+    assert_eq!(1, count_root_candidate_paths(&mut reader, "foo"));
+    assert_eq!(0, count_root_candidate_paths(&mut reader, "bar"));
+}
+
+#[test]
+fn overlay_takes_precedence_over_database() {
+    let mut reader = {
+        let mut writer = SQLiteWriter::open_in_memory().unwrap();
+
+        let mut graph = StackGraph::new();
+        let file = graph.add_file("test1").unwrap();
+        let mut partials = PartialPaths::new();
+
+        let r = StackGraph::root_node();
+        let foo = create_pop_symbol_node(&mut graph, file, "foo", true);
+        let path = create_partial_path_and_edges(&mut graph, &mut partials, &[r, foo]).unwrap();
+
+        writer
+            .store_result_for_file(&graph, file, "", &mut partials, vec![&path])
+            .unwrap();
+
+        writer.into_reader()
+    };
+
+    {
+        let mut graph = StackGraph::new();
+        let file = graph.add_file("test1").unwrap();
+        let mut partials = PartialPaths::new();
+
+        let r = StackGraph::root_node();
+        let bar = create_pop_symbol_node(&mut graph, file, "bar", true);
+        let path = create_partial_path_and_edges(&mut graph, &mut partials, &[r, bar]).unwrap();
+
+        let overlay = FileOverlay::new(&graph, file, &mut partials, vec![&path]).unwrap();
+        reader.add_overlay(overlay);
+    }
+
+    assert!(matches!(
+        reader.status_for_file("test1", Some("modified")).unwrap(),
+        FileStatus::Indexed
+    ));
+    assert_eq!(0, count_root_candidate_paths(&mut reader, "foo"));
+    assert_eq!(1, count_root_candidate_paths(&mut reader, "bar"));
+}
+
+#[test]
+fn renamed_file_is_stored_under_new_path() {
+    let mut reader = {
+        let mut writer = SQLiteWriter::open_in_memory().unwrap();
+
+        let mut graph = StackGraph::new();
+        let file = graph.add_file("test1").unwrap();
+        let mut partials = PartialPaths::new();
+
+        let r = StackGraph::root_node();
+        let foo = create_pop_symbol_node(&mut graph, file, "foo", true);
+        let path = create_partial_path_and_edges(&mut graph, &mut partials, &[r, foo]).unwrap();
+
+        writer
+            .store_result_for_file(&graph, file, "tag", &mut partials, vec![&path])
+            .unwrap();
+
+        assert!(writer
+            .rename_file(Path::new("test1"), Path::new("moved"))
+            .unwrap());
+        assert!(!writer
+            .rename_file(Path::new("test1"), Path::new("moved"))
+            .unwrap());
+
+        writer.into_reader()
+    };
+
+    assert!(matches!(
+        reader.status_for_file("test1", None::<&str>).unwrap(),
+        FileStatus::Missing
+    ));
+    assert!(matches!(
+        reader.status_for_file("moved", Some("tag")).unwrap(),
+        FileStatus::Indexed
+    ));
+    assert_eq!(1, count_root_candidate_paths(&mut reader, "foo"));
+    let (graph, _, _) = reader.get();
+    assert!(graph.get_file("moved").is_some());
+    assert!(graph.get_file("test1").is_none());
+}
+
+#[test]
+fn old_database_is_migrated_with_stale_files() {
+    let db_path = std::env::temp_dir().join(format!(
+        "stack-graphs-migrate-{}.sqlite",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&db_path);
+    {
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            r#"
+                CREATE TABLE metadata (version INTEGER NOT NULL);
+                INSERT INTO metadata (version) VALUES (1);
+                CREATE TABLE graphs (file TEXT PRIMARY KEY, tag TEXT NOT NULL, json BLOB NOT NULL);
+                INSERT INTO graphs (file, tag, json) VALUES ('test1', 'tag', '{}');
+                INSERT INTO graphs (file, tag, json) VALUES ('test2', 'tag', '{}');
+            "#,
+        )
+        .unwrap();
+    }
+
+    let result = SQLiteWriter::migrate(&db_path);
+    let reader = SQLiteReader::open(&db_path);
+    let migrated = SQLiteWriter::migrate(&db_path);
+    std::fs::remove_file(&db_path).unwrap();
+
+    assert_eq!(
+        MigrationResult::Reset {
+            from: 1,
+            stale_files: 2
+        },
+        result.unwrap()
+    );
+    assert_eq!(MigrationResult::UpToDate, migrated.unwrap());
+    let mut reader = reader.unwrap();
+    assert!(matches!(
+        reader.status_for_file("test1", Some("tag")).unwrap(),
+        FileStatus::Missing
+    ));
+    assert!(matches!(
+        reader.status_for_file("test2", None::<&str>).unwrap(),
+        FileStatus::Error(_)
+    ));
+}
+
+#[test]
+fn old_database_is_upgraded_in_place() {
+    // The fixture was written by the last released version, and contains the files `a.py` and
+    // `b.py`, which each define a symbol that the other one references.
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/storage-v6.sqlite");
+    let db_path = std::env::temp_dir().join(format!(
+        "stack-graphs-upgrade-{}.sqlite",
+        std::process::id()
+    ));
+    std::fs::copy(&fixture, &db_path).unwrap();
+
+    let result = SQLiteWriter::migrate(&db_path);
+    let reader = SQLiteReader::open(&db_path);
+    std::fs::remove_file(&db_path).unwrap();
+
+    assert_eq!(MigrationResult::Upgraded { from: 6 }, result.unwrap());
+    let mut reader = reader.unwrap();
+    for file in &["a.py", "b.py"] {
+        assert!(matches!(
+            reader.status_for_file(file, Some("v6")).unwrap(),
+            FileStatus::Indexed
+        ));
+    }
+
+    let file = reader.load_graph_for_file("a.py").unwrap();
+    let (graph, _, _) = reader.get();
+    let (definitions, references): (Vec<_>, Vec<_>) = graph
+        .nodes_for_file(file)
+        .filter(|n| graph[*n].is_definition() || graph[*n].is_reference())
+        .partition(|n| graph[*n].is_definition());
+    assert_eq!(1, definitions.len());
+    assert_eq!(1, references.len());
+    let source_info = graph
+        .source_info(definitions[0])
+        .expect("Missing source info");
+    let string = |s: ControlledOption<Handle<InternedString>>| s.into_option().map(|s| &graph[s]);
+    assert_eq!(Some("variable"), string(source_info.syntax_type));
+    assert_eq!(None, string(source_info.fully_qualified_name));
+    assert_eq!(0, source_info.span.start.line);
+    assert!(graph.node_debug_info(definitions[0]).is_some());
+
+    let mut resolved = Vec::new();
+    reader
+        .find_all_complete_partial_paths(
+            references,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, _, path| resolved.push(graph[path.end_node].display(graph).to_string()),
+        )
+        .unwrap();
+    assert_eq!(vec!["[b.py(1) definition y]"], resolved);
+
+    // the ends of the upgraded paths are recorded, so that references can be found backwards
+    let mut found = Vec::new();
+    reader
+        .find_all_complete_partial_paths_from_definitions(
+            definitions,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, _, path| found.push(graph[path.start_node].display(graph).to_string()),
+        )
+        .unwrap();
+    assert_eq!(vec!["[b.py(0) reference x]"], found);
+}
+
+#[test]
+fn edge_kinds_are_stored() {
+    let mut writer = SQLiteWriter::open_in_memory().unwrap();
+    let mut graph = StackGraph::new();
+    let mut partials = PartialPaths::new();
+    let file = graph.add_file("test").unwrap();
+    let r = StackGraph::root_node();
+    let foo = create_pop_symbol_node(&mut graph, file, "foo", true);
+    graph.add_edge(r, foo, 0);
+    let import = graph.add_string("import");
+    graph.set_edge_kind(r, foo, Some(import));
+    writer
+        .store_result_for_file(&graph, file, "tag", &mut partials, vec![])
+        .unwrap();
+
+    let mut reader = writer.into_reader();
+    let file = reader.load_graph_for_file("test").unwrap();
+    let (graph, _, _) = reader.get();
+    let foo = graph.nodes_for_file(file).next().unwrap();
+    let kind = graph.edge_kind(r, foo).expect("Missing edge kind");
+    assert_eq!("import", &graph[kind]);
+}
+
+#[test]
+fn source_info_is_stored() {
+    let mut reader = {
+        let mut writer = SQLiteWriter::open_in_memory().unwrap();
+
+        let mut graph = StackGraph::new();
+        let file = graph.add_file("test1").unwrap();
+        let mut partials = PartialPaths::new();
+
+        let foo = create_pop_symbol_node(&mut graph, file, "foo", true);
+        let syntax_type = graph.add_string("function");
+        let containing_line = graph.add_string("def foo(): pass");
+        let fully_qualified_name = graph.add_string("test1.foo");
+        let source_info = graph.source_info_mut(foo);
+        source_info.span.start.line = 1;
+        source_info.span.end.line = 1;
+        source_info.definiens_span.start.line = 1;
+        source_info.definiens_span.end.line = 2;
+        source_info.syntax_type = syntax_type.into();
+        source_info.containing_line = containing_line.into();
+        source_info.fully_qualified_name = fully_qualified_name.into();
+
+        writer
+            .store_result_for_file(&graph, file, "", &mut partials, vec![])
+            .unwrap();
+        writer.into_reader()
+    };
+
+    let file = reader.load_graph_for_file("test1").unwrap();
+    let (graph, _, _) = reader.get();
+    let foo = graph.nodes_for_file(file).next().unwrap();
+    let source_info = graph.source_info(foo).expect("Missing source info");
+    assert_eq!(1, source_info.span.start.line);
+    assert_eq!(1, source_info.definiens_span.start.line);
+    assert_eq!(2, source_info.definiens_span.end.line);
+    let string = |s: ControlledOption<Handle<InternedString>>| s.into_option().map(|s| &graph[s]);
+    assert_eq!(Some("function"), string(source_info.syntax_type));
+    assert_eq!(Some("def foo(): pass"), string(source_info.containing_line));
+    assert_eq!(Some("test1.foo"), string(source_info.fully_qualified_name));
+}
+
+fn store_reference_and_definition(
+    writer: &mut SQLiteWriter,
+    reference: &str,
+    definition: &str,
+    tag: &str,
+) {
+    let mut graph = StackGraph::new();
+    let mut partials = PartialPaths::new();
+    let r = StackGraph::root_node();
+
+    let file = graph.add_file("reference").unwrap();
+    let reference = create_push_symbol_node(&mut graph, file, reference, true);
+    let path = create_partial_path_and_edges(&mut graph, &mut partials, &[reference, r]).unwrap();
+    writer
+        .store_result_for_file(&graph, file, "tag", &mut partials, vec![&path])
+        .unwrap();
+
+    let file = graph.add_file("definition").unwrap();
+    let def = create_pop_symbol_node(&mut graph, file, definition, true);
+    let path = create_partial_path_and_edges(&mut graph, &mut partials, &[r, def]).unwrap();
+    writer
+        .store_result_for_file(&graph, file, tag, &mut partials, vec![&path])
+        .unwrap();
+}
+
+fn find_definitions_with_cache(
+    db_path: &Path,
+    cache: StitchingCache,
+    overlays: &[FileOverlay],
+) -> (Vec<String>, StitchingCache, usize) {
+    let mut reader = SQLiteReader::open(db_path).unwrap();
+    for overlay in overlays {
+        reader.add_overlay(overlay.clone());
+    }
+    reader.set_stitching_cache(cache);
+    reader.load_graph_for_file("reference").unwrap();
+    let (graph, _, _) = reader.get();
+    let references = graph
+        .iter_nodes()
+        .filter(|n| graph[*n].is_reference())
+        .collect::<Vec<_>>();
+    let mut definitions = Vec::new();
+    reader
+        .find_all_complete_partial_paths(
+            references,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, _, path| definitions.push(graph[path.end_node].display(graph).to_string()),
+        )
+        .unwrap();
+    let cached = reader.stats().complete_path_cached;
+    (definitions, reader.take_stitching_cache().unwrap(), cached)
+}
+
+#[test]
+fn stitching_cache_is_reused_until_file_changes() {
+    let db_path = std::env::temp_dir().join(format!(
+        "stack-graphs-stitching-cache-{}.sqlite",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&db_path);
+    let mut writer = SQLiteWriter::open(&db_path).unwrap();
+    store_reference_and_definition(&mut writer, "foo", "foo", "v1");
+
+    let (definitions, cache, cached) =
+        find_definitions_with_cache(&db_path, StitchingCache::new(), &[]);
+    assert_eq!(vec!["[definition(0) definition foo]"], definitions);
+    assert_eq!(0, cached);
+    assert_eq!(1, cache.len());
+
+    let (definitions, cache, cached) = find_definitions_with_cache(&db_path, cache, &[]);
+    assert_eq!(vec!["[definition(0) definition foo]"], definitions);
+    assert_eq!(1, cached);
+
+    store_reference_and_definition(&mut writer, "foo", "bar", "v2");
+    let (definitions, cache, cached) = find_definitions_with_cache(&db_path, cache, &[]);
+    std::fs::remove_file(&db_path).unwrap();
+    assert!(definitions.is_empty());
+    assert_eq!(0, cached);
+    assert_eq!(1, cache.len());
+}
+
+/// Creates an overlay for the given file, containing a definition that is reachable from the root.
+fn definition_overlay(file: &str, definition: &str) -> FileOverlay {
+    let mut graph = StackGraph::new();
+    let mut partials = PartialPaths::new();
+    let r = StackGraph::root_node();
+    let file = graph.add_file(file).unwrap();
+    let def = create_pop_symbol_node(&mut graph, file, definition, true);
+    let path = create_partial_path_and_edges(&mut graph, &mut partials, &[r, def]).unwrap();
+    FileOverlay::new(&graph, file, &mut partials, vec![&path]).unwrap()
+}
+
+#[test]
+fn stitching_cache_is_invalidated_by_overlay_with_new_paths() {
+    let db_path = std::env::temp_dir().join(format!(
+        "stack-graphs-stitching-cache-overlay-{}.sqlite",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&db_path);
+    let mut writer = SQLiteWriter::open(&db_path).unwrap();
+    store_reference_and_definition(&mut writer, "foo", "foo", "v1");
+
+    let (definitions, cache, _) = find_definitions_with_cache(&db_path, StitchingCache::new(), &[]);
+    assert_eq!(vec!["[definition(0) definition foo]"], definitions);
+    assert_eq!(1, cache.len());
+
+    // an overlay without matching root paths does not affect the cached paths
+    let (definitions, cache, cached) =
+        find_definitions_with_cache(&db_path, cache, &[definition_overlay("overlay", "bar")]);
+    assert_eq!(vec!["[definition(0) definition foo]"], definitions);
+    assert_eq!(1, cached);
+
+    // an overlay with a matching root path may extend the cached paths
+    let overlay = definition_overlay("overlay", "foo");
+    let (mut definitions, cache, cached) =
+        find_definitions_with_cache(&db_path, cache, std::slice::from_ref(&overlay));
+    definitions.sort();
+    assert_eq!(
+        vec![
+            "[definition(0) definition foo]",
+            "[overlay(0) definition foo]"
+        ],
+        definitions
+    );
+    assert_eq!(0, cached);
+
+    let (_, cache, cached) = find_definitions_with_cache(&db_path, cache, &[overlay]);
+    assert_eq!(1, cached);
+
+    // without the overlay, the cached paths are outdated again
+    let (definitions, _, cached) = find_definitions_with_cache(&db_path, cache, &[]);
+    std::fs::remove_file(&db_path).unwrap();
+    assert_eq!(vec!["[definition(0) definition foo]"], definitions);
+    assert_eq!(0, cached);
+}
+
+/// Stores a file containing a definition that is reachable from the root.
+fn store_definition(writer: &mut SQLiteWriter, file: &str, definition: &str) {
+    let mut graph = StackGraph::new();
+    let mut partials = PartialPaths::new();
+    let r = StackGraph::root_node();
+    let file = graph.add_file(file).unwrap();
+    let def = create_pop_symbol_node(&mut graph, file, definition, true);
+    let path = create_partial_path_and_edges(&mut graph, &mut partials, &[r, def]).unwrap();
+    writer
+        .store_result_for_file(&graph, file, "tag", &mut partials, vec![&path])
+        .unwrap();
+}
+
+#[test]
+fn stitching_cache_is_invalidated_by_new_files_with_new_paths() {
+    let db_path = std::env::temp_dir().join(format!(
+        "stack-graphs-stitching-cache-new-files-{}.sqlite",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&db_path);
+    let mut writer = SQLiteWriter::open(&db_path).unwrap();
+    store_reference_and_definition(&mut writer, "foo", "foo", "v1");
+
+    let (_, cache, _) = find_definitions_with_cache(&db_path, StitchingCache::new(), &[]);
+    assert_eq!(1, cache.len());
+
+    // files are indexed by another writer, which the cache does not know about
+    let mut other_writer = SQLiteWriter::open(&db_path).unwrap();
+
+    // a new file without matching root paths does not affect the cached paths
+    store_definition(&mut other_writer, "unrelated", "bar");
+    let (definitions, cache, cached) = find_definitions_with_cache(&db_path, cache, &[]);
+    assert_eq!(vec!["[definition(0) definition foo]"], definitions);
+    assert_eq!(1, cached);
+
+    // a new file with a matching root path may extend the cached paths
+    store_definition(&mut other_writer, "other", "foo");
+    let (mut definitions, _, cached) = find_definitions_with_cache(&db_path, cache, &[]);
+    std::fs::remove_file(&db_path).unwrap();
+    definitions.sort();
+    assert_eq!(
+        vec![
+            "[definition(0) definition foo]",
+            "[other(0) definition foo]"
+        ],
+        definitions
+    );
+    assert_eq!(0, cached);
+}
+
+#[test]
+fn stitching_limits_apply_to_all_nodes_with_cache() {
+    let mut writer = SQLiteWriter::open_in_memory().unwrap();
+    let mut graph = StackGraph::new();
+    let mut partials = PartialPaths::new();
+    let r = StackGraph::root_node();
+    let file = graph.add_file("test").unwrap();
+    let mut paths = Vec::new();
+    for symbol in &["foo", "bar"] {
+        let reference = create_push_symbol_node(&mut graph, file, symbol, true);
+        let definition = create_pop_symbol_node(&mut graph, file, symbol, true);
+        paths.push(
+            create_partial_path_and_edges(&mut graph, &mut partials, &[reference, r]).unwrap(),
+        );
+        paths.push(
+            create_partial_path_and_edges(&mut graph, &mut partials, &[r, definition]).unwrap(),
+        );
+    }
+    writer
+        .store_result_for_file(&graph, file, "tag", &mut partials, &paths)
+        .unwrap();
+
+    let mut reader = writer.into_reader();
+    reader.set_stitching_cache(StitchingCache::new());
+    let file = reader.load_graph_for_file("test").unwrap();
+    let (graph, _, _) = reader.get();
+    let references = graph
+        .nodes_for_file(file)
+        .filter(|n| graph[*n].is_reference())
+        .collect::<Vec<_>>();
+    let mut complete_paths = 0;
+    reader
+        .find_all_complete_partial_paths(
+            references,
+            StitcherConfig::default().with_max_complete_paths(Some(1)),
+            &NoCancellation,
+            |_, _, _| complete_paths += 1,
+        )
+        .unwrap();
+    assert_eq!(1, complete_paths);
+}
+
+fn load_resolved_definitions(db_path: &Path) -> Option<Vec<String>> {
+    let mut reader = SQLiteReader::open(db_path).unwrap();
+    let file = reader.load_graph_for_file("reference").unwrap();
+    let (graph, _, _) = reader.get();
+    let reference = graph
+        .nodes_for_file(file)
+        .find(|n| graph[*n].is_reference())
+        .unwrap();
+    let definitions = reader.load_resolved_definitions(reference).unwrap()?;
+    let (graph, _, _) = reader.get();
+    Some(
+        definitions
+            .into_iter()
+            .map(|n| graph[n].display(graph).to_string())
+            .collect(),
+    )
+}
+
+fn resolve_and_store_reference(db_path: &Path, writer: &mut SQLiteWriter) -> usize {
+    let mut reader = SQLiteReader::open(db_path).unwrap();
+    let file = reader.load_graph_for_file("reference").unwrap();
+    let (graph, _, _) = reader.get();
+    let reference = graph
+        .nodes_for_file(file)
+        .find(|n| graph[*n].is_reference())
+        .unwrap();
+    let resolution = reader
+        .resolve_reference(
+            reference,
+            StitcherConfig::default(),
+            &EdgePrecedence,
+            &NoCancellation,
+        )
+        .unwrap();
+    writer.store_resolutions(vec![&resolution]).unwrap();
+    resolution.definition_count()
+}
+
+#[test]
+fn stored_resolutions_are_used_until_invalidated() {
+    let db_path = std::env::temp_dir().join(format!(
+        "stack-graphs-resolutions-{}.sqlite",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&db_path);
+    let mut writer = SQLiteWriter::open(&db_path).unwrap();
+    store_reference_and_definition(&mut writer, "foo", "bar", "v1");
+    assert_eq!(None, load_resolved_definitions(&db_path));
+
+    assert_eq!(0, resolve_and_store_reference(&db_path, &mut writer));
+    assert_eq!(Some(vec![]), load_resolved_definitions(&db_path));
+
+    // adding a root path that the reference could use invalidates the resolution
+    let mut graph = StackGraph::new();
+    let mut partials = PartialPaths::new();
+    let file = graph.add_file("other").unwrap();
+    let def = create_pop_symbol_node(&mut graph, file, "foo", true);
+    let path =
+        create_partial_path_and_edges(&mut graph, &mut partials, &[StackGraph::root_node(), def])
+            .unwrap();
+    writer
+        .store_result_for_file(&graph, file, "v1", &mut partials, vec![&path])
+        .unwrap();
+    assert_eq!(None, load_resolved_definitions(&db_path));
+
+    assert_eq!(1, resolve_and_store_reference(&db_path, &mut writer));
+    assert_eq!(
+        Some(vec!["[other(0) definition foo]".to_string()]),
+        load_resolved_definitions(&db_path)
+    );
+
+    // removing the file of the definition invalidates the resolution
+    writer.clean_file(Path::new("other")).unwrap();
+    let definitions = load_resolved_definitions(&db_path);
+    std::fs::remove_file(&db_path).unwrap();
+    assert_eq!(None, definitions);
+}
+
+#[test]
+fn references_are_found_by_stitching_backwards() {
+    let mut writer = SQLiteWriter::open_in_memory().unwrap();
+    store_reference_and_definition(&mut writer, "foo", "foo", "v1");
+    let mut graph = StackGraph::new();
+    let mut partials = PartialPaths::new();
+    let file = graph.add_file("unrelated").unwrap();
+    let reference = create_push_symbol_node(&mut graph, file, "bar", true);
+    let path = create_partial_path_and_edges(
+        &mut graph,
+        &mut partials,
+        &[reference, StackGraph::root_node()],
+    )
+    .unwrap();
+    writer
+        .store_result_for_file(&graph, file, "v1", &mut partials, vec![&path])
+        .unwrap();
+    let mut reader = writer.into_reader();
+
+    let file = reader.load_graph_for_file("definition").unwrap();
+    let (graph, _, _) = reader.get();
+    let definition = graph
+        .nodes_for_file(file)
+        .find(|n| graph[*n].is_definition())
+        .unwrap();
+
+    let mut references = Vec::new();
+    reader
+        .find_all_complete_partial_paths_from_definitions(
+            vec![definition],
+            StitcherConfig::default(),
+            &NoCancellation,
+            |_, _, path| references.push(path.start_node),
+        )
+        .unwrap();
+    let (graph, _, _) = reader.get();
+    assert_eq!(
+        vec!["[reference(0) reference foo]"],
+        references
+            .iter()
+            .map(|n| graph[*n].display(graph).to_string())
+            .collect::<Vec<_>>()
+    );
+    // only the files of paths that can reach the definition are loaded
+    assert!(graph.get_file("unrelated").is_none());
+
+    // paths loaded for the backward search are not loaded again when stitching forwards
+    let mut definitions = Vec::new();
+    reader
+        .find_all_complete_partial_paths(
+            references,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, _, path| definitions.push(graph[path.end_node].display(graph).to_string()),
+        )
+        .unwrap();
+    assert_eq!(vec!["[definition(0) definition foo]"], definitions);
+}
@@ -59,8 +59,12 @@
 
 pub mod clean;
 pub mod database;
+pub mod db;
+pub mod diff;
+pub mod export;
 pub mod index;
 pub mod init;
+pub mod lint;
 pub mod load;
 #[cfg(feature = "lsp")]
 pub mod lsp;
@@ -79,8 +83,12 @@ pub mod path_loading {
 
     use crate::cli::clean::CleanArgs;
     use crate::cli::database::DatabaseArgs;
+    use crate::cli::db::DbCommands;
+    use crate::cli::diff::DiffArgs;
+    use crate::cli::export::ExportArgs;
     use crate::cli::index::IndexArgs;
     use crate::cli::init::InitArgs;
+    use crate::cli::lint::LintArgs;
     use crate::cli::load::PathLoaderArgs;
     #[cfg(feature = "lsp")]
     use crate::cli::lsp::LspArgs;
@@ -94,8 +102,12 @@ pub mod path_loading {
     #[derive(Subcommand)]
     pub enum Subcommands {
         Clean(Clean),
+        Db(Db),
+        Diff(Diff),
+        Export(Export),
         Index(Index),
         Init(Init),
+        Lint(Lint),
         #[cfg(feature = "lsp")]
         Lsp(Lsp),
         Match(Match),
@@ -110,8 +122,12 @@ pub mod path_loading {
         pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
             match self {
                 Self::Clean(cmd) => cmd.run(default_db_path),
+                Self::Db(cmd) => cmd.run(default_db_path),
+                Self::Diff(cmd) => cmd.run(),
+                Self::Export(cmd) => cmd.run(default_db_path),
                 Self::Index(cmd) => cmd.run(default_db_path),
                 Self::Init(cmd) => cmd.run(),
+                Self::Lint(cmd) => cmd.run(),
                 #[cfg(feature = "lsp")]
                 Self::Lsp(cmd) => cmd.run(default_db_path),
                 Self::Match(cmd) => cmd.run(),
@@ -140,6 +156,54 @@ pub mod path_loading {
         }
     }
 
+    /// Manage the indexing database.
+    #[derive(clap::Parser)]
+    pub struct Db {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(subcommand)]
+        db_command: DbCommands,
+    }
+
+    impl Db {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.db_command.run(&db_path)
+        }
+    }
+
+    /// Compare two stack graphs.
+    #[derive(clap::Parser)]
+    pub struct Diff {
+        #[clap(flatten)]
+        diff_args: DiffArgs,
+    }
+
+    impl Diff {
+        pub fn run(self) -> anyhow::Result<()> {
+            self.diff_args.run()
+        }
+    }
+
+    /// Export resolution results from the database as an LSIF dump.
+    #[derive(clap::Parser)]
+    pub struct Export {
+        #[clap(flatten)]
+        load_args: PathLoaderArgs,
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        export_args: ExportArgs,
+    }
+
+    impl Export {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let loader = self.load_args.get()?;
+            let db_path = self.db_args.get_or(default_db_path);
+            self.export_args.run(&db_path, loader)
+        }
+    }
+
     /// Index source files into the database.
     #[derive(clap::Parser)]
     pub struct Index {
@@ -172,6 +236,22 @@ pub mod path_loading {
         }
     }
 
+    /// Check the stack graphs of source files for suspicious structure.
+    #[derive(clap::Parser)]
+    pub struct Lint {
+        #[clap(flatten)]
+        load_args: PathLoaderArgs,
+        #[clap(flatten)]
+        lint_args: LintArgs,
+    }
+
+    impl Lint {
+        pub fn run(self) -> anyhow::Result<()> {
+            let loader = self.load_args.get()?;
+            self.lint_args.run(loader)
+        }
+    }
+
     /// Run an LSP server.
     #[cfg(feature = "lsp")]
     #[derive(clap::Parser)]
@@ -228,6 +308,8 @@ pub mod path_loading {
     /// Query the database to resolve references.
     #[derive(clap::Parser)]
     pub struct Query {
+        #[clap(flatten)]
+        load_args: PathLoaderArgs,
         #[clap(flatten)]
         db_args: DatabaseArgs,
         #[clap(flatten)]
@@ -236,8 +318,9 @@ pub mod path_loading {
 
     impl Query {
         pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let loader = self.load_args.get()?;
             let db_path = self.db_args.get_or(default_db_path);
-            self.query_args.run(&db_path)
+            self.query_args.run(&db_path, loader)
         }
     }
 
@@ -297,8 +380,12 @@ pub mod provided_languages {
 
     use crate::cli::clean::CleanArgs;
     use crate::cli::database::DatabaseArgs;
+    use crate::cli::db::DbCommands;
+    use crate::cli::diff::DiffArgs;
+    use crate::cli::export::ExportArgs;
     use crate::cli::index::IndexArgs;
     use crate::cli::init::InitArgs;
+    use crate::cli::lint::LintArgs;
     use crate::cli::load::LanguageConfigurationsLoaderArgs;
     #[cfg(feature = "lsp")]
     use crate::cli::lsp::LspArgs;
@@ -313,8 +400,12 @@ pub mod provided_languages {
     #[derive(Subcommand)]
     pub enum Subcommands {
         Clean(Clean),
+        Db(Db),
+        Diff(Diff),
+        Export(Export),
         Index(Index),
         Init(Init),
+        Lint(Lint),
         #[cfg(feature = "lsp")]
         Lsp(Lsp),
         Match(Match),
@@ -333,13 +424,17 @@ pub mod provided_languages {
         ) -> anyhow::Result<()> {
             match self {
                 Self::Clean(cmd) => cmd.run(default_db_path),
+                Self::Db(cmd) => cmd.run(default_db_path),
+                Self::Diff(cmd) => cmd.run(),
+                Self::Export(cmd) => cmd.run(default_db_path, configurations),
                 Self::Index(cmd) => cmd.run(default_db_path, configurations),
                 Self::Init(cmd) => cmd.run(),
+                Self::Lint(cmd) => cmd.run(configurations),
                 #[cfg(feature = "lsp")]
                 Self::Lsp(cmd) => cmd.run(default_db_path, configurations),
                 Self::Match(cmd) => cmd.run(configurations),
                 Self::Parse(cmd) => cmd.run(configurations),
-                Self::Query(cmd) => cmd.run(default_db_path),
+                Self::Query(cmd) => cmd.run(default_db_path, configurations),
                 Self::Status(cmd) => cmd.run(default_db_path),
                 Self::Test(cmd) => cmd.run(configurations),
                 Self::Visualize(cmd) => cmd.run(default_db_path),
@@ -363,6 +458,58 @@ pub mod provided_languages {
         }
     }
 
+    /// Manage the indexing database.
+    #[derive(clap::Parser)]
+    pub struct Db {
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(subcommand)]
+        db_command: DbCommands,
+    }
+
+    impl Db {
+        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+            let db_path = self.db_args.get_or(default_db_path);
+            self.db_command.run(&db_path)
+        }
+    }
+
+    /// Compare two stack graphs.
+    #[derive(clap::Parser)]
+    pub struct Diff {
+        #[clap(flatten)]
+        diff_args: DiffArgs,
+    }
+
+    impl Diff {
+        pub fn run(self) -> anyhow::Result<()> {
+            self.diff_args.run()
+        }
+    }
+
+    /// Export resolution results from the database as an LSIF dump.
+    #[derive(clap::Parser)]
+    pub struct Export {
+        #[clap(flatten)]
+        load_args: LanguageConfigurationsLoaderArgs,
+        #[clap(flatten)]
+        db_args: DatabaseArgs,
+        #[clap(flatten)]
+        export_args: ExportArgs,
+    }
+
+    impl Export {
+        pub fn run(
+            self,
+            default_db_path: PathBuf,
+            configurations: Vec<LanguageConfiguration>,
+        ) -> anyhow::Result<()> {
+            let loader = self.load_args.get(configurations)?;
+            let db_path = self.db_args.get_or(default_db_path);
+            self.export_args.run(&db_path, loader)
+        }
+    }
+
     /// Index source files into the database.
     #[derive(clap::Parser)]
     pub struct Index {
@@ -399,6 +546,22 @@ pub mod provided_languages {
         }
     }
 
+    /// Check the stack graphs of source files for suspicious structure.
+    #[derive(clap::Parser)]
+    pub struct Lint {
+        #[clap(flatten)]
+        load_args: LanguageConfigurationsLoaderArgs,
+        #[clap(flatten)]
+        lint_args: LintArgs,
+    }
+
+    impl Lint {
+        pub fn run(self, configurations: Vec<LanguageConfiguration>) -> anyhow::Result<()> {
+            let loader = self.load_args.get(configurations)?;
+            self.lint_args.run(loader)
+        }
+    }
+
     /// Run an LSP server.
     #[cfg(feature = "lsp")]
     #[derive(clap::Parser)]
@@ -459,6 +622,8 @@ pub mod provided_languages {
     /// Query the database to resolve references.
     #[derive(clap::Parser)]
     pub struct Query {
+        #[clap(flatten)]
+        load_args: LanguageConfigurationsLoaderArgs,
         #[clap(flatten)]
         db_args: DatabaseArgs,
         #[clap(flatten)]
@@ -466,9 +631,14 @@ pub mod provided_languages {
     }
 
     impl Query {
-        pub fn run(self, default_db_path: PathBuf) -> anyhow::Result<()> {
+        pub fn run(
+            self,
+            default_db_path: PathBuf,
+            configurations: Vec<LanguageConfiguration>,
+        ) -> anyhow::Result<()> {
+            let loader = self.load_args.get(configurations)?;
             let db_path = self.db_args.get_or(default_db_path);
-            self.query_args.run(&db_path)
+            self.query_args.run(&db_path, loader)
         }
     }
 
@@ -0,0 +1,55 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use clap::Args;
+use clap::Subcommand;
+use stack_graphs::storage::MigrationResult;
+use stack_graphs::storage::SQLiteWriter;
+use std::path::Path;
+
+#[derive(Subcommand)]
+pub enum DbCommands {
+    /// Migrate the database to the version supported by this tool.
+    Migrate(MigrateArgs),
+}
+
+impl DbCommands {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        match self {
+            Self::Migrate(args) => args.run(db_path),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct MigrateArgs {}
+
+impl MigrateArgs {
+    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+        match SQLiteWriter::migrate(db_path)? {
+            MigrationResult::UpToDate => {
+                println!("database {} is up to date", db_path.display());
+            }
+            MigrationResult::Upgraded { from } => {
+                println!(
+                    "upgraded database {} from version {}",
+                    db_path.display(),
+                    from
+                );
+            }
+            MigrationResult::Reset { from, stale_files } => {
+                println!(
+                    "reset database {} from version {}, {} files must be reindexed",
+                    db_path.display(),
+                    from,
+                    stale_files
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use anyhow::Context as _;
+use clap::Args;
+use clap::ValueHint;
+use serde_json::json;
+use stack_graphs::diff::GraphDiff;
+use stack_graphs::diff::NodeChange;
+use stack_graphs::serde::Edge;
+use stack_graphs::serde::Node;
+use stack_graphs::serde::StackGraph;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::cli::util::ExistingPathBufValueParser;
+use crate::cli::util::OutputFormat;
+
+/// Compare two stack graphs, as saved by `test --save-graph`
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Path of the old graph.
+    #[clap(
+        value_name = "OLD_GRAPH",
+        value_hint = ValueHint::FilePath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub old_graph: PathBuf,
+
+    /// Path of the new graph.
+    #[clap(
+        value_name = "NEW_GRAPH",
+        value_hint = ValueHint::FilePath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub new_graph: PathBuf,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+impl DiffArgs {
+    pub fn run(self) -> anyhow::Result<()> {
+        let old = Self::load_graph(&self.old_graph)?;
+        let new = Self::load_graph(&self.new_graph)?;
+        let diff = GraphDiff::from_serializable(&old, &new);
+        match self.format.json_reporter() {
+            Some(reporter) => {
+                for record in diff_to_json(&diff) {
+                    reporter.record(record);
+                }
+                reporter.finish()?;
+            }
+            None => print_diff(&diff),
+        }
+        Ok(())
+    }
+
+    fn load_graph(path: &Path) -> anyhow::Result<StackGraph> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read graph {}", path.display()))?;
+        let graph = serde_json::from_str(&json)
+            .with_context(|| format!("Cannot parse graph {}", path.display()))?;
+        Ok(graph)
+    }
+}
+
+fn diff_to_json(diff: &GraphDiff) -> Vec<serde_json::Value> {
+    let mut records = Vec::new();
+    for node in &diff.removed_nodes {
+        records.push(json!({ "type": "removed_node", "node": node }));
+    }
+    for node in &diff.added_nodes {
+        records.push(json!({ "type": "added_node", "node": node }));
+    }
+    for node in &diff.changed_nodes {
+        records.push(json!({
+            "type": "changed_node",
+            "old": node.old,
+            "new": node.new,
+            "changes": node.changes,
+        }));
+    }
+    for edge in &diff.removed_edges {
+        records.push(json!({ "type": "removed_edge", "edge": edge }));
+    }
+    for edge in &diff.added_edges {
+        records.push(json!({ "type": "added_edge", "edge": edge }));
+    }
+    for edge in &diff.changed_edges {
+        records.push(json!({
+            "type": "changed_edge",
+            "old": edge.old,
+            "new": edge.new,
+        }));
+    }
+    records
+}
+
+fn print_diff(diff: &GraphDiff) {
+    if diff.is_empty() {
+        println!("No differences");
+        return;
+    }
+    for node in &diff.removed_nodes {
+        println!("- node {}", display_node(node));
+    }
+    for node in &diff.added_nodes {
+        println!("+ node {}", display_node(node));
+    }
+    for node in &diff.changed_nodes {
+        println!("~ node {}", display_node(&node.old));
+        for change in &node.changes {
+            match change {
+                NodeChange::Id => println!("    id {} -> {}", node.old.id(), node.new.id()),
+                NodeChange::Properties => println!("    now {}", display_node(&node.new)),
+                NodeChange::SourceInfo => println!(
+                    "    source info {} -> {}",
+                    display_source_info(&node.old),
+                    display_source_info(&node.new)
+                ),
+            }
+        }
+    }
+    for edge in &diff.removed_edges {
+        println!("- edge {}", display_edge(edge));
+    }
+    for edge in &diff.added_edges {
+        println!("+ edge {}", display_edge(edge));
+    }
+    for edge in &diff.changed_edges {
+        println!("~ edge {}", display_edge(&edge.old));
+        println!("    now {}", display_edge(&edge.new));
+    }
+    println!(
+        "{} nodes added, {} removed, {} changed; {} edges added, {} removed, {} changed",
+        diff.added_nodes.len(),
+        diff.removed_nodes.len(),
+        diff.changed_nodes.len(),
+        diff.added_edges.len(),
+        diff.removed_edges.len(),
+        diff.changed_edges.len(),
+    );
+}
+
+fn display_node(node: &Node) -> String {
+    let description = match node {
+        Node::DropScopes { .. } => "drop scopes".to_string(),
+        Node::JumpToScope { .. } => "jump to scope".to_string(),
+        Node::PopScopedSymbol {
+            symbol,
+            is_definition,
+            ..
+        } => format!(
+            "pop scoped symbol `{}`{}",
+            symbol,
+            if *is_definition { " definition" } else { "" }
+        ),
+        Node::PopSymbol {
+            symbol,
+            is_definition,
+            ..
+        } => format!(
+            "pop symbol `{}`{}",
+            symbol,
+            if *is_definition { " definition" } else { "" }
+        ),
+        Node::PushScopedSymbol {
+            symbol,
+            scope,
+            is_reference,
+            ..
+        } => format!(
+            "push scoped symbol `{}` scope {}{}",
+            symbol,
+            scope,
+            if *is_reference { " reference" } else { "" }
+        ),
+        Node::PushSymbol {
+            symbol,
+            is_reference,
+            ..
+        } => format!(
+            "push symbol `{}`{}",
+            symbol,
+            if *is_reference { " reference" } else { "" }
+        ),
+        Node::Root { .. } => "root".to_string(),
+        Node::Scope { is_exported, .. } => {
+            format!("scope{}", if *is_exported { " exported" } else { "" })
+        }
+    };
+    match node.source_info() {
+        Some(_) => format!(
+            "{} {} at {}",
+            node.id(),
+            description,
+            display_source_info(node)
+        ),
+        None => format!("{} {}", node.id(), description),
+    }
+}
+
+fn display_source_info(node: &Node) -> String {
+    match node.source_info() {
+        Some(source_info) => {
+            let mut result = format!(
+                "{}:{}",
+                source_info.span.start.line + 1,
+                source_info.span.start.column.grapheme_offset + 1
+            );
+            if let Some(syntax_type) = &source_info.syntax_type {
+                result.push_str(&format!(" ({})", syntax_type));
+            }
+            result
+        }
+        None => "-".to_string(),
+    }
+}
+
+fn display_edge(edge: &Edge) -> String {
+    let mut result = format!(
+        "{} -> {} precedence {}",
+        edge.source, edge.sink, edge.precedence
+    );
+    if let Some(kind) = &edge.kind {
+        result.push_str(&format!(" kind {}", kind));
+    }
+    result
+}
@@ -0,0 +1,450 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use anyhow::anyhow;
+use clap::Args;
+use clap::ValueHint;
+use lsp_positions::Position;
+use lsp_positions::Span;
+use serde_json::json;
+use serde_json::Value;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::File;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPath;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::StitcherConfig;
+use stack_graphs::storage::FileStatus;
+use stack_graphs::storage::SQLiteReader;
+use stack_graphs::NoCancellation;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use url::Url;
+
+use crate::loader::FileReader;
+use crate::loader::Loader;
+
+/// The LSIF version of the generated dumps.
+const LSIF_VERSION: &str = "0.6.0";
+
+/// The moniker scheme used for monikers derived from fully qualified names.
+const MONIKER_SCHEME: &str = "stack-graphs";
+
+/// Export resolution results from the database as an LSIF dump
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Source file or directory paths to export. If none are given, all indexed files in the
+    /// database are exported.
+    #[clap(
+        value_name = "SOURCE_PATH",
+        value_hint = ValueHint::AnyPath,
+    )]
+    pub source_paths: Vec<PathBuf>,
+
+    /// Project root that is recorded in the dump. Defaults to the current directory.
+    #[clap(
+        long,
+        value_name = "PROJECT_ROOT",
+        value_hint = ValueHint::DirPath,
+    )]
+    pub project_root: Option<PathBuf>,
+
+    #[clap(
+        long,
+        short = 'o',
+        value_name = "OUTPUT_PATH",
+        value_hint = ValueHint::AnyPath,
+        default_value = "dump.lsif",
+    )]
+    pub output: PathBuf,
+}
+
+impl ExportArgs {
+    pub fn run(self, db_path: &Path, mut loader: Loader) -> anyhow::Result<()> {
+        let cancellation_flag = &NoCancellation;
+        let mut db = SQLiteReader::open(db_path)?;
+        if self.source_paths.is_empty() {
+            let mut indexed_files = Vec::new();
+            {
+                let mut files = db.list_all()?;
+                for file in files.try_iter()? {
+                    let file = file?;
+                    if let FileStatus::Indexed = file.status {
+                        indexed_files.push(file.path);
+                    }
+                }
+            }
+            for file in &indexed_files {
+                db.load_graph_for_file(&file.to_string_lossy())?;
+            }
+        } else {
+            for source_path in &self.source_paths {
+                let source_path = source_path.canonicalize()?;
+                db.load_graphs_for_file_or_directory(&source_path, cancellation_flag)?;
+            }
+        }
+
+        let (graph, _, _) = db.get();
+        let references = graph
+            .iter_nodes()
+            .filter(|n| graph[*n].is_reference())
+            .collect::<Vec<_>>();
+        let definitions = graph
+            .iter_nodes()
+            .filter(|n| graph[*n].is_definition())
+            .collect::<Vec<_>>();
+        let mut paths_by_reference = HashMap::<Handle<Node>, Vec<PartialPath>>::new();
+        let stitcher_config = StitcherConfig::default()
+            // always detect similar paths, we don't know the language configurations for the data in the database
+            .with_detect_similar_paths(true);
+        ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut db,
+            references,
+            stitcher_config,
+            cancellation_flag,
+            |_, _, p| {
+                paths_by_reference
+                    .entry(p.start_node)
+                    .or_default()
+                    .push(p.clone());
+            },
+        )?;
+
+        let (graph, partials, _) = db.get();
+
+        // Determine the language of every file, which is recorded in the documents of the dump,
+        // and whose shadowing policy is used to resolve the references in the file.
+        let mut file_reader = FileReader::new();
+        let mut language_names = HashMap::new();
+        let mut shadowing_policies = HashMap::new();
+        for file in graph.iter_files() {
+            let path = Path::new(graph[file].name());
+            let lcs = loader.load_for_file(path, &mut file_reader, &crate::NoCancellation)?;
+            shadowing_policies.insert(file, lcs.shadowing_policy());
+            if let Some(language_name) = lcs.primary.and_then(|lc| lc.language_name()) {
+                language_names.insert(graph[file].name().to_string(), language_name.to_string());
+            }
+        }
+
+        let mut resolved = Vec::new();
+        for (reference, paths) in paths_by_reference {
+            let file = graph[reference]
+                .file()
+                .expect("reference must be in a file");
+            let shadowing_policy = &shadowing_policies[&file];
+            let mut targets = Vec::new();
+            for path in &paths {
+                if !shadowing_policy.is_shadowed(graph, partials, path, &paths) {
+                    targets.push(path.end_node);
+                }
+            }
+            targets.sort();
+            targets.dedup();
+            resolved.push((reference, targets));
+        }
+        resolved.sort();
+
+        let project_root = match self.project_root {
+            Some(project_root) => project_root.canonicalize()?,
+            None => std::env::current_dir()?,
+        };
+        if let Some(dir) = self.output.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let output = std::fs::File::create(&self.output)?;
+        let mut writer = LsifWriter::new(BufWriter::new(output));
+        let summary = writer.write(
+            graph,
+            &project_root,
+            &language_names,
+            &definitions,
+            &resolved,
+        )?;
+        writer.out.flush()?;
+        println!(
+            "Exported {} definitions and {} references in {} documents to {}",
+            summary.definitions,
+            summary.references,
+            summary.documents,
+            self.output.display()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Summary {
+    documents: usize,
+    definitions: usize,
+    references: usize,
+}
+
+/// Writes LSIF elements as JSON lines, assigning element IDs.
+struct LsifWriter<W: Write> {
+    out: W,
+    next_id: usize,
+}
+
+impl<W: Write> LsifWriter<W> {
+    fn new(out: W) -> Self {
+        Self { out, next_id: 1 }
+    }
+
+    fn write(
+        &mut self,
+        graph: &StackGraph,
+        project_root: &Path,
+        language_names: &HashMap<String, String>,
+        definitions: &[Handle<Node>],
+        resolved: &[(Handle<Node>, Vec<Handle<Node>>)],
+    ) -> anyhow::Result<Summary> {
+        let mut summary = Summary::default();
+        self.vertex(
+            "metaData",
+            json!({
+                "version": LSIF_VERSION,
+                "projectRoot": file_uri(project_root)?,
+                "positionEncoding": "utf-16",
+                "toolInfo": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            }),
+        )?;
+
+        // Collect all nodes that need a range, grouped by document, in a stable order.
+        let mut nodes_by_file = BTreeMap::<String, Vec<(Handle<Node>, Span)>>::new();
+        let mut add_node = |node: Handle<Node>| {
+            if let Some((file, span)) = node_location(graph, node) {
+                nodes_by_file
+                    .entry(graph[file].name().to_string())
+                    .or_default()
+                    .push((node, span));
+            }
+        };
+        for definition in definitions {
+            add_node(*definition);
+        }
+        for (reference, targets) in resolved {
+            if !targets.is_empty() {
+                add_node(*reference);
+            }
+        }
+
+        // Emit documents and their ranges.
+        let mut ranges = HashMap::<Handle<Node>, (usize, usize)>::new();
+        for (file, mut nodes) in nodes_by_file {
+            nodes.sort_by_key(|(node, span)| {
+                (span.start.line, span.start.column.utf8_offset, *node)
+            });
+            nodes.dedup_by_key(|(node, _)| *node);
+            let document = self.vertex(
+                "document",
+                json!({
+                    "uri": file_uri(Path::new(&file))?,
+                    "languageId": language_names.get(&file).map_or("", String::as_str),
+                }),
+            )?;
+            summary.documents += 1;
+            let mut range_ids = Vec::new();
+            for (node, span) in nodes {
+                let range = self.vertex(
+                    "range",
+                    json!({
+                        "start": position(&span.start),
+                        "end": position(&span.end),
+                    }),
+                )?;
+                ranges.insert(node, (range, document));
+                range_ids.push(range);
+            }
+            self.edge("contains", json!({ "outV": document, "inVs": range_ids }))?;
+        }
+
+        // Emit result sets for definitions, with their definition results and monikers.
+        let mut result_sets = HashMap::<Handle<Node>, usize>::new();
+        for definition in definitions {
+            let (range, document) = match ranges.get(definition) {
+                Some(range) => *range,
+                None => continue,
+            };
+            let result_set = self.vertex("resultSet", json!({}))?;
+            self.edge("next", json!({ "outV": range, "inV": result_set }))?;
+            if let Some(fully_qualified_name) = graph
+                .source_info(*definition)
+                .and_then(|s| s.fully_qualified_name.into_option())
+            {
+                let moniker = self.vertex(
+                    "moniker",
+                    json!({
+                        "scheme": MONIKER_SCHEME,
+                        "identifier": &graph[fully_qualified_name],
+                        "kind": "export",
+                    }),
+                )?;
+                self.edge("moniker", json!({ "outV": result_set, "inV": moniker }))?;
+            }
+            let definition_result = self.vertex("definitionResult", json!({}))?;
+            self.edge(
+                "textDocument/definition",
+                json!({ "outV": result_set, "inV": definition_result }),
+            )?;
+            self.edge(
+                "item",
+                json!({ "outV": definition_result, "inVs": [range], "document": document }),
+            )?;
+            result_sets.insert(*definition, result_set);
+            summary.definitions += 1;
+        }
+
+        // Link references to the result sets of their definitions. References with several
+        // definitions get their own result set, listing all definitions.
+        let mut references_by_definition = HashMap::<Handle<Node>, Vec<Handle<Node>>>::new();
+        for (reference, targets) in resolved {
+            let (range, _) = match ranges.get(reference) {
+                Some(range) => *range,
+                None => continue,
+            };
+            let targets = targets
+                .iter()
+                .filter(|t| result_sets.contains_key(t))
+                .collect::<Vec<_>>();
+            if targets.is_empty() {
+                continue;
+            }
+            for target in &targets {
+                references_by_definition
+                    .entry(**target)
+                    .or_default()
+                    .push(*reference);
+            }
+            summary.references += 1;
+            if let [target] = targets[..] {
+                self.edge("next", json!({ "outV": range, "inV": result_sets[target] }))?;
+                continue;
+            }
+            let result_set = self.vertex("resultSet", json!({}))?;
+            self.edge("next", json!({ "outV": range, "inV": result_set }))?;
+            let definition_result = self.vertex("definitionResult", json!({}))?;
+            self.edge(
+                "textDocument/definition",
+                json!({ "outV": result_set, "inV": definition_result }),
+            )?;
+            self.items(
+                definition_result,
+                targets.into_iter().copied(),
+                &ranges,
+                None,
+            )?;
+        }
+
+        // Emit reference results for definitions that are referenced.
+        for definition in definitions {
+            let references = match references_by_definition.remove(definition) {
+                Some(references) => references,
+                None => continue,
+            };
+            let (range, document) = ranges[definition];
+            let reference_result = self.vertex("referenceResult", json!({}))?;
+            self.edge(
+                "textDocument/references",
+                json!({ "outV": result_sets[definition], "inV": reference_result }),
+            )?;
+            self.edge(
+                "item",
+                json!({
+                    "outV": reference_result,
+                    "inVs": [range],
+                    "document": document,
+                    "property": "definitions",
+                }),
+            )?;
+            self.items(
+                reference_result,
+                references.into_iter(),
+                &ranges,
+                Some("references"),
+            )?;
+        }
+
+        Ok(summary)
+    }
+
+    /// Emit item edges from the given result to the ranges of the given nodes, one per document.
+    fn items(
+        &mut self,
+        result: usize,
+        nodes: impl Iterator<Item = Handle<Node>>,
+        ranges: &HashMap<Handle<Node>, (usize, usize)>,
+        property: Option<&str>,
+    ) -> std::io::Result<()> {
+        let mut ranges_by_document = BTreeMap::<usize, Vec<usize>>::new();
+        for node in nodes {
+            let (range, document) = ranges[&node];
+            ranges_by_document.entry(document).or_default().push(range);
+        }
+        for (document, mut range_ids) in ranges_by_document {
+            range_ids.sort();
+            range_ids.dedup();
+            let mut edge = json!({ "outV": result, "inVs": range_ids, "document": document });
+            if let Some(property) = property {
+                edge["property"] = json!(property);
+            }
+            self.edge("item", edge)?;
+        }
+        Ok(())
+    }
+
+    fn vertex(&mut self, label: &str, properties: Value) -> std::io::Result<usize> {
+        self.element("vertex", label, properties)
+    }
+
+    fn edge(&mut self, label: &str, properties: Value) -> std::io::Result<usize> {
+        self.element("edge", label, properties)
+    }
+
+    fn element(
+        &mut self,
+        type_: &str,
+        label: &str,
+        mut properties: Value,
+    ) -> std::io::Result<usize> {
+        let id = self.next_id;
+        self.next_id += 1;
+        properties["id"] = json!(id);
+        properties["type"] = json!(type_);
+        properties["label"] = json!(label);
+        serde_json::to_writer(&mut self.out, &properties)?;
+        writeln!(self.out)?;
+        Ok(id)
+    }
+}
+
+/// Returns the file and source span of a node, if it has a non-empty span.
+fn node_location(graph: &StackGraph, node: Handle<Node>) -> Option<(Handle<File>, Span)> {
+    let file = graph[node].file()?;
+    let span = &graph.source_info(node)?.span;
+    if *span == Span::default() {
+        return None;
+    }
+    Some((file, span.clone()))
+}
+
+fn position(position: &Position) -> Value {
+    json!({ "line": position.line, "character": position.column.utf16_offset })
+}
+
+fn file_uri(path: &Path) -> anyhow::Result<String> {
+    Url::from_file_path(path)
+        .map(String::from)
+        .map_err(|_| anyhow!("Cannot convert {} to a file URI", path.display()))
+}
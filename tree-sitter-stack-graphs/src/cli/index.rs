@@ -7,24 +7,35 @@
 
 use clap::Args;
 use clap::ValueHint;
+use serde_json::json;
 use stack_graphs::arena::Handle;
 use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPath;
 use stack_graphs::partial::PartialPaths;
+use stack_graphs::shadowing::EdgePrecedence;
 use stack_graphs::stats::FrequencyDistribution;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
 use stack_graphs::stitching::Stats as StitchingStats;
 use stack_graphs::stitching::StitcherConfig;
+use stack_graphs::storage::FileOverlay;
 use stack_graphs::storage::FileStatus;
+use stack_graphs::storage::SQLiteReader;
 use stack_graphs::storage::SQLiteWriter;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 use thiserror::Error;
 use tree_sitter_graph::Variables;
 
 use crate::cli::util::duration_from_seconds_str;
+use crate::cli::util::indexing_stats_to_json;
 use crate::cli::util::iter_files_and_directories;
 use crate::cli::util::print_indexing_stats;
 use crate::cli::util::reporter::ConsoleReporter;
@@ -35,9 +46,11 @@ use crate::cli::util::wait_for_input;
 use crate::cli::util::BuildErrorWithSource;
 use crate::cli::util::CLIFileReporter;
 use crate::cli::util::ExistingPathBufValueParser;
+use crate::cli::util::OutputFormat;
 use crate::loader::FileLanguageConfigurations;
 use crate::loader::FileReader;
 use crate::loader::Loader;
+use crate::loader::SharedFileLanguageConfigurations;
 use crate::BuildError;
 use crate::CancelAfterDuration;
 use crate::CancellationFlag;
@@ -70,6 +83,13 @@ pub struct IndexArgs {
     #[clap(long, short = 'f')]
     pub force: bool,
 
+    /// Before indexing, remove data for files that no longer exist, and move the data of
+    /// files that were moved to their new location. Moved files are indexed again instead,
+    /// if their language's rules use the FILE_PATH variable, or if their language has special
+    /// file analyzers, because their stack graphs may contain symbols derived from the old path.
+    #[clap(long)]
+    pub prune: bool,
+
     /// Hide details of indexing errors on files.
     #[clap(long)]
     pub hide_error_details: bool,
@@ -82,9 +102,27 @@ pub struct IndexArgs {
     )]
     pub max_file_time: Option<Duration>,
 
+    /// Number of files to index in parallel.
+    #[clap(long, short = 'j', value_name = "N", default_value = "1")]
+    pub jobs: NonZeroUsize,
+
+    /// Maximum number of paths kept for similar path detection per file. Limits memory use on
+    /// files with many similar paths, at the cost of possibly more work.
+    #[clap(long, value_name = "N")]
+    pub max_similar_paths: Option<usize>,
+
+    /// After indexing, resolve the references in the indexed files, and store the results in
+    /// the database, so that queries can use them instead of stitching paths.
+    #[clap(long)]
+    pub resolve: bool,
+
     #[clap(long)]
     pub stats: bool,
 
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
     /// Wait for user input before starting analysis. Useful for profiling.
     #[clap(long)]
     pub wait_at_start: bool,
@@ -95,24 +133,61 @@ impl IndexArgs {
         Self {
             source_paths,
             force: false,
+            prune: false,
             continue_from: None,
             verbose: false,
             hide_error_details: false,
             max_file_time: None,
+            jobs: NonZeroUsize::new(1).unwrap(),
+            max_similar_paths: None,
             wait_at_start: false,
+            resolve: false,
             stats: false,
+            format: OutputFormat::Text,
         }
     }
 
-    pub fn run(self, db_path: &Path, mut loader: Loader) -> anyhow::Result<()> {
+    pub fn run(self, db_path: &Path, loader: Loader) -> anyhow::Result<()> {
         if self.wait_at_start {
             wait_for_input()?;
         }
+        match self.format.json_reporter() {
+            Some(reporter) => {
+                let result = self.run_with_reporter(db_path, loader, &reporter, |stats| {
+                    reporter.record(json!({
+                        "type": "stats",
+                        "indexing": indexing_stats_to_json(stats),
+                    }))
+                });
+                if let Err(err) = &result {
+                    reporter.error(err);
+                }
+                reporter.finish()?;
+                result
+            }
+            None => {
+                let reporter = self.get_reporter();
+                self.run_with_reporter(db_path, loader, &reporter, |stats| {
+                    println!();
+                    print_indexing_stats(stats);
+                })
+            }
+        }
+    }
+
+    fn run_with_reporter(
+        self,
+        db_path: &Path,
+        mut loader: Loader,
+        reporter: &dyn Reporter,
+        report_stats: impl FnOnce(IndexingStats),
+    ) -> anyhow::Result<()> {
         let mut db = SQLiteWriter::open(&db_path)?;
-        let reporter = self.get_reporter();
-        let mut indexer = Indexer::new(&mut db, &mut loader, &reporter);
+        let mut indexer = Indexer::new(&mut db, &mut loader, reporter);
         indexer.force = self.force;
         indexer.max_file_time = self.max_file_time;
+        indexer.jobs = self.jobs.get();
+        indexer.max_similar_paths = self.max_similar_paths;
         indexer.set_collect_stats(self.stats);
 
         let source_paths = self
@@ -120,11 +195,17 @@ impl IndexArgs {
             .into_iter()
             .map(|p| p.canonicalize())
             .collect::<std::result::Result<Vec<_>, _>>()?;
-        indexer.index_all(source_paths, self.continue_from, &NoCancellation)?;
+        if self.prune {
+            indexer.prune(&source_paths, &NoCancellation)?;
+        }
+        indexer.index_all(&source_paths, self.continue_from, &NoCancellation)?;
+        if self.resolve {
+            let mut reader = SQLiteReader::open(db_path)?;
+            indexer.resolve_all(&mut reader, &source_paths, &NoCancellation)?;
+        }
 
         if self.stats {
-            println!();
-            print_indexing_stats(indexer.into_stats());
+            report_stats(indexer.into_stats());
         }
         Ok(())
     }
@@ -164,6 +245,11 @@ pub struct Indexer<'a> {
     pub force: bool,
     /// Maximum time per file.
     pub max_file_time: Option<Duration>,
+    /// Number of files to index in parallel. Files are indexed on the calling thread if this is
+    /// less than two.
+    pub jobs: usize,
+    /// Maximum number of paths kept for similar path detection per file.
+    pub max_similar_paths: Option<usize>,
 }
 
 impl<'a> Indexer<'a> {
@@ -178,6 +264,8 @@ impl<'a> Indexer<'a> {
             reporter,
             force: false,
             max_file_time: None,
+            jobs: 1,
+            max_similar_paths: None,
             stats: None,
         }
     }
@@ -201,6 +289,9 @@ impl<'a> Indexer<'a> {
         IP: IntoIterator<Item = P>,
         Q: AsRef<Path>,
     {
+        if self.jobs > 1 {
+            return self.index_all_parallel(source_paths, continue_from, cancellation_flag);
+        }
         for (source_root, source_path, strict) in iter_files_and_directories(source_paths) {
             let mut file_status = CLIFileReporter::new(self.reporter, &source_path);
             cancellation_flag.check("indexing all files")?;
@@ -217,6 +308,101 @@ impl<'a> Indexer<'a> {
         Ok(())
     }
 
+    /// Index files using a pool of worker threads. Files are selected, and results are stored and
+    /// reported, on the calling thread, while the worker threads build the stack graphs and
+    /// partial paths.
+    fn index_all_parallel<P, IP, Q>(
+        &mut self,
+        source_paths: IP,
+        mut continue_from: Option<Q>,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<()>
+    where
+        P: AsRef<Path>,
+        IP: IntoIterator<Item = P>,
+        Q: AsRef<Path>,
+    {
+        let (job_sender, job_receiver) = mpsc::sync_channel::<FileJob>(self.jobs);
+        let job_receiver = Mutex::new(job_receiver);
+        let (outcome_sender, outcome_receiver) = mpsc::channel::<(FileJob, FileJobOutcome)>();
+        std::thread::scope(|scope| {
+            for _ in 0..self.jobs {
+                let job_receiver = &job_receiver;
+                let outcome_sender = outcome_sender.clone();
+                scope.spawn(move || loop {
+                    let job = match job_receiver.lock().expect("job queue poisoned").recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    let outcome = job.run(cancellation_flag);
+                    if outcome_sender.send((job, outcome)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(outcome_sender);
+
+            let mut result = Ok(());
+            for (source_root, source_path, strict) in iter_files_and_directories(source_paths) {
+                if let Err(err) = cancellation_flag.check("indexing all files") {
+                    result = Err(err.into());
+                    break;
+                }
+                let mut file_status = CLIFileReporter::new(self.reporter, &source_path);
+                match self.prepare_file(
+                    &source_root,
+                    &source_path,
+                    strict,
+                    &mut continue_from,
+                    &mut file_status,
+                ) {
+                    Ok(Some(job)) => job_sender.send(job).expect("indexing workers stopped"),
+                    Ok(None) => {}
+                    Err(err) => {
+                        file_status.failure("error", Some(&Self::continue_from_hint(&source_path)));
+                        result = Err(err);
+                        break;
+                    }
+                }
+                file_status.assert_reported();
+                if let Err(err) = self.store_outcomes(outcome_receiver.try_iter()) {
+                    result = Err(err);
+                    break;
+                }
+            }
+
+            // Results of files that are still being processed are stored, even if indexing failed.
+            // Otherwise, continuing from the failed file would skip those files that precede it.
+            drop(job_sender);
+            let remaining = self.store_outcomes(outcome_receiver.iter());
+            result.and(remaining)
+        })
+    }
+
+    /// Store and report the given outcomes. All outcomes are stored, even if storing one of them
+    /// fails, in which case the first error is returned.
+    fn store_outcomes<I>(&mut self, outcomes: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (FileJob, FileJobOutcome)>,
+    {
+        let mut result = Ok(());
+        for (job, outcome) in outcomes {
+            let mut file_status = CLIFileReporter::new(self.reporter, &job.source_path);
+            file_status.processing();
+            if let Err(err) = self.store_outcome(&job, outcome, &mut file_status) {
+                file_status.failure_if_processing(
+                    "error",
+                    Some(&Self::continue_from_hint(&job.source_path)),
+                );
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+            file_status.assert_reported();
+        }
+        result
+    }
+
     pub fn index(
         &mut self,
         source_root: &Path,
@@ -236,6 +422,155 @@ impl<'a> Indexer<'a> {
         Ok(())
     }
 
+    /// Remove data for files under the given source paths that no longer exist. Moved files are
+    /// detected by comparing the tags of removed files with those of files that are not yet in the
+    /// database. The data of moved files is stored under the new path, so they are not indexed again.
+    /// Stack graphs that may depend on the path of their file, see
+    /// [`FileLanguageConfigurations::uses_file_path`][crate::loader::FileLanguageConfigurations::uses_file_path],
+    /// are removed instead, so that the moved file is indexed again.
+    pub fn prune<P, IP>(
+        &mut self,
+        source_paths: IP,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<()>
+    where
+        P: AsRef<Path>,
+        IP: IntoIterator<Item = P>,
+    {
+        let source_paths = source_paths
+            .into_iter()
+            .map(|p| p.as_ref().to_path_buf())
+            .collect::<Vec<_>>();
+
+        let mut seen = HashSet::new();
+        let mut removed_by_tag = HashMap::<String, Vec<PathBuf>>::new();
+        for source_path in &source_paths {
+            let mut files = self.db.list_file_or_directory(source_path)?;
+            for entry in files.try_iter()? {
+                cancellation_flag.check("finding removed files")?;
+                let entry = entry?;
+                if !entry.path.exists() && seen.insert(entry.path.clone()) {
+                    removed_by_tag
+                        .entry(entry.tag)
+                        .or_default()
+                        .push(entry.path);
+                }
+            }
+        }
+        if removed_by_tag.is_empty() {
+            return Ok(());
+        }
+
+        for (_, source_path, _) in iter_files_and_directories(&source_paths) {
+            cancellation_flag.check("finding moved files")?;
+            if !matches!(
+                self.db
+                    .status_for_file(&source_path.to_string_lossy(), None)?,
+                FileStatus::Missing
+            ) {
+                continue;
+            }
+            let mut file_reader = FileReader::new();
+            match self
+                .loader
+                .load_for_file(&source_path, &mut file_reader, &NoCancellation)
+            {
+                // stack graphs with symbols derived from the old path cannot be moved
+                Ok(lcs) if lcs.has_some() && !lcs.uses_file_path() => {}
+                Ok(_) | Err(crate::loader::LoadError::Cancelled(_)) => continue,
+                Err(e) => return Err(IndexError::LoadError(e)),
+            }
+            let tag = sha1(file_reader.get(&source_path)?);
+            let old_path = match removed_by_tag.get_mut(&tag).and_then(|paths| paths.pop()) {
+                Some(old_path) => old_path,
+                None => continue,
+            };
+            self.db.rename_file(&old_path, &source_path)?;
+            CLIFileReporter::new(self.reporter, &source_path)
+                .success(&format!("moved from {}", old_path.display()), None);
+        }
+
+        let mut removed = removed_by_tag.into_values().flatten().collect::<Vec<_>>();
+        removed.sort();
+        for path in removed {
+            cancellation_flag.check("removing files")?;
+            self.db.clean_file(&path)?;
+            CLIFileReporter::new(self.reporter, &path).success("removed", None);
+        }
+
+        Ok(())
+    }
+
+    /// Resolve the references in all indexed files under the given source paths, and store the
+    /// results in the database. References that already have an up-to-date resolution are skipped,
+    /// so that only the resolutions that were invalidated by indexing are computed again. The
+    /// shadowing policy of the file's language is used to select the visible definitions.
+    pub fn resolve_all<P, IP>(
+        &mut self,
+        reader: &mut SQLiteReader,
+        source_paths: IP,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<()>
+    where
+        P: AsRef<Path>,
+        IP: IntoIterator<Item = P>,
+    {
+        // always detect similar paths, to get the same results as queries
+        let stitcher_config = StitcherConfig::default().with_detect_similar_paths(true);
+        for source_path in source_paths {
+            let mut files = Vec::new();
+            for entry in reader
+                .list_file_or_directory(source_path.as_ref())?
+                .try_iter()?
+            {
+                let entry = entry?;
+                if let FileStatus::Indexed = entry.status {
+                    files.push(entry.path);
+                }
+            }
+            for path in files {
+                cancellation_flag.check("resolving all files")?;
+                let mut file_status = CLIFileReporter::new(self.reporter, &path);
+                let mut file_reader = FileReader::new();
+                let shadowing_policy =
+                    match self
+                        .loader
+                        .load_for_file(&path, &mut file_reader, &NoCancellation)
+                    {
+                        Ok(lcs) => lcs.shadowing_policy(),
+                        Err(_) => Arc::new(EdgePrecedence),
+                    };
+
+                reader.clear();
+                let file = reader.load_graph_for_file(&path.to_string_lossy())?;
+                let (graph, _, _) = reader.get();
+                let references = graph
+                    .nodes_for_file(file)
+                    .filter(|n| graph[*n].is_reference())
+                    .collect::<Vec<_>>();
+                let mut resolutions = Vec::new();
+                for reference in references {
+                    if reader.is_resolved(reference)? {
+                        continue;
+                    }
+                    resolutions.push(reader.resolve_reference(
+                        reference,
+                        stitcher_config,
+                        shadowing_policy.as_ref(),
+                        &cancellation_flag,
+                    )?);
+                }
+                if resolutions.is_empty() {
+                    file_status.skipped("resolutions up to date", None);
+                    continue;
+                }
+                self.db.store_resolutions(&resolutions)?;
+                file_status.success(&format!("resolved {} references", resolutions.len()), None);
+            }
+        }
+        Ok(())
+    }
+
     /// Analyze file and add error context to any failures that are returned.
     fn index_file<P>(
         &mut self,
@@ -262,12 +597,21 @@ impl<'a> Indexer<'a> {
                 ok
             }
             err @ Err(_) => {
-                file_status.failure_if_processing("error", Some(&format!("Error analyzing file {}. To continue analysis from this file later, add: --continue-from {}", source_path.display(), source_path.display())));
+                file_status
+                    .failure_if_processing("error", Some(&Self::continue_from_hint(source_path)));
                 err
             }
         }
     }
 
+    fn continue_from_hint(source_path: &Path) -> String {
+        format!(
+            "Error analyzing file {}. To continue analysis from this file later, add: --continue-from {}",
+            source_path.display(),
+            source_path.display()
+        )
+    }
+
     fn index_file_inner<P>(
         &mut self,
         source_root: &Path,
@@ -277,34 +621,66 @@ impl<'a> Indexer<'a> {
         cancellation_flag: &dyn CancellationFlag,
         file_status: &mut CLIFileReporter<'_>,
     ) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let job = match self.prepare_file(
+            source_root,
+            source_path,
+            missing_is_error,
+            continue_from,
+            file_status,
+        )? {
+            Some(job) => job,
+            None => return Ok(()),
+        };
+
+        file_status.processing();
+
+        let outcome = job.run(cancellation_flag);
+        self.store_outcome(&job, outcome, file_status)
+    }
+
+    /// Determines whether the file must be indexed, and if so, returns a job that can be run on any
+    /// thread to index it. If the file is not indexed, that is reported before returning.
+    fn prepare_file<P>(
+        &mut self,
+        source_root: &Path,
+        source_path: &Path,
+        missing_is_error: bool,
+        continue_from: &mut Option<P>,
+        file_status: &mut CLIFileReporter<'_>,
+    ) -> Result<Option<FileJob>>
     where
         P: AsRef<Path>,
     {
         if self.should_skip(source_path, continue_from) {
             file_status.skipped("skipped", None);
-            return Ok(());
+            return Ok(None);
         }
 
         let mut file_reader = FileReader::new();
-        let lcs = match self
-            .loader
-            .load_for_file(source_path, &mut file_reader, &NoCancellation)
-        {
-            Ok(lcs) if !lcs.has_some() => {
-                if missing_is_error {
-                    file_status.failure("not supported", None);
+        let lcs =
+            match self
+                .loader
+                .load_shared_for_file(source_path, &mut file_reader, &NoCancellation)
+            {
+                Ok(lcs) if !lcs.has_some() => {
+                    if missing_is_error {
+                        file_status.failure("not supported", None);
+                    }
+                    return Ok(None);
                 }
-                return Ok(());
-            }
-            Ok(lcs) => lcs,
-            Err(crate::loader::LoadError::Cancelled(_)) => {
-                file_status.warning("language loading timed out", None);
-                return Ok(());
-            }
-            Err(e) => return Err(IndexError::LoadError(e)),
-        };
+                Ok(lcs) => lcs,
+                Err(crate::loader::LoadError::Cancelled(_)) => {
+                    file_status.warning("language loading timed out", None);
+                    return Ok(None);
+                }
+                Err(e) => return Err(IndexError::LoadError(e)),
+            };
         let stitcher_config = StitcherConfig::default()
             .with_detect_similar_paths(!lcs.no_similar_paths_in_file())
+            .with_max_similar_paths(self.max_similar_paths)
             .with_collect_stats(self.stats.is_some());
 
         let source = file_reader.get(source_path)?;
@@ -320,7 +696,7 @@ impl<'a> Indexer<'a> {
                     "reindexed"
                 } else {
                     file_status.skipped("cached index", None);
-                    return Ok(());
+                    return Ok(None);
                 }
             }
             FileStatus::Error(error) => {
@@ -328,13 +704,107 @@ impl<'a> Indexer<'a> {
                     "reindexed"
                 } else {
                     file_status.skipped(&format!("cached error ({})", error), None);
-                    return Ok(());
+                    return Ok(None);
                 }
             }
         };
 
-        let file_cancellation_flag = CancelAfterDuration::from_option(self.max_file_time);
-        let cancellation_flag = cancellation_flag | file_cancellation_flag.as_ref();
+        Ok(Some(FileJob {
+            source_root: source_root.to_path_buf(),
+            source_path: source_path.to_path_buf(),
+            source: source.to_string(),
+            tag,
+            success_status,
+            lcs,
+            stitcher_config,
+            max_file_time: self.max_file_time,
+        }))
+    }
+
+    /// Stores the outcome of the job in the database, and reports the final status of the file.
+    fn store_outcome(
+        &mut self,
+        job: &FileJob,
+        outcome: FileJobOutcome,
+        file_status: &mut CLIFileReporter<'_>,
+    ) -> Result<()> {
+        match outcome {
+            FileJobOutcome::Indexed(indexed) => {
+                let IndexedFile {
+                    graph,
+                    file,
+                    mut partials,
+                    paths,
+                    stitching_stats,
+                } = *indexed;
+                if let Some(stats) = &mut self.stats {
+                    stats.record_graph(&graph);
+                    stats.stitching_stats += stitching_stats;
+                }
+                self.db
+                    .store_result_for_file(&graph, file, &job.tag, &mut partials, &paths)?;
+                file_status.success(job.success_status, None);
+            }
+            FileJobOutcome::ParsingTimedOut => {
+                file_status.warning("parsing timed out", None);
+                self.db
+                    .store_error_for_file(&job.source_path, &job.tag, "parsing timed out")?;
+            }
+            FileJobOutcome::ParsingFailed { error, details } => {
+                file_status.failure("parsing failed", Some(&details));
+                self.db.store_error_for_file(
+                    &job.source_path,
+                    &job.tag,
+                    &format!("parsing failed: {}", error),
+                )?;
+            }
+            FileJobOutcome::BuildFailed { details } => {
+                file_status.failure("failed to build stack graph", Some(&details));
+                return Err(IndexError::StackGraph);
+            }
+            FileJobOutcome::PathsTimedOut(graph) => {
+                if let Some(stats) = &mut self.stats {
+                    stats.record_graph(&graph);
+                }
+                file_status.warning("path computation timed out", None);
+                self.db.store_error_for_file(
+                    &job.source_path,
+                    &job.tag,
+                    &format!("path computation timed out"),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the stack graph and partial paths for the given file source, and return them as an
+    /// overlay instead of storing them in the database. The source does not have to match the
+    /// file's content on disk, which makes this suitable for indexing unsaved editor buffers.
+    /// Returns `None` if the file is not supported or could not be indexed.
+    pub fn build_overlay(
+        loader: &mut Loader,
+        reporter: &dyn Reporter,
+        source_root: &Path,
+        source_path: &Path,
+        source: &str,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Option<FileOverlay>> {
+        let mut file_status = CLIFileReporter::new(reporter, source_path);
+
+        let lcs = match loader.load_for_file(source_path, &mut Some(source), &NoCancellation) {
+            Ok(lcs) if !lcs.has_some() => {
+                file_status.skipped("not supported", None);
+                return Ok(None);
+            }
+            Ok(lcs) => lcs,
+            Err(crate::loader::LoadError::Cancelled(_)) => {
+                file_status.warning("language loading timed out", None);
+                return Ok(None);
+            }
+            Err(e) => return Err(IndexError::LoadError(e)),
+        };
+        let stitcher_config =
+            StitcherConfig::default().with_detect_similar_paths(!lcs.no_similar_paths_in_file());
 
         file_status.processing();
 
@@ -348,26 +818,19 @@ impl<'a> Indexer<'a> {
             file,
             source_root,
             source_path,
-            &source,
+            source,
             lcs,
-            &cancellation_flag,
+            cancellation_flag,
         );
         if let Err(err) = result {
             match err.inner {
                 BuildError::Cancelled(_) => {
                     file_status.warning("parsing timed out", None);
-                    self.db
-                        .store_error_for_file(source_path, &tag, "parsing timed out")?;
-                    return Ok(());
+                    return Ok(None);
                 }
                 BuildError::ParseErrors { .. } => {
                     file_status.failure("parsing failed", Some(&err.display_pretty()));
-                    self.db.store_error_for_file(
-                        source_path,
-                        &tag,
-                        &format!("parsing failed: {}", err.inner),
-                    )?;
-                    return Ok(());
+                    return Ok(None);
                 }
                 _ => {
                     file_status.failure("failed to build stack graph", Some(&err.display_pretty()));
@@ -375,55 +838,29 @@ impl<'a> Indexer<'a> {
                 }
             }
         };
-        if let Some(stats) = &mut self.stats {
-            stats.total_graph_nodes.record(graph.iter_nodes().count());
-            let mut total_edges = 0;
-            for n in graph.iter_nodes() {
-                let edge_count = graph.outgoing_edges(n).count();
-                if graph[n].is_root() {
-                    stats.root_out_degree = edge_count;
-                } else {
-                    stats.node_out_degrees.record(edge_count);
-                    total_edges += edge_count;
-                }
-            }
-            stats.total_graph_edges.record(total_edges);
-        }
 
         let mut partials = PartialPaths::new();
         let mut paths = Vec::new();
-        match ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+        let result = ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
             &graph,
             &mut partials,
             file,
             stitcher_config,
-            &(&cancellation_flag as &dyn CancellationFlag),
+            &cancellation_flag,
             |_g, _ps, p| {
                 paths.push(p.clone());
             },
-        ) {
-            Ok(stitching_stats) => {
-                if let Some(stats) = &mut self.stats {
-                    stats.stitching_stats += stitching_stats;
-                }
-            }
-            Err(_) => {
-                file_status.warning("path computation timed out", None);
-                self.db.store_error_for_file(
-                    source_path,
-                    &tag,
-                    &format!("path computation timed out"),
-                )?;
-                return Ok(());
-            }
+        );
+        if result.is_err() {
+            file_status.warning("path computation timed out", None);
+            return Ok(None);
         }
 
-        self.db
-            .store_result_for_file(&graph, file, &tag, &mut partials, &paths)?;
+        let overlay = FileOverlay::new(&graph, file, &mut partials, &paths)?;
 
-        file_status.success(success_status, None);
+        file_status.success("indexed", None);
 
-        Ok(())
+        Ok(Some(overlay))
     }
 
     fn build_stack_graph<'b>(
@@ -490,6 +927,93 @@ impl<'a> Indexer<'a> {
     }
 }
 
+/// A file that must be indexed, together with everything needed to index it. Jobs do not borrow
+/// from the indexer, so that they can be run on worker threads.
+struct FileJob {
+    source_root: PathBuf,
+    source_path: PathBuf,
+    source: String,
+    tag: String,
+    success_status: &'static str,
+    lcs: SharedFileLanguageConfigurations,
+    stitcher_config: StitcherConfig,
+    max_file_time: Option<Duration>,
+}
+
+impl FileJob {
+    /// Builds the stack graph and partial paths for the file. This does not access the database,
+    /// nor does it report anything.
+    fn run(&self, cancellation_flag: &dyn CancellationFlag) -> FileJobOutcome {
+        let file_cancellation_flag = CancelAfterDuration::from_option(self.max_file_time);
+        let cancellation_flag = cancellation_flag | file_cancellation_flag.as_ref();
+
+        let mut graph = StackGraph::new();
+        let file = graph
+            .add_file(&self.source_path.to_string_lossy())
+            .expect("file not present in empty graph");
+
+        let result = Indexer::build_stack_graph(
+            &mut graph,
+            file,
+            &self.source_root,
+            &self.source_path,
+            &self.source,
+            (&self.lcs).into(),
+            &cancellation_flag,
+        );
+        if let Err(err) = result {
+            return match err.inner {
+                BuildError::Cancelled(_) => FileJobOutcome::ParsingTimedOut,
+                BuildError::ParseErrors { .. } => FileJobOutcome::ParsingFailed {
+                    error: err.inner.to_string(),
+                    details: err.display_pretty().to_string(),
+                },
+                _ => FileJobOutcome::BuildFailed {
+                    details: err.display_pretty().to_string(),
+                },
+            };
+        }
+
+        let mut partials = PartialPaths::new();
+        let mut paths = Vec::new();
+        match ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            self.stitcher_config,
+            &(&cancellation_flag as &dyn CancellationFlag),
+            |_g, _ps, p| {
+                paths.push(p.clone());
+            },
+        ) {
+            Ok(stitching_stats) => FileJobOutcome::Indexed(Box::new(IndexedFile {
+                graph,
+                file,
+                partials,
+                paths,
+                stitching_stats,
+            })),
+            Err(_) => FileJobOutcome::PathsTimedOut(Box::new(graph)),
+        }
+    }
+}
+
+enum FileJobOutcome {
+    Indexed(Box<IndexedFile>),
+    ParsingTimedOut,
+    ParsingFailed { error: String, details: String },
+    BuildFailed { details: String },
+    PathsTimedOut(Box<StackGraph>),
+}
+
+struct IndexedFile {
+    graph: StackGraph,
+    file: Handle<File>,
+    partials: PartialPaths,
+    paths: Vec<PartialPath>,
+    stitching_stats: StitchingStats,
+}
+
 #[derive(Debug, Error)]
 pub enum IndexError {
     #[error("cancelled at {0}")]
@@ -525,3 +1049,20 @@ pub struct IndexingStats {
     // The stitching statistics.
     pub stitching_stats: StitchingStats,
 }
+
+impl IndexingStats {
+    fn record_graph(&mut self, graph: &StackGraph) {
+        self.total_graph_nodes.record(graph.iter_nodes().count());
+        let mut total_edges = 0;
+        for n in graph.iter_nodes() {
+            let edge_count = graph.outgoing_edges(n).count();
+            if graph[n].is_root() {
+                self.root_out_degree = edge_count;
+            } else {
+                self.node_out_degrees.record(edge_count);
+                total_edges += edge_count;
+            }
+        }
+        self.total_graph_edges.record(total_edges);
+    }
+}
@@ -0,0 +1,146 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use anyhow::anyhow;
+use clap::Args;
+use clap::ValueHint;
+use itertools::Itertools;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::lint::lint_file;
+use std::path::Path;
+use std::path::PathBuf;
+use tree_sitter_graph::Variables;
+
+use crate::cli::util::iter_files_and_directories;
+use crate::cli::util::reporter::ConsoleReporter;
+use crate::cli::util::reporter::Level;
+use crate::cli::util::CLIFileReporter;
+use crate::cli::util::ExistingPathBufValueParser;
+use crate::loader::FileReader;
+use crate::loader::Loader;
+use crate::NoCancellation;
+
+/// Check the stack graphs of source files for suspicious structure
+#[derive(Args)]
+pub struct LintArgs {
+    /// Source file or directory paths.
+    #[clap(
+        value_name = "SOURCE_PATH",
+        required = true,
+        value_hint = ValueHint::AnyPath,
+        value_parser = ExistingPathBufValueParser,
+    )]
+    pub source_paths: Vec<PathBuf>,
+
+    /// Hide files without problems in output.
+    #[clap(long, short = 'q')]
+    pub quiet: bool,
+}
+
+impl LintArgs {
+    pub fn new(source_paths: Vec<PathBuf>) -> Self {
+        Self {
+            source_paths,
+            quiet: false,
+        }
+    }
+
+    pub fn run(self, mut loader: Loader) -> anyhow::Result<()> {
+        let reporter = self.get_reporter();
+        let mut problem_count = 0;
+        for (_, source_path, strict) in iter_files_and_directories(self.source_paths.clone()) {
+            let mut file_status = CLIFileReporter::new(&reporter, &source_path);
+            let result = self.lint_file(&source_path, strict, &mut loader, &mut file_status);
+            if result.is_err() {
+                file_status.failure_if_processing("error", None);
+            }
+            problem_count += result?;
+            file_status.assert_reported();
+        }
+        if problem_count > 0 {
+            return Err(anyhow!("{} problems found", problem_count));
+        }
+        Ok(())
+    }
+
+    fn get_reporter(&self) -> ConsoleReporter {
+        ConsoleReporter {
+            skipped_level: Level::Summary,
+            succeeded_level: if self.quiet {
+                Level::None
+            } else {
+                Level::Summary
+            },
+            failed_level: Level::Details,
+            canceled_level: Level::Details,
+        }
+    }
+
+    /// Lint a single source file, and return the number of problems found.
+    fn lint_file(
+        &self,
+        source_path: &Path,
+        strict: bool,
+        loader: &mut Loader,
+        file_status: &mut CLIFileReporter,
+    ) -> anyhow::Result<usize> {
+        let mut file_reader = FileReader::new();
+        let lc = match loader
+            .load_for_file(source_path, &mut file_reader, &NoCancellation)?
+            .primary
+        {
+            Some(lc) => lc,
+            None => {
+                if strict {
+                    file_status.skipped("not supported", None);
+                }
+                return Ok(0);
+            }
+        };
+
+        file_status.processing();
+
+        let source = file_reader.get(source_path)?;
+        let mut graph = StackGraph::new();
+        let file = graph
+            .add_file(&source_path.to_string_lossy())
+            .expect("file not present in empty graph");
+        if let Err(err) = lc.sgl.build_stack_graph_into(
+            &mut graph,
+            file,
+            source,
+            &Variables::new(),
+            &NoCancellation,
+        ) {
+            file_status.failure(
+                "failed to build stack graph",
+                Some(&err.display_pretty(
+                    source_path,
+                    source,
+                    lc.sgl.tsg_path(),
+                    lc.sgl.tsg_source(),
+                )),
+            );
+            return Err(anyhow!(
+                "Failed to build graph for {}",
+                source_path.display()
+            ));
+        }
+
+        let diagnostics = lint_file(&graph, file);
+        if diagnostics.is_empty() {
+            file_status.success("no problems", None);
+        } else {
+            let details = diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.display(&graph))
+                .join("\n");
+            file_status.failure(&format!("{} problems", diagnostics.len()), Some(&details));
+        }
+        Ok(diagnostics.len())
+    }
+}
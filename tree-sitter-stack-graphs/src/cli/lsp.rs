@@ -9,15 +9,22 @@ use capture_it::capture;
 use clap::Args;
 use crossbeam_channel::RecvTimeoutError;
 use crossbeam_channel::Sender;
+use stack_graphs::stitching::StitchingProgress;
+use stack_graphs::storage::FileOverlay;
 use stack_graphs::storage::SQLiteReader;
 use stack_graphs::storage::SQLiteWriter;
+use stack_graphs::storage::StitchingCache;
 use stack_graphs::storage::StorageError;
+use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
 use tower_lsp::jsonrpc::Error;
 use tower_lsp::jsonrpc::ErrorCode;
 use tower_lsp::jsonrpc::Result;
@@ -29,6 +36,7 @@ use tower_lsp::LspService;
 use tower_lsp::Server;
 
 use crate::cli::index::Indexer;
+use crate::cli::query::DefinitionInfo;
 use crate::cli::query::Querier;
 use crate::cli::query::QueryError;
 use crate::cli::util::duration_from_milliseconds_str;
@@ -36,10 +44,13 @@ use crate::cli::util::duration_from_seconds_str;
 use crate::cli::util::reporter::Reporter;
 use crate::cli::util::SourcePosition;
 use crate::cli::util::SourceSpan;
+use crate::loader::FileReader;
 use crate::loader::Loader;
 use crate::AtomicCancellationFlag;
 use crate::CancelAfterDuration;
+use crate::CancellationError;
 use crate::CancellationFlag;
+use crate::NoCancellation;
 
 #[derive(Args, Clone)]
 pub struct LspArgs {
@@ -77,6 +88,9 @@ impl LspArgs {
                 db_path,
                 args: self,
                 loader: Arc::new(std::sync::Mutex::new(loader)),
+                documents: Arc::new(std::sync::Mutex::new(HashMap::new())),
+                overlays: Arc::new(std::sync::Mutex::new(HashMap::new())),
+                stitching_cache: Arc::new(std::sync::Mutex::new(StitchingCache::new())),
                 jobs: Arc::new(tokio::sync::Mutex::new(None)),
                 logger: BackendLogger { client },
             });
@@ -117,6 +131,12 @@ struct Backend {
     _client: Client,
     db_path: PathBuf,
     loader: Arc<std::sync::Mutex<Loader>>,
+    /// Contents of open documents, with their version.
+    documents: Arc<std::sync::Mutex<HashMap<PathBuf, (i32, String)>>>,
+    /// Index overlays for open documents, with the document version they were built from.
+    overlays: Arc<std::sync::Mutex<HashMap<PathBuf, (i32, FileOverlay)>>>,
+    /// Stitching results that are reused between queries.
+    stitching_cache: Arc<std::sync::Mutex<StitchingCache>>,
     args: LspArgs,
     jobs: Arc<tokio::sync::Mutex<Option<(Sender<Job>, AtomicCancellationFlag)>>>,
     logger: BackendLogger,
@@ -265,7 +285,201 @@ impl Backend {
         }
     }
 
-    async fn definitions(&self, reference: SourcePosition) -> Vec<SourceSpan> {
+    /// Index the current content of an open document into an in-memory overlay, which
+    /// takes precedence over the database when querying.
+    fn update_overlay(
+        &self,
+        path: &Path,
+        handle: Handle,
+        cancellation_flag: &dyn CancellationFlag,
+    ) {
+        let (version, source) = match self.documents.lock() {
+            Ok(documents) => match documents.get(path) {
+                Some(document) => document.clone(),
+                None => return,
+            },
+            Err(e) => {
+                handle.block_on(capture!([logger = &self.logger], async move {
+                    logger
+                        .error(format!("failed to lock documents: {}", e))
+                        .await;
+                }));
+                return;
+            }
+        };
+        if let Ok(overlays) = self.overlays.lock() {
+            if matches!(overlays.get(path), Some((v, _)) if *v >= version) {
+                // changes are already included in the current overlay
+                return;
+            }
+        }
+
+        let mut loader = match self.loader.lock() {
+            Ok(l) => l,
+            Err(e) => {
+                handle.block_on(capture!([logger = &self.logger], async move {
+                    logger.error(format!("failed to lock loader: {}", e)).await;
+                }));
+                return;
+            }
+        };
+
+        let reporter = LspReporter {
+            handle: handle.clone(),
+            logger: self.logger.clone(),
+        };
+        let file_cancellation_flag =
+            CancelAfterDuration::from_option(self.args.max_file_index_time);
+        let cancellation_flag = cancellation_flag | file_cancellation_flag.as_ref();
+        let source_root = path.parent().expect("expect file to have parent");
+        let result = Indexer::build_overlay(
+            &mut loader,
+            &reporter,
+            source_root,
+            path,
+            &source,
+            &cancellation_flag,
+        );
+
+        match result {
+            Ok(Some(overlay)) => {
+                if let Ok(mut overlays) = self.overlays.lock() {
+                    overlays.insert(path.to_path_buf(), (version, overlay));
+                }
+            }
+            // keep the previous overlay, which is better than nothing
+            Ok(None) => {}
+            Err(err) => handle.block_on(capture!([logger = &self.logger, path], async move {
+                logger
+                    .error(format!("indexing failed {}: {}", path.display(), err))
+                    .await;
+            })),
+        }
+    }
+
+    /// Record the new content of an open document, and schedule updating its overlay.
+    async fn update_document(&self, uri: &Url, version: i32, text: String) {
+        let path = match uri.to_file_path() {
+            Ok(path) => path,
+            Err(_) => {
+                self.logger
+                    .error(format!("No local path for document {}", uri))
+                    .await;
+                return;
+            }
+        };
+        let result = self
+            .documents
+            .lock()
+            .map(|mut documents| documents.insert(path.clone(), (version, text)))
+            .map_err(|e| e.to_string());
+        if let Err(e) = result {
+            self.logger
+                .error(format!("failed to lock documents: {}", e))
+                .await;
+            return;
+        }
+        let jobs = self.jobs.lock().await;
+        if let Err(e) = jobs.as_ref().unwrap().0.send(Job::UpdateOverlay(path)) {
+            self.logger
+                .error(format!("Scheduling overlay job failed: {}", e))
+                .await;
+        }
+        drop(jobs);
+    }
+
+    async fn definitions(
+        &self,
+        reference: SourcePosition,
+        token: Option<&ProgressToken>,
+    ) -> Vec<SourceSpan> {
+        self.query(token, move |querier, cancellation_flag| {
+            let result = querier.definitions(reference, cancellation_flag)?;
+            Ok(result.into_iter().flat_map(|r| r.targets).collect())
+        })
+        .await
+    }
+
+    /// Find the references of the definitions at the given position. If the position is not
+    /// on a definition, the reference at that position is resolved first, and the references
+    /// of its definitions are returned.
+    async fn references(
+        &self,
+        position: SourcePosition,
+        include_declaration: bool,
+        token: Option<&ProgressToken>,
+    ) -> Vec<SourceSpan> {
+        self.query(token, move |querier, cancellation_flag| {
+            let mut results = querier.references(position.clone(), cancellation_flag)?;
+            if results.is_empty() {
+                for definition in querier
+                    .definitions(position, cancellation_flag)?
+                    .into_iter()
+                    .flat_map(|r| r.targets)
+                {
+                    results.extend(
+                        querier.references(definition.start_position(), cancellation_flag)?,
+                    );
+                }
+            }
+            let mut spans = Vec::new();
+            for result in results {
+                if include_declaration {
+                    spans.push(result.source);
+                }
+                spans.extend(result.targets);
+            }
+            Ok(spans)
+        })
+        .await
+    }
+
+    /// Get information about the definitions at the given position. If the position is not on
+    /// a definition, the reference at that position is resolved, and information about its
+    /// definitions is returned.
+    async fn definition_infos(&self, position: SourcePosition) -> Vec<DefinitionInfo> {
+        self.query(None, move |querier, cancellation_flag| {
+            let mut infos = querier.definitions_at(position.clone())?;
+            if infos.is_empty() {
+                for definition in querier
+                    .definitions(position, cancellation_flag)?
+                    .into_iter()
+                    .flat_map(|r| r.targets)
+                {
+                    infos.extend(querier.definitions_at(definition.start_position())?);
+                }
+            }
+            Ok(infos)
+        })
+        .await
+    }
+
+    async fn document_definitions(&self, path: PathBuf) -> Vec<DefinitionInfo> {
+        self.query(None, move |querier, _| querier.definitions_in_file(&path))
+            .await
+    }
+
+    /// Runs a query against the database, logging any errors and returning an empty result
+    /// in case of failure. If a progress token is given, work done progress for the query,
+    /// including stitching progress, is reported to the client.
+    async fn query<T, Q>(&self, token: Option<&ProgressToken>, query: Q) -> Vec<T>
+    where
+        Q: FnOnce(&mut Querier, &dyn CancellationFlag) -> std::result::Result<Vec<T>, QueryError>,
+        Q: Send + 'static,
+        T: Send + 'static,
+    {
+        self.begin_progress(token, "Querying").await;
+        let result = self.run_query(token, query).await;
+        self.end_progress(token).await;
+        result
+    }
+
+    async fn run_query<T, Q>(&self, token: Option<&ProgressToken>, query: Q) -> Vec<T>
+    where
+        Q: FnOnce(&mut Querier, &dyn CancellationFlag) -> std::result::Result<Vec<T>, QueryError>,
+        Q: Send + 'static,
+        T: Send + 'static,
+    {
         let mut db = match SQLiteReader::open(&self.db_path) {
             Ok(db) => db,
             Err(err) => {
@@ -280,28 +494,144 @@ impl Backend {
             }
         };
 
+        let result = self
+            .overlays
+            .lock()
+            .map(|overlays| {
+                for (_, overlay) in overlays.values() {
+                    db.add_overlay(overlay.clone());
+                }
+            })
+            .map_err(|e| e.to_string());
+        if let Err(e) = result {
+            self.logger
+                .error(format!("failed to lock overlays: {}", e))
+                .await;
+        }
+
+        // loading paths and stitching block, so run the query outside of the async runtime
+        let backend = self.clone();
+        let token = token.cloned();
+        let result =
+            tokio::task::spawn_blocking(move || backend.run_blocking_query(db, token, query)).await;
+        let (result, pending_reports) = match result {
+            Ok(result) => result,
+            Err(err) => {
+                self.logger.error(format!("query failed {}", err)).await;
+                return Vec::default();
+            }
+        };
+        // progress reports must not arrive after the progress has ended
+        for report in pending_reports {
+            let _ = report.await;
+        }
+        match result {
+            Ok(result) => result,
+            Err(QueryError::Cancelled(err))
+            | Err(QueryError::StorageError(StorageError::Cancelled(err))) => {
+                self.logger.error(format!("query timed out: {}", err)).await;
+                return Vec::default();
+            }
+            Err(err) => {
+                self.logger.error(format!("query failed {}", err)).await;
+                return Vec::default();
+            }
+        }
+    }
+
+    /// Runs a query on the current thread, returning its result and the tasks sending progress
+    /// reports for it, which may not have finished yet.
+    fn run_blocking_query<T, Q>(
+        &self,
+        mut db: SQLiteReader,
+        token: Option<ProgressToken>,
+        query: Q,
+    ) -> (std::result::Result<Vec<T>, QueryError>, Vec<JoinHandle<()>>)
+    where
+        Q: FnOnce(&mut Querier, &dyn CancellationFlag) -> std::result::Result<Vec<T>, QueryError>,
+    {
         let handle = Handle::current();
         let reporter = LspReporter {
             handle: handle.clone(),
             logger: self.logger.clone(),
         };
+        let mut pending_reports = Vec::new();
+        // keep the cache locked during the query, so that concurrent queries do not lose entries
+        let mut stitching_cache = self.stitching_cache.lock();
+        if let Ok(stitching_cache) = &mut stitching_cache {
+            db.set_stitching_cache(std::mem::take(&mut **stitching_cache));
+        }
         let result = {
             let mut querier = Querier::new(&mut db, &reporter);
+            // use the shadowing policy of the language of each reference, like the indexer does,
+            // but do not wait for index jobs that use the loader and fall back to the default
+            let loader = self.loader.clone();
+            let mut shadowing_policies = HashMap::new();
+            querier.set_shadowing_policy_selector(move |path| {
+                shadowing_policies
+                    .entry(path.to_path_buf())
+                    .or_insert_with(|| {
+                        let mut loader = loader.try_lock().ok()?;
+                        let mut file_reader = FileReader::new();
+                        let lcs = loader
+                            .load_for_file(path, &mut file_reader, &NoCancellation)
+                            .ok()?;
+                        lcs.primary.map(|lc| lc.shadowing_policy.clone())
+                    })
+                    .clone()
+            });
             let cancellation_flag = CancelAfterDuration::from_option(self.args.max_query_time);
-            querier.definitions(reference, cancellation_flag.as_ref())
-        };
-        match result {
-            Ok(result) => result.into_iter().flat_map(|r| r.targets).collect(),
-            Err(QueryError::Cancelled(at)) => {
-                self.logger
-                    .error(format!("query timed out at {}", at,))
-                    .await;
-                return Vec::default();
-            }
-            Err(err) => {
-                self.logger.error(format!("query failed {}", err)).await;
-                return Vec::default();
+            match &token {
+                Some(token) => {
+                    let cancellation_flag = ProgressReportingCancellationFlag::new(
+                        cancellation_flag.as_ref(),
+                        &self._client,
+                        &handle,
+                        token,
+                    );
+                    let result = query(&mut querier, &cancellation_flag);
+                    pending_reports = cancellation_flag.into_pending_reports();
+                    result
+                }
+                None => query(&mut querier, cancellation_flag.as_ref()),
             }
+        };
+        if let (Some(cache), Ok(stitching_cache)) =
+            (db.take_stitching_cache(), &mut stitching_cache)
+        {
+            **stitching_cache = cache;
+        }
+        (result, pending_reports)
+    }
+
+    async fn begin_progress(&self, token: Option<&ProgressToken>, title: &str) {
+        if let Some(token) = token {
+            self._client
+                .send_notification::<Progress>(ProgressParams {
+                    token: token.clone(),
+                    value: ProgressParamsValue::WorkDone(WorkDoneProgress::Begin(
+                        WorkDoneProgressBegin {
+                            title: title.to_string(),
+                            ..Default::default()
+                        },
+                    )),
+                })
+                .await;
+        }
+    }
+
+    async fn end_progress(&self, token: Option<&ProgressToken>) {
+        if let Some(token) = token {
+            self._client
+                .send_notification::<Progress>(ProgressParams {
+                    token: token.clone(),
+                    value: ProgressParamsValue::WorkDone(WorkDoneProgress::End(
+                        WorkDoneProgressEnd {
+                            ..Default::default()
+                        },
+                    )),
+                })
+                .await;
         }
     }
 }
@@ -342,8 +672,17 @@ impl LanguageServer for Backend {
                         work_done_progress: true.into(),
                     },
                 })),
+                references_provider: Some(OneOf::Right(ReferencesOptions {
+                    work_done_progress_options: WorkDoneProgressOptions {
+                        work_done_progress: true.into(),
+                    },
+                })),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                document_symbol_provider: Some(OneOf::Left(true)),
                 text_document_sync: Some(
                     TextDocumentSyncOptions {
+                        open_close: Some(true),
+                        change: Some(TextDocumentSyncKind::FULL),
                         save: Some(true.into()),
                         ..Default::default()
                     }
@@ -372,6 +711,46 @@ impl LanguageServer for Backend {
             .await;
     }
 
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        self.logger
+            .info(format!("Opened document {}", params.text_document.uri))
+            .await;
+        self.update_document(
+            &params.text_document.uri,
+            params.text_document.version,
+            params.text_document.text,
+        )
+        .await;
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        // we use full document sync, so the last change contains the complete document
+        let text = match params.content_changes.into_iter().last() {
+            Some(change) => change.text,
+            None => return,
+        };
+        self.update_document(
+            &params.text_document.uri,
+            params.text_document.version,
+            text,
+        )
+        .await;
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        self.logger
+            .info(format!("Closed document {}", params.text_document.uri))
+            .await;
+        if let Ok(path) = params.text_document.uri.to_file_path() {
+            if let Ok(mut documents) = self.documents.lock() {
+                documents.remove(&path);
+            }
+            if let Ok(mut overlays) = self.overlays.lock() {
+                overlays.remove(&path);
+            }
+        }
+    }
+
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
         let jobs = self.jobs.lock().await;
         self.logger
@@ -407,19 +786,6 @@ impl LanguageServer for Backend {
             ))
             .await;
 
-        if let Some(token) = &params.work_done_progress_params.work_done_token {
-            self._client
-                .send_notification::<Progress>(ProgressParams {
-                    token: token.clone(),
-                    value: ProgressParamsValue::WorkDone(WorkDoneProgress::Begin(
-                        WorkDoneProgressBegin {
-                            title: "Querying".to_string(),
-                            ..Default::default()
-                        },
-                    )),
-                })
-                .await;
-        }
         let path = match params
             .text_document_position_params
             .text_document
@@ -441,7 +807,10 @@ impl LanguageServer for Backend {
         let column = params.text_document_position_params.position.character as usize;
         let reference = SourcePosition { path, line, column };
         let locations = self
-            .definitions(reference)
+            .definitions(
+                reference,
+                params.work_done_progress_params.work_done_token.as_ref(),
+            )
             .await
             .into_iter()
             .filter_map(|l| l.try_into_location().ok())
@@ -456,18 +825,6 @@ impl LanguageServer for Backend {
                 params.text_document_position_params.position.character + 1
             ))
             .await;
-        if let Some(token) = &params.work_done_progress_params.work_done_token {
-            self._client
-                .send_notification::<Progress>(ProgressParams {
-                    token: token.clone(),
-                    value: ProgressParamsValue::WorkDone(WorkDoneProgress::End(
-                        WorkDoneProgressEnd {
-                            ..Default::default()
-                        },
-                    )),
-                })
-                .await;
-        }
 
         match locations.len() {
             0 => Ok(None),
@@ -476,6 +833,133 @@ impl LanguageServer for Backend {
         }
     }
 
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        self.logger
+            .info(format!(
+                "Find references {}:{}:{}",
+                params.text_document_position.text_document.uri,
+                params.text_document_position.position.line + 1,
+                params.text_document_position.position.character + 1
+            ))
+            .await;
+
+        let path = match params
+            .text_document_position
+            .text_document
+            .uri
+            .to_file_path()
+        {
+            Ok(path) => path,
+            Err(_) => {
+                self.logger
+                    .error(format!(
+                        "Not a supported file path: {}",
+                        params.text_document_position.text_document.uri,
+                    ))
+                    .await;
+                return Ok(None);
+            }
+        };
+        let line = params.text_document_position.position.line as usize;
+        let column = params.text_document_position.position.character as usize;
+        let position = SourcePosition { path, line, column };
+        let locations = self
+            .references(
+                position,
+                params.context.include_declaration,
+                params.work_done_progress_params.work_done_token.as_ref(),
+            )
+            .await
+            .into_iter()
+            .filter_map(|l| l.try_into_location().ok())
+            .collect::<Vec<_>>();
+
+        self.logger
+            .info(format!(
+                "Found {} references for {}:{}:{}",
+                locations.len(),
+                params.text_document_position.text_document.uri,
+                params.text_document_position.position.line + 1,
+                params.text_document_position.position.character + 1
+            ))
+            .await;
+
+        match locations.len() {
+            0 => Ok(None),
+            _ => Ok(Some(locations)),
+        }
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let path = match params
+            .text_document_position_params
+            .text_document
+            .uri
+            .to_file_path()
+        {
+            Ok(path) => path,
+            Err(_) => {
+                self.logger
+                    .error(format!(
+                        "Not a supported file path: {}",
+                        params.text_document_position_params.text_document.uri,
+                    ))
+                    .await;
+                return Ok(None);
+            }
+        };
+        let line = params.text_document_position_params.position.line as usize;
+        let column = params.text_document_position_params.position.character as usize;
+        let position = SourcePosition { path, line, column };
+        let infos = self.definition_infos(position).await;
+        if infos.is_empty() {
+            return Ok(None);
+        }
+
+        let value = infos
+            .iter()
+            .map(DefinitionInfo::to_markdown)
+            .collect::<Vec<_>>()
+            .join("\n\n---\n\n");
+        Ok(Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: None,
+        }))
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        self.logger
+            .info(format!("Document symbols {}", params.text_document.uri))
+            .await;
+
+        let path = match params.text_document.uri.to_file_path() {
+            Ok(path) => path,
+            Err(_) => {
+                self.logger
+                    .error(format!(
+                        "Not a supported file path: {}",
+                        params.text_document.uri,
+                    ))
+                    .await;
+                return Ok(None);
+            }
+        };
+        let symbols = self
+            .document_definitions(path)
+            .await
+            .into_iter()
+            .map(DefinitionInfo::into_document_symbol)
+            .collect::<Vec<_>>();
+
+        Ok(Some(DocumentSymbolResponse::Nested(symbols)))
+    }
+
     async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
         let jobs = self.jobs.lock().await;
         for folder in &params.event.removed {
@@ -583,6 +1067,7 @@ impl<T> FromAnyhowError<T> for std::result::Result<T, anyhow::Error> {
 pub enum Job {
     IndexPath(PathBuf),
     CleanPath(PathBuf),
+    UpdateOverlay(PathBuf),
 }
 
 impl Job {
@@ -590,6 +1075,80 @@ impl Job {
         match self {
             Self::IndexPath(path) => backend.index(&path, handle, cancellation_flag),
             Self::CleanPath(path) => backend.clean(&path, handle, cancellation_flag),
+            Self::UpdateOverlay(path) => backend.update_overlay(&path, handle, cancellation_flag),
+        }
+    }
+}
+
+/// A cancellation flag that sends the stitching progress it receives to the client, as work done
+/// progress reports for the given token. Reports are sent at most once per interval.
+struct ProgressReportingCancellationFlag<'a> {
+    inner: &'a dyn CancellationFlag,
+    client: Client,
+    handle: Handle,
+    token: ProgressToken,
+    last_report: Mutex<Option<Instant>>,
+    reports: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl<'a> ProgressReportingCancellationFlag<'a> {
+    const INTERVAL: Duration = Duration::from_millis(250);
+
+    fn new(
+        inner: &'a dyn CancellationFlag,
+        client: &Client,
+        handle: &Handle,
+        token: &ProgressToken,
+    ) -> Self {
+        Self {
+            inner,
+            client: client.clone(),
+            handle: handle.clone(),
+            token: token.clone(),
+            last_report: Mutex::new(None),
+            reports: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the tasks sending reports, which may not have finished yet.
+    fn into_pending_reports(self) -> Vec<JoinHandle<()>> {
+        self.reports.into_inner().unwrap_or_default()
+    }
+}
+
+impl CancellationFlag for ProgressReportingCancellationFlag<'_> {
+    fn check(&self, at: &'static str) -> std::result::Result<(), CancellationError> {
+        self.inner.check(at)
+    }
+
+    fn report_progress(&self, at: &'static str, progress: &StitchingProgress) {
+        self.inner.report_progress(at, progress);
+        let mut last_report = match self.last_report.lock() {
+            Ok(last_report) => last_report,
+            Err(_) => return,
+        };
+        if matches!(*last_report, Some(last) if last.elapsed() < Self::INTERVAL) {
+            return;
+        }
+        *last_report = Some(Instant::now());
+        let client = self.client.clone();
+        let token = self.token.clone();
+        let message = format!("{}: {}", at, progress);
+        let report = self.handle.spawn(async move {
+            client
+                .send_notification::<Progress>(ProgressParams {
+                    token,
+                    value: ProgressParamsValue::WorkDone(WorkDoneProgress::Report(
+                        WorkDoneProgressReport {
+                            message: Some(message),
+                            ..Default::default()
+                        },
+                    )),
+                })
+                .await;
+        });
+        if let Ok(mut reports) = self.reports.lock() {
+            reports.push(report);
         }
     }
 }
@@ -636,7 +1195,12 @@ impl Reporter for LspReporter {
 
 impl SourceSpan {
     fn try_into_location(self) -> std::result::Result<Location, ()> {
-        let uri = Url::from_file_path(self.path)?;
+        let uri = Url::from_file_path(&self.path)?;
+        let range = self.to_range();
+        Ok(Location { uri, range })
+    }
+
+    fn to_range(&self) -> Range {
         let start = Position {
             line: self.span.start.line as u32,
             character: self.span.start.column.grapheme_offset as u32,
@@ -645,7 +1209,66 @@ impl SourceSpan {
             line: self.span.end.line as u32,
             character: self.span.end.column.grapheme_offset as u32,
         };
-        let range = Range { start, end };
-        Ok(Location { uri, range })
+        Range { start, end }
+    }
+}
+
+impl DefinitionInfo {
+    fn to_markdown(&self) -> String {
+        let mut value = format!(
+            "**{}**",
+            self.fully_qualified_name.as_ref().unwrap_or(&self.symbol)
+        );
+        if let Some(syntax_type) = &self.syntax_type {
+            value.push_str(&format!(" _({})_", syntax_type));
+        }
+        if let Some(containing_line) = &self.containing_line {
+            value.push_str(&format!("\n\n```\n{}\n```", containing_line.trim()));
+        }
+        value
+    }
+
+    fn into_document_symbol(self) -> DocumentSymbol {
+        let selection_range = self.span.to_range();
+        // The range must contain the selection range, but the definiens does not necessarily
+        // include the name of the definition.
+        let range = match &self.definiens_span {
+            Some(definiens_span) => {
+                let definiens_range = definiens_span.to_range();
+                Range {
+                    start: std::cmp::min(selection_range.start, definiens_range.start),
+                    end: std::cmp::max(selection_range.end, definiens_range.end),
+                }
+            }
+            None => selection_range,
+        };
+        let kind = match self.syntax_type.as_deref() {
+            Some("module") => SymbolKind::MODULE,
+            Some("namespace") => SymbolKind::NAMESPACE,
+            Some("package") => SymbolKind::PACKAGE,
+            Some("class") => SymbolKind::CLASS,
+            Some("method") => SymbolKind::METHOD,
+            Some("property") => SymbolKind::PROPERTY,
+            Some("field") => SymbolKind::FIELD,
+            Some("constructor") => SymbolKind::CONSTRUCTOR,
+            Some("enum") => SymbolKind::ENUM,
+            Some("interface") => SymbolKind::INTERFACE,
+            Some("function") => SymbolKind::FUNCTION,
+            Some("constant") => SymbolKind::CONSTANT,
+            Some("struct") => SymbolKind::STRUCT,
+            Some("type_parameter") => SymbolKind::TYPE_PARAMETER,
+            _ => SymbolKind::VARIABLE,
+        };
+        #[allow(deprecated)]
+        DocumentSymbol {
+            name: self.symbol,
+            detail: self.fully_qualified_name,
+            kind,
+            tags: None,
+            deprecated: None,
+            range,
+            selection_range,
+            children: None,
+        }
     }
 }
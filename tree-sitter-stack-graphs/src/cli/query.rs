@@ -5,13 +5,31 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use clap::Args;
 use clap::Parser;
 use clap::Subcommand;
+use clap::ValueEnum;
 use clap::ValueHint;
+use controlled_option::ControlledOption;
+use lsp_positions::Span;
+use serde_json::json;
+use serde_json::Value;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::DebugInfo;
+use stack_graphs::graph::Edge;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPath;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::partial::PartialScopedSymbol;
+use stack_graphs::partial::PartialSymbolStack;
+use stack_graphs::shadowing::EdgePrecedence;
+use stack_graphs::shadowing::ShadowingPolicy;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
 use stack_graphs::stitching::Stats as StitchingStats;
 use stack_graphs::stitching::StitcherConfig;
@@ -20,15 +38,20 @@ use stack_graphs::storage::SQLiteReader;
 use thiserror::Error;
 use tree_sitter_graph::parse_error::Excerpt;
 
+use crate::cli::util::database_stats_to_json;
 use crate::cli::util::print_database_stats;
 use crate::cli::util::print_stitching_stats;
 use crate::cli::util::reporter::ConsoleReporter;
+use crate::cli::util::reporter::JsonReporter;
 use crate::cli::util::reporter::Reporter;
 use crate::cli::util::sha1;
+use crate::cli::util::stitching_stats_to_json;
 use crate::cli::util::wait_for_input;
+use crate::cli::util::OutputFormat;
 use crate::cli::util::SourcePosition;
 use crate::cli::util::SourceSpan;
 use crate::loader::FileReader;
+use crate::loader::Loader;
 use crate::CancellationFlag;
 use crate::NoCancellation;
 
@@ -41,39 +64,122 @@ pub struct QueryArgs {
     #[clap(long)]
     pub stats: bool,
 
+    /// Sort results in canonical order, by file name and node, instead of the order in which
+    /// they are found.
+    #[clap(long)]
+    pub sorted: bool,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
     #[clap(subcommand)]
     target: Target,
 }
 
 impl QueryArgs {
-    pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
+    pub fn run(self, db_path: &Path, mut loader: Loader) -> anyhow::Result<()> {
         if self.wait_at_start {
             wait_for_input()?;
         }
-        let mut db = SQLiteReader::open(&db_path)?;
-        let stitching_stats = self.target.run(&mut db, self.stats)?;
-        if self.stats {
-            println!();
-            print_stitching_stats(stitching_stats);
-            println!();
-            print_database_stats(db.stats());
+        let format = match (&self.target, self.format) {
+            (
+                Target::Definition(Definition {
+                    explain: Some(ExplainFormat::Json),
+                    ..
+                }),
+                OutputFormat::Text,
+            ) => OutputFormat::Json,
+            (_, format) => format,
+        };
+        let json_reporter = match format.json_reporter() {
+            Some(json_reporter) => json_reporter,
+            None => {
+                let mut db = SQLiteReader::open(&db_path)?;
+                let stitching_stats =
+                    self.target
+                        .run(&mut db, &mut loader, self.stats, self.sorted)?;
+                if self.stats {
+                    println!();
+                    print_stitching_stats(stitching_stats);
+                    println!();
+                    print_database_stats(db.stats());
+                }
+                return Ok(());
+            }
+        };
+
+        let result = SQLiteReader::open(db_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut db| {
+                let stitching_stats = self.target.run_json(
+                    &mut db,
+                    &mut loader,
+                    self.stats,
+                    self.sorted,
+                    &json_reporter,
+                )?;
+                if self.stats {
+                    json_reporter.record(json!({
+                        "type": "stats",
+                        "stitching": stitching_stats_to_json(stitching_stats),
+                        "database": database_stats_to_json(db.stats()),
+                    }));
+                }
+                Ok(())
+            });
+        if let Err(err) = &result {
+            json_reporter.error(err);
         }
-        Ok(())
+        json_reporter.finish()?;
+        result
     }
 }
 
 #[derive(Subcommand)]
 pub enum Target {
     Definition(Definition),
+    References(References),
+    Symbol(Symbol),
 }
 
 impl Target {
-    fn run(self, db: &mut SQLiteReader, collect_stats: bool) -> anyhow::Result<StitchingStats> {
+    fn run(
+        self,
+        db: &mut SQLiteReader,
+        loader: &mut Loader,
+        collect_stats: bool,
+        sort_results: bool,
+    ) -> anyhow::Result<StitchingStats> {
         let reporter = ConsoleReporter::details();
         let mut querier = Querier::new(db, &reporter);
+        querier.set_loader(loader);
         querier.set_collect_stats(collect_stats);
+        querier.set_sort_results(sort_results);
         match self {
             Self::Definition(cmd) => cmd.run(&mut querier)?,
+            Self::References(cmd) => cmd.run(&mut querier)?,
+            Self::Symbol(cmd) => cmd.run(&mut querier)?,
+        }
+        Ok(querier.into_stats())
+    }
+
+    fn run_json(
+        self,
+        db: &mut SQLiteReader,
+        loader: &mut Loader,
+        collect_stats: bool,
+        sort_results: bool,
+        reporter: &JsonReporter,
+    ) -> anyhow::Result<StitchingStats> {
+        let mut querier = Querier::new(db, reporter);
+        querier.set_loader(loader);
+        querier.set_collect_stats(collect_stats);
+        querier.set_sort_results(sort_results);
+        match self {
+            Self::Definition(cmd) => cmd.run_json(&mut querier, reporter)?,
+            Self::References(cmd) => cmd.run_json(&mut querier, reporter)?,
+            Self::Symbol(cmd) => cmd.run_json(&mut querier, reporter)?,
         }
         Ok(querier.into_stats())
     }
@@ -89,11 +195,33 @@ pub struct Definition {
         value_parser,
     )]
     pub references: Vec<SourcePosition>,
+
+    /// Explain how each definition was found, by showing the path from the reference to the
+    /// definition. The explanation is printed as text, or as JSON if the format is json, which
+    /// is the same as using `--format json`. With JSON output, the explanations are included
+    /// in the result records.
+    #[clap(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text"
+    )]
+    pub explain: Option<ExplainFormat>,
+}
+
+/// Output format of path explanations
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum ExplainFormat {
+    Text,
+    Json,
 }
 
 impl Definition {
     pub fn run(self, querier: &mut Querier) -> anyhow::Result<()> {
         let cancellation_flag = NoCancellation;
+        querier.set_explain(self.explain.is_some());
         let mut file_reader = FileReader::new();
         for mut reference in self.references {
             reference.canonicalize()?;
@@ -109,6 +237,7 @@ impl Definition {
                 QueryResult {
                     source: reference,
                     targets: definitions,
+                    explanations,
                 },
             ) in results.into_iter().enumerate()
             {
@@ -132,6 +261,7 @@ impl Definition {
                     1 => println!("{}has definition", " ".repeat(indent)),
                     n => println!("{}has {} definitions", " ".repeat(indent), n),
                 }
+                let mut explanations = explanations.into_iter().flatten();
                 for definition in definitions.into_iter() {
                     print!(
                         "{}",
@@ -143,17 +273,194 @@ impl Definition {
                             indent
                         )
                     );
+                    if let Some(explanation) = explanations.next() {
+                        explanation.print(indent);
+                    }
                 }
             }
         }
         Ok(())
     }
+
+    pub fn run_json(self, querier: &mut Querier, reporter: &JsonReporter) -> anyhow::Result<()> {
+        let cancellation_flag = NoCancellation;
+        querier.set_explain(self.explain.is_some());
+        for mut reference in self.references {
+            reference.canonicalize()?;
+            for result in querier.definitions(reference, &cancellation_flag)? {
+                reporter.record(result.to_json("definition"));
+            }
+        }
+        Ok(())
+    }
 }
 
+#[derive(Parser)]
+pub struct References {
+    /// Definition source positions, formatted as PATH:LINE:COLUMN.
+    #[clap(
+        value_name = "SOURCE_POSITION",
+        required = true,
+        value_hint = ValueHint::AnyPath,
+        value_parser,
+    )]
+    pub definitions: Vec<SourcePosition>,
+}
+
+impl References {
+    pub fn run_json(self, querier: &mut Querier, reporter: &JsonReporter) -> anyhow::Result<()> {
+        let cancellation_flag = NoCancellation;
+        for mut definition in self.definitions {
+            definition.canonicalize()?;
+            for result in querier.references(definition, &cancellation_flag)? {
+                reporter.record(result.to_json("reference"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn run(self, querier: &mut Querier) -> anyhow::Result<()> {
+        let cancellation_flag = NoCancellation;
+        let mut file_reader = FileReader::new();
+        for mut definition in self.definitions {
+            definition.canonicalize()?;
+
+            let results = querier.references(definition.clone(), &cancellation_flag)?;
+            let numbered = results.len() > 1;
+            let indent = if numbered { 6 } else { 0 };
+            if numbered {
+                println!("found {} definitions at position", results.len());
+            }
+            for (
+                idx,
+                QueryResult {
+                    source: definition,
+                    targets: references,
+                    ..
+                },
+            ) in results.into_iter().enumerate()
+            {
+                if numbered {
+                    println!("{:4}: queried definition", idx);
+                } else {
+                    println!("queried definition");
+                }
+                println!(
+                    "{}",
+                    Excerpt::from_source(
+                        &definition.path,
+                        file_reader.get(&definition.path).unwrap_or_default(),
+                        definition.first_line(),
+                        definition.first_line_column_range(),
+                        indent
+                    )
+                );
+                match references.len() {
+                    0 => println!("{}has no references", " ".repeat(indent)),
+                    1 => println!("{}has reference", " ".repeat(indent)),
+                    n => println!("{}has {} references", " ".repeat(indent), n),
+                }
+                for reference in references.into_iter() {
+                    print!(
+                        "{}",
+                        Excerpt::from_source(
+                            &reference.path,
+                            file_reader.get(&reference.path).unwrap_or_default(),
+                            reference.first_line(),
+                            reference.first_line_column_range(),
+                            indent
+                        )
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct Symbol {
+    /// Qualified name, whose components are separated by the delimiter.
+    #[clap(value_name = "NAME", required = true)]
+    pub names: Vec<String>,
+
+    /// Delimiter that separates the components of the qualified name.
+    #[clap(long, default_value = ".")]
+    pub delimiter: String,
+
+    /// Do not push the delimiter as a symbol between the components of the qualified name.
+    /// By default, the delimiter is pushed, because many languages use it to select members.
+    #[clap(long)]
+    pub drop_delimiters: bool,
+}
+
+impl Symbol {
+    pub fn run_json(self, querier: &mut Querier, reporter: &JsonReporter) -> anyhow::Result<()> {
+        let cancellation_flag = NoCancellation;
+        for name in &self.names {
+            let symbols = self.symbols(name);
+            let definitions = querier.definitions_of_symbols(&symbols, &cancellation_flag)?;
+            reporter.record(json!({
+                "type": "result",
+                "query": "symbol",
+                "name": name,
+                "targets": definitions.iter().map(SourceSpan::to_json).collect::<Vec<_>>(),
+            }));
+        }
+        Ok(())
+    }
+
+    pub fn run(self, querier: &mut Querier) -> anyhow::Result<()> {
+        let cancellation_flag = NoCancellation;
+        let mut file_reader = FileReader::new();
+        for name in &self.names {
+            let symbols = self.symbols(name);
+            let definitions = querier.definitions_of_symbols(&symbols, &cancellation_flag)?;
+            match definitions.len() {
+                0 => println!("symbol {} has no definitions", name),
+                1 => println!("symbol {} has definition", name),
+                n => println!("symbol {} has {} definitions", name, n),
+            }
+            for definition in definitions.into_iter() {
+                print!(
+                    "{}",
+                    Excerpt::from_source(
+                        &definition.path,
+                        file_reader.get(&definition.path).unwrap_or_default(),
+                        definition.first_line(),
+                        definition.first_line_column_range(),
+                        0
+                    )
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn symbols<'b>(&'b self, name: &'b str) -> Vec<&'b str> {
+        let mut symbols = Vec::new();
+        for component in name.split(&self.delimiter) {
+            if !symbols.is_empty() && !self.drop_delimiters {
+                symbols.push(self.delimiter.as_str());
+            }
+            symbols.push(component);
+        }
+        symbols
+    }
+}
+
+/// Selects the shadowing policy for references in a file, or `None` to use the default policy.
+type ShadowingPolicySelector<'a> =
+    Box<dyn FnMut(&Path) -> Option<Arc<dyn ShadowingPolicy + Send + Sync>> + 'a>;
+
 pub struct Querier<'a> {
     db: &'a mut SQLiteReader,
     reporter: &'a dyn Reporter,
     stats: Option<StitchingStats>,
+    explain: bool,
+    sort_results: bool,
+    shadowing_policy: Arc<dyn ShadowingPolicy + Send + Sync>,
+    shadowing_policy_selector: Option<ShadowingPolicySelector<'a>>,
 }
 
 impl<'a> Querier<'a> {
@@ -162,9 +469,66 @@ impl<'a> Querier<'a> {
             db,
             reporter,
             stats: None,
+            explain: false,
+            sort_results: false,
+            shadowing_policy: Arc::new(EdgePrecedence),
+            shadowing_policy_selector: None,
         }
     }
 
+    /// Sets the policy that decides which of the definitions found for a reference are visible.
+    /// The default is [`EdgePrecedence`][]. If a loader or selector is set, this policy is only
+    /// used for references in files it selects no policy for, and for queries without a reference.
+    pub fn set_shadowing_policy(
+        &mut self,
+        shadowing_policy: Arc<dyn ShadowingPolicy + Send + Sync>,
+    ) {
+        self.shadowing_policy = shadowing_policy;
+    }
+
+    /// Sets the loader that selects the shadowing policy for a reference, which is the policy of
+    /// the language of the file containing the reference. This is the same policy that is used to
+    /// compute the resolutions stored by the indexer, so that stored and computed results agree.
+    pub fn set_loader(&mut self, loader: &'a mut Loader) {
+        self.set_shadowing_policy_selector(move |path| {
+            let mut file_reader = FileReader::new();
+            let lcs = loader
+                .load_for_file(path, &mut file_reader, &NoCancellation)
+                .ok()?;
+            lcs.primary.map(|lc| lc.shadowing_policy.clone())
+        });
+    }
+
+    /// Sets a function that selects the shadowing policy for references in a file. This can be
+    /// used instead of [`set_loader`][Self::set_loader] when the loader is not always available,
+    /// in which case the function returns `None` and the default policy is used.
+    pub fn set_shadowing_policy_selector<F>(&mut self, selector: F)
+    where
+        F: FnMut(&Path) -> Option<Arc<dyn ShadowingPolicy + Send + Sync>> + 'a,
+    {
+        self.shadowing_policy_selector = Some(Box::new(selector));
+    }
+
+    /// Returns the shadowing policy for references in the given file.
+    fn shadowing_policy_for_file(&mut self, path: &Path) -> Arc<dyn ShadowingPolicy + Send + Sync> {
+        self.shadowing_policy_selector
+            .as_mut()
+            .and_then(|selector| selector(path))
+            .unwrap_or_else(|| self.shadowing_policy.clone())
+    }
+
+    /// Sets whether query results include an explanation of the path for each target.
+    pub fn set_explain(&mut self, explain: bool) {
+        self.explain = explain;
+    }
+
+    /// Sets whether query results are returned in canonical order, see
+    /// [`PartialPath::cmp_canonical`][stack_graphs::partial::PartialPath::cmp_canonical], instead
+    /// of the order in which they are found.
+    pub fn set_sort_results(&mut self, sort_results: bool) {
+        self.sort_results = sort_results;
+    }
+
     pub fn set_collect_stats(&mut self, collect_stats: bool) {
         if !collect_stats {
             self.stats = None;
@@ -179,25 +543,11 @@ impl<'a> Querier<'a> {
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<Vec<QueryResult>> {
         let log_path = PathBuf::from(reference.to_string());
+        let shadowing_policy = self.shadowing_policy_for_file(&reference.path);
 
-        let mut file_reader = FileReader::new();
-        let tag = file_reader.get(&reference.path).ok().map(sha1);
-        match self
-            .db
-            .status_for_file(&reference.path.to_string_lossy(), tag.as_ref())?
-        {
-            FileStatus::Indexed => {}
-            _ => {
-                self.reporter.started(&log_path);
-                self.reporter.failed(&log_path, "file not indexed", None);
-                return Ok(Default::default());
-            }
+        if !self.load_indexed_graph(&reference.path, &log_path)? {
+            return Ok(Default::default());
         }
-
-        self.reporter.started(&log_path);
-
-        self.db
-            .load_graph_for_file(&reference.path.to_string_lossy())?;
         let (graph, _, _) = self.db.get();
 
         let starting_nodes = reference.iter_references(graph).collect::<Vec<_>>();
@@ -214,13 +564,43 @@ impl<'a> Querier<'a> {
                 span,
             };
 
+            // use the stored resolution if it is up to date, unless paths must be explained
+            if !self.explain {
+                if let Some(mut definition_nodes) = self.db.load_resolved_definitions(node)? {
+                    let (graph, _, _) = self.db.get();
+                    if self.sort_results {
+                        definition_nodes.sort_by_key(|n| {
+                            let id = graph[*n].id();
+                            (id.file().map(|f| graph[f].name()), id.local_id())
+                        });
+                    }
+                    let definitions = definition_nodes
+                        .into_iter()
+                        .filter_map(|n| {
+                            let span = graph.source_info(n)?.span.clone();
+                            let file = graph[n].id().file()?;
+                            Some(SourceSpan {
+                                path: PathBuf::from(graph[file].name()),
+                                span,
+                            })
+                        })
+                        .collect();
+                    result.push(QueryResult {
+                        source: reference_span,
+                        targets: definitions,
+                        explanations: None,
+                    });
+                    continue;
+                }
+            }
+
             let mut reference_paths = Vec::new();
             let stitcher_config = StitcherConfig::default()
                 // always detect similar paths, we don't know the language configurations for the data in the database
                 .with_detect_similar_paths(true)
-                .with_collect_stats(self.stats.is_some());
-            let ref_result = ForwardPartialPathStitcher::find_all_complete_partial_paths(
-                self.db,
+                .with_collect_stats(self.stats.is_some())
+                .with_sorted_results(self.sort_results);
+            let ref_result = self.db.find_all_complete_partial_paths(
                 std::iter::once(node),
                 stitcher_config,
                 &cancellation_flag,
@@ -247,32 +627,33 @@ impl<'a> Querier<'a> {
                     self.reporter.failed(&log_path, "query timed out", None);
                     return Err(err.into());
                 }
-                if reference_paths
-                    .iter()
-                    .all(|other| !other.shadows(partials, reference_path))
+                if !shadowing_policy.is_shadowed(graph, partials, reference_path, &reference_paths)
                 {
                     actual_paths.push(reference_path.clone());
                 }
             }
 
-            let definitions = actual_paths
-                .into_iter()
-                .filter_map(|path| {
-                    let span = match graph.source_info(path.end_node) {
-                        Some(p) => p.span.clone(),
-                        None => return None,
-                    };
-                    let path = match graph[path.end_node].id().file() {
-                        Some(f) => PathBuf::from(graph[f].name()),
-                        None => return None,
-                    };
-                    Some(SourceSpan { path, span })
-                })
-                .collect::<Vec<_>>();
+            let mut definitions = Vec::new();
+            let mut explanations = Vec::new();
+            for path in actual_paths {
+                let span = match graph.source_info(path.end_node) {
+                    Some(p) => p.span.clone(),
+                    None => continue,
+                };
+                let file = match graph[path.end_node].id().file() {
+                    Some(f) => PathBuf::from(graph[f].name()),
+                    None => continue,
+                };
+                if self.explain {
+                    explanations.push(Explanation::from_path(graph, partials, path));
+                }
+                definitions.push(SourceSpan { path: file, span });
+            }
 
             result.push(QueryResult {
                 source: reference_span,
                 targets: definitions,
+                explanations: self.explain.then_some(explanations),
             });
         }
 
@@ -290,6 +671,308 @@ impl<'a> Querier<'a> {
         Ok(result)
     }
 
+    /// Find all references, in all indexed files, that resolve to the definitions at the given
+    /// position.
+    ///
+    /// The references are found by stitching backwards from the definitions. Partial paths cannot
+    /// be looked up by their end node in the database, so this loads the graphs and partial paths
+    /// of all indexed files, which is costly for large databases. Only the references found in this
+    /// way are resolved forwards, to filter out the ones for which the definitions are shadowed.
+    pub fn references(
+        &mut self,
+        definition: SourcePosition,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<QueryResult>> {
+        let log_path = PathBuf::from(definition.to_string());
+
+        if !self.load_indexed_graph(&definition.path, &log_path)? {
+            return Ok(Default::default());
+        }
+        let (graph, _, _) = self.db.get();
+
+        let definition_nodes = definition.iter_definitions(graph).collect::<Vec<_>>();
+        if definition_nodes.is_empty() {
+            self.reporter
+                .cancelled(&log_path, "no definitions at location", None);
+            return Ok(Default::default());
+        }
+
+        let stitcher_config = StitcherConfig::default()
+            // always detect similar paths, we don't know the language configurations for the data in the database
+            .with_detect_similar_paths(true)
+            .with_collect_stats(self.stats.is_some())
+            .with_sorted_results(self.sort_results);
+
+        // find candidate references by stitching backwards from the definitions, which loads the
+        // paths of the files that can reach them
+        let mut reference_nodes = Vec::new();
+        let ref_result = self.db.find_all_complete_partial_paths_from_definitions(
+            definition_nodes.iter().map(|(node, _)| *node),
+            stitcher_config,
+            &cancellation_flag,
+            |_g, _ps, p| {
+                reference_nodes.push(p.start_node);
+            },
+        );
+        match ref_result {
+            Ok(ref_stats) => {
+                if let Some(stats) = &mut self.stats {
+                    *stats += ref_stats
+                }
+            }
+            Err(err) => {
+                self.reporter.failed(&log_path, "query timed out", None);
+                return Err(err.into());
+            }
+        }
+        reference_nodes.sort();
+        reference_nodes.dedup();
+
+        // resolve the candidate references, to filter out shadowed definitions, using the
+        // shadowing policy of the file of each reference
+        let mut references_by_file = BTreeMap::<String, Vec<_>>::new();
+        let (graph, _, _) = self.db.get();
+        for node in reference_nodes {
+            if let Some(file) = graph[node].file() {
+                references_by_file
+                    .entry(graph[file].name().to_string())
+                    .or_default()
+                    .push(node);
+            }
+        }
+        let mut definition_paths = Vec::new();
+        for (file, reference_nodes) in references_by_file {
+            let shadowing_policy = self.shadowing_policy_for_file(Path::new(&file));
+            let def_result =
+                ForwardPartialPathStitcher::find_all_complete_partial_paths_to_definitions(
+                    self.db,
+                    reference_nodes,
+                    definition_nodes.iter().map(|(node, _)| *node),
+                    stitcher_config,
+                    shadowing_policy.as_ref(),
+                    &cancellation_flag,
+                    |_g, _ps, p| {
+                        definition_paths.push(p.clone());
+                    },
+                );
+            match def_result {
+                Ok(def_stats) => {
+                    if let Some(stats) = &mut self.stats {
+                        *stats += def_stats
+                    }
+                }
+                Err(err) => {
+                    self.reporter.failed(&log_path, "query timed out", None);
+                    return Err(err.into());
+                }
+            }
+        }
+
+        let (graph, partials, _) = self.db.get();
+        let mut result = Vec::new();
+        for (node, span) in definition_nodes {
+            let definition_span = SourceSpan {
+                path: definition.path.clone(),
+                span,
+            };
+
+            let mut references = Vec::new();
+            let mut explanations = Vec::new();
+            for path in definition_paths.iter().filter(|path| path.end_node == node) {
+                let span = match graph.source_info(path.start_node) {
+                    Some(p) => p.span.clone(),
+                    None => continue,
+                };
+                let file = match graph[path.start_node].id().file() {
+                    Some(f) => PathBuf::from(graph[f].name()),
+                    None => continue,
+                };
+                if self.explain {
+                    explanations.push(Explanation::from_path(graph, partials, path.clone()));
+                }
+                references.push(SourceSpan { path: file, span });
+            }
+
+            result.push(QueryResult {
+                source: definition_span,
+                targets: references,
+                explanations: self.explain.then_some(explanations),
+            });
+        }
+
+        let count: usize = result.iter().map(|r| r.targets.len()).sum();
+        self.reporter.succeeded(
+            &log_path,
+            &format!(
+                "found {} references for {} definitions",
+                count,
+                result.len()
+            ),
+            None,
+        );
+
+        Ok(result)
+    }
+
+    /// Find all definitions of the qualified name represented by the given symbols, by stitching
+    /// from the root node with the symbols on the symbol stack. The first symbol is at the top of
+    /// the stack. Only definitions that are reachable from the root node can be found.
+    pub fn definitions_of_symbols(
+        &mut self,
+        symbols: &[&str],
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<SourceSpan>> {
+        let log_path = PathBuf::from(symbols.concat());
+        self.reporter.started(&log_path);
+
+        let (graph, partials, _) = self.db.get();
+        let mut symbol_stack = PartialSymbolStack::empty();
+        for symbol in symbols {
+            let symbol = graph.add_symbol(symbol);
+            symbol_stack.push_back(
+                partials,
+                PartialScopedSymbol {
+                    symbol,
+                    scopes: ControlledOption::none(),
+                },
+            );
+        }
+
+        let mut definition_paths = Vec::new();
+        let stitcher_config = StitcherConfig::default()
+            // always detect similar paths, we don't know the language configurations for the data in the database
+            .with_detect_similar_paths(true)
+            .with_collect_stats(self.stats.is_some())
+            .with_sorted_results(self.sort_results);
+        let def_result = ForwardPartialPathStitcher::find_all_definitions_of_symbol_stack(
+            self.db,
+            symbol_stack,
+            stitcher_config,
+            &cancellation_flag,
+            |_g, _ps, p| {
+                definition_paths.push(p.clone());
+            },
+        );
+        match def_result {
+            Ok(def_stats) => {
+                if let Some(stats) = &mut self.stats {
+                    *stats += def_stats
+                }
+            }
+            Err(err) => {
+                self.reporter.failed(&log_path, "query timed out", None);
+                return Err(err.into());
+            }
+        }
+
+        let (graph, partials, _) = self.db.get();
+        let mut actual_paths = Vec::new();
+        for definition_path in &definition_paths {
+            if let Err(err) = cancellation_flag.check("shadowing") {
+                self.reporter.failed(&log_path, "query timed out", None);
+                return Err(err.into());
+            }
+            if !self.shadowing_policy.is_shadowed(
+                graph,
+                partials,
+                definition_path,
+                &definition_paths,
+            ) {
+                actual_paths.push(definition_path.clone());
+            }
+        }
+
+        let result = actual_paths
+            .into_iter()
+            .filter_map(|path| {
+                let span = match graph.source_info(path.end_node) {
+                    Some(p) => p.span.clone(),
+                    None => return None,
+                };
+                let path = match graph[path.end_node].id().file() {
+                    Some(f) => PathBuf::from(graph[f].name()),
+                    None => return None,
+                };
+                Some(SourceSpan { path, span })
+            })
+            .collect::<Vec<_>>();
+
+        self.reporter.succeeded(
+            &log_path,
+            &format!("found {} definitions", result.len()),
+            None,
+        );
+
+        Ok(result)
+    }
+
+    /// Return information about the definitions at the given position.
+    pub fn definitions_at(&mut self, position: SourcePosition) -> Result<Vec<DefinitionInfo>> {
+        let log_path = PathBuf::from(position.to_string());
+
+        if !self.load_indexed_graph(&position.path, &log_path)? {
+            return Ok(Default::default());
+        }
+        let (graph, _, _) = self.db.get();
+
+        let result = position
+            .iter_definitions(graph)
+            .filter_map(|(node, _)| DefinitionInfo::from_node(graph, node))
+            .collect::<Vec<_>>();
+
+        self.reporter.succeeded(
+            &log_path,
+            &format!("found {} definitions", result.len()),
+            None,
+        );
+
+        Ok(result)
+    }
+
+    /// Return information about all definitions in the given file.
+    pub fn definitions_in_file(&mut self, path: &Path) -> Result<Vec<DefinitionInfo>> {
+        if !self.load_indexed_graph(path, path)? {
+            return Ok(Default::default());
+        }
+        let (graph, _, _) = self.db.get();
+
+        let result = graph
+            .get_file(&path.to_string_lossy())
+            .into_iter()
+            .flat_map(|file| graph.nodes_for_file(file))
+            .filter(|node| graph[*node].is_definition())
+            .filter_map(|node| DefinitionInfo::from_node(graph, node))
+            .collect::<Vec<_>>();
+
+        self.reporter
+            .succeeded(path, &format!("found {} definitions", result.len()), None);
+
+        Ok(result)
+    }
+
+    /// Load the graph for the given file, if it is indexed and up-to-date. Returns false, after
+    /// reporting the failure, if that is not the case.
+    fn load_indexed_graph(&mut self, path: &Path, log_path: &Path) -> Result<bool> {
+        let mut file_reader = FileReader::new();
+        let tag = file_reader.get(path).ok().map(sha1);
+        match self
+            .db
+            .status_for_file(&path.to_string_lossy(), tag.as_ref())?
+        {
+            FileStatus::Indexed => {}
+            _ => {
+                self.reporter.started(log_path);
+                self.reporter.failed(log_path, "file not indexed", None);
+                return Ok(false);
+            }
+        }
+
+        self.reporter.started(log_path);
+
+        self.db.load_graph_for_file(&path.to_string_lossy())?;
+        Ok(true)
+    }
+
     pub fn into_stats(self) -> StitchingStats {
         self.stats.unwrap_or_default()
     }
@@ -297,8 +980,8 @@ impl<'a> Querier<'a> {
 
 #[derive(Debug, Error)]
 pub enum QueryError {
-    #[error("cancelled at {0}")]
-    Cancelled(&'static str),
+    #[error(transparent)]
+    Cancelled(stack_graphs::CancellationError),
     #[error("failed to read file")]
     ReadError(#[from] std::io::Error),
     #[error(transparent)]
@@ -307,19 +990,274 @@ pub enum QueryError {
 
 impl From<stack_graphs::CancellationError> for QueryError {
     fn from(value: stack_graphs::CancellationError) -> Self {
-        Self::Cancelled(value.0)
+        Self::Cancelled(value)
     }
 }
 
 impl From<crate::CancellationError> for QueryError {
     fn from(value: crate::CancellationError) -> Self {
-        Self::Cancelled(value.0)
+        Self::Cancelled(stack_graphs::CancellationError::new(value.0))
     }
 }
 
 pub struct QueryResult {
     pub source: SourceSpan,
     pub targets: Vec<SourceSpan>,
+    /// Explanations of the paths from the source to each target, in the same order as the
+    /// targets. Only present if the querier was asked to explain its results.
+    pub explanations: Option<Vec<Explanation>>,
+}
+
+impl QueryResult {
+    /// Returns a JSON record for this result, tagged with the kind of query that produced it.
+    pub fn to_json(&self, query: &str) -> Value {
+        let mut record = json!({
+            "type": "result",
+            "query": query,
+            "source": self.source.to_json(),
+            "targets": self.targets.iter().map(SourceSpan::to_json).collect::<Vec<_>>(),
+        });
+        if let Some(explanations) = &self.explanations {
+            record["explanations"] = explanations.iter().map(Explanation::to_json).collect();
+        }
+        record
+    }
+}
+
+/// Explains a query result by listing the steps of the path that connects source and target.
+pub struct Explanation {
+    /// The complete stitched path.
+    pub path: PartialPath,
+    /// The files that the path crosses, in order.
+    pub files: Vec<String>,
+    /// The steps of the path, starting with the start node of the path.
+    pub steps: Vec<ExplanationStep>,
+}
+
+/// A single step in a path explanation, which is the node that is reached by following an edge.
+pub struct ExplanationStep {
+    /// The node that is reached.
+    pub node: String,
+    /// The file the node belongs to, if it belongs to one.
+    pub file: Option<String>,
+    /// The source span of the node, if it has one.
+    pub span: Option<SourceSpan>,
+    /// The precedence of the edge leading to the node. Not present for the first step.
+    pub precedence: Option<i32>,
+    /// The symbol stack after the node.
+    pub symbol_stack: String,
+    /// The scope stack after the node.
+    pub scope_stack: String,
+    /// The debug entries of the node.
+    pub node_debug_info: Vec<(String, String)>,
+    /// The debug entries of the edge leading to the node.
+    pub edge_debug_info: Vec<(String, String)>,
+}
+
+impl Explanation {
+    /// Explains the given complete path, by replaying its edges from the start node.
+    fn from_path(graph: &StackGraph, partials: &mut PartialPaths, path: PartialPath) -> Self {
+        let mut current = PartialPath::from_node(graph, partials, path.start_node);
+        current.eliminate_precondition_stack_variables(partials);
+        let mut steps = vec![ExplanationStep::new(
+            graph,
+            partials,
+            &current,
+            path.start_node,
+            None,
+        )];
+
+        let mut sources = path
+            .edges
+            .iter(partials)
+            .filter_map(|e| {
+                graph
+                    .node_for_id(e.source_node_id)
+                    .map(|n| (n, e.precedence))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .peekable();
+        while let Some((source, precedence)) = sources.next() {
+            let next = sources.peek().map_or(path.end_node, |(n, _)| *n);
+            // An edge into a jump node is immediately resolved to the scope it jumps to, which
+            // is why the next edge might not start at the sink of this edge.
+            let sink = match graph.outgoing_edges(source).any(|e| e.sink == next) {
+                true => next,
+                false => StackGraph::jump_to_node(),
+            };
+            let edge = Edge {
+                source,
+                sink,
+                precedence,
+            };
+            if current.resolve_to_node(graph, partials, source).is_err()
+                || current.append(graph, partials, edge).is_err()
+            {
+                // The path was valid when it was stitched, so this should not happen, but an
+                // incomplete explanation is better than none.
+                break;
+            }
+            steps.push(ExplanationStep::new(
+                graph,
+                partials,
+                &current,
+                sink,
+                Some(&edge),
+            ));
+        }
+
+        let mut files = Vec::<String>::new();
+        for file in steps.iter().filter_map(|s| s.file.as_ref()) {
+            if files.last() != Some(file) {
+                files.push(file.clone());
+            }
+        }
+
+        Self { path, files, steps }
+    }
+
+    /// Prints the explanation as a human-readable chain of steps.
+    fn print(&self, indent: usize) {
+        let indent = " ".repeat(indent);
+        println!("{}explanation:", indent);
+        println!("{}  files: {}", indent, self.files.join(" -> "));
+        for (idx, step) in self.steps.iter().enumerate() {
+            let arrow = if idx == 0 { "  " } else { "->" };
+            print!("{}  {} {}", indent, arrow, step.node);
+            if let Some(precedence) = step.precedence.filter(|p| *p != 0) {
+                print!(" (precedence {})", precedence);
+            }
+            println!(" <{}> ({})", step.symbol_stack, step.scope_stack);
+            for (key, value) in &step.edge_debug_info {
+                println!("{}       edge {} = {}", indent, key, value);
+            }
+            for (key, value) in &step.node_debug_info {
+                println!("{}       node {} = {}", indent, key, value);
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "files": self.files,
+            "steps": self.steps.iter().map(ExplanationStep::to_json).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl ExplanationStep {
+    fn new(
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        current: &PartialPath,
+        node: Handle<Node>,
+        edge: Option<&Edge>,
+    ) -> Self {
+        fn entries(graph: &StackGraph, debug_info: Option<&DebugInfo>) -> Vec<(String, String)> {
+            debug_info
+                .into_iter()
+                .flat_map(|d| d.iter())
+                .map(|e| (graph[e.key].to_string(), graph[e.value].to_string()))
+                .collect()
+        }
+        let file = graph[node].file().map(|f| graph[f].name().to_string());
+        let span = match (graph.source_info(node), &file) {
+            (Some(source_info), Some(file)) if source_info.span != Span::default() => {
+                Some(SourceSpan {
+                    path: PathBuf::from(file),
+                    span: source_info.span.clone(),
+                })
+            }
+            _ => None,
+        };
+        let symbol_stack = current
+            .symbol_stack_postcondition
+            .display(graph, partials)
+            .to_string();
+        let scope_stack = current
+            .scope_stack_postcondition
+            .display(graph, partials)
+            .to_string();
+        Self {
+            node: graph[node].display(graph).to_string(),
+            file,
+            span,
+            precedence: edge.map(|e| e.precedence),
+            symbol_stack,
+            scope_stack,
+            node_debug_info: entries(graph, graph.node_debug_info(node)),
+            edge_debug_info: entries(
+                graph,
+                edge.and_then(|e| graph.edge_debug_info(e.source, e.sink)),
+            ),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let debug_info = |entries: &Vec<(String, String)>| {
+            entries
+                .iter()
+                .map(|(key, value)| json!({ "key": key, "value": value }))
+                .collect::<Vec<_>>()
+        };
+        json!({
+            "node": self.node,
+            "file": self.file,
+            "span": self.span.as_ref().map(SourceSpan::to_json),
+            "precedence": self.precedence,
+            "symbol_stack": self.symbol_stack,
+            "scope_stack": self.scope_stack,
+            "node_debug_info": debug_info(&self.node_debug_info),
+            "edge_debug_info": debug_info(&self.edge_debug_info),
+        })
+    }
+}
+
+/// Source information about a definition.
+pub struct DefinitionInfo {
+    pub span: SourceSpan,
+    pub symbol: String,
+    pub syntax_type: Option<String>,
+    pub fully_qualified_name: Option<String>,
+    pub containing_line: Option<String>,
+    pub definiens_span: Option<SourceSpan>,
+}
+
+impl DefinitionInfo {
+    fn from_node(graph: &StackGraph, node: Handle<Node>) -> Option<Self> {
+        let source_info = graph.source_info(node)?;
+        let path = PathBuf::from(graph[graph[node].file()?].name());
+        let symbol = graph[graph[node].symbol()?].to_string();
+        let definiens_span = if source_info.definiens_span == Span::default() {
+            None
+        } else {
+            Some(SourceSpan {
+                path: path.clone(),
+                span: source_info.definiens_span.clone(),
+            })
+        };
+        Some(Self {
+            span: SourceSpan {
+                path,
+                span: source_info.span.clone(),
+            },
+            symbol,
+            syntax_type: source_info
+                .syntax_type
+                .into_option()
+                .map(|s| graph[s].to_string()),
+            fully_qualified_name: source_info
+                .fully_qualified_name
+                .into_option()
+                .map(|s| graph[s].to_string()),
+            containing_line: source_info
+                .containing_line
+                .into_option()
+                .map(|s| graph[s].to_string()),
+            definiens_span,
+        })
+    }
 }
 
 type Result<T> = std::result::Result<T, QueryError>;
@@ -16,6 +16,7 @@ use std::path::PathBuf;
 
 use crate::cli::util::reporter::ConsoleReporter;
 use crate::cli::util::reporter::Reporter;
+use crate::cli::util::OutputFormat;
 
 #[derive(Args)]
 #[clap(group(
@@ -37,22 +38,40 @@ pub struct StatusArgs {
 
     #[clap(long, short = 'v')]
     pub verbose: bool,
+
+    /// Output format.
+    #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
 }
 
 impl StatusArgs {
     pub fn run(self, db_path: &Path) -> anyhow::Result<()> {
-        let reporter = self.get_reporter();
+        match self.format.json_reporter() {
+            Some(reporter) => {
+                let result = self.run_with_reporter(db_path, &reporter);
+                if let Err(err) = &result {
+                    reporter.error(err);
+                }
+                reporter.finish()?;
+                result
+            }
+            None => self.run_with_reporter(db_path, &self.get_reporter()),
+        }
+    }
+
+    /// Reports the status of the source paths to the given reporter, regardless of the format.
+    pub fn run_with_reporter(&self, db_path: &Path, reporter: &dyn Reporter) -> anyhow::Result<()> {
         let mut db = SQLiteReader::open(&db_path)?;
         if self.all {
             let mut files = db.list_all()?;
             let mut entries = files.try_iter()?;
-            self.status(&mut entries, &reporter)?;
+            self.status(&mut entries, reporter)?;
         } else {
             for source_path in &self.source_paths {
                 let source_path = source_path.canonicalize()?;
                 let mut files = db.list_file_or_directory(&source_path)?;
                 let mut entries = files.try_iter()?;
-                self.status(&mut entries, &reporter)?;
+                self.status(&mut entries, reporter)?;
             }
         }
         Ok(())
@@ -139,6 +139,11 @@ pub struct TestArgs {
         value_parser = duration_from_seconds_str,
     )]
     pub max_test_time: Option<Duration>,
+
+    /// Check the stack graphs of tests for suspicious structure, and report each problem
+    /// as a failed assertion.
+    #[clap(long)]
+    pub lint: bool,
 }
 
 /// Flag to control output
@@ -170,6 +175,7 @@ impl TestArgs {
             output_mode: OutputMode::OnFailure,
             no_builtins: false,
             max_test_time: None,
+            lint: false,
         }
     }
 
@@ -341,12 +347,16 @@ impl TestArgs {
                 },
             )?;
         }
-        let result = test.run(
+        let mut result = test.run(
             &mut partials,
             &mut db,
             stitcher_config,
+            lc.shadowing_policy.as_ref(),
             cancellation_flag.as_ref(),
         )?;
+        if self.lint {
+            result.absorb(test.lint());
+        }
         let success = result.failure_count() == 0;
         let outputs = if self.output_mode.test(!success) {
             let files = test.fragments.iter().map(|f| f.file).collect::<Vec<_>>();
@@ -12,7 +12,11 @@ use clap::builder::TypedValueParser;
 use clap::error::ContextKind;
 use clap::error::ContextValue;
 use clap::error::ErrorKind;
+use clap::ValueEnum;
+use lsp_positions::Position;
 use lsp_positions::Span;
+use serde_json::json;
+use serde_json::Value;
 use sha1::Digest;
 use sha1::Sha1;
 use stack_graphs::arena::Handle;
@@ -33,6 +37,7 @@ use std::time::Duration;
 use walkdir::WalkDir;
 
 use crate::cli::index::IndexingStats;
+use crate::cli::util::reporter::JsonReporter;
 use crate::cli::util::reporter::Reporter;
 
 pub mod reporter;
@@ -193,12 +198,30 @@ impl SourcePosition {
         &'a self,
         graph: &'a StackGraph,
     ) -> impl Iterator<Item = (Handle<Node>, Span)> + 'a {
+        self.iter_nodes(graph, |node| node.is_reference())
+    }
+
+    pub fn iter_definitions<'a>(
+        &'a self,
+        graph: &'a StackGraph,
+    ) -> impl Iterator<Item = (Handle<Node>, Span)> + 'a {
+        self.iter_nodes(graph, |node| node.is_definition())
+    }
+
+    fn iter_nodes<'a, P>(
+        &'a self,
+        graph: &'a StackGraph,
+        predicate: P,
+    ) -> impl Iterator<Item = (Handle<Node>, Span)> + 'a
+    where
+        P: Fn(&Node) -> bool + Copy + 'a,
+    {
         graph
             .get_file(&self.path.to_string_lossy())
             .into_iter()
             .flat_map(move |file| {
                 graph.nodes_for_file(file).filter_map(move |node| {
-                    if !graph[node].is_reference() {
+                    if !predicate(&graph[node]) {
                         return None;
                     }
                     let source_info = match graph.source_info(node) {
@@ -304,10 +327,35 @@ pub struct SourceSpan {
 }
 
 impl SourceSpan {
+    /// Returns the source position at the start of this span.
+    pub fn start_position(&self) -> SourcePosition {
+        SourcePosition {
+            path: self.path.clone(),
+            line: self.span.start.line,
+            column: self.span.start.column.grapheme_offset,
+        }
+    }
+
     pub(crate) fn first_line(&self) -> usize {
         self.span.start.line
     }
 
+    /// Returns a JSON representation of this span. Lines and columns are 0-based, and columns
+    /// are counted in graphemes, like the fields of [`SourcePosition`][].
+    pub fn to_json(&self) -> Value {
+        fn position(position: &Position) -> Value {
+            json!({
+                "line": position.line,
+                "column": position.column.grapheme_offset,
+            })
+        }
+        json!({
+            "path": self.path.to_string_lossy(),
+            "start": position(&self.span.start),
+            "end": position(&self.span.end),
+        })
+    }
+
     /// Returns a range for the first line of this span. If multiple lines are spanned, it
     /// will use usize::MAX for the range's end.
     pub(crate) fn first_line_column_range(&self) -> Range<usize> {
@@ -502,6 +550,28 @@ impl std::fmt::Display for DisplayBuildErrorPretty<'_> {
     }
 }
 
+/// Output format of commands
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
+    Text,
+    /// A single JSON array of records
+    Json,
+    /// One JSON record per line
+    Jsonl,
+}
+
+impl OutputFormat {
+    /// Returns a JSON reporter for this format, or `None` if the format is text.
+    pub(crate) fn json_reporter(self) -> Option<JsonReporter> {
+        match self {
+            Self::Text => None,
+            Self::Json => Some(JsonReporter::json()),
+            Self::Jsonl => Some(JsonReporter::json_lines()),
+        }
+    }
+}
+
 pub(super) fn print_indexing_stats(stats: IndexingStats) {
     print_quartiles_header("graph stats");
     print_quartiles_row("total graph nodes", stats.total_graph_nodes);
@@ -533,6 +603,15 @@ pub(super) fn print_stitching_stats(stats: StitchingStats) {
         "similar path bucket sizes",
         stats.similar_paths_stats.similar_path_bucket_size,
     );
+    print_value_row(
+        "similar path evictions",
+        stats.similar_paths_stats.evicted_buckets,
+    );
+    print_value_row(
+        "similar paths readmitted",
+        stats.similar_paths_stats.readmitted_paths,
+    );
+    print_value_row("discarded cycles", stats.discarded_cycles.count());
 }
 
 pub(super) fn print_database_stats(stats: StorageStats) {
@@ -553,6 +632,78 @@ pub(super) fn print_database_stats(stats: StorageStats) {
         "| {:>29} | {:>9} | {:>9} |",
         "rootpaths", stats.root_path_loads, stats.root_path_cached
     );
+    println!(
+        "| {:>29} | {:>9} | {:>9} |",
+        "complete paths", stats.complete_path_stitches, stats.complete_path_cached
+    );
+    println!(
+        "| {:>29} | {:>9} | {:>9} |",
+        "resolutions", stats.resolution_loads, ""
+    );
+}
+
+pub(super) fn indexing_stats_to_json(stats: IndexingStats) -> Value {
+    json!({
+        "total_graph_nodes": quartiles_to_json(stats.total_graph_nodes),
+        "total_graph_edges": quartiles_to_json(stats.total_graph_edges),
+        "node_out_degrees": quartiles_to_json(stats.node_out_degrees),
+        "root_out_degree": stats.root_out_degree,
+        "stitching": stitching_stats_to_json(stats.stitching_stats),
+    })
+}
+
+pub(super) fn stitching_stats_to_json(stats: StitchingStats) -> Value {
+    json!({
+        "initial_paths": quartiles_to_json(stats.initial_paths),
+        "queued_paths_per_phase": quartiles_to_json(stats.queued_paths_per_phase),
+        "processed_paths_per_phase": quartiles_to_json(stats.processed_paths_per_phase),
+        "accepted_path_length": quartiles_to_json(stats.accepted_path_length),
+        "terminal_path_length": quartiles_to_json(stats.terminal_path_lengh),
+        "node_path_candidates": quartiles_to_json(stats.candidates_per_node_path),
+        "node_path_extensions": quartiles_to_json(stats.extensions_per_node_path),
+        "root_path_candidates": quartiles_to_json(stats.candidates_per_root_path),
+        "root_path_extensions": quartiles_to_json(stats.extensions_per_root_path),
+        "node_visits": quartiles_to_json(stats.node_visits.frequencies()),
+        "root_visits": stats.root_visits,
+        "similar_path_counts": quartiles_to_json(stats.similar_paths_stats.similar_path_count),
+        "similar_path_bucket_sizes": quartiles_to_json(
+            stats.similar_paths_stats.similar_path_bucket_size
+        ),
+        "similar_path_evictions": {
+            "buckets": stats.similar_paths_stats.evicted_buckets,
+            "paths": stats.similar_paths_stats.evicted_paths,
+            "readmitted_paths": stats.similar_paths_stats.readmitted_paths,
+        },
+        "discarded_cycles": {
+            "count": stats.discarded_cycles.count(),
+            "unique": stats.discarded_cycles.unique(),
+        },
+    })
+}
+
+pub(super) fn database_stats_to_json(stats: StorageStats) -> Value {
+    json!({
+        "files": { "loads": stats.file_loads, "cached": stats.file_cached },
+        "node_paths": { "loads": stats.node_path_loads, "cached": stats.node_path_cached },
+        "root_paths": { "loads": stats.root_path_loads, "cached": stats.root_path_cached },
+        "complete_paths": { "loads": stats.complete_path_stitches, "cached": stats.complete_path_cached },
+        "resolutions": { "loads": stats.resolution_loads },
+    })
+}
+
+fn quartiles_to_json(hist: FrequencyDistribution<usize>) -> Value {
+    let qs = hist.quantiles(4);
+    if qs.is_empty() {
+        return json!({ "count": 0 });
+    }
+    json!({
+        "min": qs[0],
+        "p25": qs[1],
+        "p50": qs[2],
+        "p75": qs[3],
+        "max": qs[4],
+        "count": hist.count(),
+    })
 }
 
 fn print_quartiles_header(title: &str) {
@@ -7,8 +7,14 @@
 
 use colored::ColoredString;
 use colored::Colorize;
+use serde_json::json;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
+use std::time::Instant;
 
 /// Trait that supports reporting file processing status.
 ///
@@ -176,3 +182,117 @@ impl Reporter for ConsoleReporter {
         )
     }
 }
+
+/// A reporter that outputs structured JSON records, for consumption by other tools.
+///
+/// File statuses are reported as records of type `status`, which include the time it took to
+/// process the file if it was started. Other records, such as results or statistics, can be
+/// added with [`record`][]. In JSON lines mode, each record is printed on its own line as soon
+/// as it is added. Otherwise, the records are collected and printed as a single JSON array
+/// when [`finish`][] is called.
+///
+/// [`record`]: #method.record
+/// [`finish`]: #method.finish
+pub struct JsonReporter {
+    lines: bool,
+    records: RefCell<Vec<Value>>,
+    started: RefCell<HashMap<PathBuf, Instant>>,
+}
+
+impl JsonReporter {
+    /// Creates a reporter that prints a single JSON array.
+    pub fn json() -> Self {
+        Self::new(false)
+    }
+
+    /// Creates a reporter that prints one JSON value per line.
+    pub fn json_lines() -> Self {
+        Self::new(true)
+    }
+
+    fn new(lines: bool) -> Self {
+        Self {
+            lines,
+            records: RefCell::new(Vec::new()),
+            started: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Adds a record to the output.
+    pub fn record(&self, record: Value) {
+        if self.lines {
+            println!("{}", record);
+        } else {
+            self.records.borrow_mut().push(record);
+        }
+    }
+
+    /// Adds an error record to the output.
+    pub fn error(&self, error: &anyhow::Error) {
+        self.record(json!({
+            "type": "error",
+            "message": format!("{:#}", error),
+        }));
+    }
+
+    /// Returns the collected records, instead of printing them. Records of a reporter that prints
+    /// one value per line are not collected.
+    pub fn into_records(self) -> Vec<Value> {
+        self.records.into_inner()
+    }
+
+    /// Prints the collected records, if they were not printed already.
+    pub fn finish(&self) -> anyhow::Result<()> {
+        if !self.lines {
+            let records = self.records.take();
+            println!("{}", serde_json::to_string_pretty(&records)?);
+        }
+        Ok(())
+    }
+
+    fn status(
+        &self,
+        path: &Path,
+        status: &str,
+        summary: &str,
+        details: Option<&dyn std::fmt::Display>,
+    ) {
+        let elapsed = self
+            .started
+            .borrow_mut()
+            .remove(path)
+            .map(|started| started.elapsed().as_millis() as u64);
+        self.record(json!({
+            "type": "status",
+            "path": path.to_string_lossy(),
+            "status": status,
+            "summary": summary,
+            "details": details.map(|d| d.to_string()),
+            "elapsed_ms": elapsed,
+        }));
+    }
+}
+
+impl Reporter for JsonReporter {
+    fn skipped(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.status(path, "skipped", summary, details);
+    }
+
+    fn started(&self, path: &Path) {
+        self.started
+            .borrow_mut()
+            .insert(path.to_path_buf(), Instant::now());
+    }
+
+    fn succeeded(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.status(path, "succeeded", summary, details);
+    }
+
+    fn failed(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.status(path, "failed", summary, details);
+    }
+
+    fn cancelled(&self, path: &Path, summary: &str, details: Option<&dyn std::fmt::Display>) {
+        self.status(path, "cancelled", summary, details);
+    }
+}
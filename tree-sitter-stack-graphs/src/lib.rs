@@ -160,6 +160,13 @@
 //! }
 //! ```
 //!
+//! Nodes with location information are also given a stable ID, which is derived from the file,
+//! the enclosing definitions, and the kind and symbol of the node, instead of the order in which
+//! the rules created the nodes, or the lines on which the nodes appear.  Setting `definiens_node`
+//! on definitions scopes the numbering of the nodes inside them, so that adding nodes elsewhere in
+//! the file does not change their IDs.  Stable IDs can be used to refer to nodes across indexing runs, see
+//! [`StackGraph::assign_stable_ids`][stack_graphs::graph::StackGraph::assign_stable_ids].
+//!
 //! ### Annotating nodes with syntax type information
 //!
 //! You can annotate any stack graph node with information about its syntax type. To do this, add a `syntax_type`
@@ -217,6 +224,18 @@
 //!
 //! (If you don't specify a `precedence`, the default is 0.)
 //!
+//! You can also add a `kind` attribute to an edge, to record why the edge exists.  The kind can
+//! be any string, such as `import`, `inheritance`, or `lexical`.  It does not affect path finding,
+//! but it is preserved when the stack graph is serialized or stored in a database, and it is shown
+//! in visualizations:
+//!
+//! ``` skip
+//! (import_statement name: (dotted_name) @name) @import {
+//!   edge @import.lexical_scope -> @name.module
+//!   attr (@import.lexical_scope -> @name.module) kind = "import"
+//! }
+//! ```
+//!
 //! ### Referring to the singleton nodes
 //!
 //! The _root node_ and _jump to scope node_ are singleton nodes that always exist for all stack
@@ -343,6 +362,7 @@ use stack_graphs::graph::File;
 use stack_graphs::graph::Node;
 use stack_graphs::graph::NodeID;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::stitching::StitchingProgress;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -433,6 +453,7 @@ static SCOPE_ATTRS: Lazy<HashSet<&'static str>> =
 
 // Edge attribute names
 static PRECEDENCE_ATTR: &'static str = "precedence";
+static KIND_ATTR: &'static str = "kind";
 
 // Global variables
 static ROOT_NODE_VAR: &'static str = "ROOT_NODE";
@@ -526,6 +547,15 @@ impl StackGraphLanguage {
     pub fn tsg_source(&self) -> &Cow<'static, str> {
         &self.tsg_source
     }
+
+    /// Returns whether the rules declare the `FILE_PATH` global variable, in which case the stack
+    /// graph of a file can depend on its path.
+    pub fn uses_file_path(&self) -> bool {
+        self.tsg
+            .globals
+            .iter()
+            .any(|global| global.name.as_str() == FILE_PATH_VAR)
+    }
 }
 
 /// An error that can occur while loading in the TSG stack graph construction rules for a language
@@ -697,6 +727,10 @@ impl<'a> Builder<'a> {
 /// Trait to signal that the execution is cancelled
 pub trait CancellationFlag: Sync {
     fn check(&self, at: &'static str) -> Result<(), CancellationError>;
+
+    /// Receives the progress of path stitching. See
+    /// [`stack_graphs::CancellationFlag::report_progress`][]. Progress is ignored by default.
+    fn report_progress(&self, _at: &'static str, _progress: &StitchingProgress) {}
 }
 
 #[derive(Clone, Debug, Error)]
@@ -705,7 +739,12 @@ pub struct CancellationError(pub &'static str);
 
 impl stack_graphs::CancellationFlag for &dyn CancellationFlag {
     fn check(&self, at: &'static str) -> Result<(), stack_graphs::CancellationError> {
-        CancellationFlag::check(*self, at).map_err(|err| stack_graphs::CancellationError(err.0))
+        CancellationFlag::check(*self, at)
+            .map_err(|err| stack_graphs::CancellationError::new(err.0))
+    }
+
+    fn report_progress(&self, at: &'static str, progress: &StitchingProgress) {
+        CancellationFlag::report_progress(*self, at, progress)
     }
 }
 
@@ -731,6 +770,11 @@ impl CancellationFlag for OrCancellationFlag<'_> {
         self.1.check(at)?;
         Ok(())
     }
+
+    fn report_progress(&self, at: &'static str, progress: &StitchingProgress) {
+        self.0.report_progress(at, progress);
+        self.1.report_progress(at, progress);
+    }
 }
 
 pub struct NoCancellation;
@@ -830,7 +874,7 @@ pub enum BuildError {
 
 impl From<stack_graphs::CancellationError> for BuildError {
     fn from(value: stack_graphs::CancellationError) -> Self {
-        Self::Cancelled(value.0)
+        Self::Cancelled(value.at)
     }
 }
 
@@ -937,6 +981,7 @@ impl<'a> Builder<'a> {
         for node in self.stack_graph.nodes_for_file(self.file) {
             self.verify_node(node)?;
         }
+        self.stack_graph.assign_stable_ids(self.file);
 
         // Then add stack graph edges for each TSG edge.  Note that we _don't_ skip(...) here because
         // there might be outgoing nodes from the “root” node that we need to process.
@@ -956,6 +1001,11 @@ impl<'a> Builder<'a> {
                 let sink_handle = self.stack_graph.node_for_id(sink_node_id).unwrap();
                 self.stack_graph
                     .add_edge(source_handle, sink_handle, precedence);
+                if let Some(kind) = edge.attributes.get(KIND_ATTR) {
+                    let kind = self.stack_graph.add_string(kind.as_str()?);
+                    self.stack_graph
+                        .set_edge_kind(source_handle, sink_handle, Some(kind));
+                }
                 Self::load_edge_debug_info(
                     &mut self.stack_graph,
                     source_handle,
@@ -12,6 +12,8 @@ use itertools::Itertools;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::shadowing::EdgePrecedence;
+use stack_graphs::shadowing::ShadowingPolicy;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::ffi::OsStr;
@@ -49,6 +51,11 @@ pub struct LanguageConfiguration {
     /// incorrectly set to true, performance of path finding suffers from exponential
     /// blow up.
     pub no_similar_paths_in_file: bool,
+    /// The policy that decides which of the definitions found for a reference are visible.
+    /// Defaults to [`EdgePrecedence`][], which uses the precedences of the stack graph edges.
+    ///
+    /// [`EdgePrecedence`]: stack_graphs::shadowing::EdgePrecedence
+    pub shadowing_policy: Arc<dyn ShadowingPolicy + Send + Sync>,
 }
 
 impl LanguageConfiguration {
@@ -103,15 +110,16 @@ impl LanguageConfiguration {
             builtins,
             special_files: FileAnalyzers::new(),
             no_similar_paths_in_file: false,
+            shadowing_policy: Arc::new(EdgePrecedence),
         })
     }
 
     // Extracted from tree_sitter_loader::Loader::language_configuration_for_file_name
     fn best_for_file<'a>(
-        languages: &'a Vec<LanguageConfiguration>,
+        languages: &'a Vec<Arc<LanguageConfiguration>>,
         path: &Path,
         content: &mut dyn ContentProvider,
-    ) -> std::io::Result<Option<&'a LanguageConfiguration>> {
+    ) -> std::io::Result<Option<&'a Arc<LanguageConfiguration>>> {
         let mut best_score = -1isize;
         let mut best = None;
         for language in languages {
@@ -134,6 +142,12 @@ impl LanguageConfiguration {
     ) -> std::io::Result<bool> {
         matches_file(&self.file_types, &self.content_regex, path, content).map(|l| l.is_some())
     }
+
+    /// The name of the language, which is the last component of its scope. For example, the
+    /// language with scope `source.python` is named `python`.
+    pub fn language_name(&self) -> Option<&str> {
+        self.scope.as_deref().and_then(|s| s.rsplit('.').next())
+    }
 }
 
 #[derive(Clone, Default)]
@@ -251,6 +265,7 @@ impl Loader {
         let configurations = configurations
             .into_iter()
             .filter(|lc| scope.is_none() || lc.scope == scope)
+            .map(Arc::new)
             .collect();
         Ok(Self(LoaderImpl::Provided(LanguageConfigurationsLoader {
             configurations,
@@ -280,6 +295,31 @@ impl Loader {
         content: &mut dyn ContentProvider,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<FileLanguageConfigurations<'a>, LoadError<'static>> {
+        Ok(self
+            .select_for_file(path, content, cancellation_flag)?
+            .borrowed())
+    }
+
+    /// Load a stack graph language for the given file, like [`Self::load_for_file`]. The returned language
+    /// configurations do not borrow from the loader, so that they can be sent to other threads while the loader
+    /// is used to load the languages for other files.
+    pub fn load_shared_for_file(
+        &mut self,
+        path: &Path,
+        content: &mut dyn ContentProvider,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<SharedFileLanguageConfigurations, LoadError<'static>> {
+        Ok(self
+            .select_for_file(path, content, cancellation_flag)?
+            .shared())
+    }
+
+    fn select_for_file<'a>(
+        &'a mut self,
+        path: &Path,
+        content: &mut dyn ContentProvider,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<SelectedLanguageConfigurations<'a>, LoadError<'static>> {
         match &mut self.0 {
             LoaderImpl::Paths(loader) => loader.load_for_file(path, content, cancellation_flag),
             LoaderImpl::Provided(loader) => loader.load_for_file(path, content),
@@ -383,6 +423,95 @@ impl FileLanguageConfigurations<'_> {
         }
         return no_similar_paths_in_file;
     }
+
+    /// Returns whether the stack graph of the file can depend on its path. This is the case if the
+    /// rules of the primary language use the `FILE_PATH` variable, or if the file has secondary
+    /// languages, whose file analyzers may use the path in any way.
+    pub fn uses_file_path(&self) -> bool {
+        matches!(self.primary, Some(lc) if lc.sgl.uses_file_path()) || !self.secondary.is_empty()
+    }
+
+    /// Returns the shadowing policy of the file's primary language, or the default policy if
+    /// the file has no primary language.
+    pub fn shadowing_policy(&self) -> Arc<dyn ShadowingPolicy + Send + Sync> {
+        match &self.primary {
+            Some(lc) => lc.shadowing_policy.clone(),
+            None => Arc::new(EdgePrecedence),
+        }
+    }
+}
+
+/// Struct holding the language configurations for a file, which can be shared between threads.
+#[derive(Clone, Default)]
+pub struct SharedFileLanguageConfigurations {
+    /// The file's primary language. The language configuration's `StackGraphLanguage` should be used to process the file.
+    pub primary: Option<Arc<LanguageConfiguration>>,
+    /// Any secondary languages, which have special file analyzers for the file.
+    pub secondary: Vec<(
+        Arc<LanguageConfiguration>,
+        Arc<dyn FileAnalyzer + Send + Sync>,
+    )>,
+}
+
+impl<'a> From<&'a SharedFileLanguageConfigurations> for FileLanguageConfigurations<'a> {
+    fn from(value: &'a SharedFileLanguageConfigurations) -> Self {
+        Self {
+            primary: value.primary.as_deref(),
+            secondary: value
+                .secondary
+                .iter()
+                .map(|(lc, fa)| (lc.as_ref(), fa.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl SharedFileLanguageConfigurations {
+    pub fn has_some(&self) -> bool {
+        FileLanguageConfigurations::from(self).has_some()
+    }
+
+    pub fn no_similar_paths_in_file(&self) -> bool {
+        FileLanguageConfigurations::from(self).no_similar_paths_in_file()
+    }
+
+    pub fn shadowing_policy(&self) -> Arc<dyn ShadowingPolicy + Send + Sync> {
+        FileLanguageConfigurations::from(self).shadowing_policy()
+    }
+}
+
+/// Language configurations selected by one of the loader implementations.
+#[derive(Default)]
+struct SelectedLanguageConfigurations<'a> {
+    primary: Option<&'a Arc<LanguageConfiguration>>,
+    secondary: Vec<(
+        &'a Arc<LanguageConfiguration>,
+        Arc<dyn FileAnalyzer + Send + Sync>,
+    )>,
+}
+
+impl<'a> SelectedLanguageConfigurations<'a> {
+    fn borrowed(self) -> FileLanguageConfigurations<'a> {
+        FileLanguageConfigurations {
+            primary: self.primary.map(|lc| lc.as_ref()),
+            secondary: self
+                .secondary
+                .into_iter()
+                .map(|(lc, fa)| (lc.as_ref(), fa))
+                .collect(),
+        }
+    }
+
+    fn shared(self) -> SharedFileLanguageConfigurations {
+        SharedFileLanguageConfigurations {
+            primary: self.primary.cloned(),
+            secondary: self
+                .secondary
+                .into_iter()
+                .map(|(lc, fa)| (lc.clone(), fa))
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -469,7 +598,7 @@ impl std::fmt::Display for DisplayLoadErrorPretty<'_> {
 // provided languages loader
 
 struct LanguageConfigurationsLoader {
-    configurations: Vec<LanguageConfiguration>,
+    configurations: Vec<Arc<LanguageConfiguration>>,
 }
 
 impl LanguageConfigurationsLoader {
@@ -494,7 +623,7 @@ impl LanguageConfigurationsLoader {
         &'a mut self,
         path: &Path,
         content: &mut dyn ContentProvider,
-    ) -> Result<FileLanguageConfigurations<'a>, LoadError<'static>> {
+    ) -> Result<SelectedLanguageConfigurations<'a>, LoadError<'static>> {
         let primary = LanguageConfiguration::best_for_file(&self.configurations, path, content)?;
         let mut secondary = Vec::new();
         for language in self.configurations.iter() {
@@ -505,7 +634,7 @@ impl LanguageConfigurationsLoader {
                 secondary.push((language, fa));
             }
         }
-        Ok(FileLanguageConfigurations { primary, secondary })
+        Ok(SelectedLanguageConfigurations { primary, secondary })
     }
 }
 
@@ -518,7 +647,7 @@ struct PathLoader {
     scope: Option<String>,
     tsg_paths: Vec<LoadPath>,
     builtins_paths: Vec<LoadPath>,
-    cache: Vec<(Language, LanguageConfiguration)>,
+    cache: Vec<(Language, Arc<LanguageConfiguration>)>,
 }
 
 impl PathLoader {
@@ -563,11 +692,11 @@ impl PathLoader {
         path: &Path,
         content: &mut dyn ContentProvider,
         cancellation_flag: &dyn CancellationFlag,
-    ) -> Result<FileLanguageConfigurations<'a>, LoadError<'static>> {
+    ) -> Result<SelectedLanguageConfigurations<'a>, LoadError<'static>> {
         let selected_language = self.select_language_for_file(path, content)?;
         let language = match selected_language {
             Some(selected_language) => selected_language.clone(),
-            None => return Ok(FileLanguageConfigurations::default()),
+            None => return Ok(SelectedLanguageConfigurations::default()),
         };
         // the borrow checker is a hard master...
         let index = self.cache.iter().position(|e| &e.0 == &language.language);
@@ -595,14 +724,16 @@ impl PathLoader {
                     special_files: FileAnalyzers::new(),
                     // always detect similar paths, we don't know the language configuration when loading from the file system
                     no_similar_paths_in_file: false,
+                    // use the default policy, for the same reason
+                    shadowing_policy: Arc::new(EdgePrecedence),
                 };
-                self.cache.push((language.language, lc));
+                self.cache.push((language.language, Arc::new(lc)));
 
                 self.cache.len() - 1
             }
         };
         let lc = &self.cache[index].1;
-        Ok(FileLanguageConfigurations {
+        Ok(SelectedLanguageConfigurations {
             primary: Some(lc),
             secondary: Vec::default(),
         })
@@ -72,7 +72,10 @@ use stack_graphs::graph::File;
 use stack_graphs::graph::Node;
 use stack_graphs::graph::SourceInfo;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::lint::lint_file;
+use stack_graphs::lint::DiagnosticKind;
 use stack_graphs::partial::PartialPaths;
+use stack_graphs::shadowing::ShadowingPolicy;
 use stack_graphs::stitching::Database;
 use stack_graphs::stitching::StitcherConfig;
 use std::collections::HashMap;
@@ -492,6 +495,12 @@ pub enum TestFailure {
         unexpected_symbols: Vec<String>,
     },
     Cancelled(stack_graphs::CancellationError),
+    Lint {
+        path: PathBuf,
+        position: Option<Position>,
+        node: String,
+        kind: DiagnosticKind,
+    },
 }
 
 impl std::fmt::Display for TestFailure {
@@ -612,6 +621,23 @@ impl std::fmt::Display for TestFailure {
                 Ok(())
             }
             Self::Cancelled(err) => write!(f, "{}", err),
+            Self::Lint {
+                path,
+                position,
+                node,
+                kind,
+            } => {
+                write!(f, "{}", path.display())?;
+                if let Some(position) = position {
+                    write!(
+                        f,
+                        ":{}:{}",
+                        position.line + 1,
+                        position.column.grapheme_offset + 1
+                    )?;
+                }
+                write!(f, ": {} {}", kind, node)
+            }
         }
     }
 }
@@ -625,6 +651,7 @@ impl Test {
         partials: &mut PartialPaths,
         db: &mut Database,
         stitcher_config: StitcherConfig,
+        shadowing_policy: &dyn ShadowingPolicy,
         cancellation_flag: &dyn CancellationFlag,
     ) -> Result<TestResult, stack_graphs::CancellationError> {
         let mut result = TestResult::new();
@@ -636,6 +663,7 @@ impl Test {
                         partials,
                         db,
                         stitcher_config,
+                        shadowing_policy,
                         &cancellation_flag,
                     )
                     .map_or_else(|e| self.from_error(e), |v| Ok(v))
@@ -648,6 +676,27 @@ impl Test {
         Ok(result)
     }
 
+    /// Check the stack graph of the test fragments for suspicious structure, using
+    /// [`stack_graphs::lint`][]. Every problem that is found is reported as a failure.
+    pub fn lint(&self) -> TestResult {
+        let mut result = TestResult::new();
+        for fragment in &self.fragments {
+            for diagnostic in lint_file(&self.graph, fragment.file) {
+                let node = match self.graph.node_for_id(diagnostic.node) {
+                    Some(node) => node.display(&self.graph).to_string(),
+                    None => diagnostic.node.display(&self.graph).to_string(),
+                };
+                result.add_failure(TestFailure::Lint {
+                    path: fragment.path.clone(),
+                    position: diagnostic.span.map(|span| span.start),
+                    node,
+                    kind: diagnostic.kind,
+                });
+            }
+        }
+        result
+    }
+
     /// Construct a TestFailure from an AssertionError.
     fn from_error(&self, err: AssertionError) -> Result<(), TestFailure> {
         match err {
@@ -0,0 +1,645 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use serde_json::Value;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPath;
+use stack_graphs::partial::PartialPaths;
+use stack_graphs::serde;
+use stack_graphs::shadowing::EdgePrecedence;
+use stack_graphs::shadowing::FirstDeclaration;
+use stack_graphs::shadowing::ShadowingPolicy;
+use stack_graphs::stitching::StitcherConfig;
+use stack_graphs::storage::FileStatus;
+use stack_graphs::storage::SQLiteReader;
+use stack_graphs::storage::SQLiteWriter;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tree_sitter_stack_graphs::cli::export::ExportArgs;
+use tree_sitter_stack_graphs::cli::index::Indexer;
+use tree_sitter_stack_graphs::cli::query::Definition;
+use tree_sitter_stack_graphs::cli::query::Querier;
+use tree_sitter_stack_graphs::cli::query::References;
+use tree_sitter_stack_graphs::cli::status::StatusArgs;
+use tree_sitter_stack_graphs::cli::util::reporter::JsonReporter;
+use tree_sitter_stack_graphs::cli::util::OutputFormat;
+use tree_sitter_stack_graphs::cli::util::SourcePosition;
+use tree_sitter_stack_graphs::loader::FileAnalyzers;
+use tree_sitter_stack_graphs::loader::LanguageConfiguration;
+use tree_sitter_stack_graphs::loader::Loader;
+use tree_sitter_stack_graphs::NoCancellation;
+use tree_sitter_stack_graphs::StackGraphLanguage;
+use url::Url;
+
+const TSG: &str = r#"
+  global ROOT_NODE
+  (module) @mod {
+      node @mod.lexical_in
+      node @mod.lexical_out
+      edge @mod.lexical_in -> ROOT_NODE
+      edge ROOT_NODE -> @mod.lexical_out
+  }
+  (module (_)@stmt) @mod {
+      node @stmt.lexical_in
+      node @stmt.lexical_out
+      edge @stmt.lexical_in -> @mod.lexical_in
+      edge @mod.lexical_out -> @stmt.lexical_out
+  }
+  (module (_)@left . (_)@right) {
+      edge @right.lexical_in -> @left.lexical_out
+  }
+  (expression_statement (assignment left:(identifier)@name))@stmt {
+      node @name.def
+      attr (@name.def) type = "pop_symbol", symbol = (source-text @name), source_node = @name, is_definition
+      edge @stmt.lexical_out -> @name.def
+  }
+  [
+    (expression_statement (assignment right:(identifier)@name))@stmt
+    (expression_statement (identifier)@name)@stmt
+  ] {
+      node @name.ref
+      attr (@name.ref) type = "push_symbol", symbol = (source-text @name), source_node = @name, is_reference
+      edge @name.ref -> @stmt.lexical_in
+  }
+"#;
+
+fn loader() -> Loader {
+    loader_for_rules(TSG)
+}
+
+fn loader_for_rules(tsg: &str) -> Loader {
+    Loader::from_language_configurations(vec![language_configuration(tsg)], None)
+        .expect("Expected loader to succeed")
+}
+
+fn loader_with_shadowing_policy(policy: Arc<dyn ShadowingPolicy + Send + Sync>) -> Loader {
+    let mut lc = language_configuration(TSG);
+    lc.shadowing_policy = policy;
+    Loader::from_language_configurations(vec![lc], None).expect("Expected loader to succeed")
+}
+
+fn language_configuration(tsg: &str) -> LanguageConfiguration {
+    let language = tree_sitter_python::language();
+    let sgl = StackGraphLanguage::from_str(language, tsg).unwrap();
+    LanguageConfiguration {
+        language,
+        scope: Some("source.py".into()),
+        content_regex: None,
+        file_types: vec!["py".into()],
+        sgl,
+        builtins: StackGraph::new(),
+        special_files: FileAnalyzers::new(),
+        no_similar_paths_in_file: false,
+        shadowing_policy: Arc::new(EdgePrecedence),
+    }
+}
+
+/// Creates a fresh directory in the temporary directory, containing the given source files.
+fn create_sources(name: &str, sources: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "tree-sitter-stack-graphs-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (path, source) in sources {
+        std::fs::write(dir.join(path), source).unwrap();
+    }
+    dir
+}
+
+fn index(source_dir: &Path, db_path: &Path, jobs: usize) {
+    let mut db = SQLiteWriter::open(db_path).unwrap();
+    let mut loader = loader();
+    let reporter = JsonReporter::json();
+    let mut indexer = Indexer::new(&mut db, &mut loader, &reporter);
+    indexer.jobs = jobs;
+    indexer
+        .index_all(vec![source_dir], None::<&Path>, &NoCancellation)
+        .expect("Expected indexing to succeed");
+}
+
+/// Prunes and indexes the given directory, and returns the symbols of the definitions stored for
+/// the given file.
+fn prune_and_index(
+    loader: &mut Loader,
+    source_dir: &Path,
+    db_path: &Path,
+    file: &str,
+) -> Vec<String> {
+    let mut db = SQLiteWriter::open(db_path).unwrap();
+    let reporter = JsonReporter::json();
+    let mut indexer = Indexer::new(&mut db, loader, &reporter);
+    indexer
+        .prune(vec![source_dir], &NoCancellation)
+        .expect("Expected pruning to succeed");
+    indexer
+        .index_all(vec![source_dir], None::<&Path>, &NoCancellation)
+        .expect("Expected indexing to succeed");
+
+    let mut db = SQLiteReader::open(db_path).unwrap();
+    let file = db
+        .load_graph_for_file(&source_dir.join(file).to_string_lossy())
+        .unwrap();
+    let (graph, _, _) = db.get();
+    graph
+        .nodes_for_file(file)
+        .filter(|n| graph[*n].is_definition())
+        .map(|n| graph[graph[n].symbol().unwrap()].to_string())
+        .collect()
+}
+
+/// Sorts all debug info entries in the given JSON value, because their order is not deterministic.
+fn sort_debug_info(value: &mut Value) {
+    match value {
+        Value::Array(values) => values.iter_mut().for_each(sort_debug_info),
+        Value::Object(entries) => {
+            for (key, value) in entries.iter_mut() {
+                match value {
+                    Value::Array(entries) if key == "debug_info" => {
+                        entries.sort_by_key(|entry| entry.to_string())
+                    }
+                    value => sort_debug_info(value),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct StoredResults {
+    /// The serialized graph of each indexed file.
+    graphs: Vec<(String, Value)>,
+    /// The resolved definitions of all references in the database.
+    resolutions: Vec<(String, String)>,
+}
+
+fn stored_results(db_path: &Path) -> StoredResults {
+    let mut files = Vec::new();
+    {
+        let mut db = SQLiteReader::open(db_path).unwrap();
+        let mut entries = db.list_all().unwrap();
+        for entry in entries.try_iter().unwrap() {
+            let entry = entry.unwrap();
+            assert!(matches!(entry.status, FileStatus::Indexed));
+            files.push(entry.path.to_string_lossy().into_owned());
+        }
+    }
+    files.sort();
+
+    let mut graphs = Vec::new();
+    for file in &files {
+        let mut db = SQLiteReader::open(db_path).unwrap();
+        db.load_graph_for_file(file).unwrap();
+        let (graph, _, _) = db.get();
+        let mut graph = serde_json::to_value(serde::StackGraph::from_graph(graph)).unwrap();
+        sort_debug_info(&mut graph);
+        graphs.push((file.clone(), graph));
+    }
+
+    let mut db = SQLiteReader::open(db_path).unwrap();
+    for file in &files {
+        db.load_graph_for_file(file).unwrap();
+    }
+    let (graph, _, _) = db.get();
+    let references = graph
+        .iter_nodes()
+        .filter(|n| graph[*n].is_reference())
+        .collect::<Vec<_>>();
+    let mut resolutions = Vec::new();
+    db.find_all_complete_partial_paths(
+        references,
+        StitcherConfig::default().with_sorted_results(true),
+        &stack_graphs::NoCancellation,
+        |graph, _, path| {
+            resolutions.push((
+                graph[path.start_node].display(graph).to_string(),
+                graph[path.end_node].display(graph).to_string(),
+            ))
+        },
+    )
+    .unwrap();
+    resolutions.sort();
+
+    StoredResults {
+        graphs,
+        resolutions,
+    }
+}
+
+#[test]
+fn parallel_indexing_stores_same_results_as_sequential_indexing() {
+    let source_dir = create_sources(
+        "parallel-indexing",
+        &[
+            ("a.py", "a = 1\n"),
+            ("b.py", "b = a\n"),
+            ("c.py", "c = b\na\n"),
+            ("d.py", "d = c\nb\n"),
+            ("e.py", "e = d\nc\n"),
+        ],
+    );
+    let sequential_db = source_dir.join("sequential.sqlite");
+    let parallel_db = source_dir.join("parallel.sqlite");
+
+    index(&source_dir, &sequential_db, 1);
+    index(&source_dir, &parallel_db, 4);
+    let sequential = stored_results(&sequential_db);
+    let parallel = stored_results(&parallel_db);
+    std::fs::remove_dir_all(&source_dir).unwrap();
+
+    assert_eq!(5, sequential.graphs.len());
+    assert!(!sequential.resolutions.is_empty());
+    assert_eq!(sequential, parallel);
+}
+
+#[test]
+fn moved_files_are_indexed_again_if_rules_use_file_path() {
+    let tsg = r#"
+      global FILE_PATH
+      (module)@mod {
+          node def
+          attr (def) type = "pop_symbol", symbol = (path-filestem FILE_PATH), source_node = @mod, is_definition
+      }
+    "#;
+    let source_dir = create_sources("moved-files", &[("a.py", "pass\n")]);
+    let db_path = source_dir.join("index.sqlite");
+
+    let mut loader = loader_for_rules(tsg);
+    assert_eq!(
+        vec!["a"],
+        prune_and_index(&mut loader, &source_dir, &db_path, "a.py")
+    );
+    std::fs::rename(source_dir.join("a.py"), source_dir.join("b.py")).unwrap();
+    let symbols = prune_and_index(&mut loader, &source_dir, &db_path, "b.py");
+    std::fs::remove_dir_all(&source_dir).unwrap();
+    assert_eq!(vec!["b"], symbols);
+}
+
+#[test]
+fn export_writes_lsif_dump() {
+    let source_dir = create_sources("export", &[("a.py", "x = 1\n"), ("b.py", "y = x\nx\n")]);
+    let db_path = source_dir.join("index.sqlite");
+    let output = source_dir.join("dump.lsif");
+    index(&source_dir, &db_path, 1);
+    let export_args = ExportArgs {
+        source_paths: vec![],
+        project_root: Some(source_dir.clone()),
+        output: output.clone(),
+    };
+    export_args
+        .run(&db_path, loader())
+        .expect("Expected export to succeed");
+    let dump = std::fs::read_to_string(&output).unwrap();
+    let source_dir_uri = Url::from_file_path(source_dir.canonicalize().unwrap()).unwrap();
+    std::fs::remove_dir_all(&source_dir).unwrap();
+
+    let dump = dump
+        .replace(source_dir_uri.as_str(), "file:///project")
+        .replace(
+            &format!("\"version\":\"{}\"", env!("CARGO_PKG_VERSION")),
+            "\"version\":\"<version>\"",
+        );
+    assert_eq!(
+        EXPECTED_LSIF_DUMP.lines().collect::<Vec<_>>(),
+        dump.lines().collect::<Vec<_>>()
+    );
+}
+
+/// The expected dump for `a.py` defining `x`, and `b.py` defining `y` and referencing `x` twice.
+const EXPECTED_LSIF_DUMP: &str = r#"{"version":"0.6.0","projectRoot":"file:///project","positionEncoding":"utf-16","toolInfo":{"name":"tree-sitter-stack-graphs","version":"<version>"},"id":1,"type":"vertex","label":"metaData"}
+{"uri":"file:///project/a.py","languageId":"py","id":2,"type":"vertex","label":"document"}
+{"start":{"line":0,"character":0},"end":{"line":0,"character":1},"id":3,"type":"vertex","label":"range"}
+{"outV":2,"inVs":[3],"id":4,"type":"edge","label":"contains"}
+{"uri":"file:///project/b.py","languageId":"py","id":5,"type":"vertex","label":"document"}
+{"start":{"line":0,"character":0},"end":{"line":0,"character":1},"id":6,"type":"vertex","label":"range"}
+{"start":{"line":0,"character":4},"end":{"line":0,"character":5},"id":7,"type":"vertex","label":"range"}
+{"start":{"line":1,"character":0},"end":{"line":1,"character":1},"id":8,"type":"vertex","label":"range"}
+{"outV":5,"inVs":[6,7,8],"id":9,"type":"edge","label":"contains"}
+{"id":10,"type":"vertex","label":"resultSet"}
+{"outV":3,"inV":10,"id":11,"type":"edge","label":"next"}
+{"id":12,"type":"vertex","label":"definitionResult"}
+{"outV":10,"inV":12,"id":13,"type":"edge","label":"textDocument/definition"}
+{"outV":12,"inVs":[3],"document":2,"id":14,"type":"edge","label":"item"}
+{"id":15,"type":"vertex","label":"resultSet"}
+{"outV":6,"inV":15,"id":16,"type":"edge","label":"next"}
+{"id":17,"type":"vertex","label":"definitionResult"}
+{"outV":15,"inV":17,"id":18,"type":"edge","label":"textDocument/definition"}
+{"outV":17,"inVs":[6],"document":5,"id":19,"type":"edge","label":"item"}
+{"outV":7,"inV":10,"id":20,"type":"edge","label":"next"}
+{"outV":8,"inV":10,"id":21,"type":"edge","label":"next"}
+{"id":22,"type":"vertex","label":"referenceResult"}
+{"outV":10,"inV":22,"id":23,"type":"edge","label":"textDocument/references"}
+{"outV":22,"inVs":[3],"document":2,"property":"definitions","id":24,"type":"edge","label":"item"}
+{"outV":22,"inVs":[7,8],"document":5,"property":"references","id":25,"type":"edge","label":"item"}
+"#;
+
+#[test]
+fn export_uses_shadowing_policy_of_reference_language() {
+    let source_dir = create_sources("export-shadowing", &[("a.py", "x = 1\nx = 2\nx\n")]);
+    let db_path = source_dir.join("index.sqlite");
+    let output = source_dir.join("dump.lsif");
+    let mut loader = loader_with_shadowing_policy(Arc::new(FirstDeclaration));
+    {
+        let mut db = SQLiteWriter::open(&db_path).unwrap();
+        let reporter = JsonReporter::json();
+        let mut indexer = Indexer::new(&mut db, &mut loader, &reporter);
+        indexer
+            .index_all(vec![&source_dir], None::<&Path>, &NoCancellation)
+            .expect("Expected indexing to succeed");
+    }
+    let export_args = ExportArgs {
+        source_paths: vec![],
+        project_root: Some(source_dir.clone()),
+        output: output.clone(),
+    };
+    export_args
+        .run(&db_path, loader)
+        .expect("Expected export to succeed");
+    let dump = std::fs::read_to_string(&output).unwrap();
+    std::fs::remove_dir_all(&source_dir).unwrap();
+
+    let elements = dump
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let range_on_line = |line: u64| {
+        elements
+            .iter()
+            .find(|e| e["label"] == "range" && e["start"]["line"] == line)
+            .map(|e| e["id"].clone())
+            .unwrap()
+    };
+    let next = |range: Value| {
+        elements
+            .iter()
+            .find(|e| e["label"] == "next" && e["outV"] == range)
+            .map(|e| e["inV"].clone())
+            .unwrap()
+    };
+    // the first declaration shadows the second one, so the reference shares its result set
+    assert_eq!(next(range_on_line(0)), next(range_on_line(2)));
+}
+
+/// Removes timings, which are not deterministic, and replaces the source directory in paths.
+fn normalize_records(records: Vec<Value>, source_dir: &Path) -> Vec<Value> {
+    let source_dir = source_dir.to_string_lossy().into_owned();
+    records
+        .into_iter()
+        .map(|mut record| {
+            if let Value::Object(entries) = &mut record {
+                entries.remove("elapsed_ms");
+            }
+            let record = record.to_string().replace(&source_dir, "<dir>");
+            serde_json::from_str(&record).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn json_reporter_records_index_status_and_query_results() {
+    let source_dir = create_sources("json-reporter", &[("a.py", "x = 1\n"), ("b.py", "x\n")]);
+    let source_dir = source_dir.canonicalize().unwrap();
+    let db_path = source_dir.join("index.sqlite");
+
+    let index_records = {
+        let mut db = SQLiteWriter::open(&db_path).unwrap();
+        let mut loader = loader();
+        let reporter = JsonReporter::json();
+        let mut indexer = Indexer::new(&mut db, &mut loader, &reporter);
+        indexer
+            .index_all(vec![&source_dir], None::<&Path>, &NoCancellation)
+            .expect("Expected indexing to succeed");
+        reporter.into_records()
+    };
+
+    let status_records = {
+        let status_args = StatusArgs {
+            source_paths: vec![],
+            all: true,
+            verbose: false,
+            format: OutputFormat::Json,
+        };
+        let reporter = JsonReporter::json();
+        status_args
+            .run_with_reporter(&db_path, &reporter)
+            .expect("Expected status to succeed");
+        reporter.into_records()
+    };
+
+    let query_records = {
+        let mut db = SQLiteReader::open(&db_path).unwrap();
+        let reporter = JsonReporter::json();
+        let mut querier = Querier::new(&mut db, &reporter);
+        let definition = Definition {
+            references: vec![SourcePosition {
+                path: source_dir.join("b.py"),
+                line: 0,
+                column: 0,
+            }],
+            explain: None,
+        };
+        definition
+            .run_json(&mut querier, &reporter)
+            .expect("Expected definition query to succeed");
+        let references = References {
+            definitions: vec![SourcePosition {
+                path: source_dir.join("a.py"),
+                line: 0,
+                column: 0,
+            }],
+        };
+        references
+            .run_json(&mut querier, &reporter)
+            .expect("Expected reference query to succeed");
+        drop(querier);
+        reporter.into_records()
+    };
+    std::fs::remove_dir_all(&source_dir).unwrap();
+
+    let status = |path: &str, summary: &str| {
+        json!({
+            "type": "status",
+            "path": path,
+            "status": "succeeded",
+            "summary": summary,
+            "details": null,
+        })
+    };
+    let span = |path: &str, line: usize| {
+        json!({
+            "path": path,
+            "start": { "line": line, "column": 0 },
+            "end": { "line": line, "column": 1 },
+        })
+    };
+    assert_eq!(
+        vec![
+            status("<dir>/a.py", "indexed"),
+            status("<dir>/b.py", "indexed"),
+        ],
+        normalize_records(index_records, &source_dir)
+    );
+    assert_eq!(
+        vec![
+            status("<dir>/a.py", "indexed"),
+            status("<dir>/b.py", "indexed"),
+        ],
+        normalize_records(status_records, &source_dir)
+    );
+    assert_eq!(
+        vec![
+            status("<dir>/b.py:1:1", "found 1 definitions for 1 references"),
+            json!({
+                "type": "result",
+                "query": "definition",
+                "source": span("<dir>/b.py", 0),
+                "targets": [span("<dir>/a.py", 0)],
+            }),
+            status("<dir>/a.py:1:1", "found 1 references for 1 definitions"),
+            json!({
+                "type": "result",
+                "query": "reference",
+                "source": span("<dir>/a.py", 0),
+                "targets": [span("<dir>/b.py", 0)],
+            }),
+        ],
+        normalize_records(query_records, &source_dir)
+    );
+}
+
+/// A shadowing policy under which definitions on later lines shadow those on earlier lines.
+struct LaterDefinitionsShadow;
+
+impl ShadowingPolicy for LaterDefinitionsShadow {
+    fn shadows(
+        &self,
+        graph: &StackGraph,
+        _partials: &mut PartialPaths,
+        path: &PartialPath,
+        other: &PartialPath,
+    ) -> bool {
+        let line = |p: &PartialPath| graph.source_info(p.end_node).map(|s| s.span.start.line);
+        line(path) > line(other)
+    }
+}
+
+/// Returns the distinct lines of the definitions of the reference at the given position.
+fn definition_lines(
+    db_path: &Path,
+    loader: Option<&mut Loader>,
+    reference: SourcePosition,
+) -> Vec<usize> {
+    let mut db = SQLiteReader::open(db_path).unwrap();
+    let reporter = JsonReporter::json();
+    let mut querier = Querier::new(&mut db, &reporter);
+    if let Some(loader) = loader {
+        querier.set_loader(loader);
+    }
+    let mut lines = querier
+        .definitions(reference, &NoCancellation)
+        .expect("Expected query to succeed")
+        .into_iter()
+        .flat_map(|r| r.targets)
+        .map(|t| t.span.start.line)
+        .collect::<Vec<_>>();
+    lines.sort();
+    lines.dedup();
+    lines
+}
+
+#[test]
+fn queries_use_shadowing_policy_of_reference_language() {
+    let source_dir = create_sources("shadowing-policy", &[("a.py", "x = 1\nx = 2\nx\n")]);
+    let source_dir = source_dir.canonicalize().unwrap();
+    let db_path = source_dir.join("index.sqlite");
+    let reference = SourcePosition {
+        path: source_dir.join("a.py"),
+        line: 2,
+        column: 0,
+    };
+    let mut loader = loader_with_shadowing_policy(Arc::new(LaterDefinitionsShadow));
+    {
+        let mut db = SQLiteWriter::open(&db_path).unwrap();
+        let reporter = JsonReporter::json();
+        let mut indexer = Indexer::new(&mut db, &mut loader, &reporter);
+        indexer
+            .index_all(vec![&source_dir], None::<&Path>, &NoCancellation)
+            .expect("Expected indexing to succeed");
+    }
+
+    let without_loader = definition_lines(&db_path, None, reference.clone());
+    let computed = definition_lines(&db_path, Some(&mut loader), reference.clone());
+    {
+        let mut db = SQLiteWriter::open(&db_path).unwrap();
+        let mut reader = SQLiteReader::open(&db_path).unwrap();
+        let reporter = JsonReporter::json();
+        let mut indexer = Indexer::new(&mut db, &mut loader, &reporter);
+        indexer
+            .resolve_all(&mut reader, vec![&source_dir], &NoCancellation)
+            .expect("Expected resolving to succeed");
+    }
+    let stored = definition_lines(&db_path, Some(&mut loader), reference);
+    std::fs::remove_dir_all(&source_dir).unwrap();
+
+    assert_eq!(vec![0, 1], without_loader);
+    assert_eq!(vec![1], computed);
+    assert_eq!(vec![1], stored);
+}
+
+#[test]
+fn queries_use_shadowing_policy_from_selector() {
+    let source_dir = create_sources("shadowing-selector", &[("a.py", "x = 1\nx = 2\nx\n")]);
+    let source_dir = source_dir.canonicalize().unwrap();
+    let db_path = source_dir.join("index.sqlite");
+    let reference = SourcePosition {
+        path: source_dir.join("a.py"),
+        line: 2,
+        column: 0,
+    };
+    let mut loader = loader_with_shadowing_policy(Arc::new(EdgePrecedence));
+    {
+        let mut db = SQLiteWriter::open(&db_path).unwrap();
+        let reporter = JsonReporter::json();
+        let mut indexer = Indexer::new(&mut db, &mut loader, &reporter);
+        indexer
+            .index_all(vec![&source_dir], None::<&Path>, &NoCancellation)
+            .expect("Expected indexing to succeed");
+    }
+
+    let definition_lines = |selected: Option<Arc<dyn ShadowingPolicy + Send + Sync>>| {
+        let mut db = SQLiteReader::open(&db_path).unwrap();
+        let reporter = JsonReporter::json();
+        let mut querier = Querier::new(&mut db, &reporter);
+        let reference_path = reference.path.clone();
+        querier.set_shadowing_policy_selector(move |path| {
+            assert_eq!(reference_path, path);
+            selected.clone()
+        });
+        let mut lines = querier
+            .definitions(reference.clone(), &NoCancellation)
+            .expect("Expected query to succeed")
+            .into_iter()
+            .flat_map(|r| r.targets)
+            .map(|t| t.span.start.line)
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines.dedup();
+        lines
+    };
+    let selected = definition_lines(Some(Arc::new(LaterDefinitionsShadow)));
+    let fallback = definition_lines(None);
+    std::fs::remove_dir_all(&source_dir).unwrap();
+
+    assert_eq!(vec![1], selected);
+    assert_eq!(vec![0, 1], fallback);
+}
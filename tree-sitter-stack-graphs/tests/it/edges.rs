@@ -83,6 +83,28 @@ fn can_create_edges_with_precedence() {
     );
 }
 
+#[test]
+fn can_create_edges_with_kind() {
+    let tsg = r#"
+      (identifier) @id {
+         node source
+         attr (source) type = "pop_symbol", symbol = (source-text @id), is_definition
+         node sink
+         attr (sink) type = "push_symbol", symbol = (source-text @id), is_reference
+         edge source -> sink
+         attr (source -> sink) kind = "lexical"
+      }
+    "#;
+    let python = "a";
+    let (graph, file) = build_stack_graph(python, tsg).expect("Could not load stack graph");
+    let mut nodes = graph.nodes_for_file(file);
+    let source = nodes.next().unwrap();
+    let sink = nodes.next().unwrap();
+    let kind = graph.edge_kind(source, sink).expect("Missing edge kind");
+    assert_eq!("lexical", &graph[kind]);
+    assert_eq!(None, graph.edge_kind(sink, source));
+}
+
 #[test]
 fn can_create_edges_to_singleton_nodes() {
     let tsg = r#"
@@ -8,7 +8,9 @@
 use once_cell::sync::Lazy;
 use pretty_assertions::assert_eq;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::shadowing::EdgePrecedence;
 use std::path::PathBuf;
+use std::sync::Arc;
 use tree_sitter_stack_graphs::loader::FileAnalyzers;
 use tree_sitter_stack_graphs::loader::LanguageConfiguration;
 use tree_sitter_stack_graphs::loader::Loader;
@@ -36,6 +38,7 @@ fn can_load_from_provided_language_configuration() {
         builtins: StackGraph::new(),
         special_files: FileAnalyzers::new(),
         no_similar_paths_in_file: false,
+        shadowing_policy: Arc::new(EdgePrecedence),
     };
     let mut loader =
         Loader::from_language_configurations(vec![lc], None).expect("Expected loader to succeed");
@@ -50,3 +53,36 @@ fn can_load_from_provided_language_configuration() {
         .expect("Expected loading stack graph language to succeed");
     assert_eq!(lc.primary.map(|lc| lc.language), Some(language));
 }
+
+#[test]
+fn can_load_shared_from_provided_language_configuration() {
+    let language = tree_sitter_python::language();
+    let sgl = StackGraphLanguage::from_str(language, &TSG).unwrap();
+    let lc = LanguageConfiguration {
+        language,
+        scope: Some("source.py".into()),
+        content_regex: None,
+        file_types: vec!["py".into()],
+        sgl,
+        builtins: StackGraph::new(),
+        special_files: FileAnalyzers::new(),
+        no_similar_paths_in_file: false,
+        shadowing_policy: Arc::new(EdgePrecedence),
+    };
+    let mut loader =
+        Loader::from_language_configurations(vec![lc], None).expect("Expected loader to succeed");
+
+    let lcs = loader
+        .load_shared_for_file(&PATH, &mut None, &NoCancellation)
+        .expect("Expected loading stack graph language to succeed");
+    let lcs = std::thread::spawn(move || lcs).join().unwrap();
+    assert_eq!(lcs.primary.as_ref().map(|lc| lc.language), Some(language));
+
+    let other_lcs = loader
+        .load_shared_for_file(&PATH, &mut None, &NoCancellation)
+        .expect("Expected loading stack graph language to succeed");
+    assert!(std::sync::Arc::ptr_eq(
+        lcs.primary.as_ref().unwrap(),
+        other_lcs.primary.as_ref().unwrap()
+    ));
+}
@@ -14,6 +14,8 @@ use tree_sitter_stack_graphs::NoCancellation;
 use tree_sitter_stack_graphs::StackGraphLanguage;
 
 mod builder;
+#[cfg(feature = "cli")]
+mod cli;
 mod edges;
 mod loader;
 mod nodes;
@@ -285,6 +285,37 @@ fn can_calculate_spans() {
     assert_eq!(trimmed_line, "a");
 }
 
+#[test]
+fn can_assign_stable_ids() {
+    let tsg = r#"
+      (identifier) @id {
+         node result
+         attr (result) type = "pop_symbol", symbol = "test", source_node = @id, is_definition
+      }
+    "#;
+    let renumbering_tsg = r#"
+      (identifier) @id {
+         node extra
+         node result
+         attr (result) type = "pop_symbol", symbol = "test", source_node = @id, is_definition
+      }
+    "#;
+    let python = "  a  ";
+
+    let stable_id = |tsg: &str| {
+        let (graph, file) = build_stack_graph(python, tsg).unwrap();
+        let node_handle = graph
+            .nodes_for_file(file)
+            .find(|node| graph[*node].is_definition())
+            .unwrap();
+        let stable_id = graph.source_info(node_handle).unwrap().stable_id;
+        let stable_id = graph[stable_id.into_option().unwrap()].to_string();
+        assert_eq!(Some(node_handle), graph.node_for_stable_id(&stable_id));
+        stable_id
+    };
+    assert_eq!(stable_id(tsg), stable_id(renumbering_tsg));
+}
+
 #[test]
 fn can_set_definiens() {
     let tsg = r#"
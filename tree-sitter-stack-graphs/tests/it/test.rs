@@ -11,6 +11,7 @@ use stack_graphs::arena::Handle;
 use stack_graphs::graph::File;
 use stack_graphs::graph::StackGraph;
 use stack_graphs::partial::PartialPaths;
+use stack_graphs::shadowing::EdgePrecedence;
 use stack_graphs::stitching::Database;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
 use stack_graphs::stitching::StitcherConfig;
@@ -128,6 +129,7 @@ fn check_test(
             &mut partials,
             &mut db,
             StitcherConfig::default(),
+            &EdgePrecedence,
             &NoCancellation,
         )
         .expect("should never be cancelled");